@@ -1,28 +1,56 @@
+//! Contains the [`AnimPlugin`] that animates the sprites.
+//!
+//! The players and the chests play the [`SpriteClip`](clip::SpriteClip)s of the [`SpriteClips`] through their
+//! [`SpriteAnimation`], see [`clip`]. The enemies step through the frames of their
+//! [`EnemySprite`](crate::resources::EnemySprite) on their [`AnimationTimer`].
+
 use bevy::prelude::*;
 
-use crate::player::IFramesTimer;
+use crate::components::Invulnerable;
+use crate::player::{nearest_player_pos, AimDirection, Dying};
 use crate::prelude::*;
 use crate::{
-    enemy::Enemy,
+    enemy::{Enemy, EnemyKind},
     gun::Gun,
     player::{Player, PlayerState},
-    resources::CursorPos,
+    resources::GlobTextAtlases,
+};
+
+pub mod clip;
+
+use clip::{
+    apply_sprite_clips, play_sprite_clips, SpriteAnimation, SpriteClips, SpriteClipsHandle,
+    SpriteClipsLoader,
 };
 
 pub struct AnimPlugin;
 
 impl Plugin for AnimPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            // tick first, then run all the animation systems
-            (
-                animation_timer_tick,
-                (animate_player, animate_gun, animate_enemy),
-            )
-                .chain()
-                .run_if(in_state(GameState::GameRun)),
-        );
+        app.init_asset::<SpriteClips>()
+            .init_asset_loader::<SpriteClipsLoader>()
+            .init_resource::<SpriteClips>()
+            .init_resource::<SpriteClipsHandle>()
+            .add_systems(Update, apply_sprite_clips)
+            .add_systems(
+                Update,
+                // tick first, then run all the animation systems, the clips play once they're
+                // picked
+                (
+                    animation_timer_tick,
+                    (
+                        animate_player,
+                        animate_player_death,
+                        blink_invulnerable,
+                        stop_invulnerable_blink,
+                        animate_gun,
+                        animate_enemy,
+                    ),
+                    play_sprite_clips,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::GameRun)),
+            );
     }
 }
 
@@ -45,82 +73,102 @@ fn animate_player(
     mut player_query: Query<
         (
             &mut Sprite,
+            &mut SpriteAnimation,
             &PlayerState,
-            &Transform,
-            &AnimationTimer,
-            &IFramesTimer,
+            &AimDirection,
         ),
-        With<Player>,
+        (With<Player>, Without<Dying>),
     >,
-    cursor_pos: Res<CursorPos>,
 ) {
-    if player_query.is_empty() {
-        return;
-    }
+    for (mut player_sprite, mut anim, player_state, aim_dir) in player_query.iter_mut() {
+        anim.play(match player_state {
+            PlayerState::Stop => "player.idle",
+            PlayerState::Move => "player.walk",
+        });
 
-    let (mut player_sprite, player_state, player_transf, anim_timer, iframes_timer) =
-        player_query.single_mut();
-
-    // Animate invulnerability
-    let dmged = Vec3::new(1., 0., 0.);
-    let healthy = Vec3::new(1., 1., 1.);
-    let current = healthy.lerp(dmged, (iframes_timer.fraction() * 4.) % 1.);
-    player_sprite.color = Color::srgb(current.x, current.y, current.z);
-
-    // Animate index
-    if anim_timer.just_finished() {
-        if let Some(ta) = player_sprite.texture_atlas.as_mut() {
-            ta.index = match player_state {
-                PlayerState::Stop => 0,
-                PlayerState::Move => (ta.index + 1) % 8,
-            }
+        if let Some(aim_dir) = aim_dir.0 {
+            player_sprite.flip_x = aim_dir.x < 0.;
         }
     }
+}
 
-    if let Some(cursor_pos) = cursor_pos.0 {
-        let player_pos = player_transf.translation;
-        player_sprite.flip_x = cursor_pos.x < player_pos.x;
+/// Spins the player around while fading it out.
+fn animate_player_death(
+    mut player_query: Query<(&mut Sprite, &mut Transform, &Dying), With<Player>>,
+) {
+    for (mut player_sprite, mut player_transf, dying) in player_query.iter_mut() {
+        let t = dying.fraction();
+        player_sprite.color = Color::srgba(1., 0.3, 0.3, 1. - t);
+        player_transf.rotation = Quat::from_rotation_z(t * std::f32::consts::TAU);
+        player_transf.scale = Vec3::splat(1. - t * 0.5);
+    }
+}
+
+/// Blinks the sprites of everything that is [`Invulnerable`].
+fn blink_invulnerable(mut query: Query<(&mut Sprite, &Invulnerable), Without<Dying>>) {
+    for (mut sprite, invulnerable) in query.iter_mut() {
+        let visible = (invulnerable.elapsed_secs() * INVULNERABLE_BLINK_HZ).fract() < 0.5;
+        sprite.color.set_alpha(if visible { 1. } else { 0.3 });
+    }
+}
+
+fn stop_invulnerable_blink(
+    mut removed: RemovedComponents<Invulnerable>,
+    mut sprite_query: Query<&mut Sprite, Without<Dying>>,
+) {
+    for ent in removed.read() {
+        if let Ok(mut sprite) = sprite_query.get_mut(ent) {
+            sprite.color.set_alpha(1.);
+        }
     }
 }
 
 #[allow(clippy::type_complexity)]
 fn animate_enemy(
     mut enemy_query: Query<
-        (&mut Sprite, &Transform, &AnimationTimer),
+        (&mut Sprite, &Transform, &AnimationTimer, &EnemyKind),
         (With<Enemy>, Without<Player>),
     >,
     player_query: Query<&Transform, With<Player>>,
+    text_atlases: Res<GlobTextAtlases>,
 ) {
     if enemy_query.is_empty() {
         return;
     }
 
-    let player_pos = player_query.single().translation;
+    let player_positions = player_query
+        .iter()
+        .map(|transf| transf.translation.truncate())
+        .collect::<Vec<_>>();
 
     enemy_query
         .iter_mut()
-        .for_each(|(mut enemy_sprite, enemy_transf, anim_timer)| {
+        .for_each(|(mut enemy_sprite, enemy_transf, anim_timer, kind)| {
             if anim_timer.just_finished() {
                 if let Some(ta) = enemy_sprite.texture_atlas.as_mut() {
-                    ta.index = (ta.index + 1) % 4;
+                    ta.index = text_atlases.enemy(*kind).next_frame(ta.index);
                 }
             }
 
-            let enemy_pos = enemy_transf.translation;
-            enemy_sprite.flip_x = player_pos.x < enemy_pos.x;
+            // face the player it is chasing
+            let enemy_pos = enemy_transf.translation.truncate();
+            if let Some(player_pos) = nearest_player_pos(&player_positions, enemy_pos) {
+                enemy_sprite.flip_x = player_pos.x < enemy_pos.x;
+            }
         });
 }
 
 fn animate_gun(
-    mut gun_query: Query<(&mut Sprite, &Transform), With<Gun>>,
-    cursor_pos: Res<CursorPos>,
+    mut gun_query: Query<(&mut Sprite, &Parent), With<Gun>>,
+    aim_query: Query<&AimDirection>,
 ) {
-    if gun_query.is_empty() {
-        return;
-    }
-
-    let (mut gun_sprite, gun_transf) = gun_query.single_mut();
-    if let Some(cursor_pos) = cursor_pos.0 {
-        gun_sprite.flip_y = cursor_pos.x < gun_transf.translation.x;
+    for (mut gun_sprite, parent) in gun_query.iter_mut() {
+        if let Some(aim_dir) = aim_query
+            .get(parent.get())
+            .ok()
+            .and_then(|aim_dir| aim_dir.0)
+        {
+            gun_sprite.flip_y = aim_dir.x < 0.;
+        }
     }
 }
@@ -0,0 +1,349 @@
+//! The [`SpriteClips`] describe the frame by frame animations of the sprites, they're loaded from
+//! [`SPRITE_CLIPS_PATH`].
+//!
+//! The clips are a `key = value` file, every key starts with the name of its clip, which can
+//! contain dots itself:
+//!
+//! ```text
+//! player.walk.atlas = player
+//! player.walk.frames = 0, 1, 2, 3
+//! player.walk.durations = 0.1
+//! player.walk.looping = true
+//! ```
+//!
+//! The `atlas` is the [`AtlasKind::name`] the `frames` are tiles of. The `durations` are in
+//! seconds, either one for every frame or one for all of them, 0.1 without any. The clips loop
+//! unless `looping = false`, those stop on their last frame. A clip in the file replaces the
+//! built-in one of the same name.
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::prelude::*;
+use crate::resources::{AtlasKind, GlobTextAtlases};
+use crate::settings::cfg_entries;
+
+/// How long a frame is shown when the clip doesn't say.
+const DEFAULT_FRAME_SECS: f32 = 0.1;
+
+/// The tiles of an atlas shown one after the other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteClip {
+    pub atlas: AtlasKind,
+    /// Never empty.
+    pub frames: Vec<usize>,
+    /// How long each of the `frames` is shown, in seconds.
+    pub durations: Vec<f32>,
+    pub looping: bool,
+}
+
+impl SpriteClip {
+    pub fn new(atlas: AtlasKind, frames: Vec<usize>, frame_secs: f32, looping: bool) -> Self {
+        SpriteClip {
+            atlas,
+            durations: vec![frame_secs; frames.len()],
+            frames,
+            looping,
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.durations.iter().sum()
+    }
+
+    /// The tile shown `elapsed` seconds into the clip.
+    pub fn frame_at(&self, elapsed: f32) -> usize {
+        let duration = self.duration();
+        let mut t = if self.looping && duration > 0. {
+            elapsed % duration
+        } else {
+            elapsed
+        };
+        for (&frame, &secs) in self.frames.iter().zip(self.durations.iter()) {
+            if t < secs {
+                return frame;
+            }
+            t -= secs;
+        }
+        // past the end of a clip that doesn't loop
+        self.frames.last().copied().unwrap_or_default()
+    }
+
+    pub fn is_finished(&self, elapsed: f32) -> bool {
+        !self.looping && elapsed >= self.duration()
+    }
+}
+
+/// All the clips by their name.
+#[derive(Asset, Resource, TypePath, Debug, Clone, PartialEq, Deref)]
+pub struct SpriteClips(HashMap<String, SpriteClip>);
+
+impl Default for SpriteClips {
+    fn default() -> Self {
+        SpriteClips(HashMap::from_iter([
+            (
+                "player.idle".to_string(),
+                SpriteClip::new(AtlasKind::Player, vec![0], PLAYER_ANIM_INTERVAL_SECS, true),
+            ),
+            (
+                "player.walk".to_string(),
+                SpriteClip::new(
+                    AtlasKind::Player,
+                    (0..8).collect(),
+                    PLAYER_ANIM_INTERVAL_SECS,
+                    true,
+                ),
+            ),
+            (
+                "chest.open".to_string(),
+                SpriteClip::new(AtlasKind::Chest, vec![0, 1, 2], CHEST_FRAME_SECS, false),
+            ),
+        ]))
+    }
+}
+
+/// The fields of a clip as they are read from the file.
+#[derive(Default)]
+struct ClipEntry<'a> {
+    atlas: Option<&'a str>,
+    frames: Option<&'a str>,
+    durations: Option<&'a str>,
+    looping: Option<&'a str>,
+}
+
+impl ClipEntry<'_> {
+    fn parse(&self) -> Result<SpriteClip, String> {
+        let atlas = self.atlas.ok_or("it has no atlas")?;
+        let atlas = AtlasKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == atlas)
+            .ok_or_else(|| format!("there is no {atlas} atlas"))?;
+        let frames = parse_list::<usize>(self.frames.ok_or("it has no frames")?)
+            .filter(|frames| !frames.is_empty())
+            .ok_or("its frames are invalid")?;
+        let durations = match self.durations.map(parse_list::<f32>) {
+            None => vec![DEFAULT_FRAME_SECS; frames.len()],
+            Some(Some(durations)) if durations.iter().any(|&secs| secs <= 0.) => {
+                return Err("its durations have to be positive".to_string())
+            }
+            Some(Some(durations)) if durations.len() == 1 => vec![durations[0]; frames.len()],
+            Some(Some(durations)) if durations.len() == frames.len() => durations,
+            Some(_) => return Err("it needs a duration for all the frames or each one".into()),
+        };
+        let looping = match self.looping {
+            Some(looping) => looping.parse().map_err(|_| "its looping is invalid")?,
+            None => true,
+        };
+        Ok(SpriteClip {
+            atlas,
+            frames,
+            durations,
+            looping,
+        })
+    }
+}
+
+fn parse_list<T: std::str::FromStr>(val: &str) -> Option<Vec<T>> {
+    val.split(',')
+        .map(|item| item.trim().parse().ok())
+        .collect()
+}
+
+impl SpriteClips {
+    pub fn from_cfg(cfg: &str) -> Self {
+        let mut entries = HashMap::<&str, ClipEntry>::default();
+        for (key, val) in cfg_entries(cfg) {
+            let Some((name, field)) = key.rsplit_once('.') else {
+                warn!("Sprite clip key without a clip: {key}");
+                continue;
+            };
+            let entry = entries.entry(name).or_default();
+            match field {
+                "atlas" => entry.atlas = Some(val),
+                "frames" => entry.frames = Some(val),
+                "durations" => entry.durations = Some(val),
+                "looping" => entry.looping = Some(val),
+                _ => warn!("Unknown sprite clip key: {key}"),
+            }
+        }
+
+        let mut clips = SpriteClips::default();
+        for (name, entry) in entries {
+            match entry.parse() {
+                Ok(clip) => {
+                    clips.0.insert(name.to_string(), clip);
+                }
+                Err(e) => warn!("Skipping the {name} sprite clip, {e}"),
+            }
+        }
+        clips
+    }
+}
+
+#[derive(Resource, Debug, Deref)]
+pub struct SpriteClipsHandle(pub Handle<SpriteClips>);
+
+impl FromWorld for SpriteClipsHandle {
+    fn from_world(world: &mut World) -> Self {
+        SpriteClipsHandle(world.resource::<AssetServer>().load(SPRITE_CLIPS_PATH))
+    }
+}
+
+#[derive(Default)]
+pub(super) struct SpriteClipsLoader;
+
+impl AssetLoader for SpriteClipsLoader {
+    type Asset = SpriteClips;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let cfg = String::from_utf8(bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(SpriteClips::from_cfg(&cfg))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["clips"]
+    }
+}
+
+/// Plays the named [`SpriteClip`] on the [`Sprite`] of the entity. It's removed once a clip that
+/// doesn't loop is over.
+#[derive(Component, Debug, Clone)]
+pub struct SpriteAnimation {
+    clip: &'static str,
+    elapsed: f32,
+    /// Whether the sprite already shows the atlas of the clip.
+    started: bool,
+}
+
+impl SpriteAnimation {
+    pub fn new(clip: &'static str) -> Self {
+        SpriteAnimation {
+            clip,
+            elapsed: 0.,
+            started: false,
+        }
+    }
+
+    /// Switches to the `clip`, unless it's already playing.
+    pub fn play(&mut self, clip: &'static str) {
+        if self.clip != clip {
+            *self = SpriteAnimation::new(clip);
+        }
+    }
+
+    pub fn clip(&self) -> &'static str {
+        self.clip
+    }
+}
+
+/// Copies the clips into the [`SpriteClips`] resource once they're loaded or modified.
+pub(super) fn apply_sprite_clips(
+    mut clips: ResMut<SpriteClips>,
+    mut asset_events: EventReader<AssetEvent<SpriteClips>>,
+    handle: Res<SpriteClipsHandle>,
+    assets: Res<Assets<SpriteClips>>,
+) {
+    let reloaded = asset_events.read().any(|event| {
+        event.is_loaded_with_dependencies(handle.id()) || event.is_modified(handle.id())
+    });
+    if !reloaded {
+        return;
+    }
+    if let Some(loaded) = assets.get(handle.id()) {
+        clips.set_if_neq(loaded.clone());
+    }
+}
+
+pub(super) fn play_sprite_clips(
+    mut commands: Commands,
+    mut anim_query: Query<(Entity, &mut Sprite, &mut SpriteAnimation)>,
+    clips: Res<SpriteClips>,
+    text_atlases: Res<GlobTextAtlases>,
+    time: Res<Time>,
+) {
+    for (ent, mut sprite, mut anim) in anim_query.iter_mut() {
+        let Some(clip) = clips.get(anim.clip) else {
+            error_once!("There is no {} sprite clip", anim.clip);
+            continue;
+        };
+        let atlas = text_atlases.get(clip.atlas);
+        if anim.started {
+            anim.elapsed += time.delta_secs();
+        } else {
+            anim.started = true;
+            if sprite.image != atlas.image {
+                sprite.image = atlas.image.clone();
+            }
+            sprite.texture_atlas = Some(TextureAtlas {
+                layout: atlas.layout.clone(),
+                index: clip.frames[0],
+            });
+        }
+
+        let index = clip.frame_at(anim.elapsed);
+        // a tile past the last one would panic when it's drawn
+        if index >= atlas.tiles {
+            error_once!(
+                "The {} sprite clip is outside of the {} atlas",
+                anim.clip,
+                clip.atlas.name()
+            );
+        } else if let Some(ta) = sprite.texture_atlas.as_mut() {
+            if ta.index != index {
+                ta.index = index;
+            }
+        }
+        if clip.is_finished(anim.elapsed) {
+            commands.entity(ent).remove::<SpriteAnimation>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sprite_clips_from_cfg() {
+        let clips = SpriteClips::from_cfg(
+            "player.walk.atlas = player\nplayer.walk.frames = 4, 5, 6\n\
+             player.walk.durations = 0.1, 0.2, 0.1\nchest.open.atlas = chest\n\
+             chest.open.frames = 2, 1\nchest.open.durations = 0.5\nchest.open.looping = false\n\
+             broken.atlas = nowhere\nbroken.frames = 0\nshort.atlas = common\n\
+             short.frames = 0, 1\nshort.durations = 0.1, 0.1, 0.1\n",
+        );
+        // the broken ones are skipped, the built-in idle clip stays
+        assert!(!clips.contains_key("broken"));
+        assert!(!clips.contains_key("short"));
+        assert!(clips.contains_key("player.idle"));
+
+        let walk = &clips["player.walk"];
+        assert_eq!(walk.atlas, AtlasKind::Player);
+        assert_eq!(walk.frame_at(0.05), 4);
+        assert_eq!(walk.frame_at(0.25), 5);
+        assert_eq!(walk.frame_at(0.35), 6);
+        // loops around
+        assert_eq!(walk.frame_at(0.45), 4);
+        assert!(!walk.is_finished(10.));
+
+        let open = &clips["chest.open"];
+        assert_eq!(open.durations, [0.5, 0.5]);
+        assert_eq!(open.frame_at(0.6), 1);
+        // stops on the last frame
+        assert_eq!(open.frame_at(5.), 1);
+        assert!(open.is_finished(1.));
+    }
+}
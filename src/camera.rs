@@ -1,48 +1,600 @@
+mod pixel_perfect;
+
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
-use bevy_pancam::{PanCam, PanCamPlugin};
+use bevy::render::{camera::Viewport, view::RenderLayers};
+use bevy::window::PrimaryWindow;
+use rand::Rng;
 
-use crate::player::Player;
+use crate::player::{Controller, Player};
 use crate::prelude::*;
+use crate::resources::AimInput;
+use crate::rng::{GameRng, RngStream};
+use crate::settings::{CameraConfig, CameraFollowMode, GameConfig};
+
+pub use pixel_perfect::PixelCanvas;
 
 pub struct CamPlugin;
 
 impl Plugin for CamPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(PanCamPlugin)
-            .add_systems(OnEnter(GameState::MainMenu), spawn_cam)
+        app.init_resource::<CameraShake>()
+            .init_resource::<CameraZoom>()
+            .init_resource::<ActiveZoomPulse>()
+            .init_resource::<DebugCam>()
+            .init_resource::<PixelCanvas>()
+            .init_resource::<CameraTarget>()
+            .add_event::<ShakeCamera>()
+            .add_event::<ZoomPulse>()
+            .add_event::<FocusCamera>()
+            .add_systems(Startup, (spawn_cam, pixel_perfect::spawn_pixel_canvas))
+            .add_systems(
+                RunTeardown,
+                (
+                    reset_resource::<CameraShake>,
+                    reset_resource::<ActiveZoomPulse>,
+                    reset_resource::<DebugCam>,
+                    reset_resource::<CameraTarget>,
+                ),
+            )
+            .add_systems(OnEnter(GameState::GameInit), join_split_screen)
+            .add_systems(
+                Update,
+                (
+                    toggle_debug_cam,
+                    (
+                        update_split_screen,
+                        update_camera_target,
+                        cam_follow_player,
+                        shake_camera,
+                    )
+                        .chain()
+                        .run_if(debug_cam_inactive),
+                    move_debug_cam.run_if(not(debug_cam_inactive)),
+                    zoom_camera,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::GameRun)),
+            )
+            .add_systems(
+                Update,
+                (
+                    pixel_perfect::apply_pixel_perfect.run_if(resource_changed::<CameraConfig>),
+                    pixel_perfect::fit_pixel_canvas
+                        .run_if(|config: Res<CameraConfig>| config.pixel_perfect),
+                ),
+            )
+            // the level up selection zooms in too
             .add_systems(
                 Update,
-                cam_follow_player.run_if(in_state(GameState::GameRun)),
+                ease_camera_zoom
+                    .after(zoom_camera)
+                    .run_if(in_state(GameState::GameRun).or(in_state(GameState::LevelUp))),
             );
     }
 }
 
+/// Shakes the camera, the stronger one wins when multiple shakes overlap.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ShakeCamera {
+    /// The largest offset of the camera in pixels.
+    pub amplitude: f32,
+    /// How much of the `amplitude` is lost every second.
+    pub decay: f32,
+}
+
+/// The shake that is currently applied to the camera.
+#[derive(Resource, Debug, Default)]
+pub struct CameraShake {
+    pub amplitude: f32,
+    pub decay: f32,
+    /// The offset added to the camera last frame.
+    offset: Vec2,
+}
+
+/// The scale the camera projection eases towards, kept between the zoom limits of the
+/// [`CameraConfig`].
+#[derive(Resource, Debug, Deref, DerefMut)]
+pub struct CameraZoom(pub f32);
+
+impl Default for CameraZoom {
+    fn default() -> Self {
+        CameraZoom(CAM_DEFAULT_SCALE)
+    }
+}
+
+/// Temporarily multiplies the zoom the player picked by `scale`, it eases back once the `secs`
+/// are over. A new pulse replaces the current one.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ZoomPulse {
+    pub scale: f32,
+    pub secs: f32,
+}
+
+#[derive(Resource, Debug)]
+struct ActiveZoomPulse {
+    scale: f32,
+    timer: Timer,
+}
+
+impl Default for ActiveZoomPulse {
+    fn default() -> Self {
+        ActiveZoomPulse {
+            scale: 1.,
+            timer: Timer::default(),
+        }
+    }
+}
+
+/// Pans the shared camera over to the `entity` for `secs`, then back to the players. A new
+/// focus replaces the current one.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FocusCamera {
+    pub entity: Entity,
+    pub secs: f32,
+}
+
+/// What the shared camera follows instead of the players, until the timer finishes or the entity
+/// is gone. A split screen keeps following the players.
+#[derive(Resource, Debug, Default)]
+pub struct CameraTarget {
+    focus: Option<(Entity, Timer)>,
+}
+
+impl CameraTarget {
+    pub fn entity(&self) -> Option<Entity> {
+        self.focus.as_ref().map(|(entity, _)| *entity)
+    }
+}
+
+/// A camera that shows the world, follows its `player` or the midpoint of all the players.
+#[derive(Component, Debug, Default)]
+#[require(Camera2d, Msaa(|| Msaa::Off))]
+pub struct PlayerCam {
+    pub player: Option<Entity>,
+}
+
+/// The second [`PlayerCam`] while the screen is split between the co-op players.
+#[derive(Component)]
+#[require(PlayerCam, RunScoped)]
+struct SplitCam;
+
+/// Detaches the camera from the players, it's moved with WASD or by dragging with the mouse
+/// instead. Toggled with [`DEBUG_CAM_KEY`], the gameplay doesn't get any input in the meantime.
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct DebugCam(pub bool);
+
+fn debug_cam_inactive(debug_cam: Res<DebugCam>) -> bool {
+    !**debug_cam
+}
+
 // Init
 fn spawn_cam(mut commands: Commands) {
+    commands.spawn((
+        PlayerCam::default(),
+        player_cam_projection(CAM_DEFAULT_SCALE),
+    ));
+    // the UI covers the whole window even when the screen is split, the layer keeps the world
+    // from being drawn twice
     commands.spawn((
         Camera2d,
-        PanCam {
-            grab_buttons: vec![],
+        Camera {
+            order: 2,
+            clear_color: ClearColorConfig::None,
             ..default()
         },
-        OrthographicProjection {
-            scaling_mode: bevy::render::camera::ScalingMode::WindowSize,
-            scale: 0.35,
-            ..OrthographicProjection::default_2d()
-        },
+        IsDefaultUiCamera,
+        RenderLayers::layer(UI_RENDER_LAYER),
         Msaa::Off,
     ));
 }
 
-/// Follow player in a smooth motion
+fn player_cam_projection(scale: f32) -> OrthographicProjection {
+    OrthographicProjection {
+        scaling_mode: bevy::render::camera::ScalingMode::WindowSize,
+        scale,
+        ..OrthographicProjection::default_2d()
+    }
+}
+
+/// Computes the world-space [`Rect`] currently visible through a camera.
+pub fn camera_view_rect(projection: &OrthographicProjection, cam_transf: &GlobalTransform) -> Rect {
+    let area = projection.area;
+    Rect::from_center_size(
+        cam_transf.translation().truncate() + area.center(),
+        area.size(),
+    )
+}
+
+/// Where the camera has to be to keep the player inside of the dead zone, moves it as little as
+/// possible.
+pub fn dead_zone_target(cam_pos: Vec2, player_pos: Vec2, half_size: Vec2) -> Vec2 {
+    let offset = player_pos - cam_pos;
+    cam_pos + offset - offset.clamp(-half_size, half_size)
+}
+
+/// Splits the screen in two once the co-op players are too far apart to both fit on it, the one
+/// on the left gets the left half. Joins it again once they get close.
+fn update_split_screen(
+    mut commands: Commands,
+    mut main_cam_query: Query<
+        (
+            &mut Camera,
+            &mut PlayerCam,
+            &Transform,
+            &OrthographicProjection,
+        ),
+        Without<SplitCam>,
+    >,
+    mut split_cam_query: Query<(Entity, &mut Camera, &mut PlayerCam), With<SplitCam>>,
+    player_query: Query<(Entity, &Transform, &Controller), (With<Player>, Without<PlayerCam>)>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    config: Res<CameraConfig>,
+    zoom: Res<CameraZoom>,
+) {
+    let (Ok((mut main_cam, mut main_player_cam, main_transf, projection)), Ok(window)) =
+        (main_cam_query.get_single_mut(), window_query.get_single())
+    else {
+        return;
+    };
+    let mut players = player_query
+        .iter()
+        .map(|(ent, transf, controller)| (ent, transf.translation.truncate(), *controller))
+        .collect::<Vec<_>>();
+    // the canvas of the pixel-perfect mode can't be split
+    let (false, [primary, other]) = (config.pixel_perfect, players.as_slice()) else {
+        join_split(
+            &mut commands,
+            &mut main_cam,
+            &mut main_player_cam,
+            split_cam_query.iter().map(|(ent, _, _)| ent),
+        );
+        return;
+    };
+
+    // what the shared camera would show
+    let full_view = window.size() * **zoom;
+    let offset = (primary.1 - other.1).abs();
+    let fraction = if split_cam_query.is_empty() {
+        CAM_SPLIT_VIEW_FRACTION
+    } else {
+        CAM_JOIN_VIEW_FRACTION
+    };
+    if offset.x <= full_view.x * fraction && offset.y <= full_view.y * fraction {
+        join_split(
+            &mut commands,
+            &mut main_cam,
+            &mut main_player_cam,
+            split_cam_query.iter().map(|(ent, _, _)| ent),
+        );
+        return;
+    }
+
+    // the main camera keeps following the primary player
+    players.sort_by_key(|(_, _, controller)| *controller != Controller::Primary);
+    let (main_player, main_pos, _) = players[0];
+    let (split_player, split_pos, _) = players[1];
+    let half_size = UVec2::new(window.physical_width() / 2, window.physical_height());
+    let (main_origin, split_origin) = if main_pos.x <= split_pos.x {
+        (UVec2::ZERO, UVec2::new(half_size.x, 0))
+    } else {
+        (UVec2::new(half_size.x, 0), UVec2::ZERO)
+    };
+
+    set_viewport(&mut main_cam, Some((main_origin, half_size)));
+    main_player_cam.player = Some(main_player);
+    match split_cam_query.get_single_mut() {
+        Ok((_, mut split_cam, mut split_player_cam)) => {
+            set_viewport(&mut split_cam, Some((split_origin, half_size)));
+            split_player_cam.player = Some(split_player);
+        }
+        Err(_) => {
+            commands.spawn((
+                SplitCam,
+                PlayerCam {
+                    player: Some(split_player),
+                },
+                Camera {
+                    order: 1,
+                    viewport: Some(Viewport {
+                        physical_position: split_origin,
+                        physical_size: half_size,
+                        ..default()
+                    }),
+                    ..default()
+                },
+                player_cam_projection(projection.scale),
+                Transform::from_translation(split_pos.extend(main_transf.translation.z)),
+            ));
+        }
+    }
+}
+
+/// Puts the whole screen back on the main camera.
+fn join_split(
+    commands: &mut Commands,
+    main_cam: &mut Camera,
+    main_player_cam: &mut PlayerCam,
+    split_cams: impl Iterator<Item = Entity>,
+) {
+    set_viewport(main_cam, None);
+    main_player_cam.player = None;
+    for ent in split_cams {
+        commands.entity(ent).despawn();
+    }
+}
+
+fn join_split_screen(
+    mut commands: Commands,
+    mut main_cam_query: Query<(&mut Camera, &mut PlayerCam), Without<SplitCam>>,
+    split_cam_query: Query<(Entity, &mut Camera, &mut PlayerCam), With<SplitCam>>,
+) {
+    for (mut main_cam, mut main_player_cam) in main_cam_query.iter_mut() {
+        join_split(
+            &mut commands,
+            &mut main_cam,
+            &mut main_player_cam,
+            split_cam_query.iter().map(|(ent, _, _)| ent),
+        );
+    }
+}
+
+/// Only touches the camera when the viewport changes, so it doesn't get recomputed every frame.
+fn set_viewport(cam: &mut Camera, viewport: Option<(UVec2, UVec2)>) {
+    let current = cam
+        .viewport
+        .as_ref()
+        .map(|viewport| (viewport.physical_position, viewport.physical_size));
+    if current != viewport {
+        cam.viewport = viewport.map(|(physical_position, physical_size)| Viewport {
+            physical_position,
+            physical_size,
+            ..default()
+        });
+    }
+}
+
+fn update_camera_target(
+    mut focus_events: EventReader<FocusCamera>,
+    mut camera_target: ResMut<CameraTarget>,
+    time: Res<Time>,
+) {
+    if let Some(focus) = focus_events.read().last() {
+        camera_target.focus = Some((
+            focus.entity,
+            Timer::from_seconds(focus.secs, TimerMode::Once),
+        ));
+    }
+    if let Some((_, timer)) = &mut camera_target.focus {
+        if timer.tick(time.delta()).finished() {
+            camera_target.focus = None;
+        }
+    }
+}
+
+/// Follow the players in a smooth motion. A shared camera follows the midpoint of all the
+/// players or the [`CameraTarget`] while it has one, a split one only its own player. In the
+/// [`CameraFollowMode::DeadZone`] it stays put until they leave the dead zone around the center.
+///
+/// The camera of the primary player also looks ahead towards their [`AimInput`], so they see
+/// further in the direction they're aiming.
 fn cam_follow_player(
-    mut cam_query: Query<&mut Transform, (With<Camera>, Without<Player>)>,
-    player_query: Query<&Transform, With<Player>>,
+    mut cam_query: Query<(&mut Transform, &PlayerCam), Without<Player>>,
+    player_query: Query<(&Transform, &Controller), With<Player>>,
+    focus_query: Query<&Transform, (Without<PlayerCam>, Without<Player>)>,
+    camera_target: Res<CameraTarget>,
+    config: Res<CameraConfig>,
+    aim_input: Res<AimInput>,
     time: Res<Time>,
 ) {
-    let cam_pos = &mut cam_query.single_mut().translation;
-    let player_pos = player_query.single().translation;
-    let t = time.delta_secs();
+    if player_query.is_empty() {
+        return;
+    }
+
+    let midpoint = player_query
+        .iter()
+        .map(|(transf, _)| transf.translation.truncate())
+        .sum::<Vec2>()
+        / player_query.iter().len() as f32;
+    let focus_pos = camera_target
+        .entity()
+        .and_then(|entity| focus_query.get(entity).ok())
+        .map(|transf| transf.translation.truncate());
+    let t = (time.delta_secs() * config.follow_speed).min(1.);
+
+    for (mut cam_transf, player_cam) in cam_query.iter_mut() {
+        let cam_pos = &mut cam_transf.translation;
+        if let (None, Some(focus_pos)) = (player_cam.player, focus_pos) {
+            *cam_pos = cam_pos.lerp(focus_pos.extend(cam_pos.z), t);
+            continue;
+        }
+
+        let followed = player_cam
+            .player
+            .and_then(|player| player_query.get(player).ok());
+        let player_pos = followed.map_or(midpoint, |(transf, _)| transf.translation.truncate());
+        // only the primary player aims with a cursor
+        let aims_with_cursor =
+            followed.is_none_or(|(_, controller)| *controller == Controller::Primary);
+        let look_ahead = match aim_input.pos.filter(|_| aims_with_cursor) {
+            Some(aim_pos) => ((aim_pos - player_pos) * config.look_ahead_weight)
+                .clamp_length_max(config.look_ahead_max),
+            None => Vec2::ZERO,
+        };
 
-    *cam_pos = cam_pos.lerp(player_pos.truncate().extend(cam_pos.z), t * 5.);
+        let target = match config.follow_mode {
+            CameraFollowMode::Smooth => player_pos + look_ahead,
+            CameraFollowMode::DeadZone => dead_zone_target(
+                cam_pos.truncate(),
+                player_pos + look_ahead,
+                config.dead_zone_size * 0.5,
+            ),
+        };
+        *cam_pos = cam_pos.lerp(target.extend(cam_pos.z), t);
+    }
+}
+
+/// Offsets the camera in a random direction every frame, the offset of the previous frame is
+/// removed first so it doesn't throw off [`cam_follow_player`].
+fn shake_camera(
+    mut cam_query: Query<&mut Transform, With<PlayerCam>>,
+    mut shake_events: EventReader<ShakeCamera>,
+    mut shake: ResMut<CameraShake>,
+    mut game_rng: ResMut<GameRng>,
+    config: Res<CameraConfig>,
+    time: Res<Time>,
+) {
+    for event in shake_events.read() {
+        let amplitude = event.amplitude * config.shake_scale;
+        if amplitude > shake.amplitude {
+            shake.amplitude = amplitude;
+            shake.decay = event.decay;
+        }
+    }
+    if shake.amplitude <= 0. && shake.offset == Vec2::ZERO {
+        return;
+    }
+    let offset = if shake.amplitude > 0. {
+        let angle = game_rng
+            .stream(RngStream::Effects)
+            .gen_range(0. ..std::f32::consts::TAU);
+        Vec2::from_angle(angle) * shake.amplitude
+    } else {
+        Vec2::ZERO
+    };
+    for mut cam_transf in cam_query.iter_mut() {
+        cam_transf.translation += (offset - shake.offset).extend(0.);
+    }
+    shake.offset = offset;
+    shake.amplitude = (shake.amplitude - shake.decay * time.delta_secs()).max(0.);
+}
+
+/// Zooms in and out with the scroll wheel or by holding up and down on the D-pad, between the
+/// limits of the [`CameraConfig`].
+fn zoom_camera(
+    mut wheel_events: EventReader<MouseWheel>,
+    mut zoom: ResMut<CameraZoom>,
+    gamepads: Query<&Gamepad>,
+    config: Res<CameraConfig>,
+    time: Res<Time<Real>>,
+) {
+    let scrolled = wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / CAM_ZOOM_PIXELS_PER_LINE,
+        })
+        .sum::<f32>();
+    let held = gamepads
+        .iter()
+        .map(|gamepad| {
+            match (
+                gamepad.pressed(GamepadButton::DPadUp),
+                gamepad.pressed(GamepadButton::DPadDown),
+            ) {
+                (true, false) => 1.,
+                (false, true) => -1.,
+                _ => 0.,
+            }
+        })
+        .sum::<f32>()
+        .clamp(-1., 1.);
+
+    // zooming in shrinks the scale
+    let steps = scrolled + held * CAM_ZOOM_GAMEPAD_STEPS_PER_SEC * time.delta_secs();
+    **zoom = (**zoom * (1. - CAM_ZOOM_STEP).powf(steps)).clamp(config.zoom_min, config.zoom_max);
+}
+
+fn toggle_debug_cam(
+    mut debug_cam: ResMut<DebugCam>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    config: Res<GameConfig>,
+) {
+    if config.debug_cam && kbd_input.just_pressed(DEBUG_CAM_KEY) {
+        **debug_cam = !**debug_cam;
+        info!("Debug camera: {}", **debug_cam);
+    }
+}
+
+/// Pans with WASD, or drags the world around while the left mouse button is held.
+fn move_debug_cam(
+    mut cam_query: Query<(&mut Transform, &OrthographicProjection), With<PlayerCam>>,
+    mut motion_events: EventReader<MouseMotion>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    time: Res<Time<Real>>,
+) {
+    let dragged = motion_events
+        .read()
+        .map(|motion| motion.delta)
+        .sum::<Vec2>();
+    let dir = [
+        (KeyCode::KeyW, Vec2::Y),
+        (KeyCode::KeyS, Vec2::NEG_Y),
+        (KeyCode::KeyA, Vec2::NEG_X),
+        (KeyCode::KeyD, Vec2::X),
+    ]
+    .into_iter()
+    .filter(|(key, _)| kbd_input.pressed(*key))
+    .map(|(_, dir)| dir)
+    .sum::<Vec2>()
+    .normalize_or_zero();
+    for (mut cam_transf, projection) in cam_query.iter_mut() {
+        // the same speed on the screen at every zoom
+        let mut delta = dir * DEBUG_CAM_SPEED * projection.scale * time.delta_secs();
+        if mouse_input.pressed(MouseButton::Left) {
+            // the screen y goes down, the world y goes up
+            delta += Vec2::new(-dragged.x, dragged.y) * projection.scale;
+        }
+        cam_transf.translation += delta.extend(0.);
+    }
+}
+
+/// Eases the camera projection towards the [`CameraZoom`] with the [`ZoomPulse`] applied.
+fn ease_camera_zoom(
+    mut cam_query: Query<&mut OrthographicProjection, With<PlayerCam>>,
+    mut pulse_events: EventReader<ZoomPulse>,
+    mut pulse: ResMut<ActiveZoomPulse>,
+    zoom: Res<CameraZoom>,
+    config: Res<CameraConfig>,
+    time: Res<Time>,
+) {
+    if let Some(event) = pulse_events.read().last() {
+        pulse.scale = event.scale;
+        pulse.timer = Timer::from_seconds(event.secs, TimerMode::Once);
+    }
+    if pulse.timer.tick(time.delta()).finished() {
+        pulse.scale = 1.;
+    }
+
+    // the pixel-perfect canvas is already at one texel per pixel
+    let target = if config.pixel_perfect {
+        1.
+    } else {
+        **zoom * pulse.scale
+    };
+    let t = (time.delta_secs() * CAM_ZOOM_SMOOTHING).min(1.);
+    for mut projection in cam_query.iter_mut() {
+        projection.scale += (target - projection.scale) * t;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dead_zone_target_only_follows_outside() {
+        let half_size = Vec2::new(40., 25.);
+        // inside, the camera stays put
+        assert_eq!(
+            dead_zone_target(Vec2::ZERO, Vec2::new(30., -20.), half_size),
+            Vec2::ZERO
+        );
+        // outside, it moves just enough to put the player on the edge
+        assert_eq!(
+            dead_zone_target(Vec2::new(10., 0.), Vec2::new(60., -40.), half_size),
+            Vec2::new(20., -15.)
+        );
+    }
 }
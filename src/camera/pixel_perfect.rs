@@ -0,0 +1,138 @@
+//! The pixel-perfect mode of the [`CamPlugin`](super::CamPlugin), enabled with
+//! [`CameraConfig::pixel_perfect`].
+//!
+//! The main [`PlayerCam`] renders the world to the [`PixelCanvas`] at one texel per world pixel
+//! instead of to the window. The canvas is then shown by its own camera, scaled up by the largest
+//! whole factor that fits the window, so the sprites don't shimmer. The zoom is fixed and the
+//! screen doesn't split while it's on.
+
+use bevy::{
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        view::RenderLayers,
+    },
+    window::{PrimaryWindow, WindowRef},
+};
+
+use super::{PlayerCam, SplitCam};
+use crate::settings::CameraConfig;
+
+/// The resolution the world is rendered at, 16:9 and the same view as the default zoom.
+const PIXEL_CANVAS_SIZE: UVec2 = UVec2::new(480, 270);
+/// Only the camera that shows the [`PixelCanvas`] renders this layer.
+const PIXEL_CANVAS_LAYER: usize = 2;
+
+/// The low resolution image the world is rendered to in the pixel-perfect mode.
+#[derive(Resource, Debug)]
+pub struct PixelCanvas {
+    image: Handle<Image>,
+    /// How many physical pixels of the window a texel of the canvas covers.
+    upscale: u32,
+}
+
+impl FromWorld for PixelCanvas {
+    fn from_world(world: &mut World) -> Self {
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: PIXEL_CANVAS_SIZE.x,
+                height: PIXEL_CANVAS_SIZE.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 255],
+            TextureFormat::Bgra8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::RENDER_ATTACHMENT;
+
+        PixelCanvas {
+            image: world.resource_mut::<Assets<Image>>().add(image),
+            upscale: 1,
+        }
+    }
+}
+
+impl PixelCanvas {
+    /// Maps a position in the window to the same spot on the canvas, which is centered in it.
+    pub fn to_canvas(&self, window: &Window, pos: Vec2) -> Vec2 {
+        (pos - window.size() * 0.5) * window.scale_factor() / self.upscale as f32
+            + PIXEL_CANVAS_SIZE.as_vec2() * 0.5
+    }
+}
+
+#[derive(Component)]
+pub(super) struct CanvasCam;
+
+#[derive(Component)]
+pub(super) struct CanvasSprite;
+
+pub(super) fn spawn_pixel_canvas(mut commands: Commands, canvas: Res<PixelCanvas>) {
+    commands.spawn((
+        CanvasCam,
+        Camera2d,
+        Camera {
+            // after the main camera has rendered the canvas, before the UI
+            order: 1,
+            is_active: false,
+            clear_color: ClearColorConfig::Custom(Color::BLACK),
+            ..default()
+        },
+        RenderLayers::layer(PIXEL_CANVAS_LAYER),
+        Msaa::Off,
+    ));
+    commands.spawn((
+        CanvasSprite,
+        Sprite::from_image(canvas.image.clone()),
+        RenderLayers::layer(PIXEL_CANVAS_LAYER),
+    ));
+}
+
+/// Points the main camera at the canvas or back at the window whenever the mode is toggled.
+pub(super) fn apply_pixel_perfect(
+    mut main_cam_query: Query<&mut Camera, (With<PlayerCam>, Without<SplitCam>)>,
+    mut canvas_cam_query: Query<&mut Camera, (With<CanvasCam>, Without<PlayerCam>)>,
+    config: Res<CameraConfig>,
+    canvas: Res<PixelCanvas>,
+) {
+    let (Ok(mut main_cam), Ok(mut canvas_cam)) = (
+        main_cam_query.get_single_mut(),
+        canvas_cam_query.get_single_mut(),
+    ) else {
+        return;
+    };
+
+    main_cam.target = if config.pixel_perfect {
+        RenderTarget::Image(canvas.image.clone())
+    } else {
+        RenderTarget::Window(WindowRef::Primary)
+    };
+    canvas_cam.is_active = config.pixel_perfect;
+}
+
+/// Scales the canvas up by the largest whole factor that still fits the window.
+pub(super) fn fit_pixel_canvas(
+    mut sprite_query: Query<&mut Transform, With<CanvasSprite>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut canvas: ResMut<PixelCanvas>,
+) {
+    let (Ok(mut transf), Ok(window)) = (sprite_query.get_single_mut(), window_query.get_single())
+    else {
+        return;
+    };
+
+    let fits = window.physical_size() / PIXEL_CANVAS_SIZE;
+    let upscale = fits.min_element().max(1);
+    if canvas.upscale != upscale {
+        canvas.upscale = upscale;
+    }
+    // the canvas camera works in logical pixels
+    let scale = Vec3::splat(upscale as f32 / window.scale_factor());
+    if transf.scale != scale {
+        transf.scale = scale;
+    }
+}
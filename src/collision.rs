@@ -2,32 +2,82 @@ use std::time::Duration;
 
 use bevy::prelude::*;
 use bevy::time::common_conditions::on_timer;
+use bevy::transform::TransformSystem;
 
-use crate::player::{IFramesTimer, Player};
+use crate::player::{Dying, Player, PlayerStats};
 use crate::prelude::*;
 use crate::quadtree::quad_collider::{AsQuadCollider, QuadCollider, Shape};
 use crate::quadtree::Quadtree;
+use crate::world::config::WorldConfig;
 use crate::{
-    components::{Damage, Health},
-    enemy::Enemy,
-    gun::Bullet,
+    camera::ShakeCamera,
+    components::{Damage, Health, Invulnerable, Knockback, Shield},
+    enemy::{Boss, Enemy},
+    gun::{Bullet, BulletHit, DamageFalloff, SpawnInstant},
+    orbital::{BladeHitTimer, OrbitingBlade},
+    pickup::{Attracted, Magnetic},
+    time_scale::HitStop,
 };
 
 pub struct CollisionPlugin;
 
 impl Plugin for CollisionPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(EnemyQuadtree::default()).add_systems(
-            Update,
-            (
-                collide_enemy_bullet,
-                collide_enemy_player,
-                update_enemy_quadtree.run_if(on_timer(Duration::from_secs_f32(
-                    ENEMY_QUADTREE_REFRESH_RATE_SECS,
-                ))),
+        app.insert_resource(EnemyQuadtree::default())
+            .insert_resource(PickupQuadtree::default())
+            .insert_resource(ObstacleQuadtree::default())
+            .add_event::<DamageEvent>()
+            .add_systems(OnEnter(GameState::GameInit), reset_quadtrees)
+            // the left over entities of the last run must not be hit until the next one builds them
+            .add_systems(
+                RunTeardown,
+                (
+                    reset_resource::<EnemyQuadtree>,
+                    reset_resource::<PickupQuadtree>,
+                    reset_resource::<ObstacleQuadtree>,
+                ),
             )
-                .run_if(in_state(GameState::GameRun)),
-        );
+            .add_systems(
+                Update,
+                (
+                    (
+                        collide_enemy_bullet,
+                        collide_enemy_blade,
+                        collide_enemy_player.run_if(in_state(RunPhase::Playing)),
+                        update_enemy_quadtree.run_if(on_timer(Duration::from_secs_f32(
+                            ENEMY_QUADTREE_REFRESH_RATE_SECS,
+                        ))),
+                    ),
+                    // apply all the damage dealt this frame at once
+                    apply_damage,
+                    // has to see the players before they become invulnerable
+                    knock_back_players,
+                    grant_player_iframes,
+                    (tick_invulnerable, update_knockback),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::GameRun)),
+            )
+            .add_systems(
+                FixedUpdate,
+                recharge_shields.run_if(in_state(GameState::GameRun)),
+            )
+            .add_systems(
+                Update,
+                update_pickup_quadtree
+                    .run_if(on_timer(Duration::from_secs_f32(
+                        PICKUP_QUADTREE_REFRESH_RATE_SECS,
+                    )))
+                    .run_if(in_state(GameState::GameRun)),
+            )
+            .add_systems(Update, update_obstacle_quadtree)
+            .add_systems(
+                PostUpdate,
+                // after everything has moved, before it gets drawn
+                push_out_of_obstacles
+                    .before(TransformSystem::TransformPropagate)
+                    .run_if(in_state(GameState::GameRun)),
+            );
     }
 }
 
@@ -36,14 +86,68 @@ pub struct EnemyQuadtree(pub Quadtree<QuadVal>);
 
 impl Default for EnemyQuadtree {
     fn default() -> Self {
-        EnemyQuadtree(Quadtree::new(Rect::from_center_size(
-            Vec2::ZERO,
-            // TODO: change to WORLD_SIZE when the world gets 'closed'
-            Vec2::splat(WORLD_SIZE + 500.),
-        )))
+        EnemyQuadtree::new(&WorldConfig::default())
+    }
+}
+
+impl EnemyQuadtree {
+    pub fn new(config: &WorldConfig) -> Self {
+        EnemyQuadtree(Quadtree::new(config.rect()))
+    }
+}
+
+/// Broadphase for the pickups that can be pulled in by a [`Magnet`](crate::pickup::Magnet).
+/// Only contains the pickups that aren't [`Attracted`] yet.
+#[derive(Resource, DerefMut, Deref)]
+pub struct PickupQuadtree(pub Quadtree<QuadVal>);
+
+impl Default for PickupQuadtree {
+    fn default() -> Self {
+        PickupQuadtree::new(&WorldConfig::default())
+    }
+}
+
+impl PickupQuadtree {
+    pub fn new(config: &WorldConfig) -> Self {
+        PickupQuadtree(Quadtree::new(config.rect()))
+    }
+}
+
+/// Broadphase for the [`Obstacle`]s, only rebuilt when new ones are spawned.
+/// Also covers the border walls around the world.
+#[derive(Resource, DerefMut, Deref)]
+pub struct ObstacleQuadtree(pub Quadtree<QuadVal>);
+
+impl Default for ObstacleQuadtree {
+    fn default() -> Self {
+        ObstacleQuadtree::new(&WorldConfig::default())
     }
 }
 
+impl ObstacleQuadtree {
+    pub fn new(config: &WorldConfig) -> Self {
+        ObstacleQuadtree(Quadtree::new(config.rect().inflate(WORLD_WALL_THICKNESS)))
+    }
+}
+
+/// The world might've changed size since the last run.
+fn reset_quadtrees(
+    mut enemy_qtree: ResMut<EnemyQuadtree>,
+    mut pickup_qtree: ResMut<PickupQuadtree>,
+    mut obstacle_qtree: ResMut<ObstacleQuadtree>,
+    config: Res<WorldConfig>,
+) {
+    *enemy_qtree = EnemyQuadtree::new(&config);
+    *pickup_qtree = PickupQuadtree::new(&config);
+    *obstacle_qtree = ObstacleQuadtree::new(&config);
+}
+
+/// Blocks the movement of the players and the enemies. Its [`ColliderShape`] is either a quad or
+/// treated as a circle.
+#[derive(Component, Debug, Default)]
+#[require(RunScoped)]
+pub struct Obstacle;
+
 #[derive(Clone, PartialEq)]
 pub struct QuadVal {
     pub entity: Entity,
@@ -54,6 +158,16 @@ pub struct QuadVal {
 #[derive(Component, Clone, Copy, PartialEq, Deref, DerefMut)]
 pub struct ColliderShape(pub Shape);
 
+/// Sent whenever something gets hit.
+/// The damage is only subtracted from the [`Health`] of the `target` in [`apply_damage`].
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: u32,
+    /// Position of whatever dealt the damage.
+    pub pos: Vec2,
+}
+
 impl QuadVal {
     pub fn new(entity: Entity, pos: Vec2, shape: Shape) -> Self {
         let shape = ColliderShape(shape);
@@ -73,6 +187,7 @@ impl AsQuadCollider for QuadVal {
 fn update_enemy_quadtree(
     mut qtree: ResMut<EnemyQuadtree>,
     enemy_query: Query<(Entity, &Transform, &ColliderShape), With<Enemy>>,
+    config: Res<WorldConfig>,
 ) {
     let enemies = enemy_query
         .iter()
@@ -81,46 +196,237 @@ fn update_enemy_quadtree(
 
     if !enemies.is_empty() {
         // reset the EnemyQuadtree
-        *qtree = EnemyQuadtree::default();
+        *qtree = EnemyQuadtree::new(&config);
         qtree.insert_many(&enemies);
     }
 }
 
+fn update_pickup_quadtree(
+    mut qtree: ResMut<PickupQuadtree>,
+    pickup_query: Query<(Entity, &Transform, &ColliderShape), (With<Magnetic>, Without<Attracted>)>,
+    config: Res<WorldConfig>,
+) {
+    let pickups = pickup_query
+        .iter()
+        .map(|(ent, transf, shape)| QuadVal::new(ent, transf.translation.truncate(), **shape))
+        .collect::<Vec<_>>();
+
+    *qtree = PickupQuadtree::new(&config);
+    qtree.insert_many(&pickups);
+}
+
+fn update_obstacle_quadtree(
+    mut qtree: ResMut<ObstacleQuadtree>,
+    added_query: Query<(), Added<Obstacle>>,
+    obstacle_query: Query<(Entity, &Transform, &ColliderShape), With<Obstacle>>,
+    config: Res<WorldConfig>,
+) {
+    if added_query.is_empty() {
+        return;
+    }
+
+    let obstacles = obstacle_query
+        .iter()
+        .map(|(ent, transf, shape)| QuadVal::new(ent, transf.translation.truncate(), **shape))
+        .collect::<Vec<_>>();
+
+    *qtree = ObstacleQuadtree::new(&config);
+    qtree.insert_many(&obstacles);
+}
+
+/// Pushes the players and the enemies out of the [`Obstacle`]s they walked into.
+fn push_out_of_obstacles(
+    mut mover_query: Query<(&mut Transform, &ColliderShape), Or<(With<Player>, With<Enemy>)>>,
+    qtree: Res<ObstacleQuadtree>,
+) {
+    for (mut transf, shape) in mover_query.iter_mut() {
+        let pos = transf.translation.truncate();
+        let mover_aabb = QuadCollider::new(pos, **shape).aabb();
+        // close enough for the quads and capsules
+        let mover_radius = mover_aabb.half_size().min_element();
+
+        let push = qtree
+            .query(mover_aabb)
+            .iter()
+            .map(|obstacle| match *obstacle.shape {
+                Shape::Quad(rect) => push_out_of_rect(
+                    Rect::from_center_half_size(obstacle.pos, rect.half_size),
+                    pos,
+                    mover_radius,
+                ),
+                _ => {
+                    let radius = obstacle.as_quad_collider().aabb().half_size().min_element();
+                    let offset = pos - obstacle.pos;
+                    let overlap = radius + mover_radius - offset.length();
+                    offset.normalize_or(Vec2::X) * overlap.max(0.)
+                }
+            })
+            .sum::<Vec2>();
+        if push != Vec2::ZERO {
+            transf.translation += push.extend(0.);
+        }
+    }
+}
+
+/// How far the circle has to move to get out of the rect.
+fn push_out_of_rect(rect: Rect, pos: Vec2, radius: f32) -> Vec2 {
+    let offset = pos - pos.clamp(rect.min, rect.max);
+    if offset != Vec2::ZERO {
+        return offset.normalize() * (radius - offset.length()).max(0.);
+    }
+
+    // the center is inside, push it out through the closest edge
+    let to_min = pos - rect.min;
+    let to_max = rect.max - pos;
+    [
+        (to_min.x, Vec2::NEG_X),
+        (to_max.x, Vec2::X),
+        (to_min.y, Vec2::NEG_Y),
+        (to_max.y, Vec2::Y),
+    ]
+    .into_iter()
+    .min_by(|(a, _), (b, _)| a.total_cmp(b))
+    .map_or(Vec2::ZERO, |(dist, dir)| dir * (dist + radius))
+}
+
+/// The damage goes through the [`Shield`] first, if the target has one.
+fn apply_damage(
+    mut damage_events: EventReader<DamageEvent>,
+    mut health_query: Query<(&mut Health, Option<&mut Shield>), Without<Invulnerable>>,
+) {
+    for event in damage_events.read() {
+        if let Ok((mut hp, shield)) = health_query.get_mut(event.target) {
+            let amount = match shield {
+                Some(mut shield) => shield.absorb(event.amount),
+                None => event.amount,
+            };
+            hp.dmg(amount);
+        }
+    }
+}
+
+fn recharge_shields(mut shield_query: Query<&mut Shield>, time: Res<Time>) {
+    for mut shield in shield_query.iter_mut() {
+        shield.recharge(time.delta_secs());
+    }
+}
+
+/// Pushes the players away from whatever hit them.
+fn knock_back_players(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    player_query: Query<&Transform, (With<Player>, Without<Invulnerable>)>,
+) {
+    for event in damage_events.read() {
+        let Ok(player_transf) = player_query.get(event.target) else {
+            continue;
+        };
+        let dir = (player_transf.translation.truncate() - event.pos).normalize_or_zero();
+        commands.entity(event.target).insert(Knockback::new(
+            dir * PLAYER_KNOCKBACK_SPEED,
+            PLAYER_KNOCKBACK_SECS,
+        ));
+    }
+}
+
+fn update_knockback(
+    mut commands: Commands,
+    mut knockback_query: Query<(Entity, &mut Knockback, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (ent, mut knockback, mut transf) in knockback_query.iter_mut() {
+        if knockback.timer.tick(time.delta()).finished() {
+            commands.entity(ent).remove::<Knockback>();
+            continue;
+        }
+
+        let strength = knockback.timer.fraction_remaining();
+        transf.translation += (knockback.velocity * strength * time.delta_secs()).extend(0.);
+    }
+}
+
+/// Players are briefly [`Invulnerable`] after getting hit.
+fn grant_player_iframes(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    player_query: Query<&PlayerStats, (With<Player>, Without<Invulnerable>)>,
+) {
+    for event in damage_events.read() {
+        if let Ok(stats) = player_query.get(event.target) {
+            commands
+                .entity(event.target)
+                .insert(Invulnerable::from_secs(stats.iframes_secs));
+        }
+    }
+}
+
+fn tick_invulnerable(
+    mut commands: Commands,
+    mut invulnerable_query: Query<(Entity, &mut Invulnerable)>,
+    time: Res<Time>,
+) {
+    for (ent, mut invulnerable) in invulnerable_query.iter_mut() {
+        if invulnerable.tick(time.delta()).finished() {
+            commands.entity(ent).remove::<Invulnerable>();
+        }
+    }
+}
+
 fn collide_enemy_player(
-    mut player_query: Query<
-        (&mut Health, &mut IFramesTimer, &Transform, &ColliderShape),
-        With<Player>,
+    player_query: Query<
+        (Entity, &Transform, &ColliderShape),
+        (With<Player>, Without<Dying>, Without<Invulnerable>),
     >,
-    enemy_query: Query<(&Transform, &Damage), With<Enemy>>,
+    enemy_query: Query<(&Transform, &Damage, Has<Boss>), With<Enemy>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut shake_events: EventWriter<ShakeCamera>,
+    mut hit_stop_events: EventWriter<HitStop>,
     qtree: Res<EnemyQuadtree>,
 ) {
     if enemy_query.is_empty() {
         return;
     }
 
-    let (mut player_hp, mut iframes_timer, player_transf, player_shape) = player_query.single_mut();
-    // if player is invulnerable don't do any processing.
-    if !iframes_timer.finished() {
-        return;
-    }
-
-    // Query the quadtree in a 256px box around player.
-    let near_enemy_colliders = qtree.query(Rect::from_center_size(
-        player_transf.translation.truncate(),
-        Vec2::splat(256.),
-    ));
+    for (player_ent, player_transf, player_shape) in player_query.iter() {
+        // Query the quadtree in a 256px box around player.
+        let near_enemy_colliders = qtree.query(Rect::from_center_size(
+            player_transf.translation.truncate(),
+            Vec2::splat(256.),
+        ));
 
-    for &near_enemy_collider in near_enemy_colliders.iter() {
-        if let Ok((enemy_transf, enemy_damage)) = enemy_query.get(near_enemy_collider.entity) {
-            let enemy_quad_coll = QuadCollider::new(
-                enemy_transf.translation.truncate(),
-                *near_enemy_collider.shape,
-            );
-            let player_quad_coll =
-                QuadCollider::new(player_transf.translation.truncate(), **player_shape);
-            if enemy_quad_coll.intersects(player_quad_coll) && iframes_timer.finished() {
-                player_hp.dmg(**enemy_damage);
-                iframes_timer.reset();
+        for &near_enemy_collider in near_enemy_colliders.iter() {
+            if let Ok((enemy_transf, enemy_damage, is_boss)) =
+                enemy_query.get(near_enemy_collider.entity)
+            {
+                let enemy_quad_coll = QuadCollider::new(
+                    enemy_transf.translation.truncate(),
+                    *near_enemy_collider.shape,
+                );
+                let player_quad_coll =
+                    QuadCollider::new(player_transf.translation.truncate(), **player_shape);
+                if enemy_quad_coll.intersects(player_quad_coll) {
+                    damage_events.send(DamageEvent {
+                        target: player_ent,
+                        amount: **enemy_damage,
+                        pos: enemy_quad_coll.center(),
+                    });
+                    shake_events.send(if is_boss {
+                        hit_stop_events.send(HitStop {
+                            secs: BOSS_HIT_STOP_SECS,
+                        });
+                        ShakeCamera {
+                            amplitude: BOSS_HIT_SHAKE_AMPLITUDE,
+                            decay: BOSS_HIT_SHAKE_DECAY,
+                        }
+                    } else {
+                        ShakeCamera {
+                            amplitude: PLAYER_HIT_SHAKE_AMPLITUDE,
+                            decay: PLAYER_HIT_SHAKE_DECAY,
+                        }
+                    });
+                    // the player becomes invulnerable after the first hit
+                    break;
+                }
             }
         }
     }
@@ -128,16 +434,26 @@ fn collide_enemy_player(
 
 fn collide_enemy_bullet(
     qtree: Res<EnemyQuadtree>,
-    bullet_query: Query<(&Transform, &Damage, &ColliderShape), With<Bullet>>,
-    mut enemy_query: Query<(&mut Health, &Transform), With<Enemy>>,
+    bullet_query: Query<
+        (
+            &Transform,
+            &Damage,
+            &DamageFalloff,
+            &SpawnInstant,
+            &ColliderShape,
+        ),
+        With<Bullet>,
+    >,
+    enemy_query: Query<&Transform, With<Enemy>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut bullet_hit_events: EventWriter<BulletHit>,
 ) {
     if bullet_query.is_empty() || enemy_query.is_empty() {
         return;
     }
 
-    bullet_query
-        .iter()
-        .for_each(|(bullet_transf, bullet_dmg, bullet_shape)| {
+    bullet_query.iter().for_each(
+        |(bullet_transf, bullet_dmg, falloff, spawn_inst, bullet_shape)| {
             // Query the quadtree in a 64px box around bullet.
             let near_enemy_colliders = qtree.query(Rect::from_center_size(
                 bullet_transf.translation.truncate(),
@@ -145,9 +461,7 @@ fn collide_enemy_bullet(
             ));
 
             for &near_enemy_collider in near_enemy_colliders.iter() {
-                if let Ok((mut enemy_hp, enemy_transf)) =
-                    enemy_query.get_mut(near_enemy_collider.entity)
-                {
+                if let Ok(enemy_transf) = enemy_query.get(near_enemy_collider.entity) {
                     let enemy_quad_coll = QuadCollider::new(
                         enemy_transf.translation.truncate(),
                         *near_enemy_collider.shape,
@@ -155,9 +469,83 @@ fn collide_enemy_bullet(
                     let bullet_quad_coll =
                         QuadCollider::new(bullet_transf.translation.truncate(), **bullet_shape);
                     if enemy_quad_coll.intersects(bullet_quad_coll) {
-                        enemy_hp.dmg(**bullet_dmg);
+                        let flight_secs = spawn_inst.elapsed().as_secs_f32();
+                        damage_events.send(DamageEvent {
+                            target: near_enemy_collider.entity,
+                            amount: falloff.apply(**bullet_dmg, flight_secs),
+                            pos: bullet_quad_coll.center(),
+                        });
+                        bullet_hit_events.send(BulletHit {
+                            pos: bullet_quad_coll.center(),
+                        });
                     }
                 }
             }
-        });
+        },
+    );
+}
+
+fn collide_enemy_blade(
+    qtree: Res<EnemyQuadtree>,
+    mut blade_query: Query<
+        (&Transform, &Damage, &ColliderShape, &mut BladeHitTimer),
+        With<OrbitingBlade>,
+    >,
+    enemy_query: Query<&Transform, With<Enemy>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    time: Res<Time>,
+) {
+    if blade_query.is_empty() || enemy_query.is_empty() {
+        return;
+    }
+
+    for (blade_transf, blade_dmg, blade_shape, mut hit_timer) in blade_query.iter_mut() {
+        if !hit_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        // Query the quadtree in a 64px box around the blade.
+        let near_enemy_colliders = qtree.query(Rect::from_center_size(
+            blade_transf.translation.truncate(),
+            Vec2::splat(64.),
+        ));
+        let blade_quad_coll = QuadCollider::new(blade_transf.translation.truncate(), **blade_shape);
+
+        for &near_enemy_collider in near_enemy_colliders.iter() {
+            if let Ok(enemy_transf) = enemy_query.get(near_enemy_collider.entity) {
+                let enemy_quad_coll = QuadCollider::new(
+                    enemy_transf.translation.truncate(),
+                    *near_enemy_collider.shape,
+                );
+                if enemy_quad_coll.intersects(blade_quad_coll) {
+                    damage_events.send(DamageEvent {
+                        target: near_enemy_collider.entity,
+                        amount: **blade_dmg,
+                        pos: blade_quad_coll.center(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn push_out_of_rect_moves_to_the_closest_edge() {
+        let rect = Rect::new(0., 0., 10., 10.);
+        // touching from the outside
+        assert_eq!(
+            push_out_of_rect(rect, Vec2::new(12., 5.), 4.),
+            Vec2::new(2., 0.)
+        );
+        assert_eq!(push_out_of_rect(rect, Vec2::new(20., 5.), 4.), Vec2::ZERO);
+        // the center got inside
+        assert_eq!(
+            push_out_of_rect(rect, Vec2::new(5., 1.), 2.),
+            Vec2::new(0., -3.)
+        );
+    }
 }
@@ -30,5 +30,108 @@ impl Health {
     }
 }
 
+/// Absorbs damage before it reaches the [`Health`].
+/// Starts recharging `recharge_rate` points per second once nothing has hit it for
+/// `recharge_delay` seconds.
+#[derive(Component, Debug, Clone)]
+pub struct Shield {
+    pub current: f32,
+    pub max: f32,
+    pub recharge_delay: f32,
+    pub recharge_rate: f32,
+    /// Seconds since the last hit.
+    since_hit: f32,
+}
+
+impl Shield {
+    pub fn new(max: f32, recharge_delay: f32, recharge_rate: f32) -> Self {
+        Shield {
+            current: max,
+            max,
+            recharge_delay,
+            recharge_rate,
+            since_hit: recharge_delay,
+        }
+    }
+
+    /// Absorbs as much of the damage as possible and returns what is left for the [`Health`].
+    pub fn absorb(&mut self, val: u32) -> u32 {
+        self.since_hit = 0.;
+        let absorbed = self.current.min(val as f32);
+        self.current -= absorbed;
+        val - absorbed.ceil() as u32
+    }
+
+    pub fn recharge(&mut self, secs: f32) {
+        self.since_hit += secs;
+        if self.since_hit >= self.recharge_delay {
+            self.current = (self.current + self.recharge_rate * secs).min(self.max);
+        }
+    }
+}
+
 #[derive(Component, Debug, Deref, DerefMut, Default, Clone)]
 pub struct Damage(pub u32);
+
+/// Entities with this component don't take any damage until the timer runs out,
+/// the component is removed afterwards.
+/// Their sprite blinks in the meantime.
+#[derive(Component, Debug, Deref, DerefMut, Clone)]
+pub struct Invulnerable(pub Timer);
+
+impl Invulnerable {
+    pub fn from_secs(secs: f32) -> Self {
+        Invulnerable(Timer::from_seconds(secs, TimerMode::Once))
+    }
+}
+
+/// Multiplies the movement speed of the entity, set by the terrain it stands on.
+#[derive(Component, Debug, Deref, Clone, Copy, PartialEq)]
+pub struct SpeedModifier(pub f32);
+
+impl Default for SpeedModifier {
+    fn default() -> Self {
+        SpeedModifier(1.)
+    }
+}
+
+/// Pushes the entity with a `velocity` that eases out over the duration of the `timer`,
+/// the component is removed afterwards.
+#[derive(Component, Debug, Clone)]
+pub struct Knockback {
+    pub velocity: Vec2,
+    pub timer: Timer,
+}
+
+impl Knockback {
+    pub fn new(velocity: Vec2, secs: f32) -> Self {
+        Knockback {
+            velocity,
+            timer: Timer::from_seconds(secs, TimerMode::Once),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shield_absorbs_and_recharges() {
+        let mut shield = Shield::new(10., 2., 5.);
+        assert_eq!(shield.absorb(4), 0);
+        assert_eq!(shield.current, 6.);
+        // the rest goes through
+        assert_eq!(shield.absorb(10), 4);
+        assert_eq!(shield.current, 0.);
+
+        // nothing happens until the delay is over
+        shield.recharge(1.);
+        assert_eq!(shield.current, 0.);
+        shield.recharge(1.);
+        assert_eq!(shield.current, 5.);
+        // never exceeds max
+        shield.recharge(10.);
+        assert_eq!(shield.current, 10.);
+    }
+}
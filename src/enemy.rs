@@ -1,17 +1,32 @@
 use std::f32::consts::PI;
-use std::time::Duration;
 
-use bevy::{prelude::*, time::common_conditions::on_timer};
-use rand::Rng;
+use bevy::prelude::*;
+use rand::{
+    rngs::StdRng,
+    seq::{IteratorRandom, SliceRandom},
+    Rng,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::collision::ColliderShape;
+use crate::gui::toast::Notify;
+use crate::locale::Localizer;
 use crate::prelude::*;
 use crate::quadtree::quad_collider::Shape;
 use crate::resources::EnemyNum;
-use crate::score::{ScoreAccumulator, Worth};
+use crate::rng::{GameRng, RngStream};
+use crate::score::{Combo, ScoreAccumulator, ScoreGained, Worth};
+use crate::world::weather::Weather;
 use crate::{
-    animation::AnimationTimer, components::Damage, components::Health, player::Player,
+    animation::AnimationTimer,
+    camera::{FocusCamera, ZoomPulse},
+    components::Damage,
+    components::Health,
+    components::Invulnerable,
+    components::SpeedModifier,
+    player::{nearest_player_pos, Player},
     resources::GlobTextAtlases,
+    world::{config::WorldConfig, WorldNoise, WorldSeed},
 };
 
 pub struct EnemyPlugin;
@@ -20,101 +35,446 @@ impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         // track number of enemies first, to account for all the enemies that were despawned in
         // the previous iteration.
-        app.add_systems(
-            First,
-            track_num_of_enemies.run_if(in_state(GameState::GameRun)),
-        )
-        .add_systems(
-            Update,
-            (
-                spawn_enemies.run_if(on_timer(Duration::from_secs_f32(ENEMY_SPAWN_INTERVAL_SECS))),
-                update_enemy_transform,
+        app.add_event::<EnemyKilled>()
+            .add_event::<BossSpawned>()
+            .add_event::<BossDefeated>()
+            .insert_resource(Wave::default())
+            .init_resource::<EnemySpawnTimer>()
+            .init_resource::<BossIntroTimer>()
+            .add_systems(
+                RunTeardown,
+                (
+                    reset_resource::<Wave>,
+                    reset_resource::<EnemySpawnTimer>,
+                    reset_resource::<BossIntroTimer>,
+                ),
+            )
+            .add_systems(
+                First,
+                track_num_of_enemies.run_if(in_state(GameState::GameRun)),
+            )
+            .add_systems(
+                Update,
+                (
+                    (advance_wave, spawn_boss, spawn_enemies, promote_elites)
+                        .chain()
+                        .run_if(in_state(RunPhase::Playing)),
+                    end_boss_intro.run_if(in_state(RunPhase::BossIntro)),
+                    // the enemies hold still while the boss is introduced
+                    update_enemy_transform.run_if(in_state(RunPhase::Playing)),
+                )
+                    // spawn enemies first, then run all the updating systems
+                    .chain()
+                    .run_if(in_state(GameState::GameRun)),
+            )
+            .add_systems(
+                Last,
+                handle_enemy_death.run_if(in_state(GameState::GameRun)),
             )
-                // spawn enemies first, then run all the updating systems
-                .chain()
-                .run_if(in_state(GameState::GameRun)),
-        )
-        .add_systems(
-            Last,
-            handle_enemy_death.run_if(in_state(GameState::GameRun)),
-        );
+            .add_systems(PostUpdate, track_defeated_bosses);
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Default)]
 #[require(
     Transform,
     Sprite,
+    RunScoped,
     AnimationTimer,
+    EnemyKind,
     Health(|| Health::new(10)),
     Damage(|| Damage(5)),
-    Worth(|| Worth(1)),
+    Worth,
+    SpeedModifier,
     ColliderShape(|| ColliderShape( Shape::Quad( Rectangle::from_size(Vec2::splat(8.0)))))
 )]
 pub struct Enemy;
 
+/// The regular enemies, which ones spawn depends on the [`Biome`](crate::world::biome::Biome).
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnemyKind {
+    #[default]
+    Grunt,
+    /// Fast, but fragile.
+    Runner,
+    /// Slow, tough and hits hard.
+    Brute,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 3] = [EnemyKind::Grunt, EnemyKind::Runner, EnemyKind::Brute];
+
+    /// The name of the kind in the asset files.
+    pub fn name(self) -> &'static str {
+        match self {
+            EnemyKind::Grunt => "grunt",
+            EnemyKind::Runner => "runner",
+            EnemyKind::Brute => "brute",
+        }
+    }
+
+    pub fn health(self) -> u32 {
+        match self {
+            EnemyKind::Grunt => 10,
+            EnemyKind::Runner => 6,
+            EnemyKind::Brute => 30,
+        }
+    }
+
+    pub fn damage(self) -> u32 {
+        match self {
+            EnemyKind::Grunt => 5,
+            EnemyKind::Runner => 4,
+            EnemyKind::Brute => 10,
+        }
+    }
+
+    /// Multiplies the [`ENEMY_SPEED`].
+    pub fn speed_mult(self) -> f32 {
+        match self {
+            EnemyKind::Grunt => 1.,
+            EnemyKind::Runner => 1.8,
+            EnemyKind::Brute => 0.6,
+        }
+    }
+
+    pub fn scale(self) -> f32 {
+        match self {
+            EnemyKind::Grunt => 1.,
+            EnemyKind::Runner => 0.8,
+            EnemyKind::Brute => 1.5,
+        }
+    }
+
+    pub fn tint(self) -> Color {
+        match self {
+            EnemyKind::Grunt => Color::WHITE,
+            EnemyKind::Runner => Color::srgb(0.7, 1.0, 0.7),
+            EnemyKind::Brute => Color::srgb(0.7, 0.7, 1.0),
+        }
+    }
+}
+
+/// A tougher and bigger version of a regular [`Enemy`], worth more.
+#[derive(Component, Debug, Default)]
+#[require(Enemy)]
+pub struct Elite;
+
+/// A much stronger [`Enemy`] that shows up every [`BOSS_WAVE_INTERVAL`] waves.
+#[derive(Component, Debug)]
+#[require(Enemy)]
+pub struct Boss {
+    pub name: &'static str,
+}
+
+impl Boss {
+    const NAMES: [&'static str; 3] = ["The Swarm Mother", "Old Gnasher", "The Hollow King"];
+}
+
+/// Paces the spawning of the regular enemies, a new run starts it over.
+#[derive(Resource, Debug, Deref, DerefMut)]
+struct EnemySpawnTimer(Timer);
+
+impl Default for EnemySpawnTimer {
+    fn default() -> Self {
+        EnemySpawnTimer(Timer::from_seconds(
+            ENEMY_SPAWN_INTERVAL_SECS,
+            TimerMode::Repeating,
+        ))
+    }
+}
+
+/// How long the [`Encounter::BossIntro`] lasts, as long as the camera focuses the boss.
+#[derive(Resource, Debug, Deref, DerefMut)]
+struct BossIntroTimer(Timer);
+
+impl Default for BossIntroTimer {
+    fn default() -> Self {
+        BossIntroTimer(Timer::from_seconds(BOSS_FOCUS_SECS, TimerMode::Once))
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BossSpawned {
+    pub entity: Entity,
+    pub name: &'static str,
+}
+
+/// Sent once a [`Boss`] is gone, whether it was killed or despawned with the rest of the run.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BossDefeated {
+    pub entity: Entity,
+}
+
+/// Enemies come in waves that last [`WAVE_DURATION_SECS`], every wave spawns more of them.
+#[derive(Resource, Debug)]
+pub struct Wave {
+    pub number: u32,
+    pub timer: Timer,
+}
+
+impl Default for Wave {
+    fn default() -> Self {
+        Wave {
+            number: 1,
+            timer: Timer::from_seconds(WAVE_DURATION_SECS, TimerMode::Repeating),
+        }
+    }
+}
+
+impl Wave {
+    /// Number of enemies spawned per [`ENEMY_SPAWN_INTERVAL_SECS`] during this wave.
+    pub fn spawn_per_interval(&self) -> usize {
+        ENEMY_SPAWN_PER_INTERVAL + ENEMY_SPAWN_PER_WAVE * (self.number as usize - 1)
+    }
+}
+
+/// Sent when an enemy dies, right before it gets despawned.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct EnemyKilled {
+    pub entity: Entity,
+    pub pos: Vec2,
+    pub worth: u64,
+    pub kind: EnemyKind,
+    pub boss: bool,
+}
+
+fn advance_wave(
+    mut wave: ResMut<Wave>,
+    mut notify_events: EventWriter<Notify>,
+    time: Res<Time>,
+    localizer: Localizer,
+) {
+    wave.timer.tick(time.delta());
+    let finished = wave.timer.times_finished_this_tick();
+    if finished > 0 {
+        wave.number += finished;
+        notify_events.send(Notify::new(
+            localizer.trf("toast.wave_incoming", &[&wave.number]),
+        ));
+    }
+}
+
+fn spawn_boss(
+    mut commands: Commands,
+    mut boss_spawned_events: EventWriter<BossSpawned>,
+    mut zoom_events: EventWriter<ZoomPulse>,
+    mut focus_events: EventWriter<FocusCamera>,
+    mut next_encounter: ResMut<NextState<Encounter>>,
+    mut intro_timer: ResMut<BossIntroTimer>,
+    wave: Res<Wave>,
+    mut game_rng: ResMut<GameRng>,
+    text_atlases: Res<GlobTextAtlases>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    if !wave.timer.just_finished() || !wave.number.is_multiple_of(BOSS_WAVE_INTERVAL) {
+        return;
+    }
+    let rng = game_rng.stream(RngStream::Enemies);
+    let Some(player_pos) = player_query
+        .iter()
+        .map(|transf| transf.translation.truncate())
+        .choose(rng)
+    else {
+        return;
+    };
+
+    // a huge red grunt
+    let mut sprite = text_atlases.enemy(EnemyKind::Grunt).sprite();
+    sprite.color = Color::srgb(1.0, 0.4, 0.4);
+
+    let pos = player_pos + Vec2::from_angle(rng.gen_range(0.0..PI * 2.0)) * BOSS_SPAWN_DISTANCE;
+    let name = Boss::NAMES[(wave.number / BOSS_WAVE_INTERVAL) as usize % Boss::NAMES.len()];
+    let entity = commands
+        .spawn((
+            sprite,
+            Transform::from_translation(pos.extend(100.0)).with_scale(Vec3::splat(BOSS_SCALE)),
+            AnimationTimer::new_from_secs(ENEMY_ANIM_INTERVAL_SECS),
+            Health::new(BOSS_HEALTH),
+            Damage(BOSS_DAMAGE),
+            ColliderShape(Shape::Quad(Rectangle::from_size(Vec2::splat(
+                8.0 * BOSS_SCALE,
+            )))),
+            Boss { name },
+        ))
+        .id();
+    boss_spawned_events.send(BossSpawned { entity, name });
+    // make room to see it coming
+    zoom_events.send(ZoomPulse {
+        scale: BOSS_ZOOM_SCALE,
+        secs: BOSS_ZOOM_SECS,
+    });
+    focus_events.send(FocusCamera {
+        entity,
+        secs: BOSS_FOCUS_SECS,
+    });
+    intro_timer.reset();
+    next_encounter.set(Encounter::BossIntro);
+}
+
+fn end_boss_intro(
+    mut next_encounter: ResMut<NextState<Encounter>>,
+    mut intro_timer: ResMut<BossIntroTimer>,
+    time: Res<Time>,
+) {
+    if intro_timer.tick(time.delta()).just_finished() {
+        next_encounter.set(Encounter::Regular);
+    }
+}
+
 fn spawn_enemies(
     mut commands: Commands,
     mut num_of_enemies: ResMut<EnemyNum>,
+    mut spawn_timer: ResMut<EnemySpawnTimer>,
+    wave: Res<Wave>,
     text_atlases: Res<GlobTextAtlases>,
+    mut game_rng: ResMut<GameRng>,
+    config: Res<WorldConfig>,
+    seed: Res<WorldSeed>,
     player_query: Query<&Transform, With<Player>>,
+    time: Res<Time>,
 ) {
+    if !spawn_timer.tick(time.delta()).just_finished() {
+        return;
+    }
     let num_enemies = **num_of_enemies;
     if num_enemies >= ENEMY_MAX_INSTANCES {
         return;
     }
 
-    let enemy_spawn_count = (ENEMY_MAX_INSTANCES - num_enemies).min(ENEMY_SPAWN_PER_INTERVAL);
+    let rng = game_rng.stream(RngStream::Enemies);
+    // spread the spawns around all the players
+    let Some(player_pos) = player_query
+        .iter()
+        .map(|transf| transf.translation.truncate())
+        .choose(rng)
+    else {
+        return;
+    };
+
+    let enemy_spawn_count = (ENEMY_MAX_INSTANCES - num_enemies).min(wave.spawn_per_interval());
     **num_of_enemies += enemy_spawn_count;
 
-    let player_pos = player_query.single().translation.truncate();
-    let mut rng = rand::thread_rng();
+    let noise = WorldNoise::new(*seed);
+    let mut enemy_entities = Vec::with_capacity(enemy_spawn_count);
+    for _ in 0..enemy_spawn_count {
+        let pos = random_spawn_pos(rng, player_pos, &config);
+        let kind = noise
+            .biome(pos)
+            .enemy_table()
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .map_or(EnemyKind::Grunt, |(kind, _)| *kind);
+        if rng.gen_bool(ELITE_CHANCE) {
+            commands.spawn((enemy_bundle(kind, pos, &text_atlases), Elite));
+        } else {
+            enemy_entities.push(enemy_bundle(kind, pos, &text_atlases));
+        }
+    }
 
-    let mut get_random_around = |pos: Vec2| {
+    commands.spawn_batch(enemy_entities);
+}
+
+/// A random spot around the `pos` for an enemy to spawn at, inside the world and outside of its
+/// safe zone.
+pub fn random_spawn_pos(rng: &mut StdRng, pos: Vec2, config: &WorldConfig) -> Vec2 {
+    let whalf = Vec2::splat(config.half_size());
+    let mut res = pos;
+    for _ in 0..ENEMY_SPAWN_ATTEMPTS {
         let angle = rng.gen_range(0.0..PI * 2.0);
         let dist = rng.gen_range(200.0..2000.);
 
-        let mut res = pos + Vec2::from_angle(angle) * dist;
-        let whalf = WORLD_SIZE * 0.5;
-        res.x = res.x.clamp(-whalf, whalf);
-        res.y = res.y.clamp(-whalf, whalf);
-        res
-    };
-
-    let enemy_entities = (0..enemy_spawn_count)
-        .map(|_| {
-            let layout = text_atlases.common.clone().unwrap().layout;
-            let image = text_atlases.common.clone().unwrap().image;
+        res = (pos + Vec2::from_angle(angle) * dist).clamp(-whalf, whalf);
+        if !config.in_safe_zone(res) {
+            return res;
+        }
+    }
+    // push the last one to the edge of the safe zone
+    res.normalize_or(Vec2::X) * config.safe_zone_radius
+}
 
-            (
-                Sprite::from_atlas_image(image, TextureAtlas { layout, index: 0 }),
-                Transform::from_translation(get_random_around(player_pos).extend(100.0)),
-                AnimationTimer::new_from_secs(ENEMY_ANIM_INTERVAL_SECS),
-                Enemy,
-            )
-        })
-        .collect::<Vec<_>>();
+/// Makes the new [`Elite`]s tougher and bigger than the regular enemies of their kind.
+fn promote_elites(
+    mut elite_query: Query<
+        (&mut Health, &mut Transform, &mut Sprite, &mut ColliderShape),
+        Added<Elite>,
+    >,
+) {
+    for (mut hp, mut transf, mut sprite, mut shape) in elite_query.iter_mut() {
+        *hp = Health::new(hp.max * ELITE_HEALTH_MULT);
+        transf.scale *= ELITE_SCALE_MULT;
+        if let Shape::Quad(rect) = &mut **shape {
+            rect.half_size *= ELITE_SCALE_MULT;
+        }
+        sprite.color = Color::srgb(1.0, 0.85, 0.3);
+    }
+}
 
-    commands.spawn_batch(enemy_entities);
+/// A regular enemy of the kind, freshly spawned at the position.
+pub fn enemy_bundle(kind: EnemyKind, pos: Vec2, text_atlases: &GlobTextAtlases) -> impl Bundle {
+    let mut sprite = text_atlases.enemy(kind).sprite();
+    sprite.color = kind.tint();
+    (
+        (
+            sprite,
+            Transform::from_translation(pos.extend(100.0)).with_scale(Vec3::splat(kind.scale())),
+            AnimationTimer::new_from_secs(ENEMY_ANIM_INTERVAL_SECS),
+            // a short grace period so enemies can't be killed the moment they appear
+            Invulnerable::from_secs(ENEMY_SPAWN_INVULNERABLE_SECS),
+        ),
+        (
+            Health::new(kind.health()),
+            Damage(kind.damage()),
+            ColliderShape(Shape::Quad(Rectangle::from_size(Vec2::splat(
+                8.0 * kind.scale(),
+            )))),
+            kind,
+            Enemy,
+        ),
+    )
 }
 
 fn update_enemy_transform(
-    mut enemy_query: Query<&mut Transform, (With<Enemy>, Without<Player>)>,
+    mut enemy_query: Query<
+        (&mut Transform, &EnemyKind, &SpeedModifier),
+        (With<Enemy>, Without<Player>),
+    >,
     player_query: Query<&Transform, With<Player>>,
+    weather: Res<Weather>,
     time: Res<Time>,
 ) {
     if player_query.is_empty() || enemy_query.is_empty() {
         return;
     }
 
-    let player_pos = player_query.single().translation.truncate();
+    let player_positions = player_query
+        .iter()
+        .map(|transf| transf.translation.truncate())
+        .collect::<Vec<_>>();
+    let aggro_radius = ENEMY_AGGRO_RADIUS * weather.kind.aggro_radius_mult();
 
-    enemy_query.iter_mut().for_each(|mut etransf| {
-        let dir = (player_pos - etransf.translation.truncate()).normalize_or_zero();
+    enemy_query
+        .iter_mut()
+        .for_each(|(mut etransf, kind, speed_mod)| {
+            let enemy_pos = etransf.translation.truncate();
+            // chase the closest player
+            let Some(player_pos) = nearest_player_pos(&player_positions, enemy_pos) else {
+                return;
+            };
+            let dir = (player_pos - enemy_pos).normalize_or_zero();
+            let aggro_mult = if enemy_pos.distance_squared(player_pos) <= aggro_radius.powi(2) {
+                1.
+            } else {
+                ENEMY_DRIFT_SPEED_MULT
+            };
 
-        let enemy_vel = dir.extend(0.0) * ENEMY_SPEED * time.delta_secs();
-        etransf.translation += enemy_vel;
-    });
+            let speed = ENEMY_SPEED
+                * kind.speed_mult()
+                * aggro_mult
+                * **speed_mod
+                * weather.kind.move_speed_mult();
+            let enemy_vel = dir.extend(0.0) * speed * time.delta_secs();
+            etransf.translation += enemy_vel;
+        });
 }
 
 fn track_num_of_enemies(mut num_of_enemies: ResMut<EnemyNum>, enemy_query: Query<&Enemy>) {
@@ -123,14 +483,50 @@ fn track_num_of_enemies(mut num_of_enemies: ResMut<EnemyNum>, enemy_query: Query
 
 fn handle_enemy_death(
     mut commands: Commands,
-    mut player_query: Query<&mut ScoreAccumulator, With<Player>>,
-    enemy_query: Query<(Entity, &Health, &Worth), (Changed<Health>, With<Enemy>)>,
+    mut player_query: Query<(&mut ScoreAccumulator, &Transform), (With<Player>, Without<Enemy>)>,
+    mut enemy_killed_events: EventWriter<EnemyKilled>,
+    mut score_gained_events: EventWriter<ScoreGained>,
+    mut combo: ResMut<Combo>,
+    enemy_query: Query<
+        (Entity, &Health, &Worth, &Transform, &EnemyKind, Has<Boss>),
+        (Changed<Health>, With<Enemy>),
+    >,
 ) {
-    let mut player_score_accum = player_query.single_mut();
-    for (ent, hp, worth) in enemy_query.iter() {
+    for (ent, hp, worth, transf, kind, boss) in enemy_query.iter() {
         if hp.current == 0 {
-            **player_score_accum += **worth;
+            // credit the kill to the closest player
+            let enemy_pos = transf.translation.truncate();
+            let closest = player_query.iter_mut().min_by(|(_, a), (_, b)| {
+                let a = a.translation.truncate().distance_squared(enemy_pos);
+                let b = b.translation.truncate().distance_squared(enemy_pos);
+                a.total_cmp(&b)
+            });
+            combo.add_kill();
+            let amount = **worth * combo.multiplier();
+            if let Some((mut score_accum, _)) = closest {
+                **score_accum += amount;
+                score_gained_events.send(ScoreGained {
+                    amount,
+                    position: enemy_pos,
+                });
+            }
+            enemy_killed_events.send(EnemyKilled {
+                entity: ent,
+                pos: enemy_pos,
+                worth: **worth,
+                kind: *kind,
+                boss,
+            });
             commands.entity(ent).despawn();
         }
     }
 }
+
+fn track_defeated_bosses(
+    mut removed_bosses: RemovedComponents<Boss>,
+    mut boss_defeated_events: EventWriter<BossDefeated>,
+) {
+    for entity in removed_bosses.read() {
+        boss_defeated_events.send(BossDefeated { entity });
+    }
+}
@@ -0,0 +1,122 @@
+//! Contains [`ExperiencePlugin`] that handles gaining experience and leveling up.
+//!
+//! Entities with an [`Experience`] and a [`Level`] component gain experience through
+//! [`XpGained`] events. Once they collect enough experience, as described by the [`LevelCurve`],
+//! their level increases and a [`LevelUp`] event is sent.
+
+use bevy::prelude::*;
+
+use crate::prelude::*;
+
+pub struct ExperiencePlugin;
+
+impl Plugin for ExperiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LevelCurve::default())
+            .add_event::<XpGained>()
+            .add_event::<LevelUp>()
+            .add_systems(Update, apply_gained_xp.run_if(in_state(GameState::GameRun)));
+    }
+}
+
+/// Experience collected towards the next [`Level`].
+#[derive(Component, Debug, Default, Deref, DerefMut, Clone, Copy)]
+pub struct Experience(pub u32);
+
+#[derive(Component, Debug, Deref, DerefMut, Clone, Copy)]
+pub struct Level(pub u32);
+
+impl Default for Level {
+    fn default() -> Self {
+        Level(1)
+    }
+}
+
+/// Describes how much experience is needed to reach the next level.
+///
+/// Reaching level 2 takes `base` experience, every next level takes `growth` times more.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct LevelCurve {
+    pub base: u32,
+    pub growth: f32,
+}
+
+impl Default for LevelCurve {
+    fn default() -> Self {
+        LevelCurve {
+            base: LEVEL_CURVE_BASE_XP,
+            growth: LEVEL_CURVE_GROWTH,
+        }
+    }
+}
+
+impl LevelCurve {
+    /// Experience needed to advance from `level` to the next one.
+    pub fn xp_to_next(&self, level: u32) -> u32 {
+        let xp = self.base as f32 * self.growth.powi(level.saturating_sub(1) as i32);
+        (xp.round() as u32).max(1)
+    }
+}
+
+/// Sent when an entity collects experience.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct XpGained {
+    pub entity: Entity,
+    pub amount: u32,
+}
+
+/// Sent every time an entity reaches a new level.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LevelUp {
+    pub entity: Entity,
+    pub level: u32,
+}
+
+pub fn apply_gained_xp(
+    mut xp_events: EventReader<XpGained>,
+    mut level_up_events: EventWriter<LevelUp>,
+    mut xp_query: Query<(&mut Experience, &mut Level)>,
+    curve: Res<LevelCurve>,
+) {
+    for gained in xp_events.read() {
+        let Ok((mut xp, mut level)) = xp_query.get_mut(gained.entity) else {
+            continue;
+        };
+
+        **xp += gained.amount;
+        // a single large gain can advance multiple levels
+        while **xp >= curve.xp_to_next(**level) {
+            **xp -= curve.xp_to_next(**level);
+            **level += 1;
+            level_up_events.send(LevelUp {
+                entity: gained.entity,
+                level: **level,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn level_curve_works() {
+        let curve = LevelCurve {
+            base: 10,
+            growth: 2.,
+        };
+        assert_eq!(curve.xp_to_next(1), 10);
+        assert_eq!(curve.xp_to_next(2), 20);
+        assert_eq!(curve.xp_to_next(3), 40);
+        // level 0 isn't valid, treat it as the first level
+        assert_eq!(curve.xp_to_next(0), 10);
+
+        let flat = LevelCurve {
+            base: 0,
+            growth: 1.,
+        };
+        // always needs at least some experience
+        assert_eq!(flat.xp_to_next(5), 1);
+    }
+}
@@ -0,0 +1,257 @@
+//! Lobbed projectiles.
+//!
+//! Grenades don't travel in a straight line like bullets, they fly in an arc towards the aim
+//! target and explode once their fuse runs out. The arc is faked: the grenade moves linearly on
+//! the ground plane while its sprite is lifted and scaled up, and a shadow stays on the ground.
+use bevy::prelude::*;
+
+use crate::camera::ShakeCamera;
+use crate::collision::{DamageEvent, EnemyQuadtree};
+use crate::enemy::Enemy;
+use crate::gun::AimTarget;
+use crate::input_map::{Action, ActionInput};
+use crate::player::{Controller, ControllerGamepads, Dying, Player};
+use crate::prelude::*;
+use crate::quadtree::quad_collider::{QuadCollider, Shape};
+use crate::resources::{AtlasKind, GlobTextAtlases, InputDevice};
+use crate::time_scale::HitStop;
+
+pub struct GrenadePlugin;
+
+impl Plugin for GrenadePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Explosion>().add_systems(
+            Update,
+            (
+                throw_grenade,
+                update_grenade_flight,
+                tick_grenade_fuse,
+                damage_from_explosions,
+                (spawn_explosion_effect, fade_explosion_effects),
+            )
+                .chain()
+                .run_if(in_state(GameState::GameRun)),
+        );
+    }
+}
+
+/// Time until the player can throw the next grenade.
+#[derive(Component, Debug, Deref, DerefMut)]
+pub struct GrenadeCooldown(pub Timer);
+
+impl Default for GrenadeCooldown {
+    fn default() -> Self {
+        let mut cooldown = Timer::from_seconds(GRENADE_COOLDOWN_SECS, TimerMode::Once);
+        // the first grenade is ready immediately
+        cooldown.tick(cooldown.duration());
+        GrenadeCooldown(cooldown)
+    }
+}
+
+#[derive(Component, Debug)]
+#[require(Transform, Sprite, RunScoped)]
+pub struct Grenade {
+    /// Ground position the grenade was thrown from.
+    pub from: Vec2,
+    /// Ground position the grenade lands on.
+    pub to: Vec2,
+    pub flight: Timer,
+    /// Starts ticking once the grenade lands.
+    pub fuse: Timer,
+    pub shadow: Entity,
+}
+
+#[derive(Component, Debug)]
+#[require(Transform, Sprite, RunScoped)]
+pub struct GrenadeShadow;
+
+/// Sent when a grenade (or anything else) explodes.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Explosion {
+    pub pos: Vec2,
+    pub radius: f32,
+    pub damage: u32,
+}
+
+/// The expanding and fading visual of an [`Explosion`].
+#[derive(Component, Debug, Deref, DerefMut)]
+#[require(Transform, Sprite, RunScoped)]
+pub struct ExplosionEffect(pub Timer);
+
+/// Height of the arc at `t` in `0.0..=1.0` of the flight.
+fn arc_height(t: f32) -> f32 {
+    4. * GRENADE_ARC_HEIGHT * t * (1. - t)
+}
+
+/// Grenades are thrown with the right mouse button or the right bumper of a gamepad.
+fn throw_grenade(
+    mut commands: Commands,
+    mut player_query: Query<
+        (&mut GrenadeCooldown, &Transform, &AimTarget, &Controller),
+        (With<Player>, Without<Dying>),
+    >,
+    actions: ActionInput,
+    gamepads: ControllerGamepads,
+    input_device: Res<InputDevice>,
+    text_atlases: Res<GlobTextAtlases>,
+    time: Res<Time>,
+) {
+    for (mut cooldown, player_transf, aim_target, controller) in player_query.iter_mut() {
+        cooldown.tick(time.delta());
+        let throw_pressed = if controller.uses_gamepad(*input_device) {
+            gamepads
+                .of(*controller)
+                .any(|gamepad| gamepad.just_pressed(GamepadButton::RightTrigger))
+        } else {
+            actions.just_pressed(Action::ThrowGrenade)
+        };
+        if !cooldown.finished() || !throw_pressed {
+            continue;
+        }
+        let Some(target) = aim_target.0 else {
+            continue;
+        };
+
+        let from = player_transf.translation.truncate();
+        let to = from + (target - from).clamp_length_max(GRENADE_MAX_RANGE);
+        let layout = text_atlases.common().layout;
+        let image = text_atlases.common().image;
+        let index = text_atlases.region(AtlasKind::Common, "bullet");
+        cooldown.reset();
+
+        let shadow = commands
+            .spawn((
+                Sprite::from_color(Color::srgba(0., 0., 0., 0.35), Vec2::new(6., 3.)),
+                Transform::from_translation(from.extend(49.)),
+                GrenadeShadow,
+            ))
+            .id();
+
+        let mut sprite = Sprite::from_atlas_image(image, TextureAtlas { layout, index });
+        sprite.color = Color::srgb(0.5, 0.9, 0.4);
+        commands.spawn((
+            sprite,
+            Transform::from_translation(from.extend(53.)),
+            Grenade {
+                from,
+                to,
+                flight: Timer::from_seconds(GRENADE_FLIGHT_SECS, TimerMode::Once),
+                fuse: Timer::from_seconds(GRENADE_FUSE_SECS, TimerMode::Once),
+                shadow,
+            },
+        ));
+    }
+}
+
+fn update_grenade_flight(
+    mut grenade_query: Query<(&mut Grenade, &mut Transform), Without<GrenadeShadow>>,
+    mut shadow_query: Query<&mut Transform, With<GrenadeShadow>>,
+    time: Res<Time>,
+) {
+    for (mut grenade, mut transf) in grenade_query.iter_mut() {
+        if grenade.flight.finished() {
+            continue;
+        }
+        let t = grenade.flight.tick(time.delta()).fraction();
+        let ground = grenade.from.lerp(grenade.to, t);
+        let height = arc_height(t);
+
+        transf.translation = (ground + Vec2::Y * height).extend(transf.translation.z);
+        transf.scale = Vec3::splat(1. + height / GRENADE_ARC_HEIGHT * 0.5);
+
+        if let Ok(mut shadow_transf) = shadow_query.get_mut(grenade.shadow) {
+            shadow_transf.translation = ground.extend(shadow_transf.translation.z);
+        }
+    }
+}
+
+fn tick_grenade_fuse(
+    mut commands: Commands,
+    mut grenade_query: Query<(Entity, &mut Grenade)>,
+    mut explosion_events: EventWriter<Explosion>,
+    time: Res<Time>,
+) {
+    for (ent, mut grenade) in grenade_query.iter_mut() {
+        if !grenade.flight.finished() || !grenade.fuse.tick(time.delta()).finished() {
+            continue;
+        }
+
+        explosion_events.send(Explosion {
+            pos: grenade.to,
+            radius: GRENADE_BLAST_RADIUS,
+            damage: GRENADE_DAMAGE,
+        });
+        commands.entity(grenade.shadow).despawn();
+        commands.entity(ent).despawn();
+    }
+}
+
+fn damage_from_explosions(
+    mut explosion_events: EventReader<Explosion>,
+    mut damage_events: EventWriter<DamageEvent>,
+    enemy_query: Query<&Transform, With<Enemy>>,
+    qtree: Res<EnemyQuadtree>,
+) {
+    for explosion in explosion_events.read() {
+        let blast = QuadCollider::new(explosion.pos, Shape::Circle(Circle::new(explosion.radius)));
+
+        let near_enemy_colliders = qtree.query(blast.aabb());
+        for &near_enemy_collider in near_enemy_colliders.iter() {
+            if let Ok(enemy_transf) = enemy_query.get(near_enemy_collider.entity) {
+                let enemy_quad_coll = QuadCollider::new(
+                    enemy_transf.translation.truncate(),
+                    *near_enemy_collider.shape,
+                );
+                if enemy_quad_coll.intersects(blast) {
+                    damage_events.send(DamageEvent {
+                        target: near_enemy_collider.entity,
+                        amount: explosion.damage,
+                        pos: explosion.pos,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn spawn_explosion_effect(
+    mut commands: Commands,
+    mut explosion_events: EventReader<Explosion>,
+    mut shake_events: EventWriter<ShakeCamera>,
+    mut hit_stop_events: EventWriter<HitStop>,
+) {
+    for explosion in explosion_events.read() {
+        shake_events.send(ShakeCamera {
+            amplitude: EXPLOSION_SHAKE_AMPLITUDE,
+            decay: EXPLOSION_SHAKE_DECAY,
+        });
+        hit_stop_events.send(HitStop {
+            secs: EXPLOSION_HIT_STOP_SECS,
+        });
+        commands.spawn((
+            Sprite::from_color(
+                Color::srgba(1.0, 0.6, 0.2, 0.8),
+                Vec2::splat(explosion.radius * 2.),
+            ),
+            Transform::from_translation(explosion.pos.extend(54.)).with_scale(Vec3::splat(0.2)),
+            ExplosionEffect(Timer::from_seconds(EXPLOSION_EFFECT_SECS, TimerMode::Once)),
+        ));
+    }
+}
+
+fn fade_explosion_effects(
+    mut commands: Commands,
+    mut effect_query: Query<(Entity, &mut ExplosionEffect, &mut Sprite, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (ent, mut effect, mut sprite, mut transf) in effect_query.iter_mut() {
+        if effect.tick(time.delta()).finished() {
+            commands.entity(ent).despawn();
+            continue;
+        }
+
+        let t = effect.fraction();
+        transf.scale = Vec3::splat(0.2 + 0.8 * t.sqrt());
+        sprite.color.set_alpha(0.8 * (1. - t));
+    }
+}
@@ -3,70 +3,316 @@
 use bevy::{
     diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
     prelude::*,
+    ui::RelativeCursorPosition,
+    window::{Monitor, PrimaryWindow},
 };
 
 use crate::{
-    components::Health, player::Player, prelude::GameState, resources::EnemyNum, score::Score,
+    components::{Health, Shield},
+    input_map::{Action, Binding, InputMap},
+    inventory::Inventory,
+    locale::{Localized, Localizer},
+    orbital::OrbitalWeapon,
+    player::{
+        character::{Character, SelectedCharacter},
+        Player, PlayerStats, Stamina,
+    },
+    prelude::{
+        DespawnOnExit, GameState, PauseScreen, RunPhase, RunScoped, SettingsOpen, BG_COLOR,
+        CAM_LOOK_AHEAD_STEPS, CAM_SHAKE_STEPS, GAME_OVER_HIGH_SCORES, PAUSE_GAMEPAD_BUTTON,
+        PAUSE_KEY, UI_SCALE_MAX, UI_SCALE_MIN,
+    },
+    resources::{AssetLoadProgress, AtlasKind, EnemyNum, GlobTextAtlases},
+    save::{ResumeRun, SavedRun},
+    score::{record_high_score, Combo, HighScore, HighScores, LastRunRank, RunSummary, Score},
+    settings::{
+        next_step, ui_scale_step, AudioChannel, CameraConfig, GameConfig, UserSettings,
+        VideoSettings,
+    },
+    upgrades::{PendingLevelUps, Upgrade, UpgradeChoices, UpgradeChosen},
 };
+use hud::spawn_hud_field;
+
+pub mod crosshair;
+pub mod hud;
+pub mod kill_feed;
+pub mod score_popup;
+pub mod toast;
 
 const FONT_SIZE: f32 = 30.0;
+/// Number of frames shown in the frame time graph of the debug overlay.
+const FRAME_TIME_GRAPH_BARS: usize = 120;
+const FRAME_TIME_GRAPH_SIZE: Vec2 = Vec2::new(240., 60.);
+const FRAME_TIME_GRAPH_BG: Color = Color::srgba(0.05, 0.05, 0.05, 0.6);
+/// Frame time in milliseconds that fills the whole height of the graph.
+const FRAME_TIME_GRAPH_MAX_MS: f64 = 50.;
+const FRAME_TIME_OK: Color = Color::srgb(0.2, 0.8, 0.3);
+const FRAME_TIME_SLOW: Color = Color::srgb(0.9, 0.8, 0.2);
+const FRAME_TIME_SPIKE: Color = Color::srgb(0.9, 0.2, 0.2);
 
 pub struct GuiPlugin;
 
 impl Plugin for GuiPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(FrameTimeDiagnosticsPlugin)
+            .insert_resource(Rebinding::default())
+            .insert_resource(SelectedCard(0))
+            .add_systems(Startup, spawn_tooltip_popup)
+            .add_systems(Update, show_tooltips)
+            .add_systems(Update, (sync_loading_screen, update_loading_bar).chain())
             .add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
             .add_systems(
-                OnExit(GameState::MainMenu),
-                despawn_entities::<OnMenuScreen>,
+                Update,
+                handle_button_color.run_if(
+                    in_state(GameState::MainMenu)
+                        .or(in_state(GameState::Settings))
+                        .or(in_state(GameState::Controls))
+                        .or(in_state(GameState::CharacterSelect))
+                        .or(in_state(GameState::LevelUp))
+                        .or(in_state(GameState::Paused))
+                        .or(in_state(GameState::GameOver)),
+                ),
+            )
+            .add_systems(
+                Update,
+                handle_menu_button_action.run_if(
+                    in_state(GameState::MainMenu)
+                        .or(in_state(GameState::Settings))
+                        .or(in_state(GameState::Controls))
+                        .or(in_state(GameState::CharacterSelect))
+                        .or(in_state(GameState::Paused))
+                        .or(in_state(GameState::GameOver)),
+                ),
+            )
+            .add_systems(
+                Update,
+                toggle_pause.run_if(in_state(RunPhase::Playing).or(in_state(RunPhase::Paused))),
+            )
+            .add_systems(OnEnter(PauseScreen::Menu), spawn_pause_menu)
+            .add_systems(OnEnter(GameState::Controls), spawn_controls_menu)
+            .add_systems(
+                Update,
+                // capture first, so the click that starts rebinding doesn't get bound
+                (
+                    capture_rebinding,
+                    handle_controls_button_action,
+                    update_controls_labels,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Controls)),
+            )
+            .add_systems(OnEnter(SettingsOpen), spawn_settings_menu)
+            .add_systems(
+                Update,
+                (
+                    (handle_settings_button_action, update_settings_labels).chain(),
+                    (drag_volume_sliders, update_volume_sliders).chain(),
+                    (drag_ui_scale_slider, update_ui_scale_slider).chain(),
+                )
+                    .run_if(in_state(SettingsOpen)),
+            )
+            .add_systems(OnEnter(GameState::CharacterSelect), spawn_character_select)
+            .add_systems(
+                Update,
+                handle_character_card.run_if(in_state(GameState::CharacterSelect)),
+            )
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                spawn_game_over_screen.after(record_high_score),
             )
             .add_systems(
                 Update,
-                (handle_button_color, handle_menu_button_action)
-                    .run_if(in_state(GameState::MainMenu)),
+                (
+                    sync_upgrade_menu,
+                    (handle_upgrade_card_mouse, handle_upgrade_card_keys),
+                    highlight_selected_card,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::LevelUp)),
             )
             .add_systems(OnEnter(GameState::GameInit), spawn_debug_text)
             .add_systems(
-                FixedPostUpdate,
-                (update_debug_text.run_if(in_state(GameState::GameRun)),),
+                Update,
+                update_frame_time_graph.run_if(in_state(GameState::GameRun)),
             );
     }
 }
 
+/// Holds a [`FrameTimeBar`] for every frame in the [`FRAME_TIME_GRAPH_BARS`] long history.
 #[derive(Component)]
-#[require(TextSpan)]
-struct FpsText;
+struct FrameTimeGraph;
+
+/// Shows the frame time of the frame at its index, the last bar is the newest frame.
+#[derive(Component, Deref)]
+struct FrameTimeBar(usize);
 
 #[derive(Component)]
 #[require(TextSpan)]
-struct EnemyNumText;
+struct EnemyPosText;
 
 #[derive(Component)]
 #[require(TextSpan)]
-struct ScoreText;
+struct BulletPosText;
 
 #[derive(Component)]
-#[require(TextSpan)]
-struct PlayerHpText;
+struct OnGameScreen;
 
 #[derive(Component)]
-#[require(TextSpan)]
-struct EnemyPosText;
+struct OnLoadingScreen;
 
 #[derive(Component)]
-#[require(TextSpan)]
-struct BulletPosText;
+struct LoadingBarFill;
 
 #[derive(Component)]
-struct OnGameScreen;
+struct OnUpgradeMenu;
+
+/// Dragging it sets the volume of its channel.
+#[derive(Component, Deref)]
+#[require(Interaction, RelativeCursorPosition)]
+struct VolumeSlider(AudioChannel);
+
+#[derive(Component)]
+struct VolumeSliderFill;
+
+#[derive(Component, Deref)]
+struct VolumeText(AudioChannel);
+
+/// Dragging it picks a new UI scale. The scale is only applied once the slider is released,
+/// otherwise the slider would resize under the cursor.
+#[derive(Component, Default)]
+#[require(Interaction, RelativeCursorPosition)]
+struct UiScaleSlider {
+    pending: Option<f32>,
+}
+
+#[derive(Component)]
+struct UiScaleSliderFill;
+
+#[derive(Component)]
+struct UiScaleText;
+
+/// Pressing it starts the run as its character.
+#[derive(Component, Deref)]
+struct CharacterCard(Character);
+
+/// Pressing it starts rebinding its action.
+#[derive(Component, Deref)]
+struct ControlsButton(Action);
+
+#[derive(Component)]
+struct ControlsStatusText;
+
+/// The action waiting for a new binding and the outcome of the last attempt.
+#[derive(Resource, Default)]
+struct Rebinding {
+    action: Option<Action>,
+    status: String,
+}
+
+/// Each press cycles the setting to its next value.
+#[derive(Component, Debug, Clone, Copy)]
+enum SettingsButton {
+    WindowMode,
+    Monitor,
+    VSync,
+    Language,
+    CameraFollow,
+    LookAhead,
+    ScreenShake,
+    PixelPerfect,
+}
+
+impl SettingsButton {
+    const ALL: [SettingsButton; 8] = [
+        SettingsButton::WindowMode,
+        SettingsButton::Monitor,
+        SettingsButton::VSync,
+        SettingsButton::Language,
+        SettingsButton::CameraFollow,
+        SettingsButton::LookAhead,
+        SettingsButton::ScreenShake,
+        SettingsButton::PixelPerfect,
+    ];
+
+    fn label(
+        &self,
+        settings: &VideoSettings,
+        camera: &CameraConfig,
+        localizer: &Localizer,
+    ) -> String {
+        match self {
+            SettingsButton::WindowMode => localizer.trf(
+                "settings.window",
+                &[&localizer.tr(settings.window_mode.locale_key())],
+            ),
+            SettingsButton::Monitor => {
+                localizer.trf("settings.monitor", &[&(settings.monitor + 1)])
+            }
+            SettingsButton::VSync => localizer.trf(
+                "settings.vsync",
+                &[&localizer.tr(on_off_key(settings.vsync))],
+            ),
+            SettingsButton::Language => {
+                localizer.trf("settings.language", &[&settings.language.name()])
+            }
+            SettingsButton::CameraFollow => localizer.trf(
+                "settings.camera_follow",
+                &[&localizer.tr(camera.follow_mode.locale_key())],
+            ),
+            SettingsButton::LookAhead => localizer.trf(
+                "settings.look_ahead",
+                &[&(camera.look_ahead_weight * 100.).round()],
+            ),
+            SettingsButton::ScreenShake => localizer.trf(
+                "settings.screen_shake",
+                &[&(camera.shake_scale * 100.).round()],
+            ),
+            SettingsButton::PixelPerfect => localizer.trf(
+                "settings.pixel_perfect",
+                &[&localizer.tr(on_off_key(camera.pixel_perfect))],
+            ),
+        }
+    }
+}
+
+fn on_off_key(on: bool) -> &'static str {
+    if on {
+        "settings.on"
+    } else {
+        "settings.off"
+    }
+}
+
+/// Shows the text near the cursor after the node has been hovered for [`TOOLTIP_DELAY_SECS`].
+#[derive(Component, Debug, Deref)]
+#[require(Interaction)]
+pub struct Tooltip(pub String);
 
 #[derive(Component)]
-struct OnMenuScreen;
+struct TooltipPopup;
+
+/// An upgrade card on offer, holds its position in the [`UpgradeChoices`].
+#[derive(Component)]
+struct UpgradeCard {
+    upgrade: Upgrade,
+    index: usize,
+}
+
+/// The upgrade card that gets picked with the keyboard, follows the mouse.
+#[derive(Resource, Deref, DerefMut)]
+struct SelectedCard(usize);
 
 #[derive(Component)]
 enum MenuButtonAction {
     Play,
+    Continue,
+    Resume,
+    Settings,
+    Controls,
+    Retry,
+    MainMenu,
+    /// Leaves the settings for the menu they were opened from.
+    Back,
     Exit,
 }
 
@@ -74,8 +320,95 @@ const TITLE_BG_CD: Color = Color::srgb(0.32, 0.23, 0.42);
 const PRESSED_BUTTON_BG: Color = Color::srgb(0.32, 0.23, 0.72);
 const HOVERED_BUTTON_BG: Color = Color::srgb(0.05, 0.23, 0.62);
 const BUTTON_BG: Color = Color::srgb(0.02, 0.23, 0.42);
+const TOOLTIP_BG: Color = Color::srgba(0.05, 0.05, 0.05, 0.9);
+const TOOLTIP_BORDER: Color = Color::srgb(0.674, 0.229, 0.732);
+/// How long a node has to be hovered before its [`Tooltip`] shows up.
+const TOOLTIP_DELAY_SECS: f32 = 0.5;
+/// Distance of the tooltip from the cursor.
+const TOOLTIP_OFFSET: Vec2 = Vec2::new(16., 16.);
+/// A tile of the ground is a meter.
+const PIXELS_PER_METER: f32 = 16.;
+const PAUSE_OVERLAY_BG: Color = Color::srgba(0., 0., 0., 0.6);
+const HIGH_SCORES_BG: Color = Color::srgba(0.05, 0.05, 0.1, 0.8);
+const SLIDER_SIZE: Vec2 = Vec2::new(300., 20.);
+const SLIDER_BG: Color = Color::srgb(0.1, 0.1, 0.15);
+const SLIDER_FILL: Color = Color::srgb(0.05, 0.23, 0.62);
+const CARD_WIDTH: f32 = 240.;
+const CARD_ICON_SIZE: f32 = 64.;
+const CARD_BORDER: Color = Color::srgb(0.674, 0.229, 0.732);
+
+/// Shown during the [`GameState::AssetLoad`] and whenever a state waits for the assets it needs,
+/// see [`StateAssets`](crate::resources::preload::StateAssets).
+fn sync_loading_screen(
+    mut commands: Commands,
+    screen_query: Query<Entity, With<OnLoadingScreen>>,
+    progress: Res<AssetLoadProgress>,
+    state: Res<State<GameState>>,
+) {
+    let loading = **state == GameState::AssetLoad || progress.fraction() < 1.;
+    match (loading, screen_query.get_single()) {
+        (true, Err(_)) => spawn_loading_screen(&mut commands, progress.fraction()),
+        (false, Ok(screen)) => commands.entity(screen).despawn_recursive(),
+        _ => {}
+    }
+}
+
+/// Only a bar, the strings of the locale might not be loaded yet. It covers whatever is behind
+/// it.
+fn spawn_loading_screen(commands: &mut Commands, fraction: f32) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(BG_COLOR),
+            GlobalZIndex(i32::MAX),
+            OnLoadingScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(SLIDER_SIZE.x),
+                        height: Val::Px(SLIDER_SIZE.y),
+                        ..default()
+                    },
+                    BackgroundColor(SLIDER_BG),
+                ))
+                .with_child((
+                    Node {
+                        width: Val::Percent(fraction * 100.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    BackgroundColor(SLIDER_FILL),
+                    LoadingBarFill,
+                ));
+        });
+}
+
+fn update_loading_bar(
+    mut fill_query: Query<&mut Node, With<LoadingBarFill>>,
+    progress: Res<AssetLoadProgress>,
+) {
+    if !progress.is_changed() {
+        return;
+    }
+    for mut fill in fill_query.iter_mut() {
+        fill.width = Val::Percent(progress.fraction() * 100.);
+    }
+}
 
-fn spawn_main_menu(mut commands: Commands) {
+fn spawn_main_menu(
+    mut commands: Commands,
+    high_scores: Res<HighScores>,
+    saved_run: Res<SavedRun>,
+    localizer: Localizer,
+) {
     let button_node = Node {
         padding: UiRect::all(Val::Px(20.)),
         ..default()
@@ -95,70 +428,1218 @@ fn spawn_main_menu(mut commands: Commands) {
                 justify_content: JustifyContent::SpaceAround,
                 ..default()
             },
-            OnMenuScreen,
+            DespawnOnExit(GameState::MainMenu),
         ))
         .with_children(|parent| {
             parent
                 .spawn((BackgroundColor(TITLE_BG_CD), title_node))
                 .with_child((
-                    Text::new("MENU"),
+                    Localized("menu.title"),
                     TextFont::default().with_font_size(FONT_SIZE + 20.),
                     TextColor(Color::srgb(0.674, 0.229, 0.732)),
                 ));
 
             parent
-                .spawn((button_node.clone(), Button, MenuButtonAction::Play))
+                .spawn((
+                    button_node.clone(),
+                    Button,
+                    MenuButtonAction::Play,
+                    Tooltip(localizer.tr("tooltip.play").to_string()),
+                ))
+                .with_child((
+                    Localized("menu.play"),
+                    TextFont::default().with_font_size(FONT_SIZE),
+                ));
+
+            if saved_run.is_some() {
+                parent
+                    .spawn((
+                        button_node.clone(),
+                        Button,
+                        MenuButtonAction::Continue,
+                        Tooltip(localizer.tr("tooltip.continue").to_string()),
+                    ))
+                    .with_child((
+                        Localized("menu.continue"),
+                        TextFont::default().with_font_size(FONT_SIZE),
+                    ));
+            }
+
+            parent
+                .spawn((
+                    button_node.clone(),
+                    Button,
+                    MenuButtonAction::Settings,
+                    Tooltip(localizer.tr("tooltip.settings").to_string()),
+                ))
+                .with_child((
+                    Localized("menu.settings"),
+                    TextFont::default().with_font_size(FONT_SIZE),
+                ));
+
+            parent
+                .spawn((
+                    button_node.clone(),
+                    Button,
+                    MenuButtonAction::Controls,
+                    Tooltip(localizer.tr("tooltip.controls").to_string()),
+                ))
                 .with_child((
-                    Text::new("Play"),
+                    Localized("menu.controls"),
                     TextFont::default().with_font_size(FONT_SIZE),
                 ));
 
             parent
-                .spawn((button_node, Button, MenuButtonAction::Exit))
+                .spawn((
+                    button_node,
+                    Button,
+                    MenuButtonAction::Exit,
+                    Tooltip(localizer.tr("tooltip.exit").to_string()),
+                ))
                 .with_child((
-                    Text::new("Exit"),
+                    Localized("menu.exit"),
                     TextFont::default().with_font_size(FONT_SIZE),
                 ));
+
+            parent
+                .spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        right: Val::Px(40.),
+                        top: Val::Percent(25.),
+                        padding: UiRect::all(Val::Px(20.)),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(6.),
+                        ..default()
+                    },
+                    BackgroundColor(HIGH_SCORES_BG),
+                ))
+                .with_children(|panel| {
+                    panel.spawn((
+                        Localized("menu.high_scores"),
+                        TextFont::default().with_font_size(FONT_SIZE * 0.8),
+                        TextColor(Color::srgb(0.674, 0.229, 0.732)),
+                    ));
+                    if high_scores.is_empty() {
+                        panel.spawn((
+                            Localized("menu.no_high_scores"),
+                            TextFont::default().with_font_size(FONT_SIZE * 0.6),
+                        ));
+                    }
+                    for (rank, run) in high_scores.iter().enumerate() {
+                        panel.spawn((
+                            Text::new(high_score_line(rank, run, &localizer)),
+                            TextFont::default().with_font_size(FONT_SIZE * 0.6),
+                        ));
+                    }
+                });
         });
 }
 
-fn spawn_debug_text(mut commands: Commands) {
-    let fps_text = commands
+fn high_score_line(rank: usize, run: &HighScore, localizer: &Localizer) -> String {
+    let secs = run.time_survived_secs;
+    let time_survived = format!("{}:{:02}", secs / 60, secs % 60);
+    localizer.trf(
+        "menu.high_score",
+        &[&(rank + 1), &run.score, &time_survived, &run.character],
+    )
+}
+
+fn spawn_game_over_screen(
+    mut commands: Commands,
+    summary: Res<RunSummary>,
+    high_scores: Res<HighScores>,
+    last_run_rank: Res<LastRunRank>,
+    localizer: Localizer,
+) {
+    let stats = &summary.stats;
+    let breakdown = summary.breakdown;
+    let upgrades = summary
+        .upgrade_counts()
+        .into_iter()
+        .map(|(upgrade, count)| match count {
+            1 => localizer.tr(upgrade.locale_key()).to_string(),
+            _ => format!("{} x{count}", localizer.tr(upgrade.locale_key())),
+        })
+        .collect::<Vec<_>>();
+    let upgrades = if upgrades.is_empty() {
+        localizer.tr("game_over.no_upgrades").to_string()
+    } else {
+        upgrades.join(", ")
+    };
+    let secs = stats.time_survived_secs as u32;
+    let time_survived = format!("{}:{:02}", secs / 60, secs % 60);
+    let [grunts, runners, brutes] = stats.kills_by_kind;
+    let stat_lines = [
+        localizer.trf("game_over.score", &[&summary.score]),
+        localizer.trf(
+            "game_over.score_breakdown",
+            &[&breakdown.kills, &breakdown.survival, &breakdown.other],
+        ),
+        localizer.trf("game_over.time", &[&time_survived]),
+        localizer.trf("game_over.kills", &[&stats.kills]),
+        localizer.trf(
+            "game_over.kills_by_kind",
+            &[&grunts, &runners, &brutes, &stats.bosses_killed],
+        ),
+        localizer.trf("game_over.damage", &[&stats.damage_dealt]),
+        localizer.trf("game_over.damage_taken", &[&stats.damage_taken]),
+        localizer.trf(
+            "game_over.accuracy",
+            &[&(stats.accuracy() * 100.).round(), &stats.shots_fired],
+        ),
+        localizer.trf(
+            "game_over.distance",
+            &[&(stats.distance_traveled / PIXELS_PER_METER).round()],
+        ),
+        localizer.trf("game_over.upgrades", &[&upgrades]),
+        localizer.trf("game_over.seed", &[&summary.seed]),
+    ];
+
+    let button_node = Node {
+        padding: UiRect::all(Val::Px(20.)),
+        ..default()
+    };
+
+    commands
         .spawn((
-            Text::new("FPS: "),
-            TextFont::default().with_font_size(FONT_SIZE),
-            Node::default(),
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(20.),
+                ..default()
+            },
+            DespawnOnExit(GameState::GameOver),
         ))
-        .with_child((TextFont::default().with_font_size(FONT_SIZE), FpsText))
-        .id();
+        .with_children(|parent| {
+            parent
+                .spawn((BackgroundColor(TITLE_BG_CD), button_node.clone()))
+                .with_child((
+                    Localized("game_over.title"),
+                    TextFont::default().with_font_size(FONT_SIZE + 20.),
+                    TextColor(Color::srgb(0.674, 0.229, 0.732)),
+                ));
+
+            for line in stat_lines {
+                parent.spawn((
+                    Text::new(line),
+                    TextFont::default().with_font_size(FONT_SIZE),
+                ));
+            }
+
+            parent
+                .spawn((
+                    Node {
+                        padding: UiRect::all(Val::Px(12.)),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(6.),
+                        ..default()
+                    },
+                    BackgroundColor(HIGH_SCORES_BG),
+                ))
+                .with_children(|panel| {
+                    let title = match **last_run_rank {
+                        Some(rank) => localizer.trf("game_over.new_high_score", &[&(rank + 1)]),
+                        None => localizer.tr("menu.high_scores").to_string(),
+                    };
+                    panel.spawn((
+                        Text::new(title),
+                        TextFont::default().with_font_size(FONT_SIZE * 0.8),
+                        TextColor(Color::srgb(0.674, 0.229, 0.732)),
+                    ));
+                    let top_runs = high_scores.iter().enumerate().take(GAME_OVER_HIGH_SCORES);
+                    for (rank, run) in top_runs {
+                        // the run that just ended stands out
+                        let color = if **last_run_rank == Some(rank) {
+                            TextColor(Color::srgb(0.95, 0.8, 0.2))
+                        } else {
+                            TextColor::default()
+                        };
+                        panel.spawn((
+                            Text::new(high_score_line(rank, run, &localizer)),
+                            TextFont::default().with_font_size(FONT_SIZE * 0.6),
+                            color,
+                        ));
+                    }
+                });
+
+            parent
+                .spawn((button_node.clone(), Button, MenuButtonAction::Retry))
+                .with_child((
+                    Localized("menu.retry"),
+                    TextFont::default().with_font_size(FONT_SIZE),
+                ));
+
+            parent
+                .spawn((button_node, Button, MenuButtonAction::MainMenu))
+                .with_child((
+                    Localized("menu.main_menu"),
+                    TextFont::default().with_font_size(FONT_SIZE),
+                ));
+        });
+}
+
+/// Pauses the run or resumes it, from the keyboard or any gamepad. The settings opened from the
+/// pause menu go back to it instead.
+fn toggle_pause(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_pause_screen: ResMut<NextState<PauseScreen>>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    gamepad_query: Query<&Gamepad>,
+    pause_screen: Option<Res<State<PauseScreen>>>,
+) {
+    let pressed = kbd_input.just_pressed(PAUSE_KEY)
+        || gamepad_query
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(PAUSE_GAMEPAD_BUTTON));
+    if !pressed {
+        return;
+    }
+    match pause_screen.map(|screen| **screen) {
+        Some(PauseScreen::Settings) => next_pause_screen.set(PauseScreen::Menu),
+        Some(PauseScreen::Menu) => next_state.set(GameState::GameRun),
+        None => next_state.set(GameState::Paused),
+    }
+}
 
-    let enemies_text = commands
+/// Dims the paused run behind it.
+fn spawn_pause_menu(mut commands: Commands, localizer: Localizer) {
+    let button_node = Node {
+        padding: UiRect::all(Val::Px(20.)),
+        ..default()
+    };
+
+    commands
         .spawn((
-            Text::new("ENEMIES: "),
-            TextFont::default().with_font_size(FONT_SIZE),
-            Node::default(),
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(20.),
+                ..default()
+            },
+            BackgroundColor(PAUSE_OVERLAY_BG),
+            DespawnOnExit(PauseScreen::Menu),
         ))
-        .with_child((TextFont::default().with_font_size(FONT_SIZE), EnemyNumText))
-        .id();
+        .with_children(|parent| {
+            parent
+                .spawn((BackgroundColor(TITLE_BG_CD), button_node.clone()))
+                .with_child((
+                    Localized("pause.title"),
+                    TextFont::default().with_font_size(FONT_SIZE + 20.),
+                    TextColor(Color::srgb(0.674, 0.229, 0.732)),
+                ));
 
-    let player_hp_text = commands
+            for (action, label, tooltip) in [
+                (MenuButtonAction::Resume, "menu.resume", "tooltip.resume"),
+                (
+                    MenuButtonAction::Settings,
+                    "menu.settings",
+                    "tooltip.settings",
+                ),
+                (
+                    MenuButtonAction::MainMenu,
+                    "menu.main_menu",
+                    "tooltip.quit_run",
+                ),
+                (MenuButtonAction::Exit, "menu.exit", "tooltip.exit"),
+            ] {
+                parent
+                    .spawn((
+                        button_node.clone(),
+                        Button,
+                        action,
+                        Tooltip(localizer.tr(tooltip).to_string()),
+                    ))
+                    .with_child((
+                        Localized(label),
+                        TextFont::default().with_font_size(FONT_SIZE),
+                    ));
+            }
+        });
+}
+
+/// Dims the paused run behind it when it's opened from the pause menu.
+fn spawn_settings_menu(
+    mut commands: Commands,
+    settings: Res<UserSettings>,
+    camera: Res<CameraConfig>,
+    pause_screen: Option<Res<State<PauseScreen>>>,
+    localizer: Localizer,
+) {
+    let background = match pause_screen {
+        Some(_) => PAUSE_OVERLAY_BG,
+        None => Color::NONE,
+    };
+
+    let button_node = Node {
+        padding: UiRect::axes(Val::Px(20.), Val::Px(10.)),
+        ..default()
+    };
+
+    commands
         .spawn((
-            Text::new("PLAYER_HP: "),
-            TextFont::default().with_font_size(FONT_SIZE),
-            Node::default(),
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.),
+                ..default()
+            },
+            BackgroundColor(background),
+            DespawnOnExit(SettingsOpen),
         ))
-        .with_child((TextFont::default().with_font_size(FONT_SIZE), PlayerHpText))
-        .id();
+        .with_children(|parent| {
+            parent
+                .spawn((BackgroundColor(TITLE_BG_CD), button_node.clone()))
+                .with_child((
+                    Localized("settings.title"),
+                    TextFont::default().with_font_size(FONT_SIZE + 20.),
+                    TextColor(Color::srgb(0.674, 0.229, 0.732)),
+                ));
+
+            for button in SettingsButton::ALL {
+                parent
+                    .spawn((button_node.clone(), Button, button))
+                    .with_child((
+                        Text::new(button.label(&settings.video, &camera, &localizer)),
+                        TextFont::default().with_font_size(FONT_SIZE),
+                    ));
+            }
+
+            parent
+                .spawn(Node {
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(20.),
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn((
+                        Text::new(ui_scale_label(settings.video.ui_scale, &localizer)),
+                        TextFont::default().with_font_size(FONT_SIZE),
+                        Node {
+                            width: Val::Px(220.),
+                            ..default()
+                        },
+                        UiScaleText,
+                    ));
+                    row.spawn((
+                        Node {
+                            width: Val::Px(SLIDER_SIZE.x),
+                            height: Val::Px(SLIDER_SIZE.y),
+                            ..default()
+                        },
+                        BackgroundColor(SLIDER_BG),
+                        UiScaleSlider::default(),
+                    ))
+                    .with_child((
+                        Node {
+                            width: Val::Percent(ui_scale_fraction(settings.video.ui_scale) * 100.),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        BackgroundColor(SLIDER_FILL),
+                        UiScaleSliderFill,
+                    ));
+                });
 
-    let score_text = commands
+            for channel in AudioChannel::ALL {
+                let volume = settings.audio.volume(channel);
+                parent
+                    .spawn(Node {
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(20.),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn((
+                            Text::new(volume_label(channel, volume, &localizer)),
+                            TextFont::default().with_font_size(FONT_SIZE),
+                            Node {
+                                width: Val::Px(220.),
+                                ..default()
+                            },
+                            VolumeText(channel),
+                        ));
+                        row.spawn((
+                            Node {
+                                width: Val::Px(SLIDER_SIZE.x),
+                                height: Val::Px(SLIDER_SIZE.y),
+                                ..default()
+                            },
+                            BackgroundColor(SLIDER_BG),
+                            VolumeSlider(channel),
+                        ))
+                        .with_child((
+                            Node {
+                                width: Val::Percent(volume * 100.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            BackgroundColor(SLIDER_FILL),
+                            VolumeSliderFill,
+                        ));
+                    });
+            }
+
+            parent
+                .spawn((button_node, Button, MenuButtonAction::Back))
+                .with_child((
+                    Localized("menu.back"),
+                    TextFont::default().with_font_size(FONT_SIZE),
+                ));
+        });
+}
+
+fn handle_settings_button_action(
+    interaction_query: Query<(&Interaction, &SettingsButton), (Changed<Interaction>, With<Button>)>,
+    monitor_query: Query<&Monitor>,
+    mut settings: ResMut<UserSettings>,
+    mut camera: ResMut<CameraConfig>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let settings = &mut settings.video;
+        match button {
+            SettingsButton::WindowMode => settings.window_mode = settings.window_mode.next(),
+            SettingsButton::Monitor => {
+                settings.monitor = (settings.monitor + 1) % monitor_query.iter().len().max(1)
+            }
+            SettingsButton::VSync => settings.vsync = !settings.vsync,
+            SettingsButton::Language => settings.language = settings.language.next(),
+            SettingsButton::CameraFollow => camera.follow_mode = camera.follow_mode.next(),
+            SettingsButton::LookAhead => {
+                camera.look_ahead_weight =
+                    next_step(&CAM_LOOK_AHEAD_STEPS, camera.look_ahead_weight)
+            }
+            SettingsButton::ScreenShake => {
+                camera.shake_scale = next_step(&CAM_SHAKE_STEPS, camera.shake_scale)
+            }
+            SettingsButton::PixelPerfect => camera.pixel_perfect = !camera.pixel_perfect,
+        }
+    }
+}
+
+fn ui_scale_label(ui_scale: f32, localizer: &Localizer) -> String {
+    localizer.trf("settings.ui_scale", &[&(ui_scale * 100.).round()])
+}
+
+/// Where the UI scale is on its slider, from 0 to 1.
+fn ui_scale_fraction(ui_scale: f32) -> f32 {
+    (ui_scale - UI_SCALE_MIN) / (UI_SCALE_MAX - UI_SCALE_MIN)
+}
+
+fn drag_ui_scale_slider(
+    mut slider_query: Query<(&Interaction, &RelativeCursorPosition, &mut UiScaleSlider)>,
+    mut settings: ResMut<UserSettings>,
+) {
+    for (interaction, cursor_pos, mut slider) in slider_query.iter_mut() {
+        if *interaction != Interaction::Pressed {
+            // released, apply the scale
+            if let Some(ui_scale) = slider.pending.take() {
+                if settings.video.ui_scale != ui_scale {
+                    settings.video.set_ui_scale(ui_scale);
+                }
+            }
+            continue;
+        }
+        let Some(cursor_pos) = cursor_pos.normalized else {
+            continue;
+        };
+        let fraction = cursor_pos.x.clamp(0., 1.);
+        let ui_scale = ui_scale_step(UI_SCALE_MIN + fraction * (UI_SCALE_MAX - UI_SCALE_MIN));
+        if slider.pending != Some(ui_scale) {
+            slider.pending = Some(ui_scale);
+        }
+    }
+}
+
+fn update_ui_scale_slider(
+    settings: Res<UserSettings>,
+    slider_query: Query<(Ref<UiScaleSlider>, &Children)>,
+    mut fill_query: Query<&mut Node, With<UiScaleSliderFill>>,
+    mut text_query: Query<&mut Text, With<UiScaleText>>,
+    localizer: Localizer,
+) {
+    for (slider, children) in slider_query.iter() {
+        if !slider.is_changed() && !localizer.is_changed() {
+            continue;
+        }
+        let ui_scale = slider.pending.unwrap_or(settings.video.ui_scale);
+        let mut fills = fill_query.iter_many_mut(children);
+        while let Some(mut fill) = fills.fetch_next() {
+            fill.width = Val::Percent(ui_scale_fraction(ui_scale) * 100.);
+        }
+        for mut text in text_query.iter_mut() {
+            **text = ui_scale_label(ui_scale, &localizer);
+        }
+    }
+}
+
+fn volume_label(channel: AudioChannel, volume: f32, localizer: &Localizer) -> String {
+    let percent = (volume * 100.).round();
+    localizer.trf(
+        "settings.volume",
+        &[&localizer.tr(channel.locale_key()), &percent],
+    )
+}
+
+fn drag_volume_sliders(
+    slider_query: Query<(&Interaction, &RelativeCursorPosition, &VolumeSlider)>,
+    mut settings: ResMut<UserSettings>,
+) {
+    for (interaction, cursor_pos, slider) in slider_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(cursor_pos) = cursor_pos.normalized else {
+            continue;
+        };
+        let volume = cursor_pos.x.clamp(0., 1.);
+        // avoid triggering change detection while holding still
+        if settings.audio.volume(**slider) != volume {
+            settings.audio.set_volume(**slider, volume);
+        }
+    }
+}
+
+fn update_volume_sliders(
+    settings: Res<UserSettings>,
+    slider_query: Query<(&VolumeSlider, &Children)>,
+    mut fill_query: Query<&mut Node, With<VolumeSliderFill>>,
+    mut text_query: Query<(&mut Text, &VolumeText)>,
+    localizer: Localizer,
+) {
+    if !settings.is_changed() && !localizer.is_changed() {
+        return;
+    }
+    for (slider, children) in slider_query.iter() {
+        let volume = settings.audio.volume(**slider);
+        let mut fills = fill_query.iter_many_mut(children);
+        while let Some(mut fill) = fills.fetch_next() {
+            fill.width = Val::Percent(volume * 100.);
+        }
+    }
+    for (mut text, channel) in text_query.iter_mut() {
+        **text = volume_label(**channel, settings.audio.volume(**channel), &localizer);
+    }
+}
+
+fn update_settings_labels(
+    settings: Res<UserSettings>,
+    camera: Res<CameraConfig>,
+    button_query: Query<(&SettingsButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+    localizer: Localizer,
+) {
+    if !localizer.is_changed() && !camera.is_changed() {
+        return;
+    }
+    for (button, children) in button_query.iter() {
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            **text = button.label(&settings.video, &camera, &localizer);
+        }
+    }
+}
+
+fn spawn_controls_menu(
+    mut commands: Commands,
+    settings: Res<UserSettings>,
+    mut rebinding: ResMut<Rebinding>,
+    localizer: Localizer,
+) {
+    *rebinding = Rebinding::default();
+    let button_node = Node {
+        padding: UiRect::axes(Val::Px(20.), Val::Px(6.)),
+        ..default()
+    };
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(8.),
+                ..default()
+            },
+            DespawnOnExit(GameState::Controls),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((BackgroundColor(TITLE_BG_CD), button_node.clone()))
+                .with_child((
+                    Localized("controls.title"),
+                    TextFont::default().with_font_size(FONT_SIZE + 20.),
+                    TextColor(Color::srgb(0.674, 0.229, 0.732)),
+                ));
+
+            for action in Action::ALL {
+                parent
+                    .spawn((button_node.clone(), Button, ControlsButton(action)))
+                    .with_child((
+                        Text::new(controls_label(action, &settings.controls, None, &localizer)),
+                        TextFont::default().with_font_size(FONT_SIZE * 0.7),
+                    ));
+            }
+
+            parent.spawn((
+                Text::default(),
+                TextFont::default().with_font_size(FONT_SIZE * 0.7),
+                TextColor(Color::srgb(1.0, 0.4, 0.4)),
+                ControlsStatusText,
+            ));
+
+            parent
+                .spawn((button_node, Button, MenuButtonAction::MainMenu))
+                .with_child((
+                    Localized("menu.back"),
+                    TextFont::default().with_font_size(FONT_SIZE),
+                ));
+        });
+}
+
+fn controls_label(
+    action: Action,
+    input_map: &InputMap,
+    rebinding: Option<Action>,
+    localizer: &Localizer,
+) -> String {
+    if rebinding == Some(action) {
+        return localizer.trf("controls.waiting", &[&localizer.tr(action.locale_key())]);
+    }
+    let bindings = input_map
+        .bindings(action)
+        .iter()
+        .map(Binding::name)
+        .collect::<Vec<_>>();
+    format!(
+        "{}: {}",
+        localizer.tr(action.locale_key()),
+        bindings.join(", ")
+    )
+}
+
+/// Binds the first key or mouse button pressed to the action that's being rebound.
+/// Bindings already used by another action are refused.
+fn capture_rebinding(
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<UserSettings>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    localizer: Localizer,
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+    if kbd_input.just_pressed(KeyCode::Escape) {
+        *rebinding = Rebinding::default();
+        return;
+    }
+    let Some(binding) = kbd_input
+        .get_just_pressed()
+        .map(|&key| Binding::Key(key))
+        .chain(
+            mouse_input
+                .get_just_pressed()
+                .map(|&btn| Binding::Mouse(btn)),
+        )
+        .next()
+    else {
+        return;
+    };
+
+    rebinding.action = None;
+    rebinding.status = if !binding.is_bindable() {
+        localizer.trf("controls.unbindable", &[&binding.name()])
+    } else if let Some(other) = settings.controls.conflict(action, binding) {
+        localizer.trf(
+            "controls.conflict",
+            &[&binding.name(), &localizer.tr(other.locale_key())],
+        )
+    } else {
+        settings.controls.rebind(action, binding);
+        String::new()
+    };
+}
+
+fn handle_controls_button_action(
+    interaction_query: Query<(&Interaction, &ControlsButton), (Changed<Interaction>, With<Button>)>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            rebinding.action = Some(**button);
+            rebinding.status.clear();
+        }
+    }
+}
+
+fn update_controls_labels(
+    settings: Res<UserSettings>,
+    rebinding: Res<Rebinding>,
+    button_query: Query<(&ControlsButton, &Children)>,
+    mut text_query: Query<&mut Text, Without<ControlsStatusText>>,
+    mut status_query: Query<&mut Text, With<ControlsStatusText>>,
+    localizer: Localizer,
+) {
+    if !settings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (button, children) in button_query.iter() {
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            **text = controls_label(**button, &settings.controls, rebinding.action, &localizer);
+        }
+    }
+    for mut status in status_query.iter_mut() {
+        **status = rebinding.status.clone();
+    }
+}
+
+fn spawn_character_select(
+    mut commands: Commands,
+    text_atlases: Res<GlobTextAtlases>,
+    localizer: Localizer,
+) {
+    let layout = text_atlases.player().layout;
+    let image = text_atlases.player().image;
+    let portrait_index = text_atlases.region(AtlasKind::Player, "idle");
+
+    let button_node = Node {
+        padding: UiRect::all(Val::Px(20.)),
+        ..default()
+    };
+
+    commands
         .spawn((
-            Text::new("SCORE: "),
-            TextFont::default().with_font_size(FONT_SIZE),
-            Node::default(),
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(20.),
+                ..default()
+            },
+            DespawnOnExit(GameState::CharacterSelect),
         ))
-        .with_child((TextFont::default().with_font_size(FONT_SIZE), ScoreText))
+        .with_children(|parent| {
+            parent
+                .spawn((BackgroundColor(TITLE_BG_CD), button_node.clone()))
+                .with_child((
+                    Localized("character_select.title"),
+                    TextFont::default().with_font_size(FONT_SIZE + 20.),
+                    TextColor(Color::srgb(0.674, 0.229, 0.732)),
+                ));
+
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(20.),
+                    ..default()
+                })
+                .with_children(|row| {
+                    for character in Character::ALL {
+                        let stats = character.stats();
+                        let base_speed = PlayerStats::default().move_speed;
+                        let stat_lines = [
+                            localizer.trf("character_select.health", &[&stats.max_health]),
+                            localizer.trf(
+                                "character_select.speed",
+                                &[&(stats.stats.move_speed / base_speed * 100.).round()],
+                            ),
+                            localizer.trf(
+                                "character_select.damage",
+                                &[&(stats.stats.damage_mult * 100.).round()],
+                            ),
+                            localizer.trf(
+                                "character_select.weapon",
+                                &[&localizer.tr(stats.weapon.locale_key())],
+                            ),
+                        ];
+                        let mut portrait = ImageNode::from_atlas_image(
+                            image.clone(),
+                            TextureAtlas {
+                                layout: layout.clone(),
+                                index: portrait_index,
+                            },
+                        );
+                        portrait.color = stats.tint;
+
+                        row.spawn((
+                            Node {
+                                width: Val::Px(CARD_WIDTH),
+                                padding: UiRect::all(Val::Px(20.)),
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                row_gap: Val::Px(10.),
+                                ..default()
+                            },
+                            Button,
+                            CharacterCard(character),
+                        ))
+                        .with_children(|card| {
+                            // the player sprites are twice as tall as they are wide
+                            card.spawn((
+                                portrait,
+                                Node {
+                                    width: Val::Px(CARD_ICON_SIZE),
+                                    height: Val::Px(CARD_ICON_SIZE * 2.),
+                                    ..default()
+                                },
+                            ));
+                            card.spawn((
+                                Text::new(localizer.tr(character.locale_key())),
+                                TextFont::default().with_font_size(FONT_SIZE),
+                            ));
+                            for line in stat_lines {
+                                card.spawn((
+                                    Text::new(line),
+                                    TextFont::default().with_font_size(FONT_SIZE * 0.6),
+                                ));
+                            }
+                        });
+                    }
+                });
+
+            parent
+                .spawn((button_node, Button, MenuButtonAction::MainMenu))
+                .with_child((
+                    Localized("menu.back"),
+                    TextFont::default().with_font_size(FONT_SIZE),
+                ));
+        });
+}
+
+fn handle_character_card(
+    interaction_query: Query<(&Interaction, &CharacterCard), (Changed<Interaction>, With<Button>)>,
+    mut selected: ResMut<SelectedCharacter>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, card) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            **selected = **card;
+            game_state.set(GameState::GameInit);
+        }
+    }
+}
+
+/// Rebuilds the upgrade cards every time new upgrades are offered.
+fn sync_upgrade_menu(
+    mut commands: Commands,
+    choices: Res<UpgradeChoices>,
+    mut selected: ResMut<SelectedCard>,
+    menu_query: Query<Entity, With<OnUpgradeMenu>>,
+    pending: Res<PendingLevelUps>,
+    player_query: Query<(&PlayerStats, &Health, &OrbitalWeapon, &Inventory)>,
+    text_atlases: Res<GlobTextAtlases>,
+    localizer: Localizer,
+) {
+    if !choices.is_changed() {
+        return;
+    }
+    // the choices are always for the first pending player
+    let player = pending
+        .first()
+        .and_then(|&player_ent| player_query.get(player_ent).ok());
+    for ent in menu_query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+    **selected = 0;
+
+    let layout = text_atlases.common().layout;
+    let image = text_atlases.common().image;
+
+    let button_node = Node {
+        padding: UiRect::all(Val::Px(20.)),
+        ..default()
+    };
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(20.),
+                ..default()
+            },
+            OnUpgradeMenu,
+            DespawnOnExit(GameState::LevelUp),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((BackgroundColor(TITLE_BG_CD), button_node.clone()))
+                .with_child((
+                    Localized("level_up.title"),
+                    TextFont::default().with_font_size(FONT_SIZE + 20.),
+                    TextColor(Color::srgb(0.674, 0.229, 0.732)),
+                ));
+
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(20.),
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (index, &upgrade) in choices.iter().enumerate() {
+                        let (icon_index, icon_color) = upgrade.icon();
+                        let mut icon = ImageNode::from_atlas_image(
+                            image.clone(),
+                            TextureAtlas {
+                                layout: layout.clone(),
+                                index: icon_index,
+                            },
+                        );
+                        icon.color = icon_color;
+
+                        let mut card = row.spawn((
+                            Node {
+                                width: Val::Px(CARD_WIDTH),
+                                padding: UiRect::all(Val::Px(20.)),
+                                border: UiRect::all(Val::Px(4.)),
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                row_gap: Val::Px(10.),
+                                ..default()
+                            },
+                            Button,
+                            BackgroundColor(BUTTON_BG),
+                            BorderColor(Color::NONE),
+                            UpgradeCard { upgrade, index },
+                        ));
+                        if let Some(player) = player {
+                            card.insert(upgrade_tooltip(upgrade, player, &localizer));
+                        }
+                        card.with_children(|card| {
+                            card.spawn((
+                                icon,
+                                Node {
+                                    width: Val::Px(CARD_ICON_SIZE),
+                                    height: Val::Px(CARD_ICON_SIZE),
+                                    ..default()
+                                },
+                            ));
+                            card.spawn((
+                                Text::new(localizer.tr(upgrade.locale_key())),
+                                TextFont::default().with_font_size(FONT_SIZE),
+                            ));
+                            card.spawn((
+                                Text::new(upgrade.description(&localizer)),
+                                TextFont::default().with_font_size(FONT_SIZE * 0.6),
+                                TextLayout::new_with_justify(JustifyText::Center),
+                            ));
+                        });
+                    }
+                });
+
+            parent.spawn((
+                Localized("level_up.hint"),
+                TextFont::default().with_font_size(FONT_SIZE * 0.6),
+            ));
+        });
+}
+
+/// Shows what the player currently has of the upgrade.
+fn upgrade_tooltip(
+    upgrade: Upgrade,
+    (stats, health, orbital, inventory): (&PlayerStats, &Health, &OrbitalWeapon, &Inventory),
+    localizer: &Localizer,
+) -> Tooltip {
+    let current = match upgrade {
+        Upgrade::Damage => format!("{:.0}%", stats.damage_mult * 100.),
+        Upgrade::FireRate => format!("{:.0}%", stats.fire_rate_mult * 100.),
+        Upgrade::MoveSpeed => format!(
+            "{:.0}%",
+            stats.move_speed / PlayerStats::default().move_speed * 100.
+        ),
+        Upgrade::MaxHealth => health.max.to_string(),
+        Upgrade::OrbitingBlade => orbital.count.to_string(),
+        Upgrade::Item(item) => inventory.count(item).to_string(),
+    };
+    Tooltip(localizer.trf("tooltip.upgrade_current", &[&current]))
+}
+
+/// Hovering over a card selects it, clicking it picks it.
+fn handle_upgrade_card_mouse(
+    interaction_query: Query<(&Interaction, &UpgradeCard), Changed<Interaction>>,
+    mut selected: ResMut<SelectedCard>,
+    mut upgrade_chosen_events: EventWriter<UpgradeChosen>,
+) {
+    for (interaction, card) in interaction_query.iter() {
+        match interaction {
+            Interaction::Pressed => {
+                upgrade_chosen_events.send(UpgradeChosen(card.upgrade));
+            }
+            Interaction::Hovered => **selected = card.index,
+            Interaction::None => (),
+        }
+    }
+}
+
+fn handle_upgrade_card_keys(
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    choices: Res<UpgradeChoices>,
+    mut selected: ResMut<SelectedCard>,
+    mut upgrade_chosen_events: EventWriter<UpgradeChosen>,
+) {
+    if choices.is_empty() {
+        return;
+    }
+    if kbd_input.any_just_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]) {
+        **selected = (**selected + choices.len() - 1) % choices.len();
+    }
+    if kbd_input.any_just_pressed([KeyCode::KeyD, KeyCode::ArrowRight]) {
+        **selected = (**selected + 1) % choices.len();
+    }
+
+    let digit_keys = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3];
+    let picked = if kbd_input.any_just_pressed([KeyCode::Enter, KeyCode::Space]) {
+        Some(**selected)
+    } else {
+        digit_keys
+            .iter()
+            .position(|&key| kbd_input.just_pressed(key))
+    };
+    if let Some(&upgrade) = picked.and_then(|idx| choices.get(idx)) {
+        upgrade_chosen_events.send(UpgradeChosen(upgrade));
+    }
+}
+
+fn highlight_selected_card(
+    selected: Res<SelectedCard>,
+    mut card_query: Query<(&UpgradeCard, &mut BackgroundColor, &mut BorderColor)>,
+) {
+    for (card, mut bg_color, mut border_color) in card_query.iter_mut() {
+        let is_selected = card.index == **selected;
+        *bg_color = if is_selected {
+            HOVERED_BUTTON_BG
+        } else {
+            BUTTON_BG
+        }
+        .into();
+        *border_color = if is_selected {
+            CARD_BORDER
+        } else {
+            Color::NONE
+        }
+        .into();
+    }
+}
+
+/// The FPS and the frame time graph, only shown with the [`GameConfig::debug_overlay`].
+fn spawn_frame_stats(commands: &mut Commands) -> [Entity; 2] {
+    let fps_text = spawn_hud_field(commands, "FPS: ", |diagnostics: Res<DiagnosticsStore>| {
+        diagnostics
+            .get(&FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|fps| fps.smoothed())
+            .map_or_else(String::new, |fps| format!("{fps:.2}"))
+    });
+
+    let frame_time_graph = commands
+        .spawn((
+            Node {
+                width: Val::Px(FRAME_TIME_GRAPH_SIZE.x),
+                height: Val::Px(FRAME_TIME_GRAPH_SIZE.y),
+                align_items: AlignItems::End,
+                ..default()
+            },
+            BackgroundColor(FRAME_TIME_GRAPH_BG),
+            FrameTimeGraph,
+        ))
+        .with_children(|parent| {
+            for idx in 0..FRAME_TIME_GRAPH_BARS {
+                parent.spawn((
+                    Node {
+                        width: Val::Percent(100. / FRAME_TIME_GRAPH_BARS as f32),
+                        height: Val::Percent(0.),
+                        ..default()
+                    },
+                    BackgroundColor(FRAME_TIME_OK),
+                    FrameTimeBar(idx),
+                ));
+            }
+        })
         .id();
 
+    [fps_text, frame_time_graph]
+}
+
+fn spawn_debug_text(mut commands: Commands, config: Res<GameConfig>) {
+    let mut fields = Vec::new();
+    if config.debug_overlay {
+        fields.extend(spawn_frame_stats(&mut commands));
+    }
+    let enemies_text = spawn_hud_field(&mut commands, "ENEMIES: ", |enemies: Res<EnemyNum>| {
+        enemies.to_string()
+    });
+    let shield_text = spawn_hud_field(
+        &mut commands,
+        "SHIELD: ",
+        |shield_query: Query<&Shield, With<Player>>| {
+            join_players(
+                shield_query
+                    .iter()
+                    .map(|shield| format!("{:.0} / {:.0}", shield.current, shield.max)),
+            )
+        },
+    );
+    let stamina_text = spawn_hud_field(
+        &mut commands,
+        "STAMINA: ",
+        |stamina_query: Query<&Stamina, With<Player>>| {
+            join_players(
+                stamina_query
+                    .iter()
+                    .map(|stamina| format!("{:.0} / {:.0}", stamina.current, stamina.max)),
+            )
+        },
+    );
+    let items_text = spawn_hud_field(
+        &mut commands,
+        "ITEMS: ",
+        |inventory_query: Query<&Inventory, With<Player>>, localizer: Localizer| {
+            join_players(inventory_query.iter().map(|inventory| {
+                inventory
+                    .iter()
+                    .map(|(item, count)| format!("{} x{count}", localizer.tr(item.locale_key())))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }))
+        },
+    );
+    let score_text = spawn_hud_field(&mut commands, "SCORE: ", |score: Res<Score>| {
+        score.to_string()
+    });
+    let combo_text = spawn_hud_field(&mut commands, "COMBO: ", |combo: Res<Combo>| {
+        if combo.kills == 0 {
+            return String::new();
+        }
+        format!("{} (x{})", combo.kills, combo.multiplier())
+    });
+    fields.extend([
+        enemies_text,
+        shield_text,
+        stamina_text,
+        items_text,
+        score_text,
+        combo_text,
+    ]);
+
     commands
         .spawn((
             Node {
@@ -170,50 +1651,127 @@ fn spawn_debug_text(mut commands: Commands) {
                 ..Default::default()
             },
             OnGameScreen,
+            RunScoped,
         ))
-        .add_children(&[fps_text, enemies_text, player_hp_text, score_text]);
-}
-
-fn update_debug_text(
-    mut set: ParamSet<(
-        Query<&mut TextSpan, With<FpsText>>,
-        Query<&mut TextSpan, With<EnemyNumText>>,
-        Query<&mut TextSpan, With<PlayerHpText>>,
-        Query<&mut TextSpan, With<ScoreText>>,
-    )>,
-    player_query: Query<&Health, (With<Player>, Changed<Health>)>,
-    num_of_enemies: Res<EnemyNum>,
-    score: Res<Score>,
+        .add_children(&fields);
+}
+
+/// Joins the values of every player.
+fn join_players(values: impl Iterator<Item = String>) -> String {
+    values.collect::<Vec<_>>().join(" | ")
+}
+
+/// Shows the last [`FRAME_TIME_GRAPH_BARS`] frame times, so the spikes are easy to spot.
+fn update_frame_time_graph(
+    mut bar_query: Query<(&FrameTimeBar, &mut Node, &mut BackgroundColor)>,
     diagnostics: Res<DiagnosticsStore>,
 ) {
-    let mut fps_span = set.p0();
-    let mut fps_span = fps_span.single_mut();
-    if let Some(fps) = diagnostics.get(&FrameTimeDiagnosticsPlugin::FPS) {
-        if let Some(fps) = fps.smoothed() {
-            **fps_span = format!("{fps:.2}");
-        }
+    let Some(frame_time) = diagnostics.get(&FrameTimeDiagnosticsPlugin::FRAME_TIME) else {
+        return;
+    };
+    let history = frame_time.values().copied().collect::<Vec<_>>();
+    // align the newest frame with the last bar
+    let offset = FRAME_TIME_GRAPH_BARS as isize - history.len() as isize;
+
+    for (bar, mut node, mut bg_color) in bar_query.iter_mut() {
+        let ms = usize::try_from(**bar as isize - offset)
+            .ok()
+            .and_then(|idx| history.get(idx))
+            .copied()
+            .unwrap_or(0.);
+        node.height = Val::Percent((ms / FRAME_TIME_GRAPH_MAX_MS).min(1.) as f32 * 100.);
+        bg_color.0 = if ms < 1000. / 60. {
+            FRAME_TIME_OK
+        } else if ms < 1000. / 30. {
+            FRAME_TIME_SLOW
+        } else {
+            FRAME_TIME_SPIKE
+        };
     }
+}
+
+fn spawn_tooltip_popup(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                max_width: Val::Px(300.),
+                padding: UiRect::all(Val::Px(8.)),
+                border: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            BackgroundColor(TOOLTIP_BG),
+            BorderColor(TOOLTIP_BORDER),
+            Visibility::Hidden,
+            // above the toasts
+            GlobalZIndex(i32::MAX),
+            TooltipPopup,
+        ))
+        .with_child((
+            Text::default(),
+            TextFont::default().with_font_size(FONT_SIZE * 0.5),
+        ));
+}
 
-    let mut enemy_num_span = set.p1();
-    let mut enemy_num_span = enemy_num_span.single_mut();
-    **enemy_num_span = num_of_enemies.to_string();
+/// Shows the [`Tooltip`] of the hovered node next to the cursor once it's been hovered for
+/// [`TOOLTIP_DELAY_SECS`].
+fn show_tooltips(
+    tooltip_query: Query<(Entity, &Interaction, &Tooltip, &InheritedVisibility)>,
+    mut popup_query: Query<(&mut Node, &mut Visibility, &Children), With<TooltipPopup>>,
+    mut text_query: Query<&mut Text>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    ui_scale: Res<UiScale>,
+    time: Res<Time<Real>>,
+    mut hovered: Local<Option<(Entity, f32)>>,
+) {
+    let Ok((mut node, mut visibility, children)) = popup_query.get_single_mut() else {
+        return;
+    };
+    let hovered_tooltip = tooltip_query.iter().find(|(_, interaction, _, inherited)| {
+        **interaction != Interaction::None && inherited.get()
+    });
+    let cursor = window_query
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position);
 
-    if let Ok(player_hp) = player_query.get_single() {
-        let mut hp_span = set.p2();
-        let mut hp_span = hp_span.single_mut();
-        **hp_span = format!("{} / {}", player_hp.current, player_hp.max);
+    let (Some((ent, _, tooltip, _)), Some(cursor)) = (hovered_tooltip, cursor) else {
+        *hovered = None;
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    };
+
+    // restart the delay when moving to another node
+    let hovered_secs = match *hovered {
+        Some((hovered_ent, secs)) if hovered_ent == ent => secs + time.delta_secs(),
+        _ => 0.,
+    };
+    *hovered = Some((ent, hovered_secs));
+    if hovered_secs < TOOLTIP_DELAY_SECS {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
     }
 
-    let mut score_span = set.p3();
-    let mut score_span = score_span.single_mut();
-    **score_span = score.to_string();
+    // the cursor is in logical pixels, the UI nodes are scaled
+    let pos = cursor / ui_scale.0 + TOOLTIP_OFFSET;
+    node.left = Val::Px(pos.x);
+    node.top = Val::Px(pos.y);
+    visibility.set_if_neq(Visibility::Inherited);
+    if let Some(mut text) = children
+        .first()
+        .and_then(|&child| text_query.get_mut(child).ok())
+    {
+        if text.0 != **tooltip {
+            text.0.clone_from(tooltip);
+        }
+    }
 }
 
 // This system handles changing all buttons color based on mouse interaction
 fn handle_button_color(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),
-        (Changed<Interaction>, With<Button>),
+        (Changed<Interaction>, With<Button>, Without<UpgradeCard>),
     >,
 ) {
     for (interaction, mut background_color) in interaction_query.iter_mut() {
@@ -225,18 +1783,44 @@ fn handle_button_color(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_menu_button_action(
     interaction_query: Query<
         (&Interaction, &MenuButtonAction),
         (Changed<Interaction>, With<Button>),
     >,
     mut game_state: ResMut<NextState<GameState>>,
+    mut next_pause_screen: ResMut<NextState<PauseScreen>>,
+    pause_screen: Option<Res<State<PauseScreen>>>,
     mut app_exit_event: EventWriter<AppExit>,
+    mut resume: ResMut<ResumeRun>,
+    mut selected: ResMut<SelectedCharacter>,
+    saved_run: Res<SavedRun>,
 ) {
     for (interaction, button_action) in interaction_query.iter() {
         if *interaction == Interaction::Pressed {
             match button_action {
-                MenuButtonAction::Play => game_state.set(GameState::GameInit),
+                MenuButtonAction::Play => game_state.set(GameState::CharacterSelect),
+                MenuButtonAction::Continue => {
+                    if let Some(run) = &saved_run.0 {
+                        **selected = run.character;
+                        **resume = Some(run.clone());
+                        game_state.set(GameState::GameInit);
+                    }
+                }
+                MenuButtonAction::Resume => game_state.set(GameState::GameRun),
+                MenuButtonAction::Retry => game_state.set(GameState::GameInit),
+                // the paused run stays, so the settings don't go through the RunTeardown
+                MenuButtonAction::Settings if pause_screen.is_some() => {
+                    next_pause_screen.set(PauseScreen::Settings)
+                }
+                MenuButtonAction::Settings => game_state.set(GameState::Settings),
+                MenuButtonAction::Controls => game_state.set(GameState::Controls),
+                MenuButtonAction::MainMenu => game_state.set(GameState::MainMenu),
+                MenuButtonAction::Back if pause_screen.is_some() => {
+                    next_pause_screen.set(PauseScreen::Menu)
+                }
+                MenuButtonAction::Back => game_state.set(GameState::MainMenu),
                 MenuButtonAction::Exit => {
                     app_exit_event.send(AppExit::Success);
                 }
@@ -244,11 +1828,3 @@ fn handle_menu_button_action(
         }
     }
 }
-
-/// Generic despawn entities function
-/// Despawns all entities that have a `T` component.
-fn despawn_entities<T: Component>(mut commands: Commands, entities: Query<Entity, With<T>>) {
-    for ent in entities.iter() {
-        commands.entity(ent).despawn_recursive();
-    }
-}
@@ -0,0 +1,184 @@
+//! Replaces the OS cursor with a crosshair while the game is running.
+//!
+//! The crosshair follows the [`AimInput`] of the primary player, its shape depends on the
+//! [`Weapon`] they hold and it flashes whenever a bullet hits something.
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::gun::{BulletHit, Gun, Weapon};
+use crate::player::{Controller, Player};
+use crate::prelude::*;
+use crate::resources::AimInput;
+
+const CROSSHAIR_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const HIT_MARKER_COLOR: Color = Color::srgb(1.0, 0.2, 0.2);
+const HIT_MARKER_SCALE: f32 = 1.4;
+
+pub struct CrosshairPlugin;
+
+impl Plugin for CrosshairPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::GameRun),
+            (spawn_crosshair, set_cursor_visibility(false)),
+        )
+        .add_systems(OnExit(GameState::GameRun), set_cursor_visibility(true))
+        .add_systems(
+            Update,
+            (
+                update_crosshair_style,
+                update_crosshair_pos,
+                (flash_hit_marker, fade_hit_marker).chain(),
+            )
+                .run_if(in_state(GameState::GameRun)),
+        );
+    }
+}
+
+#[derive(Component, Debug, Default)]
+#[require(Transform, Visibility, RunScoped)]
+struct Crosshair;
+
+/// One of the four lines that make up the [`Crosshair`], holds the direction it points in.
+#[derive(Component, Debug, Deref)]
+struct CrosshairLine(Vec2);
+
+/// Shows that a bullet hit something until the timer runs out.
+#[derive(Component, Debug, Deref, DerefMut)]
+struct HitMarker(Timer);
+
+/// How the crosshair looks for each [`Weapon`].
+#[derive(Debug, Clone, Copy)]
+struct CrosshairStyle {
+    /// Distance of the lines from the center.
+    gap: f32,
+    length: f32,
+}
+
+fn crosshair_style(weapon: Weapon) -> CrosshairStyle {
+    match weapon {
+        Weapon::Pistol => CrosshairStyle {
+            gap: 2.,
+            length: 3.,
+        },
+        Weapon::Smg => CrosshairStyle {
+            gap: 3.,
+            length: 3.,
+        },
+        // the spread is much wider
+        Weapon::Shotgun => CrosshairStyle {
+            gap: 6.,
+            length: 2.,
+        },
+    }
+}
+
+/// The crosshair is only spawned once per run, pausing doesn't despawn it.
+fn spawn_crosshair(mut commands: Commands, crosshair_query: Query<(), With<Crosshair>>) {
+    if !crosshair_query.is_empty() {
+        return;
+    }
+    commands
+        .spawn((
+            // above everything else
+            Transform::from_xyz(0., 0., 500.),
+            Crosshair,
+        ))
+        .with_children(|parent| {
+            for dir in [Vec2::X, Vec2::NEG_X, Vec2::Y, Vec2::NEG_Y] {
+                parent.spawn((
+                    Sprite::from_color(CROSSHAIR_COLOR, Vec2::ONE),
+                    CrosshairLine(dir),
+                ));
+            }
+        });
+}
+
+fn set_cursor_visibility(visible: bool) -> impl FnMut(Query<&mut Window, With<PrimaryWindow>>) {
+    move |mut window_query| {
+        if let Ok(mut window) = window_query.get_single_mut() {
+            window.cursor_options.visible = visible;
+        }
+    }
+}
+
+fn update_crosshair_pos(
+    mut crosshair_query: Query<(&mut Transform, &mut Visibility), With<Crosshair>>,
+    aim_input: Res<AimInput>,
+) {
+    let Ok((mut transf, mut visibility)) = crosshair_query.get_single_mut() else {
+        return;
+    };
+    // the virtual cursor of a gamepad is drawn the same way
+    let Some(pos) = aim_input.pos else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Inherited;
+    transf.translation = pos.extend(transf.translation.z);
+}
+
+fn update_crosshair_style(
+    mut line_query: Query<(&mut Transform, &mut Sprite, &CrosshairLine)>,
+    gun_query: Query<(&Weapon, &Parent), (With<Gun>, Changed<Weapon>)>,
+    player_query: Query<&Controller, With<Player>>,
+) {
+    // only the primary player aims with the mouse
+    let Some((weapon, _)) = gun_query.iter().find(|(_, parent)| {
+        player_query
+            .get(parent.get())
+            .is_ok_and(|controller| *controller == Controller::Primary)
+    }) else {
+        return;
+    };
+    let style = crosshair_style(*weapon);
+
+    for (mut transf, mut sprite, dir) in line_query.iter_mut() {
+        let offset = **dir * (style.gap + style.length * 0.5);
+        transf.translation = offset.extend(0.);
+        // lines are one unit thick
+        sprite.custom_size = Some((dir.abs() * (style.length - 1.)) + Vec2::ONE);
+    }
+}
+
+fn flash_hit_marker(
+    mut commands: Commands,
+    mut hit_events: EventReader<BulletHit>,
+    crosshair_query: Query<Entity, With<Crosshair>>,
+) {
+    if hit_events.read().count() == 0 {
+        return;
+    }
+    if let Ok(ent) = crosshair_query.get_single() {
+        commands.entity(ent).insert(HitMarker(Timer::from_seconds(
+            HIT_MARKER_SECS,
+            TimerMode::Once,
+        )));
+    }
+}
+
+fn fade_hit_marker(
+    mut commands: Commands,
+    mut crosshair_query: Query<
+        (Entity, &mut Transform, &mut HitMarker, &Children),
+        With<Crosshair>,
+    >,
+    mut line_query: Query<&mut Sprite, With<CrosshairLine>>,
+    time: Res<Time<Real>>,
+) {
+    let Ok((ent, mut transf, mut marker, children)) = crosshair_query.get_single_mut() else {
+        return;
+    };
+    marker.tick(time.delta());
+    let t = marker.fraction_remaining();
+
+    transf.scale = Vec3::splat(1. + (HIT_MARKER_SCALE - 1.) * t);
+    let mut lines = line_query.iter_many_mut(children);
+    while let Some(mut sprite) = lines.fetch_next() {
+        sprite.color = CROSSHAIR_COLOR.mix(&HIT_MARKER_COLOR, t);
+    }
+
+    if marker.finished() {
+        commands.entity(ent).remove::<HitMarker>();
+    }
+}
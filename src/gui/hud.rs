@@ -0,0 +1,793 @@
+//! In-game HUD widgets that are bound to the player components.
+//!
+//! Every player gets their own widgets, they are spawned under the run scoped roots once the player
+//! is added and despawned once the player leaves the run.
+//!
+//! Simple text fields don't need their own marker and update system, a [`HudBinding`] fills the
+//! [`TextSpan`] with the output of any system, see [`spawn_hud_field`].
+
+use std::time::Duration;
+
+use bevy::{ecs::system::BoxedSystem, prelude::*, time::common_conditions::on_timer};
+
+use super::FONT_SIZE;
+use crate::collision::EnemyQuadtree;
+use crate::components::Health;
+use crate::enemy::{BossDefeated, BossSpawned, Wave};
+use crate::experience::{apply_gained_xp, Experience, Level, LevelCurve, XpGained};
+use crate::gun::{Ammo, Gun, Reloading, Weapon};
+use crate::locale::Localizer;
+use crate::player::Player;
+use crate::prelude::*;
+use crate::resources::{AtlasKind, GlobTextAtlases};
+use crate::score::RunStats;
+use crate::world::config::WorldConfig;
+
+const HEALTH_BAR_SIZE: Vec2 = Vec2::new(200., 24.);
+const HEALTH_BAR_BG: Color = Color::srgb(0.15, 0.05, 0.05);
+const HEALTH_BAR_FILL: Color = Color::srgb(0.75, 0.12, 0.12);
+const WEAPON_ICON_SIZE: f32 = 32.;
+const RELOAD_BAR_SIZE: Vec2 = Vec2::new(80., 6.);
+const RELOAD_BAR_BG: Color = Color::srgb(0.1, 0.1, 0.1);
+const RELOAD_BAR_FILL: Color = Color::srgb(0.9, 0.8, 0.3);
+const XP_BAR_HEIGHT: f32 = 8.;
+const XP_BAR_BG: Color = Color::srgb(0.05, 0.05, 0.15);
+const XP_BAR_FILL: Color = Color::srgb(0.3, 0.7, 1.0);
+const BOSS_BAR_HEIGHT: f32 = 16.;
+const BOSS_BAR_BG: Color = Color::srgb(0.1, 0.02, 0.1);
+const BOSS_BAR_FILL: Color = Color::srgb(0.6, 0.1, 0.6);
+const MINIMAP_SIZE: f32 = 160.;
+/// The minimap is split into `MINIMAP_CELLS` x `MINIMAP_CELLS` cells that show the enemy density.
+const MINIMAP_CELLS: usize = 16;
+/// Number of enemies in a cell at which it's shown fully opaque.
+const MINIMAP_CELL_MAX_ENEMIES: f32 = 30.;
+const MINIMAP_MARKER_SIZE: f32 = 6.;
+const MINIMAP_BG: Color = Color::srgba(0.05, 0.05, 0.05, 0.6);
+const MINIMAP_BORDER: Color = Color::srgb(0.6, 0.6, 0.6);
+const MINIMAP_ENEMY: Color = Color::srgb(0.9, 0.1, 0.1);
+const MINIMAP_PLAYER: Color = Color::srgb(0.2, 1.0, 0.3);
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::GameInit),
+            (
+                spawn_hud_root,
+                spawn_xp_root,
+                spawn_minimap,
+                spawn_wave_text,
+                spawn_boss_bar_root,
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                (
+                    spawn_health_bars,
+                    update_health_bars,
+                    despawn_player_widgets::<HealthBar>,
+                )
+                    .chain(),
+                (
+                    spawn_weapon_widgets,
+                    update_weapon_widgets,
+                    despawn_player_widgets::<WeaponWidget>,
+                )
+                    .chain(),
+                (
+                    spawn_xp_bars,
+                    update_xp_bars.after(apply_gained_xp),
+                    despawn_player_widgets::<XpBar>,
+                )
+                    .chain(),
+                update_wave_text,
+                (spawn_boss_bars, update_boss_bars, despawn_boss_bars).chain(),
+                (
+                    spawn_minimap_markers,
+                    (update_minimap_cells, update_minimap_markers)
+                        .run_if(on_timer(Duration::from_secs_f32(MINIMAP_REFRESH_RATE_SECS))),
+                    despawn_player_widgets::<MinimapMarker>,
+                )
+                    .chain(),
+            )
+                .run_if(in_state(GameState::GameRun)),
+        )
+        .add_systems(
+            Update,
+            update_hud_bindings.run_if(in_state(GameState::GameRun)),
+        );
+    }
+}
+
+/// Fills the [`TextSpan`] with the output of its system, which can take any system params.
+///
+/// ```ignore
+/// HudBinding::new(|score: Res<Score>| score.to_string())
+/// ```
+#[derive(Component)]
+#[require(TextSpan)]
+pub struct HudBinding {
+    /// Taken out while the system runs, so it can get the whole world.
+    system: Option<BoxedSystem<(), String>>,
+    initialized: bool,
+}
+
+impl HudBinding {
+    pub fn new<M>(system: impl IntoSystem<(), String, M>) -> Self {
+        HudBinding {
+            system: Some(Box::new(IntoSystem::into_system(system))),
+            initialized: false,
+        }
+    }
+}
+
+/// Spawns a text with the label, followed by a span bound to the system.
+pub fn spawn_hud_field<M>(
+    commands: &mut Commands,
+    label: &str,
+    binding: impl IntoSystem<(), String, M>,
+) -> Entity {
+    commands
+        .spawn((
+            Text::new(label),
+            TextFont::default().with_font_size(FONT_SIZE),
+            Node::default(),
+        ))
+        .with_child((
+            TextFont::default().with_font_size(FONT_SIZE),
+            HudBinding::new(binding),
+        ))
+        .id()
+}
+
+/// Holds the player widgets, anchored to the bottom left corner.
+#[derive(Component)]
+#[require(RunScoped)]
+struct HudRoot;
+
+/// The health bar of the player entity it holds.
+#[derive(Component, Deref)]
+struct HealthBar(Entity);
+
+#[derive(Component)]
+struct HealthBarFill;
+
+#[derive(Component)]
+struct HealthBarText;
+
+/// The equipped weapon, ammo and reload progress of the player entity it holds.
+#[derive(Component, Deref)]
+struct WeaponWidget(Entity);
+
+#[derive(Component)]
+struct WeaponIcon;
+
+#[derive(Component)]
+struct AmmoText;
+
+/// Only visible while the gun is reloading.
+#[derive(Component)]
+struct ReloadBar;
+
+#[derive(Component)]
+struct ReloadBarFill;
+
+/// Holds the XP bars, spans the top of the screen.
+#[derive(Component)]
+#[require(RunScoped)]
+struct XpRoot;
+
+/// The experience bar and level of the player entity it holds.
+#[derive(Component, Deref)]
+struct XpBar(Entity);
+
+#[derive(Component)]
+struct XpBarFill;
+
+#[derive(Component)]
+struct LevelText;
+
+/// Shows the current wave and the time survived, at the top of the screen.
+#[derive(Component)]
+struct WaveText;
+
+/// Holds the boss health bars, below the wave text.
+#[derive(Component)]
+#[require(RunScoped)]
+struct BossBarRoot;
+
+/// The health bar of the boss entity it holds.
+#[derive(Component, Deref)]
+struct BossBar(Entity);
+
+#[derive(Component)]
+struct BossBarFill;
+
+/// Shows the whole world, anchored to the bottom right corner.
+#[derive(Component)]
+#[require(RunScoped)]
+struct Minimap;
+
+/// A part of the minimap that shows the enemy density in its world-space [`Rect`].
+#[derive(Component, Deref)]
+struct MinimapCell(Rect);
+
+/// The minimap marker of the player entity it holds.
+#[derive(Component, Deref)]
+struct MinimapMarker(Entity);
+
+fn spawn_hud_root(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.),
+            bottom: Val::Px(10.),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(6.),
+            ..default()
+        },
+        HudRoot,
+    ));
+}
+
+/// Every player that joins the run gets their own health bar.
+fn spawn_health_bars(
+    mut commands: Commands,
+    player_query: Query<Entity, Added<Player>>,
+    root_query: Query<Entity, With<HudRoot>>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    for player_ent in player_query.iter() {
+        commands.entity(root).with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(HEALTH_BAR_SIZE.x),
+                        height: Val::Px(HEALTH_BAR_SIZE.y),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(HEALTH_BAR_BG),
+                    HealthBar(player_ent),
+                ))
+                .with_children(|bar| {
+                    bar.spawn((
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(0.),
+                            width: Val::Percent(100.),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        BackgroundColor(HEALTH_BAR_FILL),
+                        HealthBarFill,
+                    ));
+                    bar.spawn((
+                        Text::default(),
+                        TextFont::default().with_font_size(FONT_SIZE * 0.6),
+                        HealthBarText,
+                    ));
+                });
+        });
+    }
+}
+
+fn update_health_bars(
+    player_query: Query<&Health, (With<Player>, Changed<Health>)>,
+    bar_query: Query<(&HealthBar, &Children)>,
+    mut fill_query: Query<&mut Node, With<HealthBarFill>>,
+    mut text_query: Query<&mut Text, With<HealthBarText>>,
+) {
+    for (bar, children) in bar_query.iter() {
+        let Ok(hp) = player_query.get(**bar) else {
+            continue;
+        };
+        let ratio = hp.current as f32 / hp.max.max(1) as f32;
+
+        for &child in children.iter() {
+            if let Ok(mut fill) = fill_query.get_mut(child) {
+                fill.width = Val::Percent(ratio * 100.);
+            }
+            if let Ok(mut text) = text_query.get_mut(child) {
+                **text = format!("{} / {}", hp.current, hp.max);
+            }
+        }
+    }
+}
+
+fn spawn_weapon_widgets(
+    mut commands: Commands,
+    player_query: Query<Entity, Added<Player>>,
+    root_query: Query<Entity, With<HudRoot>>,
+    text_atlases: Res<GlobTextAtlases>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    for player_ent in player_query.iter() {
+        let layout = text_atlases.common().layout;
+        let image = text_atlases.common().image;
+        let index = text_atlases.region(AtlasKind::Common, "gun");
+
+        commands.entity(root).with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    WeaponWidget(player_ent),
+                ))
+                .with_children(|row| {
+                    // all weapons share the gun sprite, same as in the world
+                    row.spawn((
+                        ImageNode::from_atlas_image(image, TextureAtlas { layout, index }),
+                        Node {
+                            width: Val::Px(WEAPON_ICON_SIZE),
+                            height: Val::Px(WEAPON_ICON_SIZE),
+                            ..default()
+                        },
+                        WeaponIcon,
+                    ));
+                    row.spawn((
+                        Text::default(),
+                        TextFont::default().with_font_size(FONT_SIZE * 0.6),
+                        AmmoText,
+                    ));
+                    row.spawn((
+                        Node {
+                            width: Val::Px(RELOAD_BAR_SIZE.x),
+                            height: Val::Px(RELOAD_BAR_SIZE.y),
+                            ..default()
+                        },
+                        BackgroundColor(RELOAD_BAR_BG),
+                        Visibility::Hidden,
+                        ReloadBar,
+                    ))
+                    .with_child((
+                        Node {
+                            width: Val::Percent(0.),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        BackgroundColor(RELOAD_BAR_FILL),
+                        ReloadBarFill,
+                    ));
+                });
+        });
+    }
+}
+
+fn update_weapon_widgets(
+    gun_query: Query<(&Weapon, &Ammo, Option<&Reloading>, &Parent), With<Gun>>,
+    widget_query: Query<(&WeaponWidget, &Children)>,
+    mut icon_query: Query<&mut ImageNode, With<WeaponIcon>>,
+    mut text_query: Query<&mut Text, With<AmmoText>>,
+    mut reload_bar_query: Query<(&mut Visibility, &Children), With<ReloadBar>>,
+    mut fill_query: Query<&mut Node, With<ReloadBarFill>>,
+) {
+    for (widget, children) in widget_query.iter() {
+        let Some((weapon, ammo, reloading, _)) = gun_query
+            .iter()
+            .find(|(.., parent)| parent.get() == **widget)
+        else {
+            continue;
+        };
+
+        for &child in children.iter() {
+            if let Ok(mut icon) = icon_query.get_mut(child) {
+                icon.color = weapon.stats().tint;
+            }
+            if let Ok(mut text) = text_query.get_mut(child) {
+                let ammo_text = match ammo.reserve {
+                    Some(reserve) => format!("{} / {reserve}", ammo.clip),
+                    None => ammo.clip.to_string(),
+                };
+                // don't relayout the text every frame
+                if **text != ammo_text {
+                    **text = ammo_text;
+                }
+            }
+            if let Ok((mut visibility, bar_children)) = reload_bar_query.get_mut(child) {
+                *visibility = if reloading.is_some() {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                };
+                let progress = reloading.map_or(0., |reloading| reloading.fraction());
+                let mut fills = fill_query.iter_many_mut(bar_children);
+                while let Some(mut fill) = fills.fetch_next() {
+                    fill.width = Val::Percent(progress * 100.);
+                }
+            }
+        }
+    }
+}
+
+fn spawn_xp_root(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(0.),
+            width: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(2.),
+            ..default()
+        },
+        XpRoot,
+    ));
+}
+
+fn spawn_xp_bars(
+    mut commands: Commands,
+    player_query: Query<(Entity, &Level), Added<Player>>,
+    root_query: Query<Entity, With<XpRoot>>,
+    localizer: Localizer,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    for (player_ent, level) in player_query.iter() {
+        commands.entity(root).with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(100.),
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(6.),
+                        ..default()
+                    },
+                    XpBar(player_ent),
+                ))
+                .with_children(|row| {
+                    row.spawn((
+                        Text::new(localizer.trf("hud.level", &[&**level])),
+                        TextFont::default().with_font_size(FONT_SIZE * 0.5),
+                        LevelText,
+                    ));
+                    row.spawn((
+                        Node {
+                            flex_grow: 1.,
+                            height: Val::Px(XP_BAR_HEIGHT),
+                            ..default()
+                        },
+                        BackgroundColor(XP_BAR_BG),
+                    ))
+                    .with_child((
+                        Node {
+                            width: Val::Percent(0.),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        BackgroundColor(XP_BAR_FILL),
+                        XpBarFill,
+                    ));
+                });
+        });
+    }
+}
+
+fn update_xp_bars(
+    mut xp_events: EventReader<XpGained>,
+    player_query: Query<(&Experience, &Level), With<Player>>,
+    bar_query: Query<(&XpBar, &Children)>,
+    bar_bg_query: Query<&Children, Without<XpBar>>,
+    mut fill_query: Query<&mut Node, With<XpBarFill>>,
+    mut text_query: Query<&mut Text, With<LevelText>>,
+    curve: Res<LevelCurve>,
+    localizer: Localizer,
+) {
+    for gained in xp_events.read() {
+        let Ok((xp, level)) = player_query.get(gained.entity) else {
+            continue;
+        };
+        let ratio = **xp as f32 / curve.xp_to_next(**level) as f32;
+
+        for (_, children) in bar_query.iter().filter(|(bar, _)| ***bar == gained.entity) {
+            for &child in children.iter() {
+                if let Ok(mut text) = text_query.get_mut(child) {
+                    **text = localizer.trf("hud.level", &[&**level]);
+                }
+                // the fill is nested in the bar background
+                let Ok(bg_children) = bar_bg_query.get(child) else {
+                    continue;
+                };
+                let mut fills = fill_query.iter_many_mut(bg_children);
+                while let Some(mut fill) = fills.fetch_next() {
+                    fill.width = Val::Percent(ratio * 100.);
+                }
+            }
+        }
+    }
+}
+
+fn spawn_wave_text(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(20.),
+                width: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            RunScoped,
+        ))
+        .with_child((
+            Text::default(),
+            TextFont::default().with_font_size(FONT_SIZE * 0.8),
+            WaveText,
+        ));
+}
+
+fn update_wave_text(
+    mut text_query: Query<&mut Text, With<WaveText>>,
+    wave: Res<Wave>,
+    stats: Res<RunStats>,
+    localizer: Localizer,
+) {
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+    let secs = stats.time_survived_secs as u32;
+    let time_survived = format!("{}:{:02}", secs / 60, secs % 60);
+    **text = localizer.trf("hud.wave", &[&wave.number, &time_survived]);
+}
+
+fn spawn_boss_bar_root(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(60.),
+            width: Val::Percent(100.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(6.),
+            ..default()
+        },
+        BossBarRoot,
+    ));
+}
+
+fn spawn_boss_bars(
+    mut commands: Commands,
+    mut boss_spawned_events: EventReader<BossSpawned>,
+    root_query: Query<Entity, With<BossBarRoot>>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+
+    for spawned in boss_spawned_events.read() {
+        commands.entity(root).with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(60.),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BossBar(spawned.entity),
+                ))
+                .with_children(|bar| {
+                    bar.spawn((
+                        Text::new(spawned.name),
+                        TextFont::default().with_font_size(FONT_SIZE * 0.6),
+                    ));
+                    bar.spawn((
+                        Node {
+                            width: Val::Percent(100.),
+                            height: Val::Px(BOSS_BAR_HEIGHT),
+                            ..default()
+                        },
+                        BackgroundColor(BOSS_BAR_BG),
+                    ))
+                    .with_child((
+                        Node {
+                            width: Val::Percent(100.),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        BackgroundColor(BOSS_BAR_FILL),
+                        BossBarFill,
+                    ));
+                });
+        });
+    }
+}
+
+fn update_boss_bars(
+    boss_query: Query<&Health, Changed<Health>>,
+    bar_query: Query<(&BossBar, &Children)>,
+    bar_bg_query: Query<&Children, Without<BossBar>>,
+    mut fill_query: Query<&mut Node, With<BossBarFill>>,
+) {
+    for (bar, children) in bar_query.iter() {
+        let Ok(hp) = boss_query.get(**bar) else {
+            continue;
+        };
+        let ratio = hp.current as f32 / hp.max.max(1) as f32;
+
+        // the fill is nested in the bar background
+        for bg_children in bar_bg_query.iter_many(children) {
+            let mut fills = fill_query.iter_many_mut(bg_children);
+            while let Some(mut fill) = fills.fetch_next() {
+                fill.width = Val::Percent(ratio * 100.);
+            }
+        }
+    }
+}
+
+fn despawn_boss_bars(
+    mut commands: Commands,
+    mut boss_defeated_events: EventReader<BossDefeated>,
+    bar_query: Query<(Entity, &BossBar)>,
+) {
+    for defeated in boss_defeated_events.read() {
+        for (ent, _) in bar_query
+            .iter()
+            .filter(|(_, bar)| ***bar == defeated.entity)
+        {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
+
+fn spawn_minimap(mut commands: Commands, config: Res<WorldConfig>) {
+    let cell_size = config.size / MINIMAP_CELLS as f32;
+    let cell_percent = 100. / MINIMAP_CELLS as f32;
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.),
+                bottom: Val::Px(10.),
+                width: Val::Px(MINIMAP_SIZE),
+                height: Val::Px(MINIMAP_SIZE),
+                border: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            BackgroundColor(MINIMAP_BG),
+            BorderColor(MINIMAP_BORDER),
+            Minimap,
+        ))
+        .with_children(|parent| {
+            for row in 0..MINIMAP_CELLS {
+                for col in 0..MINIMAP_CELLS {
+                    // the rows go top to bottom, the world's y axis goes up
+                    let min = Vec2::new(
+                        col as f32 * cell_size - config.half_size(),
+                        config.half_size() - (row + 1) as f32 * cell_size,
+                    );
+                    parent.spawn((
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Percent(col as f32 * cell_percent),
+                            top: Val::Percent(row as f32 * cell_percent),
+                            width: Val::Percent(cell_percent),
+                            height: Val::Percent(cell_percent),
+                            ..default()
+                        },
+                        BackgroundColor(MINIMAP_ENEMY.with_alpha(0.)),
+                        MinimapCell(Rect::from_corners(min, min + cell_size)),
+                    ));
+                }
+            }
+        });
+}
+
+fn spawn_minimap_markers(
+    mut commands: Commands,
+    player_query: Query<Entity, Added<Player>>,
+    minimap_query: Query<Entity, With<Minimap>>,
+) {
+    let Ok(minimap) = minimap_query.get_single() else {
+        return;
+    };
+
+    for player_ent in player_query.iter() {
+        commands.entity(minimap).with_child((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Px(MINIMAP_MARKER_SIZE),
+                height: Val::Px(MINIMAP_MARKER_SIZE),
+                ..default()
+            },
+            BackgroundColor(MINIMAP_PLAYER),
+            // above the cells
+            ZIndex(1),
+            MinimapMarker(player_ent),
+        ));
+    }
+}
+
+fn update_minimap_cells(
+    qtree: Res<EnemyQuadtree>,
+    mut cell_query: Query<(&MinimapCell, &mut BackgroundColor)>,
+) {
+    for (cell, mut bg_color) in cell_query.iter_mut() {
+        let density = qtree.query(**cell).len() as f32 / MINIMAP_CELL_MAX_ENEMIES;
+        bg_color.0 = MINIMAP_ENEMY.with_alpha(density.min(1.));
+    }
+}
+
+fn update_minimap_markers(
+    player_query: Query<&Transform, With<Player>>,
+    mut marker_query: Query<(&MinimapMarker, &mut Node)>,
+    config: Res<WorldConfig>,
+) {
+    for (marker, mut node) in marker_query.iter_mut() {
+        let Ok(player_transf) = player_query.get(**marker) else {
+            continue;
+        };
+        // normalized to 0..1, with the origin in the top left corner like the UI
+        let pos = player_transf.translation.truncate() / config.size;
+        let pos = Vec2::new(pos.x + 0.5, 0.5 - pos.y).clamp(Vec2::ZERO, Vec2::ONE);
+        node.left = Val::Percent(pos.x * 100.);
+        node.top = Val::Percent(pos.y * 100.);
+    }
+}
+
+/// Runs the system of every [`HudBinding`] and writes the text if it changed.
+fn update_hud_bindings(world: &mut World) {
+    let bindings = world
+        .query::<(Entity, &mut HudBinding)>()
+        .iter_mut(world)
+        .filter_map(|(ent, mut binding)| {
+            let initialized = binding.initialized;
+            binding
+                .system
+                .take()
+                .map(|system| (ent, system, initialized))
+        })
+        .collect::<Vec<_>>();
+
+    for (ent, mut system, initialized) in bindings {
+        if !initialized {
+            system.initialize(world);
+        }
+        // skip the update if e.g. a `Single` param doesn't match
+        let text = system.validate_param(world).then(|| system.run((), world));
+
+        // the system might've despawned the widget
+        let Ok(mut ent_mut) = world.get_entity_mut(ent) else {
+            continue;
+        };
+        if let Some(mut binding) = ent_mut.get_mut::<HudBinding>() {
+            binding.system = Some(system);
+            binding.initialized = true;
+        }
+        if let (Some(text), Some(mut span)) = (text, ent_mut.get_mut::<TextSpan>()) {
+            if span.0 != text {
+                span.0 = text;
+            }
+        }
+    }
+}
+
+/// Despawns the widgets of the players that left the run.
+fn despawn_player_widgets<T: Component + std::ops::Deref<Target = Entity>>(
+    mut commands: Commands,
+    mut removed_players: RemovedComponents<Player>,
+    widget_query: Query<(Entity, &T)>,
+) {
+    for player_ent in removed_players.read() {
+        for (ent, _) in widget_query
+            .iter()
+            .filter(|(_, widget)| ***widget == player_ent)
+        {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! A small panel in the top right corner that lists the most notable things that happened
+//! during the run.
+//!
+//! New entries are added to the bottom, the oldest ones are pushed out once there are more than
+//! [`KILL_FEED_MAX_ENTRIES`] of them. Every entry fades out after [`KILL_FEED_ENTRY_SECS`].
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::FONT_SIZE;
+use crate::enemy::{BossDefeated, BossSpawned, EnemyKilled};
+use crate::experience::LevelUp;
+use crate::locale::Localizer;
+use crate::player::{Player, PlayerDied};
+use crate::prelude::*;
+
+/// How long before the end of its life an entry starts to fade out.
+const KILL_FEED_FADE_SECS: f32 = 1.;
+const KILL_FEED_KILL_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const KILL_FEED_BOSS_COLOR: Color = Color::srgb(0.8, 0.4, 0.9);
+const KILL_FEED_LEVEL_UP_COLOR: Color = Color::srgb(0.3, 0.7, 1.0);
+const KILL_FEED_DEATH_COLOR: Color = Color::srgb(0.9, 0.2, 0.2);
+
+pub struct KillFeedPlugin;
+
+impl Plugin for KillFeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::GameInit), spawn_kill_feed)
+            .add_systems(
+                Update,
+                (
+                    (
+                        push_enemy_entries,
+                        push_level_up_entries,
+                        push_player_died_entries,
+                    ),
+                    (trim_kill_feed, fade_kill_feed_entries).chain(),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::GameRun)),
+            );
+    }
+}
+
+/// Holds the [`KillFeedEntry`]s, anchored to the top right corner.
+#[derive(Component)]
+#[require(RunScoped)]
+struct KillFeed;
+
+/// A line in the [`KillFeed`], despawned once the timer runs out.
+#[derive(Component, Deref, DerefMut)]
+struct KillFeedEntry(Timer);
+
+impl Default for KillFeedEntry {
+    fn default() -> Self {
+        KillFeedEntry(Timer::from_seconds(KILL_FEED_ENTRY_SECS, TimerMode::Once))
+    }
+}
+
+fn spawn_kill_feed(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(60.),
+            right: Val::Px(20.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::FlexEnd,
+            row_gap: Val::Px(4.),
+            ..default()
+        },
+        KillFeed,
+    ));
+}
+
+fn push_entry(commands: &mut Commands, feed: Entity, text: String, color: Color) {
+    commands.entity(feed).with_child((
+        Text::new(text),
+        TextFont::default().with_font_size(FONT_SIZE * 0.6),
+        TextColor(color),
+        KillFeedEntry::default(),
+    ));
+}
+
+/// Adds the boss fights and kills of enemies worth at least [`KILL_FEED_MIN_WORTH`].
+fn push_enemy_entries(
+    mut commands: Commands,
+    mut enemy_killed_events: EventReader<EnemyKilled>,
+    mut boss_spawned_events: EventReader<BossSpawned>,
+    mut boss_defeated_events: EventReader<BossDefeated>,
+    // bosses are already despawned by the time their kill is read, so remember their names
+    mut boss_names: Local<HashMap<Entity, &'static str>>,
+    feed_query: Query<Entity, With<KillFeed>>,
+    localizer: Localizer,
+) {
+    let Ok(feed) = feed_query.get_single() else {
+        return;
+    };
+
+    for event in boss_spawned_events.read() {
+        boss_names.insert(event.entity, event.name);
+        push_entry(
+            &mut commands,
+            feed,
+            localizer.trf("feed.boss_appears", &[&event.name]),
+            KILL_FEED_BOSS_COLOR,
+        );
+    }
+    for event in enemy_killed_events.read() {
+        if let Some(name) = boss_names.get(&event.entity) {
+            push_entry(
+                &mut commands,
+                feed,
+                localizer.trf("feed.boss_slain", &[name, &event.worth]),
+                KILL_FEED_BOSS_COLOR,
+            );
+        } else if event.worth >= KILL_FEED_MIN_WORTH {
+            push_entry(
+                &mut commands,
+                feed,
+                localizer.trf("feed.elite_slain", &[&event.worth]),
+                KILL_FEED_KILL_COLOR,
+            );
+        }
+    }
+    for event in boss_defeated_events.read() {
+        boss_names.remove(&event.entity);
+    }
+}
+
+fn push_level_up_entries(
+    mut commands: Commands,
+    mut level_up_events: EventReader<LevelUp>,
+    feed_query: Query<Entity, With<KillFeed>>,
+    player_query: Query<(), With<Player>>,
+    localizer: Localizer,
+) {
+    let Ok(feed) = feed_query.get_single() else {
+        return;
+    };
+    for event in level_up_events.read() {
+        if player_query.contains(event.entity) {
+            push_entry(
+                &mut commands,
+                feed,
+                localizer.trf("feed.level_up", &[&event.level]),
+                KILL_FEED_LEVEL_UP_COLOR,
+            );
+        }
+    }
+}
+
+fn push_player_died_entries(
+    mut commands: Commands,
+    mut player_died_events: EventReader<PlayerDied>,
+    feed_query: Query<Entity, With<KillFeed>>,
+    localizer: Localizer,
+) {
+    let Ok(feed) = feed_query.get_single() else {
+        return;
+    };
+    for _ in player_died_events.read() {
+        push_entry(
+            &mut commands,
+            feed,
+            localizer.tr("feed.player_down").to_string(),
+            KILL_FEED_DEATH_COLOR,
+        );
+    }
+}
+
+/// Despawns the oldest entries once there are too many of them.
+fn trim_kill_feed(mut commands: Commands, feed_query: Query<&Children, With<KillFeed>>) {
+    let Ok(children) = feed_query.get_single() else {
+        return;
+    };
+    let overflow = children.len().saturating_sub(KILL_FEED_MAX_ENTRIES);
+    for &entry in children.iter().take(overflow) {
+        commands.entity(entry).despawn_recursive();
+    }
+}
+
+fn fade_kill_feed_entries(
+    mut commands: Commands,
+    mut entry_query: Query<(Entity, &mut KillFeedEntry, &mut TextColor)>,
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut entry, mut color) in entry_query.iter_mut() {
+        entry.tick(time.delta());
+        if entry.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = (entry.remaining_secs() / KILL_FEED_FADE_SECS).min(1.);
+        color.set_alpha(alpha);
+    }
+}
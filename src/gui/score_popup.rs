@@ -0,0 +1,58 @@
+//! The "+N" that pops up where an enemy died, showing what the kill was worth.
+//!
+//! The popups live in the world, so they rise and fade out with the gameplay time and freeze
+//! along with it while the game is paused.
+
+use bevy::prelude::*;
+
+use crate::prelude::*;
+use crate::score::ScoreGained;
+
+const SCORE_POPUP_FONT_SIZE: f32 = 10.;
+const SCORE_POPUP_COLOR: Color = Color::srgb(1., 0.85, 0.3);
+
+pub struct ScorePopupPlugin;
+
+impl Plugin for ScorePopupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (spawn_score_popups, rise_score_popups)
+                .chain()
+                .run_if(in_state(GameState::GameRun)),
+        );
+    }
+}
+
+/// Despawned once the timer runs out.
+#[derive(Component, Deref, DerefMut)]
+#[require(Transform, RunScoped)]
+struct ScorePopup(Timer);
+
+fn spawn_score_popups(mut commands: Commands, mut score_gained_events: EventReader<ScoreGained>) {
+    for event in score_gained_events.read() {
+        commands.spawn((
+            Text2d::new(format!("+{}", event.amount)),
+            TextFont::default().with_font_size(SCORE_POPUP_FONT_SIZE),
+            TextColor(SCORE_POPUP_COLOR),
+            // above everything else in the world
+            Transform::from_translation(event.position.extend(60.)),
+            ScorePopup(Timer::from_seconds(SCORE_POPUP_SECS, TimerMode::Once)),
+        ));
+    }
+}
+
+fn rise_score_popups(
+    mut commands: Commands,
+    mut popup_query: Query<(Entity, &mut ScorePopup, &mut Transform, &mut TextColor)>,
+    time: Res<Time>,
+) {
+    for (ent, mut popup, mut transf, mut color) in popup_query.iter_mut() {
+        if popup.tick(time.delta()).finished() {
+            commands.entity(ent).despawn();
+            continue;
+        }
+        transf.translation.y += SCORE_POPUP_RISE_SPEED * time.delta_secs();
+        color.set_alpha(1. - popup.fraction());
+    }
+}
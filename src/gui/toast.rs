@@ -0,0 +1,117 @@
+//! Short lived notifications stacked in the top left corner.
+//!
+//! Any system can show a toast by sending a [`Notify`] event. At most [`TOAST_MAX_VISIBLE`]
+//! toasts are shown at the same time, the rest wait in the [`ToastQueue`] until there is room.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use super::FONT_SIZE;
+use crate::prelude::*;
+
+const TOAST_BG: Color = Color::srgba(0.05, 0.05, 0.05, 0.8);
+const TOAST_BORDER: Color = Color::srgb(0.6, 0.6, 0.6);
+
+pub struct ToastPlugin;
+
+impl Plugin for ToastPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Notify>()
+            .init_resource::<ToastQueue>()
+            .add_systems(Startup, spawn_toast_root)
+            .add_systems(
+                Update,
+                (queue_toasts, expire_toasts, show_queued_toasts).chain(),
+            );
+    }
+}
+
+/// Shows the text as a toast.
+#[derive(Event, Debug, Clone)]
+pub struct Notify {
+    pub text: String,
+}
+
+impl Notify {
+    pub fn new(text: impl Into<String>) -> Self {
+        Notify { text: text.into() }
+    }
+}
+
+/// Texts of the toasts that are waiting to be shown.
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct ToastQueue(VecDeque<String>);
+
+/// Holds the toasts, shown above everything else.
+#[derive(Component)]
+struct ToastRoot;
+
+/// A toast that's despawned once the timer runs out.
+#[derive(Component, Deref, DerefMut)]
+struct Toast(Timer);
+
+fn spawn_toast_root(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(60.),
+            left: Val::Px(20.),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(8.),
+            ..default()
+        },
+        GlobalZIndex(i32::MAX),
+        ToastRoot,
+    ));
+}
+
+fn queue_toasts(mut notify_events: EventReader<Notify>, mut queue: ResMut<ToastQueue>) {
+    queue.extend(notify_events.read().map(|event| event.text.clone()));
+}
+
+fn expire_toasts(
+    mut commands: Commands,
+    mut toast_query: Query<(Entity, &mut Toast)>,
+    time: Res<Time<Real>>,
+) {
+    for (entity, mut toast) in toast_query.iter_mut() {
+        // real time, so toasts also go away while the game is paused
+        toast.tick(time.delta());
+        if toast.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn show_queued_toasts(
+    mut commands: Commands,
+    mut queue: ResMut<ToastQueue>,
+    root_query: Query<Entity, With<ToastRoot>>,
+    toast_query: Query<&Toast>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        return;
+    };
+    // expired toasts are still around until the commands are applied
+    let visible = toast_query.iter().filter(|toast| !toast.finished()).count();
+    let room = TOAST_MAX_VISIBLE.saturating_sub(visible).min(queue.len());
+    for text in queue.drain(..room) {
+        commands
+            .spawn((
+                Node {
+                    padding: UiRect::axes(Val::Px(12.), Val::Px(6.)),
+                    border: UiRect::all(Val::Px(2.)),
+                    ..default()
+                },
+                BackgroundColor(TOAST_BG),
+                BorderColor(TOAST_BORDER),
+                Toast(Timer::from_seconds(TOAST_SECS, TimerMode::Once)),
+            ))
+            .with_child((
+                Text::new(text),
+                TextFont::default().with_font_size(FONT_SIZE * 0.6),
+            ))
+            .set_parent(root);
+    }
+}
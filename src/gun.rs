@@ -1,10 +1,17 @@
-use crate::collision::ColliderShape;
+use crate::camera::{camera_view_rect, PlayerCam};
+use crate::collision::{ColliderShape, EnemyQuadtree};
+use crate::enemy::Enemy;
+use crate::input_map::{Action, ActionInput};
 use crate::prelude::*;
 use crate::quadtree::quad_collider::Shape;
+use crate::world::config::WorldConfig;
 use crate::{
     components::Damage,
-    player::Player,
-    resources::{CursorPos, GlobTextAtlases},
+    player::{
+        character::Character, AimDirection, Controller, ControllerGamepads, Dying, Player,
+        PlayerStats,
+    },
+    resources::{update_aim_direction, AimInput, AtlasKind, GlobTextAtlases, InputDevice},
 };
 
 use bevy::math::vec2;
@@ -16,10 +23,34 @@ pub struct GunPlugin;
 
 impl Plugin for GunPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::GameInit), spawn_gun)
+        app.insert_resource(AimMode::default())
+            .add_event::<GunFired>()
+            .add_event::<BulletHit>()
             .add_systems(
                 Update,
-                (handle_gun_input, update_gun_pos, update_bullet_pos)
+                (
+                    (
+                        toggle_aim_mode,
+                        equip_new_players,
+                        (apply_weapon_tint, refill_ammo),
+                    ),
+                    update_aim_target.after(update_aim_direction),
+                    (
+                        handle_reload_input.run_if(in_state(RunPhase::Playing)),
+                        finish_reloads,
+                    ),
+                    handle_gun_input.run_if(in_state(RunPhase::Playing)),
+                    (gun_feedback, update_bullet_pos),
+                    update_gun_pos,
+                    (
+                        recover_gun_recoil,
+                        despawn_muzzle_flashes,
+                        emit_bullet_trails,
+                        fade_trail_segments,
+                    ),
+                )
+                    // the aim target has to be known before the gun moves or fires
+                    .chain()
                     .run_if(in_state(GameState::GameRun)),
             )
             .add_systems(Last, despawn_bullets.run_if(in_state(GameState::GameRun)));
@@ -27,9 +58,188 @@ impl Plugin for GunPlugin {
 }
 
 #[derive(Component)]
-#[require(Transform, Sprite, GunTimer)]
+#[require(Transform, Sprite, GunTimer, GunRecoil, Weapon, Ammo)]
 pub struct Gun;
 
+/// The kind of weapon a [`Gun`] currently is.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Weapon {
+    #[default]
+    Pistol,
+    Smg,
+    Shotgun,
+}
+
+/// Stats that describe how a [`Weapon`] fires.
+#[derive(Debug, Clone, Copy)]
+pub struct WeaponStats {
+    pub fire_interval_secs: f32,
+    pub damage: u32,
+    pub bullets_per_shot: u32,
+    /// Angle in radians the bullets of a single shot are spread across.
+    pub spread: f32,
+    /// See [`DamageFalloff`].
+    pub falloff_per_sec: f32,
+    /// See [`BulletRange`].
+    pub max_range: f32,
+    pub clip_size: u32,
+    /// Rounds the weapon comes with besides the full clip, `None` if it never runs out.
+    pub reserve: Option<u32>,
+    pub reload_secs: f32,
+    /// All weapons share the same sprite and are told apart by color.
+    pub tint: Color,
+}
+
+impl Weapon {
+    pub const ALL: [Weapon; 3] = [Weapon::Pistol, Weapon::Smg, Weapon::Shotgun];
+
+    /// Key of the localized name.
+    pub fn locale_key(self) -> &'static str {
+        match self {
+            Weapon::Pistol => "weapon.pistol",
+            Weapon::Smg => "weapon.smg",
+            Weapon::Shotgun => "weapon.shotgun",
+        }
+    }
+
+    pub fn stats(self) -> WeaponStats {
+        match self {
+            Weapon::Pistol => WeaponStats {
+                fire_interval_secs: BULLET_SPAWN_INTERVAL_SECS,
+                damage: 10,
+                bullets_per_shot: 1,
+                spread: 0.,
+                falloff_per_sec: -0.2,
+                max_range: 500.,
+                clip_size: 12,
+                reserve: None,
+                reload_secs: 1.,
+                tint: Color::WHITE,
+            },
+            Weapon::Smg => WeaponStats {
+                fire_interval_secs: BULLET_SPAWN_INTERVAL_SECS * 0.5,
+                damage: 6,
+                bullets_per_shot: 1,
+                spread: 0.,
+                falloff_per_sec: -0.35,
+                max_range: 350.,
+                clip_size: 30,
+                reserve: Some(120),
+                reload_secs: 1.5,
+                tint: Color::srgb(0.6, 0.9, 1.0),
+            },
+            Weapon::Shotgun => WeaponStats {
+                fire_interval_secs: BULLET_SPAWN_INTERVAL_SECS * 6.,
+                damage: 8,
+                bullets_per_shot: 5,
+                spread: 0.6,
+                falloff_per_sec: -0.9,
+                max_range: 200.,
+                clip_size: 6,
+                reserve: Some(30),
+                reload_secs: 2.,
+                tint: Color::srgb(1.0, 0.6, 0.5),
+            },
+        }
+    }
+}
+
+/// Rounds left in the clip of a [`Gun`] and in reserve.
+/// Changing the [`Weapon`] of the gun refills it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ammo {
+    pub clip: u32,
+    /// `None` if the weapon never runs out.
+    pub reserve: Option<u32>,
+}
+
+impl Default for Ammo {
+    fn default() -> Self {
+        Ammo::full(Weapon::default())
+    }
+}
+
+impl Ammo {
+    pub fn full(weapon: Weapon) -> Self {
+        let stats = weapon.stats();
+        Ammo {
+            clip: stats.clip_size,
+            reserve: stats.reserve,
+        }
+    }
+
+    /// Whether there's anything in reserve that would fit into the clip.
+    pub fn can_reload(&self, clip_size: u32) -> bool {
+        self.clip < clip_size && self.reserve != Some(0)
+    }
+
+    /// Fills the clip up from the reserve.
+    pub fn reload(&mut self, clip_size: u32) {
+        let missing = clip_size.saturating_sub(self.clip);
+        let loaded = self.reserve.map_or(missing, |reserve| reserve.min(missing));
+        self.clip += loaded;
+        if let Some(reserve) = self.reserve.as_mut() {
+            *reserve -= loaded;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clip == 0 && self.reserve == Some(0)
+    }
+}
+
+/// Added to a [`Gun`] while it's reloading, it can't fire until the timer runs out.
+#[derive(Component, Debug, Deref, DerefMut)]
+pub struct Reloading(pub Timer);
+
+impl Reloading {
+    pub fn new(weapon: Weapon) -> Self {
+        Reloading(Timer::from_seconds(
+            weapon.stats().reload_secs,
+            TimerMode::Once,
+        ))
+    }
+}
+
+/// How much recoil the gun is currently experiencing, `1.0` right after a shot and `0.0` at rest.
+#[derive(Component, Debug, Default, Deref, DerefMut)]
+pub struct GunRecoil(pub f32);
+
+/// Sent every time a gun fires a bullet.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GunFired {
+    pub gun: Entity,
+    /// Position of the gun barrel.
+    pub pos: Vec2,
+    pub dir: Vec2,
+}
+
+/// Sent every time a bullet hits an enemy.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BulletHit {
+    pub pos: Vec2,
+}
+
+/// A short lived flash spawned at the gun barrel on each shot.
+#[derive(Component, Debug, Deref, DerefMut)]
+#[require(Transform, Sprite, RunScoped)]
+pub struct MuzzleFlash(pub Timer);
+
+/// Determines how the gun picks where to aim.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AimMode {
+    /// Aim at the cursor and fire while the left mouse button is held.
+    #[default]
+    Manual,
+    /// Aim at the nearest on-screen enemy and fire automatically.
+    Auto,
+}
+
+/// The world position a player's gun is currently aiming at.
+/// `None` if there is nothing to aim at.
+#[derive(Component, Debug, Default, Deref, DerefMut)]
+pub struct AimTarget(pub Option<Vec2>);
+
 #[derive(Component, Debug, Default, Deref, DerefMut)]
 pub struct GunTimer(pub Stopwatch);
 
@@ -37,8 +247,11 @@ pub struct GunTimer(pub Stopwatch);
 #[require(
     Transform,
     Sprite,
+    RunScoped,
     BulletDirection,
     Damage,
+    DamageFalloff,
+    BulletRange,
     SpawnInstant(|| SpawnInstant(Instant::now())),
     ColliderShape(|| ColliderShape(Shape::Circle(Circle::new(4.0))))
 )]
@@ -50,68 +263,395 @@ pub struct SpawnInstant(pub Instant);
 #[derive(Component, Debug, Deref, DerefMut, Default)]
 pub struct BulletDirection(Vec2);
 
-fn spawn_gun(mut commands: Commands, text_atlases: Res<GlobTextAtlases>) {
-    let layout = text_atlases.common.clone().unwrap().layout;
-    let image = text_atlases.common.clone().unwrap().image;
+/// Bullets get despawned once they are further than `max` away from where they were fired.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct BulletRange {
+    pub origin: Vec2,
+    pub max: f32,
+}
+
+/// Fraction of the base damage a bullet gains for every second it flies.
+/// Negative values make bullets weaker the longer they fly.
+#[derive(Component, Debug, Deref, DerefMut, Default, Clone, Copy)]
+pub struct DamageFalloff(pub f32);
 
-    // Gun
-    commands.spawn((
-        Sprite::from_atlas_image(image, TextureAtlas { layout, index: 10 }),
-        Transform::from_translation(Vec3::new(0., 0., 55.)),
-        GunTimer(Stopwatch::new()),
-        Gun,
-    ));
+impl DamageFalloff {
+    /// Computes the damage of a bullet with `base` damage that has been flying for `secs`.
+    pub fn apply(self, base: u32, secs: f32) -> u32 {
+        (base as f32 * (1. + self.0 * secs)).max(0.).round() as u32
+    }
+}
+
+/// Configures the trail left behind by the bullets fired from a gun.
+/// Guns without it fire bullets that leave no trail.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BulletTrail {
+    /// Seconds between two consecutive trail segments.
+    pub interval_secs: f32,
+    /// Seconds it takes a segment to fully fade out.
+    pub fade_secs: f32,
+    pub color: Color,
+    /// Size of a single segment, `x` is along the bullet direction.
+    pub size: Vec2,
+}
+
+impl Default for BulletTrail {
+    fn default() -> Self {
+        BulletTrail {
+            interval_secs: BULLET_TRAIL_INTERVAL_SECS,
+            fade_secs: BULLET_TRAIL_FADE_SECS,
+            color: Color::srgba(1.0, 0.9, 0.6, 0.6),
+            size: vec2(BULLET_SPEED * BULLET_TRAIL_INTERVAL_SECS, 2.0),
+        }
+    }
+}
+
+/// Added to bullets fired from a gun with a [`BulletTrail`].
+#[derive(Component, Debug)]
+pub struct TrailEmitter {
+    pub trail: BulletTrail,
+    pub timer: Timer,
+}
+
+impl TrailEmitter {
+    pub fn new(trail: BulletTrail) -> Self {
+        let timer = Timer::from_seconds(trail.interval_secs, TimerMode::Repeating);
+        TrailEmitter { trail, timer }
+    }
+}
+
+/// A single fading piece of a bullet trail.
+#[derive(Component, Debug)]
+#[require(Transform, Sprite, RunScoped)]
+pub struct TrailSegment {
+    pub fade: Timer,
+    /// Alpha the segment was spawned with.
+    pub alpha: f32,
+}
+
+/// Gives every newly spawned player a gun with the starting weapon of their [`Character`].
+/// The gun is spawned as a child of the player, so it follows the player around and gets
+/// despawned together with it.
+fn equip_new_players(
+    mut commands: Commands,
+    player_query: Query<(Entity, &Character), Added<Player>>,
+    text_atlases: Res<GlobTextAtlases>,
+) {
+    for (player, character) in player_query.iter() {
+        let layout = text_atlases.common().layout;
+        let image = text_atlases.common().image;
+        let index = text_atlases.region(AtlasKind::Common, "gun");
+
+        // Gun
+        commands.entity(player).with_child((
+            Sprite::from_atlas_image(image, TextureAtlas { layout, index }),
+            // Render in front of the player
+            Transform::from_translation(Vec3::new(0., 0., 5.)),
+            GunTimer(Stopwatch::new()),
+            BulletTrail::default(),
+            character.stats().weapon,
+            Gun,
+        ));
+    }
+}
+
+fn toggle_aim_mode(mut aim_mode: ResMut<AimMode>, actions: ActionInput) {
+    if actions.just_pressed(Action::ToggleAimMode) {
+        *aim_mode = match *aim_mode {
+            AimMode::Manual => AimMode::Auto,
+            AimMode::Auto => AimMode::Manual,
+        };
+    }
+}
+
+/// In [`AimMode::Manual`] the gun follows the cursor or the right stick of a gamepad.
+/// In [`AimMode::Auto`] it targets the nearest enemy that is visible on screen and overrides
+/// the [`AimDirection`].
+fn update_aim_target(
+    mut player_query: Query<
+        (&mut AimTarget, &mut AimDirection, &Transform, &Controller),
+        With<Player>,
+    >,
+    aim_mode: Res<AimMode>,
+    aim_input: Res<AimInput>,
+    qtree: Res<EnemyQuadtree>,
+    enemy_query: Query<&Transform, (With<Enemy>, Without<Player>)>,
+    cam_query: Query<(&OrthographicProjection, &GlobalTransform), With<PlayerCam>>,
+) {
+    if *aim_mode == AimMode::Manual {
+        for (mut aim_target, aim_dir, player_transf, controller) in player_query.iter_mut() {
+            let player_pos = player_transf.translation.truncate();
+            **aim_target = if *controller == Controller::Primary {
+                aim_input.pos
+            } else {
+                aim_dir.map(|dir| player_pos + dir * GAMEPAD_AIM_DISTANCE)
+            };
+        }
+        return;
+    }
+
+    let views = cam_query
+        .iter()
+        .map(|(projection, cam_transf)| camera_view_rect(projection, cam_transf))
+        .collect::<Vec<_>>();
+
+    // The quadtree is only refreshed periodically, use the current enemy positions.
+    let enemy_pos = |entity| {
+        enemy_query
+            .get(entity)
+            .ok()
+            .map(|transf| transf.translation.truncate())
+            .filter(|pos| views.iter().any(|view| view.contains(*pos)))
+    };
+
+    for (mut aim_target, mut aim_dir, player_transf, _) in player_query.iter_mut() {
+        let player_pos = player_transf.translation.truncate();
+
+        // `nearest` only looks at the node containing the player, if that comes up empty
+        // fall back to scanning every enemy on screen.
+        **aim_target = qtree
+            .nearest(player_pos)
+            .and_then(|near| enemy_pos(near.entity))
+            .or_else(|| {
+                views
+                    .iter()
+                    .flat_map(|view| qtree.query(*view))
+                    .filter_map(|near| enemy_pos(near.entity))
+                    .min_by(|a, b| {
+                        a.distance_squared(player_pos)
+                            .total_cmp(&b.distance_squared(player_pos))
+                    })
+            });
+
+        if let Some(dir) = aim_target.and_then(|target| Dir2::new(target - player_pos).ok()) {
+            **aim_dir = Some(dir);
+        }
+    }
+}
+
+/// Refills the [`Ammo`] and cancels the reload whenever the [`Weapon`] changes.
+fn refill_ammo(
+    mut cmds: Commands,
+    mut gun_query: Query<(Entity, &mut Ammo, &Weapon), (With<Gun>, Changed<Weapon>)>,
+) {
+    for (gun_ent, mut ammo, weapon) in gun_query.iter_mut() {
+        *ammo = Ammo::full(*weapon);
+        cmds.entity(gun_ent).remove::<Reloading>();
+    }
+}
+
+fn handle_reload_input(
+    mut cmds: Commands,
+    gun_query: Query<(Entity, &Ammo, &Weapon, &Parent), (With<Gun>, Without<Reloading>)>,
+    owner_query: Query<&Controller, Without<Dying>>,
+    actions: ActionInput,
+    gamepads: ControllerGamepads,
+    input_device: Res<InputDevice>,
+) {
+    for (gun_ent, ammo, weapon, parent) in gun_query.iter() {
+        let Ok(controller) = owner_query.get(parent.get()) else {
+            continue;
+        };
+        let reload_pressed = if controller.uses_gamepad(*input_device) {
+            gamepads
+                .of(*controller)
+                .any(|gamepad| gamepad.just_pressed(RELOAD_GAMEPAD_BUTTON))
+        } else {
+            actions.just_pressed(Action::Reload)
+        };
+        if reload_pressed && ammo.can_reload(weapon.stats().clip_size) {
+            cmds.entity(gun_ent).insert(Reloading::new(*weapon));
+        }
+    }
+}
+
+fn finish_reloads(
+    mut cmds: Commands,
+    mut gun_query: Query<(Entity, &mut Reloading, &mut Ammo, &Weapon), With<Gun>>,
+    time: Res<Time>,
+) {
+    for (gun_ent, mut reloading, mut ammo, weapon) in gun_query.iter_mut() {
+        if reloading.tick(time.delta()).finished() {
+            ammo.reload(weapon.stats().clip_size);
+            cmds.entity(gun_ent).remove::<Reloading>();
+        }
+    }
 }
 
 fn handle_gun_input(
     mut cmds: Commands,
-    mut gun_query: Query<(&mut GunTimer, &Transform), With<Gun>>,
-    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut gun_query: Query<
+        (
+            Entity,
+            &mut GunTimer,
+            &mut Ammo,
+            &mut Weapon,
+            &GlobalTransform,
+            &Parent,
+            Option<&BulletTrail>,
+        ),
+        (With<Gun>, Without<Reloading>),
+    >,
+    owner_query: Query<(&PlayerStats, &AimTarget, &Controller), Without<Dying>>,
+    mut gun_fired_events: EventWriter<GunFired>,
+    actions: ActionInput,
+    gamepads: ControllerGamepads,
+    input_device: Res<InputDevice>,
+    aim_mode: Res<AimMode>,
     text_atlases: Res<GlobTextAtlases>,
     time: Res<Time>,
 ) {
-    let (mut gun_timer, gun_transf) = gun_query.single_mut();
-    gun_timer.tick(time.delta());
-
-    if mouse_input.pressed(MouseButton::Left)
-        && gun_timer.elapsed_secs() >= BULLET_SPAWN_INTERVAL_SECS
+    for (gun_ent, mut gun_timer, mut ammo, mut weapon, gun_transf, parent, bullet_trail) in
+        gun_query.iter_mut()
     {
-        let gun_pos = gun_transf.translation.truncate();
-        let bullet_dir = gun_transf.local_x().truncate().normalize_or_zero();
-        let layout = text_atlases.common.clone().unwrap().layout;
-        let image = text_atlases.common.clone().unwrap().image;
+        gun_timer.tick(time.delta());
+        // the owner can't shoot while dying
+        let Ok((owner_stats, aim_target, controller)) = owner_query.get(parent.get()) else {
+            continue;
+        };
+        let stats = weapon.stats();
+        let fire_interval_secs = stats.fire_interval_secs / owner_stats.fire_rate_mult;
+        let damage = (stats.damage as f32 * owner_stats.damage_mult).round() as u32;
+
+        let trigger_pulled = match *aim_mode {
+            AimMode::Manual if controller.uses_gamepad(*input_device) => gamepads
+                .of(*controller)
+                .any(|gamepad| gamepad.pressed(GamepadButton::RightTrigger2)),
+            AimMode::Manual => actions.pressed(Action::Fire),
+            AimMode::Auto => aim_target.is_some(),
+        };
+
+        if !trigger_pulled || gun_timer.elapsed_secs() < fire_interval_secs {
+            continue;
+        }
+        if ammo.is_empty() {
+            // fall back to the pistol, which never runs out
+            *weapon = Weapon::default();
+            continue;
+        }
+        if ammo.clip == 0 {
+            cmds.entity(gun_ent).insert(Reloading::new(*weapon));
+            continue;
+        }
+
+        let gun_pos = gun_transf.translation().truncate();
+        let bullet_dir = gun_transf.right().truncate().normalize_or_zero();
+        let layout = text_atlases.common().layout;
+        let image = text_atlases.common().image;
+        let index = text_atlases.region(AtlasKind::Common, "bullet");
 
         gun_timer.reset();
+        ammo.clip -= 1;
+        for i in 0..stats.bullets_per_shot {
+            // spread the bullets evenly across the spread angle
+            let spread_offs = if stats.bullets_per_shot > 1 {
+                stats.spread * (i as f32 / (stats.bullets_per_shot - 1) as f32 - 0.5)
+            } else {
+                0.
+            };
+            let dir = Vec2::from_angle(spread_offs).rotate(bullet_dir);
+
+            let mut bullet = cmds.spawn((
+                Sprite::from_atlas_image(
+                    image.clone(),
+                    TextureAtlas {
+                        layout: layout.clone(),
+                        index,
+                    },
+                ),
+                // Spawn between the player and the gun on Z-axis
+                Transform::from_translation(gun_pos.extend(52.5)).with_scale(Vec3::splat(0.95)),
+                Bullet,
+                BulletDirection(dir),
+                Damage(damage),
+                DamageFalloff(stats.falloff_per_sec),
+                BulletRange {
+                    origin: gun_pos,
+                    max: stats.max_range,
+                },
+            ));
+            if let Some(&trail) = bullet_trail {
+                bullet.insert(TrailEmitter::new(trail));
+            }
+        }
+
+        gun_fired_events.send(GunFired {
+            gun: gun_ent,
+            pos: gun_pos + bullet_dir * GUN_BARREL_LENGTH,
+            dir: bullet_dir,
+        });
+    }
+}
+
+/// Spawns a muzzle flash at the barrel and kicks the gun back on every shot.
+fn gun_feedback(
+    mut cmds: Commands,
+    mut gun_fired_events: EventReader<GunFired>,
+    mut gun_query: Query<&mut GunRecoil, With<Gun>>,
+) {
+    for fired in gun_fired_events.read() {
+        if let Ok(mut recoil) = gun_query.get_mut(fired.gun) {
+            **recoil = 1.0;
+        }
+
         cmds.spawn((
-            Sprite::from_atlas_image(image, TextureAtlas { layout, index: 11 }),
-            // Spawn between the player and the gun on Z-axis
-            Transform::from_translation(gun_pos.extend(52.5)).with_scale(Vec3::splat(0.95)),
-            Bullet,
-            BulletDirection(bullet_dir),
-            Damage(10),
+            Sprite::from_color(Color::srgb(1.0, 0.85, 0.4), Vec2::splat(5.0)),
+            // Render in front of the gun
+            Transform::from_translation(fired.pos.extend(56.))
+                .with_rotation(Quat::from_rotation_z(PI * 0.25)),
+            MuzzleFlash(Timer::from_seconds(MUZZLE_FLASH_SECS, TimerMode::Once)),
         ));
     }
 }
 
-fn update_gun_pos(
-    mut gun_query: Query<&mut Transform, (With<Gun>, Without<Player>)>,
-    player_query: Query<&Transform, With<Player>>,
-    cursor_pos: Res<CursorPos>,
+/// Recolors the gun sprite whenever its [`Weapon`] changes.
+fn apply_weapon_tint(mut gun_query: Query<(&mut Sprite, &Weapon), (With<Gun>, Changed<Weapon>)>) {
+    for (mut sprite, weapon) in gun_query.iter_mut() {
+        sprite.color = weapon.stats().tint;
+    }
+}
+
+fn recover_gun_recoil(mut gun_query: Query<&mut GunRecoil, With<Gun>>, time: Res<Time>) {
+    for mut recoil in gun_query.iter_mut() {
+        **recoil = (**recoil - time.delta_secs() / GUN_RECOIL_RECOVERY_SECS).max(0.0);
+    }
+}
+
+fn despawn_muzzle_flashes(
+    mut cmds: Commands,
+    mut flash_query: Query<(Entity, &mut MuzzleFlash)>,
+    time: Res<Time>,
 ) {
-    let player_pos = player_query.single().translation.truncate();
-    let mut gun_transf = gun_query.single_mut();
-    let cursor_pos = cursor_pos.unwrap_or(player_pos);
+    for (ent, mut flash) in flash_query.iter_mut() {
+        if flash.tick(time.delta()).finished() {
+            cmds.entity(ent).despawn();
+        }
+    }
+}
 
-    let angle = (player_pos.y - cursor_pos.y).atan2(player_pos.x - cursor_pos.x) + PI;
-    gun_transf.rotation = Quat::from_rotation_z(angle);
+/// Rotates the gun in the [`AimDirection`].
+/// The gun is a child of the player, so only its offset relative to the player is updated.
+fn update_gun_pos(
+    mut gun_query: Query<(&mut Transform, &GunRecoil, &Parent), With<Gun>>,
+    aim_query: Query<&AimDirection>,
+) {
+    for (mut gun_transf, recoil, parent) in gun_query.iter_mut() {
+        // point to the left until there is some aiming input
+        let angle = aim_query
+            .get(parent.get())
+            .ok()
+            .and_then(|aim_dir| aim_dir.0)
+            .map_or(PI, |dir| dir.to_angle());
+        // kick the barrel upwards, which is the other way around when the gun is flipped
+        let kick_dir = if angle.cos() < 0. { -1. } else { 1. };
+        let kick_angle = **recoil * GUN_RECOIL_ANGLE * kick_dir;
+        gun_transf.rotation = Quat::from_rotation_z(angle + kick_angle);
 
-    let offs = 4.;
-    let new_gun_pos = vec2(
-        player_pos.x + offs * angle.cos(),
-        player_pos.y + offs * angle.sin() - 4.,
-    );
+        let offs = 4. - **recoil * GUN_RECOIL_DISTANCE;
+        let local_gun_pos = vec2(offs * angle.cos(), offs * angle.sin() - 4.);
 
-    gun_transf.translation = new_gun_pos.extend(gun_transf.translation.z);
+        gun_transf.translation = local_gun_pos.extend(gun_transf.translation.z);
+    }
 }
 
 fn update_bullet_pos(
@@ -127,13 +667,119 @@ fn update_bullet_pos(
     });
 }
 
+fn emit_bullet_trails(
+    mut cmds: Commands,
+    mut emitter_query: Query<(&mut TrailEmitter, &Transform, &BulletDirection), With<Bullet>>,
+    time: Res<Time>,
+) {
+    for (mut emitter, bullet_transf, dir) in emitter_query.iter_mut() {
+        if !emitter.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        let trail = emitter.trail;
+        // Place the segment right behind the bullet so it connects to the previous one.
+        let pos = bullet_transf.translation.truncate() - **dir * trail.size.x * 0.5;
+        cmds.spawn((
+            Sprite::from_color(trail.color, trail.size),
+            // Render the trail just below the bullets
+            Transform::from_translation(pos.extend(52.))
+                .with_rotation(Quat::from_rotation_z(dir.to_angle())),
+            TrailSegment {
+                fade: Timer::from_seconds(trail.fade_secs, TimerMode::Once),
+                alpha: trail.color.alpha(),
+            },
+        ));
+    }
+}
+
+fn fade_trail_segments(
+    mut cmds: Commands,
+    mut segment_query: Query<(Entity, &mut TrailSegment, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (ent, mut segment, mut sprite) in segment_query.iter_mut() {
+        if segment.fade.tick(time.delta()).finished() {
+            cmds.entity(ent).despawn();
+            continue;
+        }
+
+        let alpha = segment.alpha * segment.fade.fraction_remaining();
+        sprite.color.set_alpha(alpha);
+    }
+}
+
+/// Despawns bullets that are too old, have left the world or have exceeded their range.
 fn despawn_bullets(
     mut commands: Commands,
-    bullet_query: Query<(Entity, &SpawnInstant), With<Bullet>>,
+    bullet_query: Query<(Entity, &SpawnInstant, &Transform, &BulletRange), With<Bullet>>,
+    config: Res<WorldConfig>,
 ) {
-    bullet_query.iter().for_each(|(ent, inst)| {
-        if inst.elapsed().as_secs_f32() >= BULLET_LIFE_SECS {
+    let world = config.rect();
+
+    bullet_query.iter().for_each(|(ent, inst, transf, range)| {
+        let pos = transf.translation.truncate();
+        if inst.elapsed().as_secs_f32() >= BULLET_LIFE_SECS
+            || !world.contains(pos)
+            || pos.distance_squared(range.origin) >= range.max * range.max
+        {
             commands.entity(ent).despawn()
         }
     });
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn damage_falloff_works() {
+        let no_falloff = DamageFalloff(0.);
+        assert_eq!(no_falloff.apply(10, 0.), 10);
+        assert_eq!(no_falloff.apply(10, 5.), 10);
+
+        let falloff = DamageFalloff(-0.5);
+        assert_eq!(falloff.apply(10, 0.), 10);
+        assert_eq!(falloff.apply(10, 1.), 5);
+        // never goes below zero
+        assert_eq!(falloff.apply(10, 3.), 0);
+
+        let gain = DamageFalloff(0.5);
+        assert_eq!(gain.apply(10, 2.), 20);
+    }
+
+    #[test]
+    fn ammo_reloads_from_reserve() {
+        let mut ammo = Ammo {
+            clip: 2,
+            reserve: Some(5),
+        };
+        assert!(ammo.can_reload(6));
+        ammo.reload(6);
+        assert_eq!(
+            ammo,
+            Ammo {
+                clip: 6,
+                reserve: Some(1)
+            }
+        );
+        ammo.clip = 0;
+        ammo.reload(6);
+        assert_eq!(
+            ammo,
+            Ammo {
+                clip: 1,
+                reserve: Some(0)
+            }
+        );
+        assert!(!ammo.can_reload(6));
+
+        let mut unlimited = Ammo {
+            clip: 0,
+            reserve: None,
+        };
+        unlimited.reload(12);
+        assert_eq!(unlimited.clip, 12);
+        assert!(!unlimited.is_empty());
+    }
+}
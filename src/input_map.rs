@@ -0,0 +1,342 @@
+//! Maps logical [`Action`]s to keyboard and mouse [`Binding`]s.
+//!
+//! Gameplay systems should read the input through [`ActionInput`] instead of checking the keys
+//! directly, so the bindings can be changed by the user. The [`InputMap`] is a part of the
+//! [`UserSettings`] and persisted with them.
+//!
+//! Gamepads always use their default buttons.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::camera::DebugCam;
+use crate::settings::UserSettings;
+
+/// Everything the player can do with the keyboard and mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Sprint,
+    Fire,
+    Reload,
+    ThrowGrenade,
+    Ability1,
+    Ability2,
+    ToggleAimMode,
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Sprint,
+        Action::Fire,
+        Action::Reload,
+        Action::ThrowGrenade,
+        Action::Ability1,
+        Action::Ability2,
+        Action::ToggleAimMode,
+    ];
+
+    /// Key of the localized name.
+    pub fn locale_key(self) -> &'static str {
+        match self {
+            Action::MoveUp => "action.move_up",
+            Action::MoveDown => "action.move_down",
+            Action::MoveLeft => "action.move_left",
+            Action::MoveRight => "action.move_right",
+            Action::Sprint => "action.sprint",
+            Action::Fire => "action.fire",
+            Action::Reload => "action.reload",
+            Action::ThrowGrenade => "action.throw_grenade",
+            Action::Ability1 => "action.ability_1",
+            Action::Ability2 => "action.ability_2",
+            Action::ToggleAimMode => "action.toggle_aim_mode",
+        }
+    }
+
+    /// The key used in the controls file.
+    fn cfg_key(self) -> &'static str {
+        match self {
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Sprint => "sprint",
+            Action::Fire => "fire",
+            Action::Reload => "reload",
+            Action::ThrowGrenade => "throw_grenade",
+            Action::Ability1 => "ability_1",
+            Action::Ability2 => "ability_2",
+            Action::ToggleAimMode => "toggle_aim_mode",
+        }
+    }
+
+    fn default_bindings(self) -> Vec<Binding> {
+        use Binding::{Key, Mouse};
+        match self {
+            Action::MoveUp => vec![Key(KeyCode::KeyW), Key(KeyCode::ArrowUp)],
+            Action::MoveDown => vec![Key(KeyCode::KeyS), Key(KeyCode::ArrowDown)],
+            Action::MoveLeft => vec![Key(KeyCode::KeyA), Key(KeyCode::ArrowLeft)],
+            Action::MoveRight => vec![Key(KeyCode::KeyD), Key(KeyCode::ArrowRight)],
+            Action::Sprint => vec![Key(KeyCode::ShiftLeft), Key(KeyCode::ShiftRight)],
+            Action::Fire => vec![Mouse(MouseButton::Left)],
+            Action::Reload => vec![Key(KeyCode::KeyR)],
+            Action::ThrowGrenade => vec![Mouse(MouseButton::Right)],
+            Action::Ability1 => vec![Key(KeyCode::KeyQ)],
+            Action::Ability2 => vec![Key(KeyCode::KeyE)],
+            Action::ToggleAimMode => vec![Key(KeyCode::KeyT)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// The keys that can be bound, anything else can't be saved.
+const BINDABLE_KEYS: [KeyCode; 63] = [
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::ArrowUp,
+    KeyCode::ArrowDown,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::AltLeft,
+    KeyCode::AltRight,
+    KeyCode::Space,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::CapsLock,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+];
+
+const BINDABLE_MOUSE_BUTTONS: [MouseButton; 5] = [
+    MouseButton::Left,
+    MouseButton::Right,
+    MouseButton::Middle,
+    MouseButton::Back,
+    MouseButton::Forward,
+];
+
+impl Binding {
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{key:?}"),
+            Binding::Mouse(button) => format!("Mouse{button:?}"),
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        BINDABLE_KEYS
+            .into_iter()
+            .map(Binding::Key)
+            .chain(BINDABLE_MOUSE_BUTTONS.into_iter().map(Binding::Mouse))
+            .find(|binding| binding.name() == name)
+    }
+
+    pub fn is_bindable(&self) -> bool {
+        match self {
+            Binding::Key(key) => BINDABLE_KEYS.contains(key),
+            Binding::Mouse(button) => BINDABLE_MOUSE_BUTTONS.contains(button),
+        }
+    }
+}
+
+/// The [`Binding`]s of every [`Action`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputMap {
+    bindings: Vec<(Action, Vec<Binding>)>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        InputMap {
+            bindings: Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_bindings()))
+                .collect(),
+        }
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map_or(&[], |(_, bindings)| bindings)
+    }
+
+    /// The other action that already uses the binding, if any.
+    pub fn conflict(&self, action: Action, binding: Binding) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(a, bindings)| *a != action && bindings.contains(&binding))
+            .map(|(a, _)| *a)
+    }
+
+    /// Replaces all the bindings of the action with the new one.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        if let Some((_, bindings)) = self.bindings.iter_mut().find(|(a, _)| *a == action) {
+            *bindings = vec![binding];
+        }
+    }
+
+    pub fn to_cfg(&self) -> String {
+        self.bindings
+            .iter()
+            .map(|(action, bindings)| {
+                let names = bindings.iter().map(Binding::name).collect::<Vec<_>>();
+                format!("{} = {}\n", action.cfg_key(), names.join(", "))
+            })
+            .collect()
+    }
+
+    pub fn from_cfg(cfg: &str) -> Self {
+        let mut map = InputMap::default();
+        for (key, val) in cfg
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, val)| (key.trim(), val.trim()))
+        {
+            let Some((_, bindings)) = map.bindings.iter_mut().find(|(a, _)| a.cfg_key() == key)
+            else {
+                warn!("Unknown action: {key}");
+                continue;
+            };
+            let parsed = val
+                .split(',')
+                .filter_map(|name| Binding::parse(name.trim()))
+                .collect::<Vec<_>>();
+            // keep the defaults instead of leaving the action unbound
+            if !parsed.is_empty() {
+                *bindings = parsed;
+            }
+        }
+        map
+    }
+}
+
+/// Reads the state of the [`Action`]s through the [`InputMap`] of the [`UserSettings`].
+/// Nothing is pressed while the [`DebugCam`] is on.
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    settings: Res<'w, UserSettings>,
+    kbd_input: Res<'w, ButtonInput<KeyCode>>,
+    mouse_input: Res<'w, ButtonInput<MouseButton>>,
+    debug_cam: Res<'w, DebugCam>,
+}
+
+impl ActionInput<'_> {
+    pub fn pressed(&self, action: Action) -> bool {
+        !**self.debug_cam
+            && self
+                .settings
+                .controls
+                .bindings(action)
+                .iter()
+                .any(|binding| match binding {
+                    Binding::Key(key) => self.kbd_input.pressed(*key),
+                    Binding::Mouse(button) => self.mouse_input.pressed(*button),
+                })
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        !**self.debug_cam
+            && self
+                .settings
+                .controls
+                .bindings(action)
+                .iter()
+                .any(|binding| match binding {
+                    Binding::Key(key) => self.kbd_input.just_pressed(*key),
+                    Binding::Mouse(button) => self.mouse_input.just_pressed(*button),
+                })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn input_map_rebinds_and_roundtrips() {
+        let mut map = InputMap::default();
+        assert_eq!(
+            map.conflict(Action::Ability1, Binding::Key(KeyCode::KeyW)),
+            Some(Action::MoveUp)
+        );
+        assert_eq!(
+            map.conflict(Action::MoveUp, Binding::Key(KeyCode::KeyW)),
+            None
+        );
+
+        map.rebind(Action::Fire, Binding::Key(KeyCode::Space));
+        assert_eq!(map.bindings(Action::Fire), &[Binding::Key(KeyCode::Space)]);
+        assert_eq!(InputMap::from_cfg(&map.to_cfg()), map);
+
+        // unknown bindings fall back to the defaults
+        let map = InputMap::from_cfg("sprint = NotAKey\n");
+        assert_eq!(
+            map.bindings(Action::Sprint),
+            &Action::Sprint.default_bindings()[..]
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Contains [`InventoryPlugin`] which manages the passive items the player collects.
+//!
+//! Items are acquired by sending an [`ItemAcquired`] event, this way upgrades, pickups or
+//! anything else can hand out items without knowing what they do.
+//! Every item is stored in the [`Inventory`] and applies its bonus to the owner's stats.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::Health;
+use crate::gui::toast::Notify;
+use crate::locale::Localizer;
+use crate::player::PlayerStats;
+use crate::prelude::*;
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        // not bound to a state, items can be handed out from the level up menu as well
+        app.add_event::<ItemAcquired>()
+            .add_systems(Update, acquire_items);
+    }
+}
+
+/// Items that passively improve the stats of whoever holds them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PassiveItem {
+    /// Increases the movement speed.
+    Boots,
+    /// Increases the max health.
+    ArmorPlate,
+}
+
+impl PassiveItem {
+    pub const ALL: [PassiveItem; 2] = [PassiveItem::Boots, PassiveItem::ArmorPlate];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PassiveItem::Boots => "Boots",
+            PassiveItem::ArmorPlate => "Armor Plate",
+        }
+    }
+
+    /// Key of the localized name.
+    pub fn locale_key(self) -> &'static str {
+        match self {
+            PassiveItem::Boots => "item.boots",
+            PassiveItem::ArmorPlate => "item.armor_plate",
+        }
+    }
+
+    pub fn description(self, localizer: &Localizer) -> String {
+        match self {
+            PassiveItem::Boots => localizer.trf(
+                "item.boots_description",
+                &[&(ITEM_BOOTS_SPEED_BONUS * 100.).round()],
+            ),
+            PassiveItem::ArmorPlate => localizer.trf(
+                "item.armor_plate_description",
+                &[&ITEM_ARMOR_PLATE_HEALTH_BONUS],
+            ),
+        }
+    }
+}
+
+/// The passive items held by an entity, the same item can be stacked multiple times.
+#[derive(Component, Debug, Default, Clone)]
+pub struct Inventory {
+    items: Vec<(PassiveItem, u32)>,
+}
+
+impl Inventory {
+    /// Adds the item to the inventory and returns how many of it are held now.
+    pub fn add(&mut self, item: PassiveItem) -> u32 {
+        match self.items.iter_mut().find(|(held, _)| *held == item) {
+            Some((_, count)) => {
+                *count += 1;
+                *count
+            }
+            None => {
+                self.items.push((item, 1));
+                1
+            }
+        }
+    }
+
+    /// How many of the `item` are held.
+    pub fn count(&self, item: PassiveItem) -> u32 {
+        self.items
+            .iter()
+            .find(|(held, _)| *held == item)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    /// Held items and their stack counts, in the order they were first acquired.
+    pub fn iter(&self) -> impl Iterator<Item = (PassiveItem, u32)> + '_ {
+        self.items.iter().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Send this to give an `item` to the `entity`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ItemAcquired {
+    pub entity: Entity,
+    pub item: PassiveItem,
+}
+
+fn acquire_items(
+    mut acquired_events: EventReader<ItemAcquired>,
+    mut owner_query: Query<(&mut Inventory, &mut PlayerStats, &mut Health)>,
+    mut notify_events: EventWriter<Notify>,
+    localizer: Localizer,
+) {
+    for event in acquired_events.read() {
+        let Ok((mut inventory, mut stats, mut hp)) = owner_query.get_mut(event.entity) else {
+            continue;
+        };
+
+        inventory.add(event.item);
+        notify_events.send(Notify::new(
+            localizer.trf("toast.picked_up", &[&localizer.tr(event.item.locale_key())]),
+        ));
+        match event.item {
+            PassiveItem::Boots => stats.move_speed += PLAYER_SPEED * ITEM_BOOTS_SPEED_BONUS,
+            PassiveItem::ArmorPlate => {
+                hp.max += ITEM_ARMOR_PLATE_HEALTH_BONUS;
+                hp.heal(ITEM_ARMOR_PLATE_HEALTH_BONUS as u16);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn inventory_stacks_items() {
+        let mut inventory = Inventory::default();
+        assert!(inventory.is_empty());
+
+        assert_eq!(inventory.add(PassiveItem::Boots), 1);
+        assert_eq!(inventory.add(PassiveItem::ArmorPlate), 1);
+        assert_eq!(inventory.add(PassiveItem::Boots), 2);
+
+        assert_eq!(inventory.count(PassiveItem::Boots), 2);
+        assert_eq!(inventory.count(PassiveItem::ArmorPlate), 1);
+        assert_eq!(
+            inventory.iter().collect::<Vec<_>>(),
+            vec![(PassiveItem::Boots, 2), (PassiveItem::ArmorPlate, 1)]
+        );
+    }
+}
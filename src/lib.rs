@@ -1,6 +1,6 @@
-//! All the modules except for [`components`], [`state`] and [`quadtree`] contain their own plugin.
+//! All the modules except for [`components`], [`quadtree`] and [`rng`] contain their own plugin.
 
-#![allow(clippy::type_complexity)]
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
 
 pub mod prelude;
 
@@ -8,8 +8,17 @@ pub mod prelude;
 pub mod components;
 // generic resources and asset loading
 pub mod resources;
+// saving and continuing a run
+pub mod save;
 pub mod score;
+// user settings
+pub mod locale;
+pub mod settings;
+pub mod sound;
+// keyboard and mouse bindings
+pub mod input_map;
 pub mod state;
+pub mod time_scale;
 // world decorations etc.
 pub mod world;
 
@@ -18,8 +27,17 @@ pub mod gui;
 
 pub mod collision;
 pub mod quadtree;
+pub mod rng;
 
 pub mod animation;
 pub mod enemy;
+pub mod experience;
+pub mod grenade;
 pub mod gun;
+pub mod inventory;
+pub mod orbital;
+pub mod pickup;
 pub mod player;
+pub mod poi;
+pub mod portal;
+pub mod upgrades;
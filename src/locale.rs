@@ -0,0 +1,205 @@
+//! Contains the [`LocalePlugin`] that translates the UI text to the [`Language`] picked in the
+//! [`VideoSettings`](crate::settings::VideoSettings).
+//!
+//! Every language has its own `assets/locale/<code>.lang` file with `key = value` lines.
+//! The values can contain `{}` placeholders that get filled in by [`Localizer::trf`].
+//! Missing keys fall back to English and then to the key itself.
+//!
+//! Static text should be spawned with a [`Localized`] component, so it's translated again
+//! whenever the language changes.
+
+use std::fmt::{Display, Write};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    ecs::system::SystemParam,
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::settings::{cfg_entries, UserSettings};
+
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LocaleStrings>()
+            .init_asset_loader::<LocaleStringsLoader>()
+            .init_resource::<Locale>()
+            .add_systems(PostUpdate, update_localized_texts);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::German];
+
+    /// The name of the language in the language itself.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::German => "Deutsch",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lang| lang.code() == code)
+    }
+
+    pub fn next(&self) -> Self {
+        let idx = Self::ALL.iter().position(|lang| lang == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    fn path(&self) -> String {
+        format!("locale/{}.lang", self.code())
+    }
+}
+
+/// The translated text of every key in a language file.
+#[derive(Asset, TypePath, Debug, Default, Deref)]
+pub struct LocaleStrings(HashMap<String, String>);
+
+impl LocaleStrings {
+    pub fn from_cfg(cfg: &str) -> Self {
+        LocaleStrings(
+            cfg_entries(cfg)
+                .map(|(key, val)| (key.to_string(), val.to_string()))
+                .collect(),
+        )
+    }
+}
+
+#[derive(Default)]
+struct LocaleStringsLoader;
+
+impl AssetLoader for LocaleStringsLoader {
+    type Asset = LocaleStrings;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let cfg = String::from_utf8(bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(LocaleStrings::from_cfg(&cfg))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lang"]
+    }
+}
+
+/// Handles to the [`LocaleStrings`] of all the languages, they are small enough to keep them
+/// all loaded.
+#[derive(Resource, Debug)]
+pub struct Locale(HashMap<Language, Handle<LocaleStrings>>);
+
+impl FromWorld for Locale {
+    fn from_world(world: &mut World) -> Self {
+        let asset_serv = world.resource::<AssetServer>();
+        Locale(
+            Language::ALL
+                .into_iter()
+                .map(|lang| (lang, asset_serv.load(lang.path())))
+                .collect(),
+        )
+    }
+}
+
+/// Keeps the [`Text`] translated to the selected language, holds the key of the text.
+#[derive(Component, Debug, Deref)]
+#[require(Text)]
+pub struct Localized(pub &'static str);
+
+/// Translates the keys to the selected [`Language`].
+#[derive(SystemParam)]
+pub struct Localizer<'w> {
+    settings: Res<'w, UserSettings>,
+    locale: Res<'w, Locale>,
+    strings: Res<'w, Assets<LocaleStrings>>,
+}
+
+impl Localizer<'_> {
+    /// The text of the key in the selected language.
+    pub fn tr<'a>(&'a self, key: &'a str) -> &'a str {
+        [self.settings.video.language, Language::English]
+            .iter()
+            .filter_map(|lang| self.locale.0.get(lang))
+            .filter_map(|handle| self.strings.get(handle))
+            .find_map(|strings| strings.get(key))
+            .map_or(key, String::as_str)
+    }
+
+    /// The text of the key in the selected language with the `{}` placeholders replaced by the
+    /// arguments, in order.
+    pub fn trf(&self, key: &str, args: &[&dyn Display]) -> String {
+        fill_placeholders(self.tr(key), args)
+    }
+
+    /// Whether the language changed or a language file was (re)loaded since the system last ran.
+    pub fn is_changed(&self) -> bool {
+        self.settings.is_changed() || self.strings.is_changed()
+    }
+}
+
+fn fill_placeholders(template: &str, args: &[&dyn Display]) -> String {
+    let mut parts = template.split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_string();
+    let mut args = args.iter();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            let _ = write!(filled, "{arg}");
+        }
+        filled.push_str(part);
+    }
+    filled
+}
+
+fn update_localized_texts(
+    mut text_query: Query<(Ref<Localized>, &mut Text)>,
+    localizer: Localizer,
+) {
+    let changed = localizer.is_changed();
+    for (localized, mut text) in text_query.iter_mut() {
+        if changed || localized.is_added() {
+            **text = localizer.tr(localized.0).to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn locale_strings_fill_placeholders() {
+        let strings = LocaleStrings::from_cfg("hud.level = LV {}\nfeed.boss = {} slain +{}\n");
+        assert_eq!(fill_placeholders(&strings["hud.level"], &[&3]), "LV 3");
+        assert_eq!(
+            fill_placeholders(&strings["feed.boss"], &[&"Old Gnasher", &100]),
+            "Old Gnasher slain +100"
+        );
+        // missing arguments leave the placeholder empty
+        assert_eq!(fill_placeholders("{} / {}", &[&1]), "1 / ");
+    }
+}
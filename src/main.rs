@@ -1,40 +1,74 @@
 use bevy::prelude::*;
 
 use tutgame::prelude::*;
+use tutgame::settings::{GameConfig, LaunchArgs, UserSettings};
 
 fn main() {
+    let mut game_config = GameConfig::load();
+    let launch_args = LaunchArgs::from_env();
+    game_config.seed = launch_args.seed.or(game_config.seed);
+    let mut user_settings = UserSettings::load(game_config.video);
+    user_settings.video = launch_args.apply(user_settings.video);
+
     App::new()
         .add_plugins(
             DefaultPlugins
                 .set(ImagePlugin::default_nearest())
+                .set(AssetPlugin {
+                    watch_for_changes_override: Some(
+                        cfg!(feature = "hot_reload") && game_config.hot_reload,
+                    ),
+                    ..default()
+                })
                 .set(WindowPlugin {
-                    primary_window: Some(Window {
+                    primary_window: Some(launch_args.window(Window {
                         resizable: true,
                         focused: true,
-                        present_mode: bevy::window::PresentMode::Immediate,
-                        // FIXME: change from displaying on second monitor.
-                        mode: bevy::window::WindowMode::BorderlessFullscreen(
-                            MonitorSelection::Index(1),
-                        ),
-                        ..default()
-                    }),
+                        ..user_settings.video.window()
+                    })),
                     ..default()
                 }),
         )
+        .insert_resource(user_settings)
+        .insert_resource(game_config)
         // State
-        .init_state::<GameState>()
+        .add_plugins(StatePlugin)
         // Internal plugins
         .add_plugins((
-            GuiPlugin,
-            ResourcePlugin,
-            WorldPlugin,
-            CamPlugin,
-            PlayerPlugin,
-            EnemyPlugin,
-            GunPlugin,
-            AnimPlugin,
-            CollisionPlugin,
-            ScorePlugin,
+            (
+                GuiPlugin,
+                HudPlugin,
+                CrosshairPlugin,
+                KillFeedPlugin,
+                ToastPlugin,
+                SettingsPlugin,
+                LocalePlugin,
+                SoundPlugin,
+                ResourcePlugin,
+                WorldPlugin,
+                CamPlugin,
+                AnimPlugin,
+                CollisionPlugin,
+                ScorePlugin,
+            ),
+            (
+                WeatherPlugin,
+                TerrainPlugin,
+                PlayerPlugin,
+                AbilityPlugin,
+                EnemyPlugin,
+                ExperiencePlugin,
+                UpgradePlugin,
+                InventoryPlugin,
+                GunPlugin,
+                GrenadePlugin,
+                PickupPlugin,
+                PortalPlugin,
+                PoiPlugin,
+                OrbitalPlugin,
+                TimeScalePlugin,
+            ),
+            (ScorePopupPlugin, SavePlugin),
         ))
         .run();
 }
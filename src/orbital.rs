@@ -0,0 +1,134 @@
+//! Passive orbiting weapons.
+//!
+//! An entity with an [`OrbitalWeapon`] gets a ring of [`OrbitingBlade`]s that spin around it
+//! and damage every enemy they touch, see [`crate::collision`].
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+use crate::collision::ColliderShape;
+use crate::components::Damage;
+use crate::prelude::*;
+use crate::quadtree::quad_collider::Shape;
+
+pub struct OrbitalPlugin;
+
+impl Plugin for OrbitalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (sync_orbiting_blades, update_orbiting_blades)
+                .chain()
+                .run_if(in_state(GameState::GameRun)),
+        );
+    }
+}
+
+/// Spins `count` blades around the entity it is attached to.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct OrbitalWeapon {
+    pub count: u32,
+    pub radius: f32,
+    /// Radians per second.
+    pub angular_speed: f32,
+    pub damage: u32,
+    /// Current rotation of the whole ring.
+    pub angle: f32,
+}
+
+impl Default for OrbitalWeapon {
+    fn default() -> Self {
+        OrbitalWeapon {
+            count: ORBITAL_BLADE_COUNT,
+            radius: ORBITAL_RADIUS,
+            angular_speed: ORBITAL_ANGULAR_SPEED,
+            damage: 5,
+            angle: 0.,
+        }
+    }
+}
+
+/// A single blade of an [`OrbitalWeapon`].
+#[derive(Component, Debug)]
+#[require(
+    Transform,
+    Sprite,
+    RunScoped,
+    Damage,
+    BladeHitTimer,
+    ColliderShape(|| ColliderShape(Shape::Circle(Circle::new(5.0))))
+)]
+pub struct OrbitingBlade {
+    pub owner: Entity,
+    pub index: u32,
+}
+
+/// Blades deal damage periodically, instead of every frame they overlap an enemy.
+#[derive(Component, Debug, Deref, DerefMut)]
+pub struct BladeHitTimer(pub Timer);
+
+impl Default for BladeHitTimer {
+    fn default() -> Self {
+        BladeHitTimer(Timer::from_seconds(
+            ORBITAL_HIT_INTERVAL_SECS,
+            TimerMode::Repeating,
+        ))
+    }
+}
+
+/// Makes sure every [`OrbitalWeapon`] owns exactly `count` blades.
+fn sync_orbiting_blades(
+    mut commands: Commands,
+    orbital_query: Query<(Entity, &OrbitalWeapon), Changed<OrbitalWeapon>>,
+    blade_query: Query<(Entity, &OrbitingBlade)>,
+) {
+    for (owner, orbital) in orbital_query.iter() {
+        let owned = blade_query
+            .iter()
+            .filter(|(_, blade)| blade.owner == owner)
+            .collect::<Vec<_>>();
+        if owned.len() == orbital.count as usize {
+            continue;
+        }
+
+        owned
+            .iter()
+            .for_each(|(ent, _)| commands.entity(*ent).despawn());
+        let blades = (0..orbital.count)
+            .map(|index| {
+                (
+                    Sprite::from_color(Color::srgb(0.8, 0.85, 0.9), Vec2::new(10., 3.)),
+                    OrbitingBlade { owner, index },
+                    Damage(orbital.damage),
+                )
+            })
+            .collect::<Vec<_>>();
+        commands.spawn_batch(blades);
+    }
+}
+
+fn update_orbiting_blades(
+    mut commands: Commands,
+    mut orbital_query: Query<(&mut OrbitalWeapon, &Transform)>,
+    mut blade_query: Query<(Entity, &mut Transform, &OrbitingBlade), Without<OrbitalWeapon>>,
+    time: Res<Time>,
+) {
+    for (mut orbital, _) in orbital_query.iter_mut() {
+        let angle = (orbital.angle + orbital.angular_speed * time.delta_secs()) % TAU;
+        // don't trigger change detection every frame, it is used to respawn the blades
+        orbital.bypass_change_detection().angle = angle;
+    }
+
+    for (ent, mut blade_transf, blade) in blade_query.iter_mut() {
+        let Ok((orbital, owner_transf)) = orbital_query.get(blade.owner) else {
+            // the owner is gone, e.g. a co-op player that died
+            commands.entity(ent).despawn();
+            continue;
+        };
+
+        let angle = orbital.angle + TAU * blade.index as f32 / orbital.count.max(1) as f32;
+        let pos = owner_transf.translation.truncate() + Vec2::from_angle(angle) * orbital.radius;
+        blade_transf.translation = pos.extend(owner_transf.translation.z + 1.);
+        // point the blades outwards
+        blade_transf.rotation = Quat::from_rotation_z(angle);
+    }
+}
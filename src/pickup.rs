@@ -0,0 +1,418 @@
+//! Items lying around the world that the player can pick up by walking over them.
+//!
+//! Pickups use a [`ColliderShape`] as a sensor: they never block or damage anything, they only
+//! check whether the player overlaps them.
+//!
+//! [`Chest`]s are placed when the world is generated and open once, the first time a player
+//! touches them.
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::animation::clip::SpriteAnimation;
+use crate::collision::{ColliderShape, PickupQuadtree};
+use crate::enemy::EnemyKilled;
+use crate::experience::XpGained;
+use crate::gui::toast::Notify;
+use crate::gun::{Gun, Weapon};
+use crate::inventory::{ItemAcquired, PassiveItem};
+use crate::locale::Localizer;
+use crate::player::{nearest_player_pos, Dying, Player};
+use crate::prelude::*;
+use crate::quadtree::quad_collider::{QuadCollider, Shape};
+use crate::resources::{AtlasKind, GlobTextAtlases};
+use crate::rng::{GameRng, RngStream};
+use crate::score::ScoreAccumulator;
+use crate::world::{config::WorldConfig, pick_world_seed, WorldNoise, WorldSeed};
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::GameInit),
+            (spawn_weapon_pickups, spawn_chests).after(pick_world_seed),
+        )
+        .add_systems(
+            Update,
+            (
+                (arm_weapon_pickups, collect_weapon_pickups).chain(),
+                open_chests,
+                (
+                    spawn_xp_gems,
+                    (find_attracted_pickups, pull_attracted_pickups).chain(),
+                    collect_xp_gems,
+                )
+                    .chain(),
+            )
+                .run_if(in_state(GameState::GameRun)),
+        );
+    }
+}
+
+/// A weapon lying on the ground, walking over it swaps it with the currently equipped one.
+#[derive(Component, Debug, Deref, DerefMut)]
+#[require(
+    Transform,
+    Sprite,
+    RunScoped,
+    ColliderShape(|| ColliderShape(Shape::Circle(Circle::new(8.0))))
+)]
+pub struct WeaponPickup(pub Weapon);
+
+/// Hands out its [`ChestLoot`] to the first player that touches it.
+#[derive(Component, Debug, Deref)]
+#[require(
+    Transform,
+    Sprite,
+    RunScoped,
+    ColliderShape(|| ColliderShape(Shape::Circle(Circle::new(10.0))))
+)]
+pub struct Chest(pub ChestLoot);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChestLoot {
+    /// Dropped next to the chest.
+    Weapon(Weapon),
+    Item(PassiveItem),
+    Score(u64),
+}
+
+impl ChestLoot {
+    fn random(rng: &mut impl Rng) -> Self {
+        match rng.gen_range(0..3) {
+            0 => ChestLoot::Weapon(Weapon::ALL[rng.gen_range(0..Weapon::ALL.len())]),
+            1 => ChestLoot::Item(PassiveItem::ALL[rng.gen_range(0..PassiveItem::ALL.len())]),
+            _ => ChestLoot::Score(CHEST_SCORE),
+        }
+    }
+}
+
+/// A [`Chest`] that has already been opened, it can't be opened again.
+#[derive(Component, Debug, Default)]
+pub struct Opened;
+
+/// Dropped by enemies on death, grants experience when collected.
+#[derive(Component, Debug, Deref, DerefMut)]
+#[require(
+    Transform,
+    Sprite,
+    RunScoped,
+    Magnetic,
+    ColliderShape(|| ColliderShape(Shape::Circle(Circle::new(4.0))))
+)]
+pub struct XpGem(pub u32);
+
+/// Pickups within this radius of the player get pulled towards them.
+#[derive(Component, Debug, Deref, DerefMut)]
+pub struct Magnet(pub f32);
+
+/// Marks a pickup that can be pulled in by a [`Magnet`].
+#[derive(Component, Debug, Default)]
+pub struct Magnetic;
+
+/// A [`Magnetic`] pickup that is flying towards the player, holds its current speed.
+#[derive(Component, Debug, Default, Deref, DerefMut)]
+pub struct Attracted(pub f32);
+
+/// Marks a pickup that can't be collected until the player stops touching it.
+/// Used for the weapons the player drops, so they don't get picked up again immediately.
+#[derive(Component, Debug)]
+pub struct Disarmed;
+
+fn weapon_pickup_bundle(
+    weapon: Weapon,
+    pos: Vec2,
+    text_atlases: &GlobTextAtlases,
+) -> (Sprite, Transform, WeaponPickup) {
+    let layout = text_atlases.common().layout;
+    let image = text_atlases.common().image;
+
+    let mut sprite = Sprite::from_atlas_image(
+        image,
+        TextureAtlas {
+            layout,
+            index: text_atlases.region(AtlasKind::Common, "gun"),
+        },
+    );
+    sprite.color = weapon.stats().tint;
+    (
+        sprite,
+        // Above the decor, below the player
+        Transform::from_translation(pos.extend(20.)),
+        WeaponPickup(weapon),
+    )
+}
+
+fn spawn_weapon_pickups(
+    mut commands: Commands,
+    mut game_rng: ResMut<GameRng>,
+    config: Res<WorldConfig>,
+    text_atlases: Res<GlobTextAtlases>,
+) {
+    let rng = game_rng.stream(RngStream::WeaponPickups);
+    let whalf = config.half_size();
+
+    let pickups = (0..WEAPON_PICKUP_NUM)
+        .map(|_| {
+            let weapon = Weapon::ALL[rng.gen_range(0..Weapon::ALL.len())];
+            let pos = Vec2::new(rng.gen_range(-whalf..whalf), rng.gen_range(-whalf..whalf));
+            weapon_pickup_bundle(weapon, pos, &text_atlases)
+        })
+        .collect::<Vec<_>>();
+
+    commands.spawn_batch(pickups);
+}
+
+/// Scatters the chests around the world, they aren't placed in the biomes with obstacles, so they
+/// can't end up inside of a wall.
+fn spawn_chests(
+    mut commands: Commands,
+    mut game_rng: ResMut<GameRng>,
+    config: Res<WorldConfig>,
+    text_atlases: Res<GlobTextAtlases>,
+    seed: Res<WorldSeed>,
+) {
+    let rng = game_rng.stream(RngStream::Chests);
+    let noise = WorldNoise::new(*seed);
+    let chest = text_atlases.chest();
+    let whalf = config.half_size() - chest.tile_size.x as f32;
+    let closed = text_atlases.region(AtlasKind::Chest, "closed");
+
+    let mut chests = Vec::new();
+    // give up on the chests that can't find a free spot, instead of looping forever
+    for _ in 0..CHEST_NUM * 10 {
+        if chests.len() == CHEST_NUM as usize {
+            break;
+        }
+        let pos = Vec2::new(rng.gen_range(-whalf..whalf), rng.gen_range(-whalf..whalf));
+        if noise.biome(pos).has_obstacles() {
+            continue;
+        }
+        chests.push((
+            Sprite::from_atlas_image(
+                chest.image.clone(),
+                TextureAtlas {
+                    layout: chest.layout.clone(),
+                    index: closed,
+                },
+            ),
+            // above the decor, below the player
+            Transform::from_translation(pos.extend(20.)),
+            Chest(ChestLoot::random(rng)),
+        ));
+    }
+
+    commands.spawn_batch(chests);
+}
+
+/// Opens the chests the players touch and hands out the loot.
+fn open_chests(
+    mut commands: Commands,
+    chest_query: Query<(Entity, &Chest, &Transform, &ColliderShape), Without<Opened>>,
+    mut player_query: Query<
+        (Entity, &Transform, &ColliderShape, &mut ScoreAccumulator),
+        (With<Player>, Without<Dying>),
+    >,
+    mut item_events: EventWriter<ItemAcquired>,
+    mut notify_events: EventWriter<Notify>,
+    text_atlases: Res<GlobTextAtlases>,
+    localizer: Localizer,
+) {
+    for (ent, chest, chest_transf, chest_shape) in chest_query.iter() {
+        let chest_pos = chest_transf.translation.truncate();
+        let chest_coll = QuadCollider::new(chest_pos, **chest_shape);
+        let Some((player_ent, _, _, mut score_accum)) =
+            player_query.iter_mut().find(|(_, transf, shape, _)| {
+                QuadCollider::new(transf.translation.truncate(), ***shape).intersects(chest_coll)
+            })
+        else {
+            continue;
+        };
+
+        commands
+            .entity(ent)
+            .insert((Opened, SpriteAnimation::new("chest.open")));
+        match **chest {
+            ChestLoot::Weapon(weapon) => {
+                // don't pick it up right away, the player might already have a better one
+                commands.spawn((
+                    weapon_pickup_bundle(weapon, chest_pos - Vec2::Y * 16., &text_atlases),
+                    Disarmed,
+                ));
+            }
+            ChestLoot::Item(item) => {
+                item_events.send(ItemAcquired {
+                    entity: player_ent,
+                    item,
+                });
+            }
+            ChestLoot::Score(score) => {
+                **score_accum += score;
+                notify_events.send(Notify::new(localizer.trf("toast.chest_score", &[&score])));
+            }
+        }
+    }
+}
+
+/// Re-arms dropped pickups once no player is standing on them.
+fn arm_weapon_pickups(
+    mut commands: Commands,
+    pickup_query: Query<(Entity, &Transform, &ColliderShape), (With<WeaponPickup>, With<Disarmed>)>,
+    player_query: Query<(&Transform, &ColliderShape), With<Player>>,
+) {
+    let player_colls = player_query
+        .iter()
+        .map(|(transf, shape)| QuadCollider::new(transf.translation.truncate(), **shape))
+        .collect::<Vec<_>>();
+
+    for (ent, pickup_transf, pickup_shape) in pickup_query.iter() {
+        let pickup_coll = QuadCollider::new(pickup_transf.translation.truncate(), **pickup_shape);
+        if !player_colls
+            .iter()
+            .any(|&player_coll| pickup_coll.intersects(player_coll))
+        {
+            commands.entity(ent).remove::<Disarmed>();
+        }
+    }
+}
+
+/// Equips the weapon the player walks over and leaves the previous one behind.
+fn collect_weapon_pickups(
+    mut commands: Commands,
+    pickup_query: Query<(Entity, &WeaponPickup, &Transform, &ColliderShape), Without<Disarmed>>,
+    player_query: Query<(Entity, &Transform, &ColliderShape), (With<Player>, Without<Dying>)>,
+    mut gun_query: Query<(&mut Weapon, &Parent), With<Gun>>,
+    text_atlases: Res<GlobTextAtlases>,
+) {
+    for (ent, pickup, pickup_transf, pickup_shape) in pickup_query.iter() {
+        let pickup_pos = pickup_transf.translation.truncate();
+        let pickup_coll = QuadCollider::new(pickup_pos, **pickup_shape);
+        let Some(player_ent) = player_query
+            .iter()
+            .find(|(_, transf, shape)| {
+                QuadCollider::new(transf.translation.truncate(), ***shape).intersects(pickup_coll)
+            })
+            .map(|(ent, _, _)| ent)
+        else {
+            continue;
+        };
+        let Some((mut equipped, _)) = gun_query
+            .iter_mut()
+            .find(|(_, parent)| parent.get() == player_ent)
+        else {
+            continue;
+        };
+
+        let dropped = std::mem::replace(&mut *equipped, **pickup);
+        commands.entity(ent).despawn();
+        commands.spawn((
+            weapon_pickup_bundle(dropped, pickup_pos, &text_atlases),
+            Disarmed,
+        ));
+        // only swap one weapon per frame
+        break;
+    }
+}
+
+fn spawn_xp_gems(mut commands: Commands, mut enemy_killed_events: EventReader<EnemyKilled>) {
+    let gems = enemy_killed_events
+        .read()
+        .map(|killed| {
+            (
+                Sprite::from_color(Color::srgb(0.3, 0.7, 1.0), Vec2::splat(4.)),
+                // Above the decor, below the player
+                Transform::from_translation(killed.pos.extend(15.))
+                    .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+                XpGem(XP_GEM_VALUE),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if !gems.is_empty() {
+        commands.spawn_batch(gems);
+    }
+}
+
+/// Starts pulling in the magnetic pickups that got within a player's [`Magnet`] radius.
+fn find_attracted_pickups(
+    mut commands: Commands,
+    qtree: Res<PickupQuadtree>,
+    pickup_query: Query<&Transform, (With<Magnetic>, Without<Attracted>)>,
+    player_query: Query<(&Transform, &Magnet), (With<Player>, Without<Dying>)>,
+) {
+    for (player_transf, magnet) in player_query.iter() {
+        let player_pos = player_transf.translation.truncate();
+
+        let near_pickups = qtree.query(Rect::from_center_size(
+            player_pos,
+            Vec2::splat(**magnet * 2.),
+        ));
+        for near_pickup in near_pickups {
+            // the quadtree is only refreshed periodically, so use the current position
+            let Ok(pickup_transf) = pickup_query.get(near_pickup.entity) else {
+                continue;
+            };
+            if pickup_transf.translation.truncate().distance(player_pos) <= **magnet {
+                commands
+                    .entity(near_pickup.entity)
+                    .insert(Attracted::default());
+            }
+        }
+    }
+}
+
+/// Pulls the attracted pickups towards the closest player.
+fn pull_attracted_pickups(
+    mut pickup_query: Query<(&mut Transform, &mut Attracted), Without<Player>>,
+    player_query: Query<&Transform, (With<Player>, Without<Dying>)>,
+    time: Res<Time>,
+) {
+    let player_positions = player_query
+        .iter()
+        .map(|transf| transf.translation.truncate())
+        .collect::<Vec<_>>();
+
+    for (mut pickup_transf, mut attracted) in pickup_query.iter_mut() {
+        let pickup_pos = pickup_transf.translation.truncate();
+        let Some(player_pos) = nearest_player_pos(&player_positions, pickup_pos) else {
+            continue;
+        };
+        **attracted += MAGNET_PULL_ACCEL * time.delta_secs();
+
+        let step = (**attracted * time.delta_secs()).min(pickup_pos.distance(player_pos));
+        let delta = (player_pos - pickup_pos).normalize_or_zero() * step;
+        pickup_transf.translation += delta.extend(0.);
+    }
+}
+
+fn collect_xp_gems(
+    mut commands: Commands,
+    mut xp_events: EventWriter<XpGained>,
+    gem_query: Query<(Entity, &XpGem, &Transform, &ColliderShape)>,
+    player_query: Query<(Entity, &Transform, &ColliderShape), (With<Player>, Without<Dying>)>,
+) {
+    let player_colls = player_query
+        .iter()
+        .map(|(ent, transf, shape)| {
+            (
+                ent,
+                QuadCollider::new(transf.translation.truncate(), **shape),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    for (ent, gem, gem_transf, gem_shape) in gem_query.iter() {
+        let gem_coll = QuadCollider::new(gem_transf.translation.truncate(), **gem_shape);
+        // a gem can only be collected by a single player
+        let collector = player_colls
+            .iter()
+            .find(|(_, player_coll)| gem_coll.intersects(*player_coll));
+        if let Some(&(player_ent, _)) = collector {
+            xp_events.send(XpGained {
+                entity: player_ent,
+                amount: **gem,
+            });
+            commands.entity(ent).despawn();
+        }
+    }
+}
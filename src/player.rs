@@ -1,23 +1,54 @@
-use std::time::Duration;
+pub mod abilities;
+pub mod character;
 
-use crate::collision::ColliderShape;
-use crate::components::Health;
+use crate::camera::DebugCam;
+use crate::collision::{ColliderShape, DamageEvent};
+use crate::components::{Health, Shield, SpeedModifier};
+use crate::experience::{Experience, Level};
+use crate::grenade::GrenadeCooldown;
+use crate::gun::AimTarget;
+use crate::input_map::{Action, ActionInput};
+use crate::inventory::Inventory;
+use crate::orbital::OrbitalWeapon;
+use crate::pickup::Magnet;
 use crate::prelude::*;
 use crate::quadtree::quad_collider::Shape;
+use crate::resources::InputDevice;
 use crate::score::ScoreAccumulator;
-use crate::{animation::AnimationTimer, resources::GlobTextAtlases};
+use crate::time_scale::SlowMotion;
+use crate::world::weather::Weather;
+use crate::{
+    animation::clip::SpriteAnimation,
+    resources::{AtlasKind, GlobTextAtlases},
+};
 
-use bevy::prelude::*;
+use abilities::AbilitySlots;
+use bevy::{ecs::system::SystemParam, prelude::*};
+use character::{Character, SelectedCharacter};
+use serde::{Deserialize, Serialize};
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::GameInit), spawn_player)
+        app.add_event::<PlayerDied>()
+            .init_resource::<SelectedCharacter>()
+            .add_systems(OnEnter(GameState::GameInit), spawn_player)
             .add_systems(
                 Update,
-                (handle_player_input, tick_player_iframes_timer)
+                (
+                    (join_coop_player, handle_player_input).run_if(in_state(RunPhase::Playing)),
+                    (handle_player_death, tick_player_death).chain(),
+                )
                     .run_if(in_state(GameState::GameRun)),
+            )
+            .add_systems(
+                Update,
+                interrupt_regen_on_hit.run_if(in_state(GameState::GameRun)),
+            )
+            .add_systems(
+                FixedUpdate,
+                regenerate_health.run_if(in_state(GameState::GameRun)),
             );
     }
 }
@@ -26,16 +57,133 @@ impl Plugin for PlayerPlugin {
 #[derive(Component)]
 #[require(
     Transform,
-    Health(|| Health::new(50)),
+    RunScoped,
+    Controller,
+    Character,
+    AimDirection,
+    AimTarget,
+    GrenadeCooldown,
+    Health(|| Health::new(PLAYER_HEALTH)),
     Sprite,
-    AnimationTimer,
+    SpriteAnimation(|| SpriteAnimation::new("player.idle")),
     PlayerState,
+    Experience,
+    Level,
+    PlayerStats,
+    Inventory,
+    Stamina,
+    SpeedModifier,
+    AbilitySlots,
+    Magnet(|| Magnet(PLAYER_MAGNET_RADIUS)),
     ScoreAccumulator(|| ScoreAccumulator(0)),
-    IFramesTimer(|| IFramesTimer::new_from_secs_f32(PLAYER_IFRAMES_DURATION_SECS)),
     ColliderShape(|| ColliderShape(Shape::Quad(Rectangle::new(11., 13.))))
 )]
 pub struct Player;
 
+/// Stats of a player that upgrades, items and status effects can modify per player.
+/// Every player starts out with the defaults from the prelude.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    /// Movement speed in pixels per second.
+    pub move_speed: f32,
+    /// Multiplies the `move_speed` while sprinting.
+    pub sprint_mult: f32,
+    /// How long the player is [`Invulnerable`](crate::components::Invulnerable) after getting hit.
+    pub iframes_secs: f32,
+    pub damage_mult: f32,
+    pub fire_rate_mult: f32,
+}
+
+impl Default for PlayerStats {
+    fn default() -> Self {
+        PlayerStats {
+            move_speed: PLAYER_SPEED,
+            sprint_mult: PLAYER_SPRINT_SPEED_MULT,
+            iframes_secs: PLAYER_IFRAMES_DURATION_SECS,
+            damage_mult: 1.,
+            fire_rate_mult: 1.,
+        }
+    }
+}
+
+/// Drained while sprinting and refilled while not.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Stamina {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Stamina {
+            current: PLAYER_STAMINA_MAX,
+            max: PLAYER_STAMINA_MAX,
+        }
+    }
+}
+
+/// Decides which input devices control a [`Player`].
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Controller {
+    /// Keyboard and mouse or any gamepad that isn't claimed by another player,
+    /// depending on the current [`InputDevice`].
+    #[default]
+    Primary,
+    /// A single gamepad, used for the co-op players.
+    Gamepad(Entity),
+}
+
+impl Controller {
+    /// Whether the player should currently be read from a gamepad.
+    pub fn uses_gamepad(self, input_device: InputDevice) -> bool {
+        match self {
+            Controller::Primary => input_device == InputDevice::Gamepad,
+            Controller::Gamepad(_) => true,
+        }
+    }
+}
+
+/// Looks up the gamepads that belong to a [`Controller`].
+/// The controllers don't have any while the [`DebugCam`] is on.
+#[derive(SystemParam)]
+pub struct ControllerGamepads<'w, 's> {
+    gamepad_query: Query<'w, 's, (Entity, &'static Gamepad)>,
+    controller_query: Query<'w, 's, &'static Controller>,
+    debug_cam: Res<'w, DebugCam>,
+}
+
+impl ControllerGamepads<'_, '_> {
+    pub fn of(&self, controller: Controller) -> impl Iterator<Item = &Gamepad> {
+        self.gamepad_query
+            .iter()
+            .filter(|_| !**self.debug_cam)
+            .filter(move |(ent, _)| match controller {
+                Controller::Primary => !self.is_claimed(*ent),
+                Controller::Gamepad(gamepad) => *ent == gamepad,
+            })
+            .map(|(_, gamepad)| gamepad)
+    }
+
+    /// Gamepads that aren't controlling a co-op player.
+    pub fn unclaimed(&self) -> impl Iterator<Item = (Entity, &Gamepad)> {
+        self.gamepad_query
+            .iter()
+            .filter(|(ent, _)| !self.is_claimed(*ent))
+    }
+
+    fn is_claimed(&self, gamepad: Entity) -> bool {
+        self.controller_query
+            .iter()
+            .any(|controller| *controller == Controller::Gamepad(gamepad))
+    }
+}
+
+/// The direction the player is aiming in.
+/// Fed by the mouse cursor or the right stick of a gamepad, see [`Controller`].
+/// Keeps the last direction when there is no new input, e.g. when the stick is released.
+#[derive(Component, Debug, Default, Deref, DerefMut)]
+pub struct AimDirection(pub Option<Dir2>);
+
 /// Used for player animation.
 #[derive(Component, Default, PartialEq, Eq)]
 pub enum PlayerState {
@@ -44,52 +192,286 @@ pub enum PlayerState {
     Move,
 }
 
-#[derive(Component, DerefMut, Deref, Clone)]
-pub struct IFramesTimer(pub Timer);
-impl IFramesTimer {
-    /// Cretes a new IFRAME timer, by default it is set to finished aka no IFRAMES active
-    fn new_from_secs_f32(secs: f32) -> Self {
-        let mut t = IFramesTimer(Timer::new(Duration::from_secs_f32(secs), TimerMode::Once));
-        t.set_elapsed(Duration::from_secs_f32(secs));
-        t
+/// Added to the player once their health reaches zero.
+/// The input is disabled while the death animation plays, after that the game is over.
+#[derive(Component, Debug, Deref, DerefMut)]
+pub struct Dying(pub Timer);
+
+/// Sent once the player's health reaches zero.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerDied {
+    pub entity: Entity,
+    pub pos: Vec2,
+}
+
+/// Passive health regeneration, restores `per_sec` health per second
+/// once nothing has hit the entity for `delay_after_hit` seconds.
+#[derive(Component, Debug, Clone)]
+pub struct Regen {
+    pub per_sec: f32,
+    pub delay_after_hit: f32,
+    /// Seconds since the last hit.
+    since_hit: f32,
+    /// Fractional health that wasn't applied yet.
+    pending: f32,
+}
+
+impl Regen {
+    pub fn new(per_sec: f32, delay_after_hit: f32) -> Self {
+        Regen {
+            per_sec,
+            delay_after_hit,
+            since_hit: delay_after_hit,
+            pending: 0.,
+        }
     }
 }
 
+fn player_bundle(
+    controller: Controller,
+    character: Character,
+    pos: Vec2,
+    text_atlases: &GlobTextAtlases,
+) -> (
+    Sprite,
+    Health,
+    PlayerStats,
+    Transform,
+    OrbitalWeapon,
+    Regen,
+    Shield,
+    Controller,
+    Character,
+    Player,
+) {
+    let image = text_atlases.player().image;
+    let layout = text_atlases.player().layout;
+    let index = text_atlases.region(AtlasKind::Player, "idle");
+    let stats = character.stats();
+    let mut sprite = Sprite::from_atlas_image(image, TextureAtlas { layout, index });
+    sprite.color = stats.tint;
+
+    (
+        sprite,
+        Health::new(stats.max_health),
+        stats.stats,
+        Transform::from_translation(pos.extend(50.)),
+        OrbitalWeapon::default(),
+        Regen::new(PLAYER_REGEN_PER_SEC, PLAYER_REGEN_DELAY_SECS),
+        Shield::new(
+            PLAYER_SHIELD_MAX,
+            PLAYER_SHIELD_RECHARGE_DELAY_SECS,
+            PLAYER_SHIELD_RECHARGE_PER_SEC,
+        ),
+        controller,
+        character,
+        Player,
+    )
+}
+
 fn spawn_player(
     mut commands: Commands,
     mut next_state: ResMut<NextState<GameState>>,
+    selected: Res<SelectedCharacter>,
     text_atlases: Res<GlobTextAtlases>,
 ) {
-    let image = text_atlases.player.clone().unwrap().image;
-    let layout = text_atlases.player.clone().unwrap().layout;
-
-    // Player
-    commands.spawn((
-        Sprite::from_atlas_image(image, TextureAtlas { layout, index: 0 }),
-        Transform::from_translation(Vec3::new(0., 0., 50.)),
-        AnimationTimer::new_from_secs(PLAYER_ANIM_INTERVAL_SECS),
-        Player,
+    commands.spawn(player_bundle(
+        Controller::Primary,
+        **selected,
+        Vec2::ZERO,
+        &text_atlases,
     ));
 
     next_state.set(GameState::GameRun)
 }
 
-fn tick_player_iframes_timer(mut iframe_query: Query<&mut IFramesTimer>, time: Res<Time>) {
-    let mut iframe_timer = iframe_query.single_mut();
-    iframe_timer.tick(time.delta());
+/// Pressing start on a gamepad that isn't controlling anyone spawns another player next to the
+/// first one. That gamepad then stops controlling the primary player.
+fn join_coop_player(
+    mut commands: Commands,
+    gamepads: ControllerGamepads,
+    player_query: Query<&Transform, With<Player>>,
+    selected: Res<SelectedCharacter>,
+    text_atlases: Res<GlobTextAtlases>,
+) {
+    if player_query.iter().len() >= PLAYER_MAX_INSTANCES {
+        return;
+    }
+    let Some((gamepad, _)) = gamepads
+        .unclaimed()
+        .find(|(_, gamepad)| gamepad.just_pressed(GamepadButton::Start))
+    else {
+        return;
+    };
+
+    let pos = player_query
+        .iter()
+        .next()
+        .map_or(Vec2::ZERO, |transf| transf.translation.truncate())
+        + Vec2::new(16., 0.);
+    commands.spawn(player_bundle(
+        Controller::Gamepad(gamepad),
+        **selected,
+        pos,
+        &text_atlases,
+    ));
+}
+
+/// Position of the player that is closest to `pos`, if there are any.
+pub fn nearest_player_pos(player_positions: &[Vec2], pos: Vec2) -> Option<Vec2> {
+    player_positions
+        .iter()
+        .copied()
+        .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)))
+}
+
+fn interrupt_regen_on_hit(
+    mut damage_events: EventReader<DamageEvent>,
+    mut regen_query: Query<&mut Regen>,
+) {
+    for event in damage_events.read() {
+        if let Ok(mut regen) = regen_query.get_mut(event.target) {
+            regen.since_hit = 0.;
+            regen.pending = 0.;
+        }
+    }
+}
+
+fn regenerate_health(
+    mut regen_query: Query<(&mut Regen, &mut Health), Without<Dying>>,
+    time: Res<Time>,
+) {
+    for (mut regen, mut hp) in regen_query.iter_mut() {
+        regen.since_hit += time.delta_secs();
+        if regen.since_hit < regen.delay_after_hit || hp.current >= hp.max {
+            regen.pending = 0.;
+            continue;
+        }
+
+        regen.pending += regen.per_sec * time.delta_secs();
+        let whole = regen.pending.floor();
+        if whole >= 1. {
+            regen.pending -= whole;
+            hp.heal(whole as u16);
+        }
+    }
+}
+
+fn handle_player_death(
+    mut commands: Commands,
+    mut player_died_events: EventWriter<PlayerDied>,
+    mut slow_motion_events: EventWriter<SlowMotion>,
+    player_query: Query<
+        (Entity, &Health, &Transform),
+        (With<Player>, Without<Dying>, Changed<Health>),
+    >,
+) {
+    for (ent, hp, transf) in player_query.iter() {
+        if hp.current == 0 {
+            commands.entity(ent).insert(Dying(Timer::from_seconds(
+                PLAYER_DEATH_ANIM_SECS,
+                TimerMode::Once,
+            )));
+            player_died_events.send(PlayerDied {
+                entity: ent,
+                pos: transf.translation.truncate(),
+            });
+            slow_motion_events.send(SlowMotion {
+                scale: PLAYER_DEATH_SLOW_MO_SCALE,
+                secs: PLAYER_DEATH_SLOW_MO_SECS,
+            });
+        }
+    }
+}
+
+/// Removes the player once the death animation finishes,
+/// the game is over once there is no one left alive.
+fn tick_player_death(
+    mut commands: Commands,
+    mut dying_query: Query<(Entity, &mut Dying), With<Player>>,
+    alive_query: Query<(), (With<Player>, Without<Dying>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    for (ent, mut dying) in dying_query.iter_mut() {
+        if !dying.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        if alive_query.is_empty() {
+            next_state.set(GameState::GameOver);
+        } else {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
 }
 
+/// Moves the players, holding Shift (or the left stick on a gamepad) sprints while there is
+/// [`Stamina`] left.
 fn handle_player_input(
-    mut player_query: Query<(&mut Transform, &mut PlayerState), With<Player>>,
-    kbd_input: Res<ButtonInput<KeyCode>>,
+    mut player_query: Query<
+        (
+            &mut Transform,
+            &mut PlayerState,
+            &mut Stamina,
+            &PlayerStats,
+            &SpeedModifier,
+            &Controller,
+        ),
+        (With<Player>, Without<Dying>),
+    >,
+    actions: ActionInput,
+    gamepads: ControllerGamepads,
+    input_device: Res<InputDevice>,
+    weather: Res<Weather>,
     time: Res<Time>,
 ) {
-    let (mut player_transf, mut player_state) = player_query.single_mut();
+    for (mut player_transf, mut player_state, mut stamina, stats, speed_mod, controller) in
+        player_query.iter_mut()
+    {
+        let (dir_delta, sprint_held) = if controller.uses_gamepad(*input_device) {
+            // the stick deflection scales the speed, so the player can move slowly
+            let dir = gamepads
+                .of(*controller)
+                .map(|gamepad| gamepad.left_stick())
+                .find(|stick| stick.length() >= GAMEPAD_MOVE_DEADZONE)
+                .unwrap_or_default()
+                .clamp_length_max(1.);
+            let sprint = gamepads
+                .of(*controller)
+                .any(|gamepad| gamepad.pressed(GamepadButton::LeftThumb));
+            (dir, sprint)
+        } else {
+            (keyboard_move_dir(&actions), actions.pressed(Action::Sprint))
+        };
+
+        let sprinting = sprint_held && stamina.current > 0. && dir_delta.length() > 0.0;
+        stamina.current = if sprinting {
+            (stamina.current - PLAYER_STAMINA_DRAIN_PER_SEC * time.delta_secs()).max(0.)
+        } else {
+            (stamina.current + PLAYER_STAMINA_REGEN_PER_SEC * time.delta_secs()).min(stamina.max)
+        };
+
+        if dir_delta.length() > 0.0 {
+            let sprint_mult = if sprinting { stats.sprint_mult } else { 1. };
+            let speed =
+                stats.move_speed * sprint_mult * **speed_mod * weather.kind.move_speed_mult();
+            player_transf.translation +=
+                Vec3::new(dir_delta.x, dir_delta.y, 0.) * Vec3::splat(speed) * time.delta_secs();
+
+            *player_state = PlayerState::Move;
+        } else {
+            *player_state = PlayerState::Stop;
+        }
+    }
+}
 
-    let up = kbd_input.pressed(KeyCode::KeyW) || kbd_input.pressed(KeyCode::ArrowUp);
-    let down = kbd_input.pressed(KeyCode::KeyS) || kbd_input.pressed(KeyCode::ArrowDown);
-    let left = kbd_input.pressed(KeyCode::KeyA) || kbd_input.pressed(KeyCode::ArrowLeft);
-    let right = kbd_input.pressed(KeyCode::KeyD) || kbd_input.pressed(KeyCode::ArrowRight);
+/// Normalized movement direction from WASD or the arrow keys.
+fn keyboard_move_dir(actions: &ActionInput) -> Vec2 {
+    let up = actions.pressed(Action::MoveUp);
+    let down = actions.pressed(Action::MoveDown);
+    let left = actions.pressed(Action::MoveLeft);
+    let right = actions.pressed(Action::MoveRight);
 
     let mut dir_delta = Vec2::ZERO;
     if up {
@@ -104,14 +486,5 @@ fn handle_player_input(
     if right {
         dir_delta.x += 1.;
     }
-    dir_delta = dir_delta.normalize_or_zero();
-
-    if dir_delta.length() > 0.0 {
-        player_transf.translation +=
-            Vec3::new(dir_delta.x, dir_delta.y, 0.) * Vec3::splat(PLAYER_SPEED) * time.delta_secs();
-
-        *player_state = PlayerState::Move;
-    } else {
-        *player_state = PlayerState::Stop;
-    }
+    dir_delta.normalize_or_zero()
 }
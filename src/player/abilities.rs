@@ -0,0 +1,160 @@
+//! Active abilities the player can trigger with Q and E (or the left bumper and trigger of a
+//! gamepad).
+//!
+//! Every ability implements the [`Ability`] trait and sits in one of the [`AbilitySlots`] of a
+//! player together with its cooldown.
+
+use bevy::prelude::*;
+
+use super::{Controller, ControllerGamepads, Dying, Player};
+use crate::components::Invulnerable;
+use crate::grenade::Explosion;
+use crate::input_map::{Action, ActionInput};
+use crate::prelude::*;
+use crate::resources::InputDevice;
+
+pub struct AbilityPlugin;
+
+impl Plugin for AbilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (tick_ability_cooldowns, use_abilities)
+                .chain()
+                .run_if(in_state(GameState::GameRun)),
+        );
+    }
+}
+
+/// Behavior of an ability that can be put into an [`AbilitySlot`].
+pub trait Ability: Send + Sync + 'static {
+    fn name(&self) -> &'static str;
+    fn cooldown_secs(&self) -> f32;
+    /// Triggers the ability for the `caster` standing at `caster_pos`.
+    fn activate(&self, caster: Entity, caster_pos: Vec2, commands: &mut Commands);
+}
+
+/// Damages all the enemies around the caster.
+pub struct AoeBlast;
+
+impl Ability for AoeBlast {
+    fn name(&self) -> &'static str {
+        "Blast"
+    }
+
+    fn cooldown_secs(&self) -> f32 {
+        ABILITY_BLAST_COOLDOWN_SECS
+    }
+
+    fn activate(&self, _caster: Entity, caster_pos: Vec2, commands: &mut Commands) {
+        commands.send_event(Explosion {
+            pos: caster_pos,
+            radius: ABILITY_BLAST_RADIUS,
+            damage: ABILITY_BLAST_DAMAGE,
+        });
+    }
+}
+
+/// Makes the caster [`Invulnerable`] for a short while.
+pub struct TemporaryShield;
+
+impl Ability for TemporaryShield {
+    fn name(&self) -> &'static str {
+        "Shield"
+    }
+
+    fn cooldown_secs(&self) -> f32 {
+        ABILITY_SHIELD_COOLDOWN_SECS
+    }
+
+    fn activate(&self, caster: Entity, _caster_pos: Vec2, commands: &mut Commands) {
+        commands
+            .entity(caster)
+            .insert(Invulnerable::from_secs(ABILITY_SHIELD_SECS));
+    }
+}
+
+/// An [`Ability`] together with the time until it can be used again.
+pub struct AbilitySlot {
+    pub ability: Box<dyn Ability>,
+    pub cooldown: Timer,
+}
+
+impl AbilitySlot {
+    pub fn new(ability: impl Ability) -> Self {
+        let mut cooldown = Timer::from_seconds(ability.cooldown_secs(), TimerMode::Once);
+        // ready to use right away
+        cooldown.tick(cooldown.duration());
+        AbilitySlot {
+            ability: Box::new(ability),
+            cooldown,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.cooldown.finished()
+    }
+}
+
+/// The abilities of a player, the first slot is bound to Q and the second one to E.
+#[derive(Component)]
+pub struct AbilitySlots(pub [Option<AbilitySlot>; 2]);
+
+impl Default for AbilitySlots {
+    fn default() -> Self {
+        AbilitySlots([
+            Some(AbilitySlot::new(AoeBlast)),
+            Some(AbilitySlot::new(TemporaryShield)),
+        ])
+    }
+}
+
+const ABILITY_ACTIONS: [Action; 2] = [Action::Ability1, Action::Ability2];
+const ABILITY_BUTTONS: [GamepadButton; 2] =
+    [GamepadButton::LeftTrigger, GamepadButton::LeftTrigger2];
+
+fn tick_ability_cooldowns(mut slots_query: Query<&mut AbilitySlots>, time: Res<Time>) {
+    for mut slots in slots_query.iter_mut() {
+        for slot in slots.0.iter_mut().flatten() {
+            slot.cooldown.tick(time.delta());
+        }
+    }
+}
+
+fn use_abilities(
+    mut commands: Commands,
+    mut player_query: Query<
+        (Entity, &mut AbilitySlots, &Transform, &Controller),
+        (With<Player>, Without<Dying>),
+    >,
+    actions: ActionInput,
+    gamepads: ControllerGamepads,
+    input_device: Res<InputDevice>,
+) {
+    for (player_ent, mut slots, player_transf, controller) in player_query.iter_mut() {
+        let uses_gamepad = controller.uses_gamepad(*input_device);
+
+        for (i, slot) in slots.0.iter_mut().enumerate() {
+            let Some(slot) = slot.as_mut().filter(|slot| slot.is_ready()) else {
+                continue;
+            };
+            let pressed = if uses_gamepad {
+                gamepads
+                    .of(*controller)
+                    .any(|gamepad| gamepad.just_pressed(ABILITY_BUTTONS[i]))
+            } else {
+                actions.just_pressed(ABILITY_ACTIONS[i])
+            };
+            if !pressed {
+                continue;
+            }
+
+            slot.ability.activate(
+                player_ent,
+                player_transf.translation.truncate(),
+                &mut commands,
+            );
+            slot.cooldown.reset();
+        }
+    }
+}
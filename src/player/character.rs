@@ -0,0 +1,87 @@
+//! The playable characters, picked on the character select screen before every run.
+//!
+//! The [`SelectedCharacter`] is used for every player that joins the run, co-op players
+//! included.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::PlayerStats;
+use crate::gun::Weapon;
+use crate::prelude::*;
+
+/// What a player is playing as, decides their starting stats and weapon.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Character {
+    #[default]
+    Knight,
+    Ranger,
+    Brute,
+}
+
+/// Everything a [`Character`] starts the run with.
+#[derive(Debug, Clone, Copy)]
+pub struct CharacterStats {
+    pub max_health: u32,
+    pub stats: PlayerStats,
+    pub weapon: Weapon,
+    /// All characters share the same sprite and are told apart by color.
+    pub tint: Color,
+}
+
+impl Character {
+    pub const ALL: [Character; 3] = [Character::Knight, Character::Ranger, Character::Brute];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Character::Knight => "Knight",
+            Character::Ranger => "Ranger",
+            Character::Brute => "Brute",
+        }
+    }
+
+    /// Key of the localized name.
+    pub fn locale_key(self) -> &'static str {
+        match self {
+            Character::Knight => "character.knight",
+            Character::Ranger => "character.ranger",
+            Character::Brute => "character.brute",
+        }
+    }
+
+    pub fn stats(self) -> CharacterStats {
+        let base = PlayerStats::default();
+        match self {
+            Character::Knight => CharacterStats {
+                max_health: PLAYER_HEALTH,
+                stats: base,
+                weapon: Weapon::Pistol,
+                tint: Color::WHITE,
+            },
+            Character::Ranger => CharacterStats {
+                max_health: PLAYER_HEALTH * 4 / 5,
+                stats: PlayerStats {
+                    move_speed: base.move_speed * 1.2,
+                    fire_rate_mult: 1.1,
+                    ..base
+                },
+                weapon: Weapon::Smg,
+                tint: Color::srgb(0.6, 1.0, 0.6),
+            },
+            Character::Brute => CharacterStats {
+                max_health: PLAYER_HEALTH * 8 / 5,
+                stats: PlayerStats {
+                    move_speed: base.move_speed * 0.85,
+                    damage_mult: 1.25,
+                    ..base
+                },
+                weapon: Weapon::Shotgun,
+                tint: Color::srgb(1.0, 0.6, 0.5),
+            },
+        }
+    }
+}
+
+/// The character picked for the next run.
+#[derive(Resource, Debug, Default, Clone, Copy, Deref, DerefMut)]
+pub struct SelectedCharacter(pub Character);
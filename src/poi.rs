@@ -0,0 +1,274 @@
+//! Contains the [`PoiPlugin`] that places points of interest around the world.
+//!
+//! Every [`PointOfInterest`] triggers its event once, the first time a player reaches it, and
+//! stays [`Activated`] afterwards. The events are sent as [`PoiActivated`] and handled by their
+//! own systems: an ambush spawns a ring of enemies around the player, a free upgrade opens the
+//! upgrade selection and a blessing is a temporary [`Blessing`] buff.
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::collision::ColliderShape;
+use crate::enemy::{enemy_bundle, EnemyKind};
+use crate::gui::toast::Notify;
+use crate::locale::Localizer;
+use crate::player::{Dying, Player, PlayerStats};
+use crate::prelude::*;
+use crate::quadtree::quad_collider::{QuadCollider, Shape};
+use crate::resources::{EnemyNum, GlobTextAtlases};
+use crate::rng::{GameRng, RngStream};
+use crate::upgrades::FreeUpgrade;
+use crate::world::{config::WorldConfig, pick_world_seed, WorldNoise, WorldSeed};
+
+/// Gives up on the points of interest that can't find a spot after this many tries.
+const POI_ATTEMPTS: u32 = 20;
+const POI_SIZE: f32 = 14.;
+
+pub struct PoiPlugin;
+
+impl Plugin for PoiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PoiActivated>()
+            .add_systems(
+                OnEnter(GameState::GameInit),
+                spawn_pois.after(pick_world_seed),
+            )
+            .add_systems(
+                Update,
+                (
+                    activate_pois,
+                    (spawn_ambushes, grant_free_upgrades, grant_blessings),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::GameRun)),
+            )
+            .add_systems(
+                Update,
+                expire_blessings.run_if(in_state(GameState::GameRun)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoiKind {
+    /// An altar that calls a horde of enemies on whoever touches it.
+    Altar,
+    /// A shrine that grants a free upgrade.
+    UpgradeShrine,
+    /// A shrine that grants a temporary [`Blessing`].
+    BlessingShrine,
+}
+
+impl PoiKind {
+    pub const ALL: [PoiKind; 3] = [
+        PoiKind::Altar,
+        PoiKind::UpgradeShrine,
+        PoiKind::BlessingShrine,
+    ];
+
+    fn color(self) -> Color {
+        match self {
+            PoiKind::Altar => Color::srgb(0.75, 0.1, 0.15),
+            PoiKind::UpgradeShrine => Color::srgb(0.95, 0.8, 0.2),
+            PoiKind::BlessingShrine => Color::srgb(0.3, 0.85, 0.9),
+        }
+    }
+
+    fn toast_key(self) -> &'static str {
+        match self {
+            PoiKind::Altar => "toast.poi_altar",
+            PoiKind::UpgradeShrine => "toast.poi_upgrade",
+            PoiKind::BlessingShrine => "toast.poi_blessing",
+        }
+    }
+}
+
+#[derive(Component, Debug, Deref)]
+#[require(
+    Transform,
+    Sprite,
+    RunScoped,
+    ColliderShape(|| ColliderShape(Shape::Circle(Circle::new(POI_SIZE))))
+)]
+pub struct PointOfInterest(pub PoiKind);
+
+/// A [`PointOfInterest`] that has already been used.
+#[derive(Component, Debug, Default)]
+pub struct Activated;
+
+/// Sent when a player reaches a [`PointOfInterest`] for the first time.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PoiActivated {
+    pub kind: PoiKind,
+    pub player: Entity,
+}
+
+/// Temporarily raises the damage and the movement speed of the player.
+#[derive(Component, Debug, Deref, DerefMut)]
+pub struct Blessing(pub Timer);
+
+fn spawn_pois(
+    mut commands: Commands,
+    mut game_rng: ResMut<GameRng>,
+    config: Res<WorldConfig>,
+    seed: Res<WorldSeed>,
+) {
+    let rng = game_rng.stream(RngStream::Pois);
+    let noise = WorldNoise::new(*seed);
+    let whalf = config.half_size() - POI_SIZE * 2.;
+
+    for _ in 0..POI_NUM {
+        let Some(pos) = (0..POI_ATTEMPTS)
+            .map(|_| Vec2::new(rng.gen_range(-whalf..whalf), rng.gen_range(-whalf..whalf)))
+            .find(|&pos| !noise.biome(pos).has_obstacles() && !config.in_safe_zone(pos))
+        else {
+            continue;
+        };
+        let kind = *PoiKind::ALL.choose(rng).unwrap();
+        commands.spawn((
+            Sprite::from_color(kind.color(), Vec2::splat(POI_SIZE)),
+            // above the decor, below the player
+            Transform::from_translation(pos.extend(20.))
+                .with_rotation(Quat::from_rotation_z(TAU / 8.)),
+            PointOfInterest(kind),
+        ));
+    }
+}
+
+fn activate_pois(
+    mut commands: Commands,
+    mut poi_query: Query<
+        (
+            Entity,
+            &PointOfInterest,
+            &Transform,
+            &ColliderShape,
+            &mut Sprite,
+        ),
+        Without<Activated>,
+    >,
+    player_query: Query<(Entity, &Transform, &ColliderShape), (With<Player>, Without<Dying>)>,
+    mut activated_events: EventWriter<PoiActivated>,
+    mut notify_events: EventWriter<Notify>,
+    localizer: Localizer,
+) {
+    for (ent, poi, poi_transf, poi_shape, mut sprite) in poi_query.iter_mut() {
+        let poi_coll = QuadCollider::new(poi_transf.translation.truncate(), **poi_shape);
+        let Some((player, _, _)) = player_query.iter().find(|(_, transf, shape)| {
+            QuadCollider::new(transf.translation.truncate(), ***shape).intersects(poi_coll)
+        }) else {
+            continue;
+        };
+
+        commands.entity(ent).insert(Activated);
+        // burnt out
+        sprite.color = sprite.color.with_alpha(0.3);
+        activated_events.send(PoiActivated {
+            kind: **poi,
+            player,
+        });
+        notify_events.send(Notify::new(localizer.tr(poi.toast_key())));
+    }
+}
+
+/// Surrounds the player that touched an altar with enemies of the biome, as many as still fit
+/// under [`ENEMY_MAX_INSTANCES`] and inside the world.
+fn spawn_ambushes(
+    mut commands: Commands,
+    mut activated_events: EventReader<PoiActivated>,
+    mut game_rng: ResMut<GameRng>,
+    mut num_of_enemies: ResMut<EnemyNum>,
+    player_query: Query<&Transform, With<Player>>,
+    text_atlases: Res<GlobTextAtlases>,
+    config: Res<WorldConfig>,
+    seed: Res<WorldSeed>,
+) {
+    let rng = game_rng.stream(RngStream::Enemies);
+    let noise = WorldNoise::new(*seed);
+    let whalf = Vec2::splat(config.half_size());
+    for activated in activated_events
+        .read()
+        .filter(|activated| activated.kind == PoiKind::Altar)
+    {
+        let Ok(player_transf) = player_query.get(activated.player) else {
+            continue;
+        };
+        let spawn_count =
+            (POI_AMBUSH_ENEMIES as usize).min(ENEMY_MAX_INSTANCES.saturating_sub(**num_of_enemies));
+        **num_of_enemies += spawn_count;
+
+        let center = player_transf.translation.truncate();
+        let enemies = (0..spawn_count)
+            .map(|idx| {
+                let angle = idx as f32 / POI_AMBUSH_ENEMIES as f32 * TAU;
+                let pos =
+                    (center + Vec2::from_angle(angle) * POI_AMBUSH_RADIUS).clamp(-whalf, whalf);
+                let kind = noise
+                    .biome(pos)
+                    .enemy_table()
+                    .choose_weighted(rng, |(_, weight)| *weight)
+                    .map_or(EnemyKind::Grunt, |(kind, _)| *kind);
+                enemy_bundle(kind, pos, &text_atlases)
+            })
+            .collect::<Vec<_>>();
+        commands.spawn_batch(enemies);
+    }
+}
+
+fn grant_free_upgrades(
+    mut activated_events: EventReader<PoiActivated>,
+    mut free_upgrade_events: EventWriter<FreeUpgrade>,
+) {
+    for activated in activated_events
+        .read()
+        .filter(|activated| activated.kind == PoiKind::UpgradeShrine)
+    {
+        free_upgrade_events.send(FreeUpgrade {
+            entity: activated.player,
+        });
+    }
+}
+
+/// Blesses the player, a blessed player only gets the duration refreshed.
+fn grant_blessings(
+    mut commands: Commands,
+    mut activated_events: EventReader<PoiActivated>,
+    mut player_query: Query<(&mut PlayerStats, Option<&mut Blessing>), With<Player>>,
+) {
+    for activated in activated_events
+        .read()
+        .filter(|activated| activated.kind == PoiKind::BlessingShrine)
+    {
+        let Ok((mut stats, blessing)) = player_query.get_mut(activated.player) else {
+            continue;
+        };
+        if let Some(mut blessing) = blessing {
+            blessing.reset();
+            continue;
+        }
+        stats.damage_mult += POI_BLESSING_DAMAGE_BONUS;
+        stats.move_speed += PLAYER_SPEED * POI_BLESSING_SPEED_BONUS;
+        commands
+            .entity(activated.player)
+            .insert(Blessing(Timer::from_seconds(
+                POI_BLESSING_SECS,
+                TimerMode::Once,
+            )));
+    }
+}
+
+fn expire_blessings(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &mut Blessing, &mut PlayerStats)>,
+    time: Res<Time>,
+) {
+    for (ent, mut blessing, mut stats) in player_query.iter_mut() {
+        if blessing.tick(time.delta()).finished() {
+            stats.damage_mult -= POI_BLESSING_DAMAGE_BONUS;
+            stats.move_speed -= PLAYER_SPEED * POI_BLESSING_SPEED_BONUS;
+            commands.entity(ent).remove::<Blessing>();
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Contains the [`PortalPlugin`] that places pairs of [`Portal`]s around the world.
+//!
+//! Walking into a portal moves the player to its twin and makes them briefly [`Invulnerable`],
+//! so they can escape a horde. A [`PortalCooldown`] keeps them from being sent straight back.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::collision::ColliderShape;
+use crate::components::Invulnerable;
+use crate::player::{Dying, Player};
+use crate::prelude::*;
+use crate::quadtree::quad_collider::{QuadCollider, Shape};
+use crate::rng::{GameRng, RngStream};
+use crate::world::{config::WorldConfig, pick_world_seed, WorldNoise, WorldSeed};
+
+const PORTAL_COLOR: Color = Color::srgba(0.55, 0.25, 0.95, 0.85);
+const PORTAL_SPIN_SPEED: f32 = 1.5;
+/// Gives up on the pairs that can't find a spot after this many tries.
+const PORTAL_ATTEMPTS: u32 = 20;
+
+pub struct PortalPlugin;
+
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::GameInit),
+            spawn_portals.after(pick_world_seed),
+        )
+        .add_systems(
+            Update,
+            (
+                (tick_portal_cooldowns, enter_portals).chain(),
+                spin_portals,
+                fade_teleport_flashes,
+            )
+                .run_if(in_state(GameState::GameRun)),
+        );
+    }
+}
+
+/// Sends the players that walk into it to its `twin`.
+#[derive(Component, Debug)]
+#[require(
+    Transform,
+    Sprite,
+    RunScoped,
+    ColliderShape(|| ColliderShape(Shape::Circle(Circle::new(PORTAL_RADIUS))))
+)]
+pub struct Portal {
+    pub twin: Entity,
+}
+
+/// The player can't use a portal until the timer finishes.
+#[derive(Component, Debug, Deref, DerefMut)]
+pub struct PortalCooldown(pub Timer);
+
+/// The flash at both ends of a teleport.
+#[derive(Component, Debug, Deref, DerefMut)]
+#[require(Transform, Sprite, RunScoped)]
+struct TeleportFlash(Timer);
+
+/// Places the pairs far apart from each other, outside of the biomes with obstacles and the
+/// safe zone.
+fn spawn_portals(
+    mut commands: Commands,
+    mut game_rng: ResMut<GameRng>,
+    config: Res<WorldConfig>,
+    seed: Res<WorldSeed>,
+) {
+    let rng = game_rng.stream(RngStream::Portals);
+    let noise = WorldNoise::new(*seed);
+    let whalf = config.half_size() - PORTAL_RADIUS * 2.;
+    let mut random_spot = || {
+        (0..PORTAL_ATTEMPTS)
+            .map(|_| Vec2::new(rng.gen_range(-whalf..whalf), rng.gen_range(-whalf..whalf)))
+            .find(|&pos| !noise.biome(pos).has_obstacles() && !config.in_safe_zone(pos))
+    };
+
+    for _ in 0..PORTAL_PAIRS {
+        let Some(a) = random_spot() else {
+            continue;
+        };
+        let Some(b) = (0..PORTAL_ATTEMPTS)
+            .filter_map(|_| random_spot())
+            .find(|b| b.distance(a) >= PORTAL_MIN_PAIR_DISTANCE)
+        else {
+            continue;
+        };
+
+        let ent_a = commands.spawn_empty().id();
+        let ent_b = commands.spawn_empty().id();
+        for (ent, pos, twin) in [(ent_a, a, ent_b), (ent_b, b, ent_a)] {
+            commands.entity(ent).insert((
+                Sprite::from_color(PORTAL_COLOR, Vec2::splat(PORTAL_RADIUS * 1.5)),
+                // above the decor, below the player
+                Transform::from_translation(pos.extend(20.)),
+                Portal { twin },
+            ));
+        }
+    }
+}
+
+fn enter_portals(
+    mut commands: Commands,
+    mut player_query: Query<
+        (Entity, &mut Transform, &ColliderShape),
+        (With<Player>, Without<Dying>, Without<PortalCooldown>),
+    >,
+    portal_query: Query<(&Transform, &ColliderShape, &Portal), Without<Player>>,
+) {
+    for (player_ent, mut player_transf, player_shape) in player_query.iter_mut() {
+        let player_coll = QuadCollider::new(player_transf.translation.truncate(), **player_shape);
+        let Some(twin_pos) = portal_query
+            .iter()
+            .find(|(transf, shape, _)| {
+                QuadCollider::new(transf.translation.truncate(), ***shape).intersects(player_coll)
+            })
+            .and_then(|(_, _, portal)| portal_query.get(portal.twin).ok())
+            .map(|(twin_transf, _, _)| twin_transf.translation.truncate())
+        else {
+            continue;
+        };
+
+        for pos in [player_transf.translation.truncate(), twin_pos] {
+            commands.spawn((
+                Sprite::from_color(PORTAL_COLOR, Vec2::splat(PORTAL_RADIUS * 3.)),
+                Transform::from_translation(pos.extend(54.)).with_scale(Vec3::splat(0.2)),
+                TeleportFlash(Timer::from_seconds(TELEPORT_FLASH_SECS, TimerMode::Once)),
+            ));
+        }
+
+        player_transf.translation.x = twin_pos.x;
+        player_transf.translation.y = twin_pos.y;
+        commands.entity(player_ent).insert((
+            Invulnerable::from_secs(PORTAL_IFRAMES_SECS),
+            PortalCooldown(Timer::from_seconds(PORTAL_COOLDOWN_SECS, TimerMode::Once)),
+        ));
+    }
+}
+
+fn tick_portal_cooldowns(
+    mut commands: Commands,
+    mut cooldown_query: Query<(Entity, &mut PortalCooldown)>,
+    time: Res<Time>,
+) {
+    for (ent, mut cooldown) in cooldown_query.iter_mut() {
+        if cooldown.tick(time.delta()).finished() {
+            commands.entity(ent).remove::<PortalCooldown>();
+        }
+    }
+}
+
+fn spin_portals(mut portal_query: Query<&mut Transform, With<Portal>>, time: Res<Time>) {
+    for mut transf in portal_query.iter_mut() {
+        transf.rotate_z(PORTAL_SPIN_SPEED * time.delta_secs());
+    }
+}
+
+fn fade_teleport_flashes(
+    mut commands: Commands,
+    mut flash_query: Query<(Entity, &mut TeleportFlash, &mut Sprite, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (ent, mut flash, mut sprite, mut transf) in flash_query.iter_mut() {
+        if flash.tick(time.delta()).finished() {
+            commands.entity(ent).despawn();
+            continue;
+        }
+
+        let t = flash.fraction();
+        transf.scale = Vec3::splat(0.2 + 0.8 * t.sqrt());
+        sprite.color.set_alpha(0.85 * (1. - t));
+    }
+}
@@ -3,50 +3,228 @@
 
 use bevy::{
     color::{Color, Srgba},
-    math::UVec2,
+    input::{gamepad::GamepadButton, keyboard::KeyCode},
+    math::Vec2,
 };
 
 // Re-export Plugins
 pub use crate::{
     animation::AnimPlugin, camera::CamPlugin, collision::CollisionPlugin, enemy::EnemyPlugin,
-    gui::GuiPlugin, gun::GunPlugin, player::PlayerPlugin, resources::ResourcePlugin,
-    score::ScorePlugin, state::*, world::WorldPlugin,
+    experience::ExperiencePlugin, grenade::GrenadePlugin, gui::crosshair::CrosshairPlugin,
+    gui::hud::HudPlugin, gui::kill_feed::KillFeedPlugin, gui::score_popup::ScorePopupPlugin,
+    gui::toast::ToastPlugin, gui::GuiPlugin, gun::GunPlugin, inventory::InventoryPlugin,
+    locale::LocalePlugin, orbital::OrbitalPlugin, pickup::PickupPlugin,
+    player::abilities::AbilityPlugin, player::PlayerPlugin, poi::PoiPlugin, portal::PortalPlugin,
+    resources::ResourcePlugin, save::SavePlugin, score::ScorePlugin, settings::SettingsPlugin,
+    sound::SoundPlugin, state::*, time_scale::TimeScalePlugin, upgrades::UpgradePlugin,
+    world::terrain::TerrainPlugin, world::weather::WeatherPlugin, world::WorldPlugin,
 };
 
 // Colors
 pub const BG_COLOR: Color = Color::Srgba(Srgba::new(0.078, 0.064, 0.015, 1.));
 
 // Sprites
-pub const SPRITESH_PLAYER_PATH: &str = "player_sprites.png";
-pub const SPRITESH_PLAYER_COL: u32 = 4;
-pub const SPRITESH_PLAYER_ROW: u32 = 2;
-pub const SPRITESH_PLAYER_TILESIZE: UVec2 = UVec2::new(16, 32);
+pub const ATLAS_MANIFEST_PATH: &str = "sprites.atlases";
+/// The [`SpriteClips`](crate::animation::clip::SpriteClips) the sprites are animated with.
+pub const SPRITE_CLIPS_PATH: &str = "sprites.clips";
 
-pub const SPRITESH_COMMON_PATH: &str = "combined_sprites.png";
-pub const SPRITESH_COMMON_COL: u32 = 4;
-pub const SPRITESH_COMMON_ROW: u32 = 4;
-pub const SPRITESH_COMMON_TILESIZE: UVec2 = UVec2::splat(16);
+// HUD
+pub const MINIMAP_REFRESH_RATE_SECS: f32 = 0.5;
+pub const HIT_MARKER_SECS: f32 = 0.15;
+pub const KILL_FEED_ENTRY_SECS: f32 = 4.;
+pub const KILL_FEED_MAX_ENTRIES: usize = 6;
+/// Kills of enemies worth less than this don't show up in the kill feed.
+pub const KILL_FEED_MIN_WORTH: u64 = 10;
+pub const SCORE_POPUP_SECS: f32 = 0.8;
+/// How fast the score popups rise above the kill, in pixels per second.
+pub const SCORE_POPUP_RISE_SPEED: f32 = 30.;
+pub const TOAST_SECS: f32 = 3.;
+pub const TOAST_MAX_VISIBLE: usize = 3;
 
-pub const SPRITESH_FOLIAGE_PATH: &str = "foliage_sprites.png";
-pub const SPRITESH_FOLIAGE_COL: u32 = 4;
-pub const SPRITESH_FOLIAGE_ROW: u32 = 4;
-pub const SPRITESH_FOLIAGE_TILESIZE: UVec2 = UVec2::splat(16);
+// Settings
+/// Kept in the platform's config directory, see [`config_path`](crate::settings::config_path).
+pub const USER_SETTINGS_PATH: &str = "settings.cfg";
+/// The [`GameConfig`](crate::settings::GameConfig), it's never written by the game.
+pub const GAME_CONFIG_PATH: &str = "game.cfg";
+pub const CAMERA_SETTINGS_PATH: &str = "camera.cfg";
+/// Kept in the platform's data directory, see [`data_path`](crate::settings::data_path).
+pub const HIGH_SCORES_PATH: &str = "high_scores.cfg";
+/// The [`HIGH_SCORES_PATH`] as JSON for sharing, the runs in it are merged in on startup.
+pub const LEADERBOARD_PATH: &str = "leaderboard.json";
+/// Every finished run is appended to it, next to the [`HIGH_SCORES_PATH`].
+pub const RUN_HISTORY_PATH: &str = "run_history.cfg";
+/// The [`RunSave`](crate::save::RunSave) of the run that was quit, next to the
+/// [`HIGH_SCORES_PATH`].
+pub const RUN_SAVE_PATH: &str = "run_save.json";
+/// The [`ScoreTable`](crate::score::ScoreTable) the enemies are scored with.
+pub const SCORE_TABLE_PATH: &str = "score/default.score";
+/// The directory of the game inside the platform's data directory.
+pub const DATA_DIR_NAME: &str = "exigra";
+pub const UI_SCALE_MIN: f32 = 0.5;
+pub const UI_SCALE_MAX: f32 = 2.;
+/// The UI scale is always a multiple of this.
+pub const UI_SCALE_STEP: f32 = 0.05;
+pub const VOLUME_PREVIEW_INTERVAL_SECS: f32 = 0.15;
+
+// Score
+/// Number of runs kept on the high score table.
+pub const HIGH_SCORES_MAX: usize = 10;
+/// Number of runs from the top of the table shown on the game over screen.
+pub const GAME_OVER_HIGH_SCORES: usize = 5;
+/// The combo is lost when there's no kill for this long.
+pub const COMBO_WINDOW_SECS: f32 = 2.5;
+/// Number of kills in a combo that raise its multiplier by one.
+pub const COMBO_KILLS_PER_STEP: u32 = 10;
+pub const COMBO_MAX_MULTIPLIER: u64 = 5;
+/// The default rate of the [`SurvivalScore`](crate::score::SurvivalScore), in points per second.
+pub const SURVIVAL_SCORE_PER_SEC: f32 = 2.;
+/// The default scores that send a [`ScoreMilestone`](crate::score::ScoreMilestone).
+pub const SCORE_MILESTONES: [u64; 7] = [100, 250, 500, 1000, 2500, 5000, 10000];
 
 // World
-pub const WORLD_DECOR_NUM: u32 = 1000;
-pub const WORLD_SIZE: f32 = 2000.;
+/// The [`WorldConfig`](crate::world::config::WorldConfig) used unless another one is picked.
+pub const WORLD_CONFIG_PATH: &str = "world/default.world";
+/// The ground is drawn in chunks of `WORLD_CHUNK_TILES` x `WORLD_CHUNK_TILES` tiles.
+pub const WORLD_CHUNK_TILES: usize = 16;
+/// Rough size of the biomes in pixels.
+pub const WORLD_BIOME_SIZE: f32 = 700.;
+pub const WORLD_OBSTACLE_CLUSTERS: u32 = 25;
+/// The walls are outside of the world area, thick enough that nothing can tunnel through.
+pub const WORLD_WALL_THICKNESS: f32 = 32.;
+
+// Input
+/// Pauses and resumes the run, it can't be rebound.
+pub const PAUSE_KEY: KeyCode = KeyCode::Escape;
+/// Pause is on Select because Start is taken by co-op join (`join_coop_player`).
+pub const PAUSE_GAMEPAD_BUTTON: GamepadButton = GamepadButton::Select;
+pub const RELOAD_GAMEPAD_BUTTON: GamepadButton = GamepadButton::West;
+pub const GAMEPAD_AIM_DEADZONE: f32 = 0.3;
+pub const GAMEPAD_MOVE_DEADZONE: f32 = 0.15;
+/// How far from the player the aim target is placed when aiming with a gamepad.
+pub const GAMEPAD_AIM_DISTANCE: f32 = 100.;
+/// How fast the right stick moves the virtual cursor, in pixels per second.
+pub const VIRTUAL_CURSOR_SPEED: f32 = 350.;
+/// The virtual cursor stays this close to the player.
+pub const VIRTUAL_CURSOR_MAX_DISTANCE: f32 = 160.;
+
+// Culling
+pub const CULL_REFRESH_RATE_SECS: f32 = 0.2;
+/// How far outside of the camera view the culled sprites are still shown.
+pub const CULL_MARGIN: f32 = 150.;
+
+// Camera
+pub const CAM_DEFAULT_SCALE: f32 = 0.35;
+/// Only the UI camera renders this layer, so it doesn't draw the world again.
+pub const UI_RENDER_LAYER: usize = 1;
+/// The screen splits once the co-op players are further apart than this fraction of the view.
+pub const CAM_SPLIT_VIEW_FRACTION: f32 = 0.4;
+/// The screen joins again once they are closer than this fraction of the view.
+pub const CAM_JOIN_VIEW_FRACTION: f32 = 0.3;
+pub const DEBUG_CAM_KEY: KeyCode = KeyCode::F8;
+/// Screen pixels per second, the world speed depends on the zoom.
+pub const DEBUG_CAM_SPEED: f32 = 800.;
+pub const CAM_FOLLOW_SPEED: f32 = 5.;
+pub const CAM_ZOOM_MIN: f32 = 0.2;
+pub const CAM_ZOOM_MAX: f32 = 0.6;
+/// How much one step of the scroll wheel zooms in.
+pub const CAM_ZOOM_STEP: f32 = 0.1;
+pub const CAM_ZOOM_PIXELS_PER_LINE: f32 = 40.;
+pub const CAM_ZOOM_GAMEPAD_STEPS_PER_SEC: f32 = 6.;
+pub const CAM_DEAD_ZONE_SIZE: Vec2 = Vec2::new(80., 50.);
+pub const CAM_LOOK_AHEAD_WEIGHT: f32 = 0.25;
+/// The camera follows the cursor that it moves itself, it would run away with a weight of 1.
+pub const CAM_LOOK_AHEAD_MAX_WEIGHT: f32 = 0.9;
+pub const CAM_LOOK_AHEAD_MAX: f32 = 60.;
+/// The look ahead weights the settings menu cycles through.
+pub const CAM_LOOK_AHEAD_STEPS: [f32; 3] = [0., 0.25, 0.5];
+/// The screen shake strengths the settings menu cycles through.
+pub const CAM_SHAKE_STEPS: [f32; 3] = [0., 0.5, 1.];
+pub const BOSS_ZOOM_SCALE: f32 = 1.3;
+pub const BOSS_ZOOM_SECS: f32 = 2.5;
+/// How long the camera looks at a new boss before it returns to the players.
+pub const BOSS_FOCUS_SECS: f32 = 1.2;
+pub const LEVEL_UP_ZOOM_SCALE: f32 = 0.8;
+pub const LEVEL_UP_ZOOM_SECS: f32 = 1.;
+/// How quickly the camera eases towards the zoom, higher is faster.
+pub const CAM_ZOOM_SMOOTHING: f32 = 10.;
+pub const EXPLOSION_SHAKE_AMPLITUDE: f32 = 4.;
+pub const EXPLOSION_SHAKE_DECAY: f32 = 12.;
+pub const PLAYER_HIT_SHAKE_AMPLITUDE: f32 = 2.;
+pub const PLAYER_HIT_SHAKE_DECAY: f32 = 8.;
+pub const BOSS_HIT_SHAKE_AMPLITUDE: f32 = 6.;
+pub const BOSS_HIT_SHAKE_DECAY: f32 = 10.;
+
+// Time scale
+pub const BOSS_HIT_STOP_SECS: f32 = 0.1;
+pub const EXPLOSION_HIT_STOP_SECS: f32 = 0.04;
+pub const PLAYER_DEATH_SLOW_MO_SCALE: f32 = 0.3;
+pub const PLAYER_DEATH_SLOW_MO_SECS: f32 = 1.5;
+/// Eases back into the action after an upgrade is picked.
+pub const LEVEL_UP_SLOW_MO_SCALE: f32 = 0.5;
+pub const LEVEL_UP_SLOW_MO_SECS: f32 = 0.75;
+
+// Weather
+pub const WEATHER_CHANGE_SECS: f32 = 45.;
+/// How long the screen takes to fade to the tint of the new weather.
+pub const WEATHER_FADE_SECS: f32 = 3.;
+pub const RAIN_DROPS_PER_SEC: f32 = 150.;
+pub const RAIN_DROP_LIFE_SECS: f32 = 0.6;
+pub const RAIN_DROP_VELOCITY: Vec2 = Vec2::new(-60., -350.);
+pub const RAIN_MOVE_SPEED_MULT: f32 = 0.9;
+pub const FOG_AGGRO_RADIUS_MULT: f32 = 0.5;
+
+// Terrain
+pub const TERRAIN_PATCHES: u32 = 40;
+pub const TERRAIN_PATCH_MIN_SIZE: f32 = 60.;
+pub const TERRAIN_PATCH_MAX_SIZE: f32 = 180.;
+pub const MUD_SPEED_MULT: f32 = 0.55;
+pub const SHALLOWS_SPEED_MULT: f32 = 0.75;
 
 // Player
+pub const PLAYER_MAX_INSTANCES: usize = 2;
 pub const PLAYER_ANIM_INTERVAL_SECS: f32 = 0.1;
 pub const PLAYER_SPEED: f32 = 100.;
+pub const PLAYER_HEALTH: u32 = 50;
 pub const PLAYER_IFRAMES_DURATION_SECS: f32 = 1.25;
+pub const PLAYER_DEATH_ANIM_SECS: f32 = 1.5;
+pub const PLAYER_REGEN_PER_SEC: f32 = 0.5;
+pub const PLAYER_REGEN_DELAY_SECS: f32 = 5.;
+pub const PLAYER_SPRINT_SPEED_MULT: f32 = 1.6;
+pub const PLAYER_STAMINA_MAX: f32 = 100.;
+pub const PLAYER_STAMINA_DRAIN_PER_SEC: f32 = 40.;
+pub const PLAYER_STAMINA_REGEN_PER_SEC: f32 = 20.;
+pub const PLAYER_KNOCKBACK_SPEED: f32 = 250.;
+pub const PLAYER_KNOCKBACK_SECS: f32 = 0.2;
+pub const PLAYER_SHIELD_MAX: f32 = 20.;
+pub const PLAYER_SHIELD_RECHARGE_DELAY_SECS: f32 = 4.;
+pub const PLAYER_SHIELD_RECHARGE_PER_SEC: f32 = 5.;
 
 // Enemy
 pub const ENEMY_SPAWN_INTERVAL_SECS: f32 = 2.0;
 pub const ENEMY_SPAWN_PER_INTERVAL: usize = 50;
+/// Every wave spawns this many more enemies per interval than the previous one.
+pub const ENEMY_SPAWN_PER_WAVE: usize = 10;
+pub const WAVE_DURATION_SECS: f32 = 60.;
+pub const BOSS_WAVE_INTERVAL: u32 = 5;
+pub const BOSS_HEALTH: u32 = 2000;
+pub const BOSS_DAMAGE: u32 = 20;
+pub const BOSS_SCALE: f32 = 3.;
+pub const BOSS_SPAWN_DISTANCE: f32 = 250.;
 pub const ENEMY_ANIM_INTERVAL_SECS: f32 = 0.2;
 pub const ENEMY_MAX_INSTANCES: usize = 50_000;
 pub const ENEMY_SPEED: f32 = 10.;
+/// Enemies further than this from every player lose track of them and only drift closer.
+pub const ENEMY_AGGRO_RADIUS: f32 = 600.;
+/// Multiplies the speed of the enemies outside of the aggro radius.
+pub const ENEMY_DRIFT_SPEED_MULT: f32 = 0.3;
+pub const ENEMY_SPAWN_INVULNERABLE_SECS: f32 = 0.5;
+/// The chance of a regular enemy to spawn as an [`Elite`](crate::enemy::Elite).
+pub const ELITE_CHANCE: f64 = 0.02;
+pub const ELITE_HEALTH_MULT: u32 = 5;
+pub const ELITE_SCALE_MULT: f32 = 1.4;
+/// Spawn spots in the safe zone are re-rolled this many times before being pushed out of it.
+pub const ENEMY_SPAWN_ATTEMPTS: u32 = 5;
+pub const INVULNERABLE_BLINK_HZ: f32 = 8.;
 
 pub const ENEMY_QUADTREE_REFRESH_RATE_SECS: f32 = 0.5;
 
@@ -54,3 +232,73 @@ pub const BULLET_SPAWN_INTERVAL_SECS: f32 = 0.1;
 // Gun
 pub const BULLET_LIFE_SECS: f32 = 2.0;
 pub const BULLET_SPEED: f32 = 300.;
+pub const BULLET_TRAIL_INTERVAL_SECS: f32 = 0.02;
+pub const BULLET_TRAIL_FADE_SECS: f32 = 0.25;
+pub const GUN_BARREL_LENGTH: f32 = 8.;
+pub const GUN_RECOIL_DISTANCE: f32 = 2.;
+pub const GUN_RECOIL_ANGLE: f32 = 0.2;
+pub const GUN_RECOIL_RECOVERY_SECS: f32 = 0.1;
+pub const MUZZLE_FLASH_SECS: f32 = 0.05;
+
+// Grenade
+pub const GRENADE_COOLDOWN_SECS: f32 = 1.5;
+pub const GRENADE_FLIGHT_SECS: f32 = 0.6;
+pub const GRENADE_FUSE_SECS: f32 = 0.8;
+pub const GRENADE_ARC_HEIGHT: f32 = 30.;
+pub const GRENADE_MAX_RANGE: f32 = 200.;
+pub const GRENADE_BLAST_RADIUS: f32 = 40.;
+pub const GRENADE_DAMAGE: u32 = 25;
+pub const EXPLOSION_EFFECT_SECS: f32 = 0.3;
+
+// Orbital
+pub const ORBITAL_BLADE_COUNT: u32 = 2;
+pub const ORBITAL_RADIUS: f32 = 28.;
+pub const ORBITAL_ANGULAR_SPEED: f32 = 3.;
+pub const ORBITAL_HIT_INTERVAL_SECS: f32 = 0.25;
+
+// Pickups
+pub const WEAPON_PICKUP_NUM: u32 = 12;
+pub const XP_GEM_VALUE: u32 = 1;
+pub const PICKUP_QUADTREE_REFRESH_RATE_SECS: f32 = 0.25;
+pub const CHEST_NUM: u32 = 8;
+/// Score granted by the chests that hold score.
+pub const CHEST_SCORE: u64 = 250;
+pub const CHEST_FRAME_SECS: f32 = 0.12;
+pub const PORTAL_PAIRS: u32 = 3;
+pub const PORTAL_RADIUS: f32 = 12.;
+pub const PORTAL_MIN_PAIR_DISTANCE: f32 = 600.;
+pub const PORTAL_COOLDOWN_SECS: f32 = 2.;
+pub const PORTAL_IFRAMES_SECS: f32 = 1.;
+pub const TELEPORT_FLASH_SECS: f32 = 0.3;
+// Points of interest
+pub const POI_NUM: u32 = 6;
+pub const POI_AMBUSH_ENEMIES: u32 = 24;
+pub const POI_AMBUSH_RADIUS: f32 = 120.;
+pub const POI_BLESSING_SECS: f32 = 20.;
+pub const POI_BLESSING_DAMAGE_BONUS: f32 = 0.5;
+pub const POI_BLESSING_SPEED_BONUS: f32 = 0.2;
+
+pub const PLAYER_MAGNET_RADIUS: f32 = 40.;
+pub const MAGNET_PULL_ACCEL: f32 = 400.;
+
+// Experience
+pub const LEVEL_CURVE_BASE_XP: u32 = 10;
+pub const LEVEL_CURVE_GROWTH: f32 = 1.25;
+
+// Upgrades
+pub const UPGRADE_CHOICES_NUM: usize = 3;
+pub const UPGRADE_DAMAGE_BONUS: f32 = 0.15;
+pub const UPGRADE_FIRE_RATE_BONUS: f32 = 0.1;
+pub const UPGRADE_MOVE_SPEED_BONUS: f32 = 0.1;
+pub const UPGRADE_MAX_HEALTH_BONUS: u32 = 10;
+
+// ABILITIES
+pub const ABILITY_BLAST_COOLDOWN_SECS: f32 = 8.;
+pub const ABILITY_BLAST_RADIUS: f32 = 60.;
+pub const ABILITY_BLAST_DAMAGE: u32 = 30;
+pub const ABILITY_SHIELD_COOLDOWN_SECS: f32 = 15.;
+pub const ABILITY_SHIELD_SECS: f32 = 3.;
+
+// INVENTORY
+pub const ITEM_BOOTS_SPEED_BONUS: f32 = 0.15;
+pub const ITEM_ARMOR_PLATE_HEALTH_BONUS: u32 = 20;
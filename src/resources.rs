@@ -1,21 +1,80 @@
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::{
+    asset::LoadState,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    state::state::StateTransitionSteps,
+    utils::HashMap,
+    window::{CursorMoved, PrimaryWindow},
+};
 
+use crate::camera::{PixelCanvas, PlayerCam};
+use crate::enemy::EnemyKind;
+use crate::player::{AimDirection, Controller, ControllerGamepads, Player};
 use crate::prelude::*;
+use crate::settings::CameraConfig;
 
-/// Loads all the assets into `Resources` and advances the GameState,
+pub mod manifest;
+pub mod preload;
+
+use manifest::{AtlasEntry, AtlasManifest, AtlasManifestLoader};
+use preload::{swap_state_assets, track_state_assets, LoadedStateAssets, StateAssets};
+
+/// Loads all the assets into `Resources` and advances the GameState once they are ready,
 /// then it keeps track of and updates all the `Resources`.
+///
+/// The spritesheets are listed in the [`AtlasManifest`], the atlases are built from it once it's
+/// loaded and looked up by their [`AtlasKind`] in the [`GlobTextAtlases`].
+///
+/// The progress of the loading is kept in the [`AssetLoadProgress`] for the loading screen. The
+/// textures that fail to load are replaced with a magenta one, so the game still runs.
+///
+/// The assets that only some states need are declared as [`StateAssets`], see [`preload`]. The
+/// textures of the [`AtlasKind::RUN_ONLY`] atlases are among them.
 pub struct ResourcePlugin;
 
 impl Plugin for ResourcePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(GlobTextAtlases::default())
-            .insert_resource(CursorPos(None))
+            .init_resource::<AimInput>()
             .insert_resource(ClearColor(BG_COLOR))
             .insert_resource(EnemyNum(0))
+            .insert_resource(InputDevice::default())
+            .init_resource::<AssetLoadProgress>()
+            .init_asset::<AtlasManifest>()
+            .init_asset_loader::<AtlasManifestLoader>()
+            .init_resource::<AtlasManifestHandle>()
+            .init_resource::<StateAssets>()
+            .init_resource::<LoadedStateAssets>()
             .add_systems(OnEnter(GameState::AssetLoad), load_resources)
             .add_systems(
                 Update,
-                update_cursor_pos.run_if(in_state(GameState::GameRun)),
+                track_asset_loading.run_if(in_state(GameState::AssetLoad)),
+            )
+            // the OnEnter systems of the new state already get the handles of its assets
+            .add_systems(
+                StateTransition,
+                (swap_state_assets, bind_run_atlases)
+                    .chain()
+                    .run_if(state_changed::<GameState>)
+                    .in_set(StateTransitionSteps::ExitSchedules),
+            )
+            .add_systems(
+                Update,
+                track_state_assets.run_if(not(in_state(GameState::AssetLoad))),
+            )
+            .add_systems(RunTeardown, reset_resource::<EnemyNum>)
+            .add_systems(
+                Update,
+                (
+                    track_input_device,
+                    (update_cursor_pos, move_virtual_cursor),
+                    update_aim_direction,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::GameRun)),
             );
     }
 }
@@ -24,87 +83,577 @@ impl Plugin for ResourcePlugin {
 #[derive(Resource, Debug, Default, DerefMut, Deref)]
 pub struct EnemyNum(pub usize);
 
+/// The atlases the game draws with, each one is listed in the [`AtlasManifest`] under its
+/// [`AtlasKind::name`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtlasKind {
+    Player,
+    Common,
+    Foliage,
+    Floor,
+    Walls,
+    Chest,
+}
+
+impl AtlasKind {
+    pub const ALL: [AtlasKind; 6] = [
+        AtlasKind::Player,
+        AtlasKind::Common,
+        AtlasKind::Foliage,
+        AtlasKind::Floor,
+        AtlasKind::Walls,
+        AtlasKind::Chest,
+    ];
+    /// Only drawn during a run, their textures are [`StateAssets`] of the run states instead of
+    /// staying loaded for the whole game.
+    pub const RUN_ONLY: [AtlasKind; 2] = [AtlasKind::Floor, AtlasKind::Walls];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AtlasKind::Player => "player",
+            AtlasKind::Common => "common",
+            AtlasKind::Foliage => "foliage",
+            AtlasKind::Floor => "floor",
+            AtlasKind::Walls => "walls",
+            AtlasKind::Chest => "chest",
+        }
+    }
+}
+
+/// The sprite of an [`EnemyKind`], its walk animation runs through the `frames` tiles from the
+/// `first` one.
+#[derive(Debug, Default, Clone)]
+pub struct EnemySprite {
+    pub atlas: TextureAtlasHandle,
+    pub first: usize,
+    pub frames: usize,
+}
+
+impl EnemySprite {
+    pub fn sprite(&self) -> Sprite {
+        Sprite::from_atlas_image(
+            self.atlas.image.clone(),
+            TextureAtlas {
+                layout: self.atlas.layout.clone(),
+                index: self.first,
+            },
+        )
+    }
+
+    /// The frame of the walk animation after the `index`.
+    pub fn next_frame(&self, index: usize) -> usize {
+        let frame = index.saturating_sub(self.first) + 1;
+        self.first + frame % self.frames.max(1)
+    }
+}
+
+/// Every [`AtlasKind`] cut from its spritesheet in the [`AtlasManifest`] and the
+/// [`EnemySprite`] of every [`EnemyKind`]. They are all filled in during the
+/// [`GameState::AssetLoad`], the ones that are missing or fail to load are the fallback, so there
+/// is always something to draw with.
 #[derive(Resource, Debug, Default)]
 pub struct GlobTextAtlases {
-    pub player: Option<TextureAtlasHandle>,
-    pub common: Option<TextureAtlasHandle>,
-    pub foliage: Option<TextureAtlasHandle>,
+    atlases: [TextureAtlasHandle; AtlasKind::ALL.len()],
+    regions: [HashMap<String, usize>; AtlasKind::ALL.len()],
+    enemies: [EnemySprite; EnemyKind::ALL.len()],
+    /// A single magenta pixel, stands in for whatever is missing.
+    pub fallback: TextureAtlasHandle,
+    /// The texture paths of the [`AtlasKind::RUN_ONLY`] atlases, [`bind_run_atlases`] points the
+    /// atlases at them in the states that load them.
+    run_textures: Vec<(AtlasKind, String)>,
+}
+
+impl GlobTextAtlases {
+    pub fn get(&self, kind: AtlasKind) -> TextureAtlasHandle {
+        self.atlases[kind as usize].clone()
+    }
+
+    /// The index of the named region in the atlas, the first tile when there is no such region.
+    pub fn region(&self, kind: AtlasKind, region: &str) -> usize {
+        self.regions[kind as usize]
+            .get(region)
+            .copied()
+            .unwrap_or_else(|| {
+                error_once!("The {} atlas has no {region} region", kind.name());
+                0
+            })
+    }
+
+    pub fn player(&self) -> TextureAtlasHandle {
+        self.get(AtlasKind::Player)
+    }
+
+    pub fn common(&self) -> TextureAtlasHandle {
+        self.get(AtlasKind::Common)
+    }
+
+    pub fn foliage(&self) -> TextureAtlasHandle {
+        self.get(AtlasKind::Foliage)
+    }
+
+    pub fn floor(&self) -> TextureAtlasHandle {
+        self.get(AtlasKind::Floor)
+    }
+
+    pub fn walls(&self) -> TextureAtlasHandle {
+        self.get(AtlasKind::Walls)
+    }
+
+    pub fn chest(&self) -> TextureAtlasHandle {
+        self.get(AtlasKind::Chest)
+    }
+
+    pub fn enemy(&self, kind: EnemyKind) -> &EnemySprite {
+        &self.enemies[kind as usize]
+    }
+
+    /// The textures of all the atlases, some of them share one.
+    fn images(&self) -> impl Iterator<Item = &Handle<Image>> {
+        self.atlases
+            .iter()
+            .chain(self.enemies.iter().map(|enemy| &enemy.atlas))
+            .map(|atlas| &atlas.image)
+    }
+
+    fn images_mut(&mut self) -> impl Iterator<Item = &mut Handle<Image>> {
+        self.atlases
+            .iter_mut()
+            .chain(self.enemies.iter_mut().map(|enemy| &mut enemy.atlas))
+            .map(|atlas| &mut atlas.image)
+    }
+}
+
+/// The [`AtlasManifest`] the [`GlobTextAtlases`] are built from.
+#[derive(Resource, Debug, Deref)]
+pub struct AtlasManifestHandle(pub Handle<AtlasManifest>);
+
+impl FromWorld for AtlasManifestHandle {
+    fn from_world(world: &mut World) -> Self {
+        AtlasManifestHandle(world.resource::<AssetServer>().load(ATLAS_MANIFEST_PATH))
+    }
+}
+
+/// How many of the textures, or the [`StateAssets`] after the startup, are done loading.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct AssetLoadProgress {
+    pub loaded: usize,
+    pub total: usize,
 }
 
-#[derive(Debug, Clone)]
+impl AssetLoadProgress {
+    /// From 0 to 1, it's done when there is nothing to load.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 1.;
+        }
+        self.loaded as f32 / self.total as f32
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct TextureAtlasHandle {
     pub layout: Handle<TextureAtlasLayout>,
     pub image: Handle<Image>,
+    pub tile_size: UVec2,
+    /// The number of tiles in the layout.
+    pub tiles: usize,
 }
 impl TextureAtlasHandle {
-    fn new(layout: Handle<TextureAtlasLayout>, image: Handle<Image>) -> Self {
-        TextureAtlasHandle { layout, image }
+    fn new(
+        layout: Handle<TextureAtlasLayout>,
+        image: Handle<Image>,
+        tile_size: UVec2,
+        tiles: usize,
+    ) -> Self {
+        TextureAtlasHandle {
+            layout,
+            image,
+            tile_size,
+            tiles,
+        }
     }
 }
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct CursorPos(pub Option<Vec2>);
+/// Where the primary player aims in the world, the crosshair is drawn there.
+///
+/// With the mouse it's the real cursor. With a gamepad it's a virtual cursor that the right stick
+/// moves around the player, it keeps following them when the stick is let go.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct AimInput {
+    /// `None` until the cursor first enters the window.
+    pub pos: Option<Vec2>,
+    pub source: AimSource,
+    /// Where the virtual cursor is relative to the player.
+    virtual_offset: Vec2,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AimSource {
+    #[default]
+    Cursor,
+    Virtual,
+}
+
+/// The device that was most recently used to control the primary player.
+/// Co-op players are always controlled by their own gamepad.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InputDevice {
+    #[default]
+    KeyboardMouse,
+    Gamepad,
+}
 
 fn load_resources(
     mut text_atlases: ResMut<GlobTextAtlases>,
     mut texture_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let fallback_txtr = images.add(Image::new_fill(
+        Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[255, 0, 255, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ));
+    // the sprites keep their indices, those past the single tile show the whole texture
+    let fallback_layout =
+        texture_layouts.add(TextureAtlasLayout::from_grid(UVec2::ONE, 1, 1, None, None));
+    // the world is laid out as if the missing sheets had the usual tiles
+    text_atlases.fallback =
+        TextureAtlasHandle::new(fallback_layout, fallback_txtr, UVec2::splat(16), 1);
+    text_atlases.atlases = std::array::from_fn(|_| text_atlases.fallback.clone());
+    text_atlases.enemies = std::array::from_fn(|_| EnemySprite {
+        atlas: text_atlases.fallback.clone(),
+        first: 0,
+        frames: 1,
+    });
+}
+
+/// Cuts the spritesheets of the manifest into atlases and starts loading their textures. The
+/// atlases that aren't in the manifest, or all of them without one, stay the fallback. So do the
+/// enemies without a valid sprite.
+fn build_atlases(
+    text_atlases: &mut GlobTextAtlases,
+    manifest: Option<&AtlasManifest>,
+    texture_layouts: &mut Assets<TextureAtlasLayout>,
+    asset_serv: &AssetServer,
+) {
+    let Some(manifest) = manifest else {
+        return;
+    };
+    // the enemies can use the atlases of their own as well
+    let used = |name: &str| {
+        AtlasKind::ALL.iter().any(|kind| kind.name() == name)
+            || manifest.enemies.values().any(|enemy| enemy.atlas == name)
+    };
+    for name in manifest.atlases.keys().filter(|name| !used(name)) {
+        warn!("The atlas manifest has an unknown {name} atlas, it isn't used");
+    }
+
+    let fallback = text_atlases.fallback.image.clone();
+    let mut built = HashMap::<String, TextureAtlasHandle>::default();
+    let mut build = |name: &str, entry: &AtlasEntry| {
+        if AtlasKind::RUN_ONLY.iter().any(|kind| kind.name() == name) {
+            // the texture is loaded once a run starts, see `bind_run_atlases`
+            return TextureAtlasHandle::new(
+                texture_layouts.add(entry.layout()),
+                fallback.clone(),
+                entry.tile_size,
+                entry.tiles(),
+            );
+        }
+        built
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                TextureAtlasHandle::new(
+                    texture_layouts.add(entry.layout()),
+                    asset_serv.load(&entry.path),
+                    entry.tile_size,
+                    entry.tiles(),
+                )
+            })
+            .clone()
+    };
+
+    for kind in AtlasKind::ALL {
+        let Some(entry) = manifest.atlases.get(kind.name()) else {
+            error!(
+                "The atlas manifest has no {} atlas, using the fallback texture",
+                kind.name()
+            );
+            continue;
+        };
+        text_atlases.atlases[kind as usize] = build(kind.name(), entry);
+        if AtlasKind::RUN_ONLY.contains(&kind) {
+            text_atlases.run_textures.push((kind, entry.path.clone()));
+        }
+        // a region past the last tile would panic when it's drawn
+        text_atlases.regions[kind as usize] = entry
+            .regions
+            .iter()
+            .filter(|(region, &idx)| {
+                if idx >= entry.tiles() {
+                    error!(
+                        "The {region} region is outside of the {} atlas",
+                        kind.name()
+                    );
+                }
+                idx < entry.tiles()
+            })
+            .map(|(region, &idx)| (region.clone(), idx))
+            .collect();
+    }
+
+    for kind in EnemyKind::ALL {
+        let sprite = manifest.enemies.get(kind.name()).and_then(|sprite| {
+            let entry = manifest.atlases.get(&sprite.atlas)?;
+            let first = *entry.regions.get(&sprite.region)?;
+            // the whole animation has to fit into the atlas
+            (first + sprite.frames <= entry.tiles()).then(|| EnemySprite {
+                atlas: build(&sprite.atlas, entry),
+                first,
+                frames: sprite.frames,
+            })
+        });
+        match sprite {
+            Some(sprite) => text_atlases.enemies[kind as usize] = sprite,
+            None => error!(
+                "The atlas manifest has no valid sprite for the {} enemies, using the fallback \
+                 texture",
+                kind.name()
+            ),
+        }
+    }
+}
+
+/// Moves on to the [`GameState::MainMenu`] once all the textures are loaded, so nothing is drawn
+/// without its texture.
+#[allow(clippy::too_many_arguments)]
+fn track_asset_loading(
+    mut progress: ResMut<AssetLoadProgress>,
     mut next_state: ResMut<NextState<GameState>>,
+    mut text_atlases: ResMut<GlobTextAtlases>,
+    mut texture_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut atlases_built: Local<bool>,
+    mut state_assets: ResMut<StateAssets>,
+    manifest_handle: Res<AtlasManifestHandle>,
+    manifests: Res<Assets<AtlasManifest>>,
     asset_serv: Res<AssetServer>,
 ) {
-    let player_txtr = asset_serv.load(SPRITESH_PLAYER_PATH);
-    let common_txtr = asset_serv.load(SPRITESH_COMMON_PATH);
-    let foliage_txtr = asset_serv.load(SPRITESH_FOLIAGE_PATH);
-
-    let player_layout = TextureAtlasLayout::from_grid(
-        SPRITESH_PLAYER_TILESIZE,
-        SPRITESH_PLAYER_COL,
-        SPRITESH_PLAYER_ROW,
-        None,
-        None,
-    );
-    let player_ta_layout = texture_layouts.add(player_layout);
-    let player_atlas_handle = TextureAtlasHandle::new(player_ta_layout, player_txtr);
-    text_atlases.player = Some(player_atlas_handle);
-
-    let common_layout = TextureAtlasLayout::from_grid(
-        SPRITESH_COMMON_TILESIZE,
-        SPRITESH_COMMON_COL,
-        SPRITESH_COMMON_ROW,
-        None,
-        None,
-    );
-    let common_ta_layout = texture_layouts.add(common_layout);
-    let common_atlas_handle = TextureAtlasHandle::new(common_ta_layout, common_txtr);
-    text_atlases.common = Some(common_atlas_handle);
-
-    let foliage_layout = TextureAtlasLayout::from_grid(
-        SPRITESH_FOLIAGE_TILESIZE,
-        SPRITESH_FOLIAGE_COL,
-        SPRITESH_FOLIAGE_ROW,
-        None,
-        None,
-    );
-    let foliage_ta_layout = texture_layouts.add(foliage_layout);
-    let foliage_atlas_handle = TextureAtlasHandle::new(foliage_ta_layout, foliage_txtr);
-    text_atlases.foliage = Some(foliage_atlas_handle);
-
-    next_state.set(GameState::MainMenu);
+    // the textures are only known once the manifest is loaded
+    if !*atlases_built {
+        match asset_serv.get_load_state(manifest_handle.id()) {
+            Some(LoadState::Loaded) => {}
+            Some(LoadState::Failed(e)) => {
+                error!("Couldn't load the atlas manifest, using the fallback texture: {e}");
+            }
+            _ => {
+                *progress = AssetLoadProgress {
+                    loaded: 0,
+                    total: 1,
+                };
+                return;
+            }
+        }
+        build_atlases(
+            &mut text_atlases,
+            manifests.get(manifest_handle.id()),
+            &mut texture_layouts,
+            &asset_serv,
+        );
+        for (_, path) in text_atlases.run_textures.iter() {
+            for state in [
+                GameState::GameInit,
+                GameState::GameRun,
+                GameState::LevelUp,
+                GameState::Paused,
+            ] {
+                state_assets.add(state, &[path]);
+            }
+        }
+        *atlases_built = true;
+    }
+
+    // a texture that failed to load won't ever be done, don't wait for it
+    let fallback = text_atlases.fallback.image.clone();
+    for image in text_atlases.images_mut() {
+        if let Some(LoadState::Failed(e)) = asset_serv.get_load_state(image.id()) {
+            error!("Couldn't load a texture, using the fallback instead: {e}");
+            *image = fallback.clone();
+        }
+    }
+
+    let mut images = text_atlases
+        .images()
+        .map(|image| image.id())
+        .collect::<Vec<_>>();
+    images.sort();
+    images.dedup();
+
+    let loaded = images
+        .iter()
+        .filter(|&&id| id == fallback.id() || asset_serv.is_loaded_with_dependencies(id))
+        .count();
+    *progress = AssetLoadProgress {
+        loaded,
+        total: images.len(),
+    };
+    if loaded == images.len() {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+/// Points the [`AtlasKind::RUN_ONLY`] atlases at their textures in the states that load them and
+/// back at the fallback in the others. Once the run entities are gone nothing holds on to the
+/// textures anymore and they are freed.
+fn bind_run_atlases(
+    mut text_atlases: ResMut<GlobTextAtlases>,
+    state_assets: Res<StateAssets>,
+    state: Res<State<GameState>>,
+    asset_serv: Res<AssetServer>,
+) {
+    let paths = state_assets.paths(**state);
+    let GlobTextAtlases {
+        atlases,
+        run_textures,
+        fallback,
+        ..
+    } = &mut *text_atlases;
+    for (kind, path) in run_textures.iter() {
+        atlases[*kind as usize].image = if paths.contains(path) {
+            asset_serv.load(path)
+        } else {
+            fallback.image.clone()
+        };
+    }
 }
 
 fn update_cursor_pos(
-    mut cursor_pos: ResMut<CursorPos>,
+    mut aim_input: ResMut<AimInput>,
     window_query: Query<&Window, With<PrimaryWindow>>,
-    cam_query: Query<(&Camera, &GlobalTransform)>,
+    cam_query: Query<(&Camera, &GlobalTransform), With<PlayerCam>>,
+    input_device: Res<InputDevice>,
+    camera_config: Res<CameraConfig>,
+    pixel_canvas: Res<PixelCanvas>,
 ) {
+    if *input_device != InputDevice::KeyboardMouse {
+        return;
+    }
     let win = window_query.single();
-    let (cam, cam_transform) = cam_query.single();
 
-    let Some(win_cpos) = win
-        .cursor_position()
-        .and_then(|cursor| cam.viewport_to_world_2d(cam_transform, cursor).ok())
+    // the camera under the cursor, when the screen is split
+    let Some(win_cpos) = win.cursor_position().and_then(|cursor| {
+        let cursor = if camera_config.pixel_perfect {
+            pixel_canvas.to_canvas(win, cursor)
+        } else {
+            cursor
+        };
+        cam_query.iter().find_map(|(cam, cam_transform)| {
+            let viewport = cam.logical_viewport_rect()?;
+            viewport
+                .contains(cursor)
+                .then(|| {
+                    cam.viewport_to_world_2d(cam_transform, cursor - viewport.min)
+                        .ok()
+                })
+                .flatten()
+        })
+    }) else {
+        return;
+    };
+
+    aim_input.pos = Some(win_cpos);
+    aim_input.source = AimSource::Cursor;
+}
+
+/// Moves the virtual cursor with the right stick of the primary player's gamepad. It's moved in
+/// real time, so the aim doesn't freeze during a hit-stop.
+fn move_virtual_cursor(
+    mut aim_input: ResMut<AimInput>,
+    player_query: Query<(&Transform, &Controller), With<Player>>,
+    input_device: Res<InputDevice>,
+    gamepads: ControllerGamepads,
+    time: Res<Time<Real>>,
+) {
+    if *input_device != InputDevice::Gamepad {
+        return;
+    }
+    let Some(player_pos) = player_query
+        .iter()
+        .find(|(_, controller)| **controller == Controller::Primary)
+        .map(|(transf, _)| transf.translation.truncate())
     else {
         return;
     };
 
-    cursor_pos.0 = Some(win_cpos);
+    if aim_input.source != AimSource::Virtual {
+        // picks up where the mouse left off
+        aim_input.virtual_offset = aim_input.pos.map_or(Vec2::X * GAMEPAD_AIM_DISTANCE, |pos| {
+            (pos - player_pos).clamp_length_max(VIRTUAL_CURSOR_MAX_DISTANCE)
+        });
+        aim_input.source = AimSource::Virtual;
+    }
+    let stick = gamepads
+        .of(Controller::Primary)
+        .map(|gamepad| gamepad.right_stick())
+        .find(|stick| stick.length() >= GAMEPAD_AIM_DEADZONE);
+    if let Some(stick) = stick {
+        aim_input.virtual_offset = (aim_input.virtual_offset
+            + stick * VIRTUAL_CURSOR_SPEED * time.delta_secs())
+        .clamp_length_max(VIRTUAL_CURSOR_MAX_DISTANCE);
+    }
+    aim_input.pos = Some(player_pos + aim_input.virtual_offset);
+}
+
+/// Switches the [`InputDevice`] to whichever device had any activity this frame.
+fn track_input_device(
+    mut input_device: ResMut<InputDevice>,
+    mut cursor_moved_events: EventReader<CursorMoved>,
+    kbd_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    gamepads: ControllerGamepads,
+) {
+    let gamepad_active = gamepads.of(Controller::Primary).any(|gamepad| {
+        gamepad.left_stick().length() >= GAMEPAD_MOVE_DEADZONE
+            || gamepad.right_stick().length() >= GAMEPAD_AIM_DEADZONE
+            || gamepad.digital().get_just_pressed().next().is_some()
+    });
+    let kbm_active = cursor_moved_events.read().count() > 0
+        || kbd_input.get_just_pressed().next().is_some()
+        || mouse_input.get_just_pressed().next().is_some();
+
+    if gamepad_active {
+        *input_device = InputDevice::Gamepad;
+    } else if kbm_active {
+        *input_device = InputDevice::KeyboardMouse;
+    }
+}
+
+/// The primary player aims at the [`AimInput`], the co-op players with their right stick.
+pub(crate) fn update_aim_direction(
+    mut player_query: Query<(&mut AimDirection, &Transform, &Controller), With<Player>>,
+    aim_input: Res<AimInput>,
+    gamepads: ControllerGamepads,
+) {
+    for (mut aim_dir, player_transf, controller) in player_query.iter_mut() {
+        if *controller != Controller::Primary {
+            let stick = gamepads
+                .of(*controller)
+                .map(|gamepad| gamepad.right_stick())
+                .find(|stick| stick.length() >= GAMEPAD_AIM_DEADZONE);
+            if let Some(stick) = stick {
+                **aim_dir = Dir2::new(stick).ok();
+            }
+        } else if let Some(aim_pos) = aim_input.pos {
+            let player_pos = player_transf.translation.truncate();
+            // keep the last direction if the cursor is right on top of the player
+            if let Ok(dir) = Dir2::new(aim_pos - player_pos) {
+                **aim_dir = Some(dir);
+            }
+        }
+    }
 }
@@ -0,0 +1,218 @@
+//! The [`AtlasManifest`] lists the spritesheets the [`GlobTextAtlases`](super::GlobTextAtlases)
+//! are cut from.
+//!
+//! The manifest is a `key = value` file, every key starts with the name of its atlas:
+//!
+//! ```text
+//! common.path = combined_sprites.png
+//! common.tile_size = 16
+//! common.columns = 4
+//! common.rows = 4
+//! common.region.gun = 10
+//! ```
+//!
+//! The `tile_size` and `offset` take either one value for both axes or `x, y`. The `offset` is
+//! where the grid starts in the spritesheet, so an atlas can be cut from a part of a bigger
+//! one. The regions name the tiles the game looks up. Atlases without a `path` are dropped.
+//!
+//! The `enemies` section picks the sprite of every
+//! [`EnemyKind`](crate::enemy::EnemyKind) as `atlas, region, frames`, the walk animation runs
+//! through the `frames` tiles starting at the region:
+//!
+//! ```text
+//! enemies.grunt = common, enemy, 4
+//! ```
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::settings::cfg_entries;
+
+/// One spritesheet cut into a grid of tiles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasEntry {
+    pub path: String,
+    pub tile_size: UVec2,
+    pub columns: u32,
+    pub rows: u32,
+    pub offset: UVec2,
+    /// The indices of the named tiles.
+    pub regions: HashMap<String, usize>,
+}
+
+impl Default for AtlasEntry {
+    fn default() -> Self {
+        AtlasEntry {
+            path: String::new(),
+            tile_size: UVec2::splat(16),
+            columns: 1,
+            rows: 1,
+            offset: UVec2::ZERO,
+            regions: HashMap::default(),
+        }
+    }
+}
+
+impl AtlasEntry {
+    pub fn layout(&self) -> TextureAtlasLayout {
+        TextureAtlasLayout::from_grid(
+            self.tile_size,
+            self.columns,
+            self.rows,
+            None,
+            Some(self.offset),
+        )
+    }
+
+    /// The number of tiles in the atlas.
+    pub fn tiles(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+}
+
+/// The part of an atlas an enemy is drawn with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnemySpriteEntry {
+    pub atlas: String,
+    pub region: String,
+    pub frames: usize,
+}
+
+impl EnemySpriteEntry {
+    /// Either `atlas, region` or `atlas, region, frames`.
+    fn parse(val: &str) -> Option<Self> {
+        let mut parts = val.split(',').map(str::trim);
+        let atlas = parts.next().filter(|atlas| !atlas.is_empty())?;
+        let region = parts.next().filter(|region| !region.is_empty())?;
+        let frames = match parts.next() {
+            Some(frames) => frames.parse().ok().filter(|&frames| frames > 0)?,
+            None => 1,
+        };
+        Some(EnemySpriteEntry {
+            atlas: atlas.to_string(),
+            region: region.to_string(),
+            frames,
+        })
+    }
+}
+
+#[derive(Asset, TypePath, Debug, Default, Clone)]
+pub struct AtlasManifest {
+    pub atlases: HashMap<String, AtlasEntry>,
+    /// The sprites of the enemies by the [`EnemyKind::name`](crate::enemy::EnemyKind::name).
+    pub enemies: HashMap<String, EnemySpriteEntry>,
+}
+
+impl AtlasManifest {
+    pub fn from_cfg(cfg: &str) -> Self {
+        let mut atlases = HashMap::<String, AtlasEntry>::default();
+        let mut enemies = HashMap::default();
+        for (key, val) in cfg_entries(cfg) {
+            if let Some(kind) = key.strip_prefix("enemies.") {
+                match EnemySpriteEntry::parse(val) {
+                    Some(entry) => {
+                        enemies.insert(kind.to_string(), entry);
+                    }
+                    None => warn!("Invalid enemy sprite, expected atlas, region, frames: {key}"),
+                }
+                continue;
+            }
+            let Some((name, field)) = key.split_once('.') else {
+                warn!("Atlas manifest key without an atlas: {key}");
+                continue;
+            };
+            let entry = atlases.entry(name.to_string()).or_default();
+            match field {
+                "path" => entry.path = val.to_string(),
+                "tile_size" => entry.tile_size = parse_uvec2(val).unwrap_or(entry.tile_size),
+                "offset" => entry.offset = parse_uvec2(val).unwrap_or(entry.offset),
+                "columns" => entry.columns = val.parse().unwrap_or(entry.columns).max(1),
+                "rows" => entry.rows = val.parse().unwrap_or(entry.rows).max(1),
+                _ => match field
+                    .strip_prefix("region.")
+                    .map(|region| (region, val.parse()))
+                {
+                    Some((region, Ok(idx))) => {
+                        entry.regions.insert(region.to_string(), idx);
+                    }
+                    _ => warn!("Unknown atlas manifest key: {key}"),
+                },
+            }
+        }
+        atlases.retain(|name, entry| {
+            if entry.path.is_empty() {
+                warn!("The {name} atlas has no path, skipping it");
+            }
+            !entry.path.is_empty()
+        });
+        AtlasManifest { atlases, enemies }
+    }
+}
+
+/// Either `16` for both axes or `16, 32`.
+fn parse_uvec2(val: &str) -> Option<UVec2> {
+    match val.split_once(',') {
+        Some((x, y)) => Some(UVec2::new(x.trim().parse().ok()?, y.trim().parse().ok()?)),
+        None => val.parse().ok().map(UVec2::splat),
+    }
+}
+
+#[derive(Default)]
+pub(super) struct AtlasManifestLoader;
+
+impl AssetLoader for AtlasManifestLoader {
+    type Asset = AtlasManifest;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let cfg = String::from_utf8(bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(AtlasManifest::from_cfg(&cfg))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["atlases"]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn atlas_manifest_from_cfg() {
+        let manifest = AtlasManifest::from_cfg(
+            "player.path = player.png\nplayer.tile_size = 16, 32\nplayer.columns = 4\n\
+             player.region.idle = 0\nchest.path = dungeon.png\nchest.offset = 304, 416\n\
+             chest.rows = none\nchest.region.open = last\nbroken.columns = 2\n\
+             enemies.grunt = player, idle, 2\nenemies.runner = player, idle\n\
+             enemies.brute = player, idle, 0\n",
+        );
+        assert_eq!(manifest.atlases.len(), 2);
+        assert_eq!(manifest.enemies.len(), 2);
+        assert_eq!(manifest.enemies["grunt"].frames, 2);
+        assert_eq!(manifest.enemies["runner"].frames, 1);
+
+        let player = &manifest.atlases["player"];
+        assert_eq!(player.tile_size, UVec2::new(16, 32));
+        assert_eq!(player.tiles(), 4);
+        assert_eq!(player.regions["idle"], 0);
+
+        let chest = &manifest.atlases["chest"];
+        assert_eq!(chest.offset, UVec2::new(304, 416));
+        assert_eq!(chest.tile_size, UVec2::splat(16));
+        assert_eq!(chest.rows, 1);
+        assert!(chest.regions.is_empty());
+    }
+}
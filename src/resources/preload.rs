@@ -0,0 +1,173 @@
+//! Assets that only some of the [`GameState`]s need, so they don't stay loaded for the whole
+//! game.
+//!
+//! A plugin declares them with [`StateAssetsAppExt::preload_assets`]:
+//!
+//! ```ignore
+//! app.preload_assets(&[GameState::GameRun, GameState::LevelUp], &["music/run.ogg"]);
+//! ```
+//!
+//! Entering a state starts loading its assets and drops the handles of the previous state, the
+//! ones both states need stay loaded. The handles are kept in the [`LoadedStateAssets`], once
+//! nothing else holds on to them either the assets are freed. They are swapped during the
+//! [`StateTransition`], so the `OnEnter` systems of the state can already load them again with the
+//! [`AssetServer`] by their path, which is instant while the state keeps them.
+//!
+//! Until they are all loaded the [`AssetLoadProgress`] shows the loading screen and the
+//! [`TimeScalePlugin`](crate::time_scale::TimeScalePlugin) holds the virtual [`Time`], so the
+//! gameplay doesn't run ahead of its assets.
+
+use bevy::{
+    asset::{LoadState, LoadedUntypedAsset},
+    prelude::*,
+    utils::HashMap,
+};
+
+use super::AssetLoadProgress;
+use crate::prelude::*;
+
+pub trait StateAssetsAppExt {
+    /// Loads the assets at the `paths` whenever the game enters one of the `states`.
+    fn preload_assets(&mut self, states: &[GameState], paths: &[&str]) -> &mut Self;
+}
+
+impl StateAssetsAppExt for App {
+    fn preload_assets(&mut self, states: &[GameState], paths: &[&str]) -> &mut Self {
+        let mut state_assets = self.world_mut().get_resource_or_init::<StateAssets>();
+        for &state in states {
+            state_assets.add(state, paths);
+        }
+        self
+    }
+}
+
+/// The paths of the assets every [`GameState`] needs.
+#[derive(Resource, Debug, Default)]
+pub struct StateAssets(HashMap<GameState, Vec<String>>);
+
+impl StateAssets {
+    pub fn add(&mut self, state: GameState, paths: &[&str]) {
+        let state_paths = self.0.entry(state).or_default();
+        for path in paths {
+            if !state_paths.iter().any(|known| known == path) {
+                state_paths.push(path.to_string());
+            }
+        }
+    }
+
+    pub fn paths(&self, state: GameState) -> &[String] {
+        self.0.get(&state).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Keeps the assets of the current state loaded.
+#[derive(Resource, Debug, Default)]
+pub struct LoadedStateAssets(Vec<Handle<LoadedUntypedAsset>>);
+
+pub(super) fn swap_state_assets(
+    mut loaded: ResMut<LoadedStateAssets>,
+    state_assets: Res<StateAssets>,
+    state: Res<State<GameState>>,
+    asset_serv: Res<AssetServer>,
+) {
+    // the new handles are taken before the old ones are dropped, so the shared assets stay
+    let handles = state_assets
+        .paths(**state)
+        .iter()
+        .map(|path| asset_serv.load_untyped(path))
+        .collect();
+    loaded.0 = handles;
+}
+
+/// Counts the assets of the state that are done loading.
+pub(super) fn track_state_assets(
+    mut progress: ResMut<AssetLoadProgress>,
+    loaded: Res<LoadedStateAssets>,
+    asset_serv: Res<AssetServer>,
+) {
+    let done = loaded
+        .0
+        .iter()
+        .filter(|handle| match asset_serv.get_load_state(handle.id()) {
+            // the asset server reports it, there is nothing to wait for
+            Some(LoadState::Failed(_)) => true,
+            _ => asset_serv.is_loaded_with_dependencies(handle.id()),
+        })
+        .count();
+    progress.set_if_neq(AssetLoadProgress {
+        loaded: done,
+        total: loaded.0.len(),
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::state::{app::StatesPlugin, state::StateTransitionSteps};
+
+    use super::*;
+
+    fn enter(app: &mut App, state: GameState) {
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(state);
+        app.update();
+    }
+
+    #[test]
+    fn state_assets_are_declared_once_per_state() {
+        let mut app = App::new();
+        app.preload_assets(
+            &[GameState::GameRun, GameState::LevelUp],
+            &["a.png", "b.ogg"],
+        )
+        .preload_assets(&[GameState::GameRun], &["b.ogg", "c.png"]);
+
+        let state_assets = app.world().resource::<StateAssets>();
+        assert_eq!(
+            state_assets.paths(GameState::GameRun),
+            ["a.png", "b.ogg", "c.png"]
+        );
+        assert_eq!(state_assets.paths(GameState::LevelUp), ["a.png", "b.ogg"]);
+        assert!(state_assets.paths(GameState::MainMenu).is_empty());
+    }
+
+    #[test]
+    fn state_assets_are_released_on_exit() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), StatesPlugin))
+            .init_state::<GameState>()
+            .init_resource::<LoadedStateAssets>()
+            .preload_assets(
+                &[GameState::GameRun, GameState::LevelUp],
+                &["locale/en.lang"],
+            )
+            .add_systems(
+                StateTransition,
+                swap_state_assets
+                    .run_if(state_changed::<GameState>)
+                    .in_set(StateTransitionSteps::ExitSchedules),
+            );
+        app.update();
+        let handles = |app: &App| app.world().resource::<LoadedStateAssets>().0.len();
+        assert_eq!(handles(&app), 0);
+
+        enter(&mut app, GameState::GameRun);
+        assert_eq!(handles(&app), 1);
+        let id = app.world().resource::<LoadedStateAssets>().0[0].id();
+        // the asset both states need stays
+        enter(&mut app, GameState::LevelUp);
+        assert_eq!(app.world().resource::<LoadedStateAssets>().0[0].id(), id);
+        assert!(app
+            .world()
+            .resource::<AssetServer>()
+            .get_load_state(id)
+            .is_some());
+
+        enter(&mut app, GameState::MainMenu);
+        assert_eq!(handles(&app), 0);
+        // the dropped handle is only processed by the asset server on the next update
+        app.update();
+        let asset_serv = app.world().resource::<AssetServer>();
+        assert!(asset_serv.get_load_state(id).is_none());
+    }
+}
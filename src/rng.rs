@@ -0,0 +1,98 @@
+//! The [`GameRng`] everything random in a run is rolled from.
+//!
+//! It's reseeded from the [`WorldSeed`](crate::world::WorldSeed) at the start of every run, so a
+//! run with the same seed and the same inputs plays out the same way. The seed is random, unless
+//! it's set in the [`GameConfig`](crate::settings::GameConfig) or with `--seed` to replay a run.
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Independent streams of random numbers, so rolling one thing more often doesn't change the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RngStream {
+    Decor,
+    Obstacles,
+    Ground,
+    BorderWalls,
+    Chests,
+    Portals,
+    Pois,
+    Terrain,
+    WeaponPickups,
+    Enemies,
+    Upgrades,
+    Weather,
+    /// The purely visual effects like the rain and the screen shake. They roll every frame, so
+    /// they depend on the frame rate and are kept away from everything else.
+    Effects,
+}
+
+impl RngStream {
+    pub const ALL: [RngStream; 13] = [
+        RngStream::Decor,
+        RngStream::Obstacles,
+        RngStream::Ground,
+        RngStream::BorderWalls,
+        RngStream::Chests,
+        RngStream::Portals,
+        RngStream::Pois,
+        RngStream::Terrain,
+        RngStream::WeaponPickups,
+        RngStream::Enemies,
+        RngStream::Upgrades,
+        RngStream::Weather,
+        RngStream::Effects,
+    ];
+}
+
+/// The random numbers of the current run, a stream for every [`RngStream`].
+#[derive(Resource, Debug)]
+pub struct GameRng(Vec<StdRng>);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(0)
+    }
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng(
+            RngStream::ALL
+                .iter()
+                .map(|&stream| StdRng::seed_from_u64(stream_seed(seed, stream)))
+                .collect(),
+        )
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut StdRng {
+        &mut self.0[stream as usize]
+    }
+}
+
+/// Mixes the stream into the seed, so the streams of neighbouring seeds don't overlap.
+fn stream_seed(seed: u64, stream: RngStream) -> u64 {
+    // splitmix64 finalizer
+    let mut hash = seed ^ (stream as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^ (hash >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn neighbouring_seeds_dont_share_streams() {
+        let seeds = RngStream::ALL
+            .iter()
+            .flat_map(|&stream| [stream_seed(42, stream), stream_seed(43, stream)])
+            .collect::<Vec<_>>();
+        for (i, seed) in seeds.iter().enumerate() {
+            assert!(!seeds[i + 1..].contains(seed));
+        }
+    }
+}
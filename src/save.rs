@@ -0,0 +1,401 @@
+//! Contains [`SavePlugin`] that lets a run be continued after the game was closed.
+//!
+//! Pausing or quitting the game in the middle of a run writes the essential state of it into a
+//! [`RunSave`] at [`RUN_SAVE_PATH`], in the platform's data directory. The main menu offers to
+//! continue the [`SavedRun`], which puts it into the [`ResumeRun`]. The world of the run is
+//! generated again from its seed, and the rest of the run is restored on top of it once it
+//! starts.
+//!
+//! Only what the run needs to go on is kept. The alive enemies are saved as counts per kind and
+//! spawn around the player again, the bosses and the state of the world, like the opened chests,
+//! start over. Only the first player is restored, the co-op players join again with start. The
+//! save is deleted once the run is over.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::Health;
+use crate::enemy::{enemy_bundle, random_spawn_pos, Boss, Elite, Enemy, EnemyKind, Wave};
+use crate::experience::{Experience, Level};
+use crate::inventory::{Inventory, PassiveItem};
+use crate::orbital::OrbitalWeapon;
+use crate::player::character::{Character, SelectedCharacter};
+use crate::player::{Dying, Player, PlayerStats};
+use crate::poi::Blessing;
+use crate::prelude::*;
+use crate::resources::GlobTextAtlases;
+use crate::rng::{GameRng, RngStream};
+use crate::score::{MilestonesReached, RunStats, Score, ScoreTable};
+use crate::settings::data_path;
+use crate::world::{config::WorldConfig, WorldSeed};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SavedRun::load())
+            .init_resource::<ResumeRun>()
+            // everything of the run is set up by then, the restored state goes on top of it
+            .add_systems(
+                OnTransition {
+                    exited: GameState::GameInit,
+                    entered: GameState::GameRun,
+                },
+                restore_run,
+            )
+            .add_systems(OnEnter(GameState::Paused), save_run)
+            .add_systems(Update, delete_saved_run)
+            .add_systems(
+                Last,
+                save_run.run_if(
+                    on_event::<AppExit>.and(
+                        in_state(GameState::GameRun)
+                            .or(in_state(GameState::LevelUp))
+                            .or(in_state(GameState::Paused)),
+                    ),
+                ),
+            );
+    }
+}
+
+/// Everything needed to continue a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSave {
+    /// The [`WorldSeed`], the world is generated from it again.
+    pub seed: u64,
+    pub character: Character,
+    pub score: u64,
+    pub wave: u32,
+    /// Seconds into the current wave.
+    pub wave_elapsed_secs: f32,
+    pub stats: RunStats,
+    pub players: Vec<PlayerSave>,
+    pub enemies: Vec<EnemyCount>,
+}
+
+/// A player that was alive when the run was saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSave {
+    pub pos: [f32; 2],
+    pub health: u32,
+    pub max_health: u32,
+    pub level: u32,
+    pub xp: u32,
+    /// Already includes the bonuses of the upgrades, the items and the [`Blessing`].
+    pub stats: PlayerStats,
+    /// Seconds left of the [`Blessing`], so its bonus still runs out after the run is continued.
+    pub blessing_secs: Option<f32>,
+    pub orbital_blades: u32,
+    pub items: Vec<(PassiveItem, u32)>,
+}
+
+/// How many enemies of the kind were alive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnemyCount {
+    pub kind: EnemyKind,
+    pub elite: bool,
+    pub count: u32,
+}
+
+impl RunSave {
+    /// Loads the save from [`RUN_SAVE_PATH`], if there is one.
+    pub fn load() -> Option<Self> {
+        let json = std::fs::read_to_string(data_path(RUN_SAVE_PATH)).ok()?;
+        match Self::from_json(&json) {
+            Ok(run) => Some(run),
+            Err(e) => {
+                warn!("Couldn't load the saved run from {RUN_SAVE_PATH}: {e}");
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = data_path(RUN_SAVE_PATH);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_json())
+    }
+
+    pub fn delete() -> std::io::Result<()> {
+        match std::fs::remove_file(data_path(RUN_SAVE_PATH)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        // plain data, it can't fail
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+/// Counts the enemies by their kind and whether they are [`Elite`]s.
+pub fn count_enemies(enemies: impl IntoIterator<Item = (EnemyKind, bool)>) -> Vec<EnemyCount> {
+    let mut counts: Vec<EnemyCount> = Vec::new();
+    for (kind, elite) in enemies {
+        match counts
+            .iter_mut()
+            .find(|count| count.kind == kind && count.elite == elite)
+        {
+            Some(count) => count.count += 1,
+            None => counts.push(EnemyCount {
+                kind,
+                elite,
+                count: 1,
+            }),
+        }
+    }
+    counts
+}
+
+/// The run that can be continued from the main menu, if there is one.
+#[derive(Resource, Debug, Default, Deref)]
+pub struct SavedRun(pub Option<RunSave>);
+
+impl SavedRun {
+    pub fn load() -> Self {
+        SavedRun(RunSave::load())
+    }
+}
+
+/// The run being continued, it's restored once it starts.
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct ResumeRun(pub Option<RunSave>);
+
+fn save_run(
+    mut saved_run: ResMut<SavedRun>,
+    player_query: Query<
+        (
+            &Transform,
+            &Health,
+            &Level,
+            &Experience,
+            &PlayerStats,
+            &OrbitalWeapon,
+            &Inventory,
+            Option<&Blessing>,
+        ),
+        (With<Player>, Without<Dying>),
+    >,
+    enemy_query: Query<(&EnemyKind, Has<Elite>), (With<Enemy>, Without<Boss>)>,
+    score: Res<Score>,
+    wave: Res<Wave>,
+    stats: Res<RunStats>,
+    character: Res<SelectedCharacter>,
+    seed: Res<WorldSeed>,
+) {
+    let players = player_query
+        .iter()
+        .map(
+            |(transf, hp, level, xp, stats, orbital, inventory, blessing)| PlayerSave {
+                pos: transf.translation.truncate().to_array(),
+                health: hp.current,
+                max_health: hp.max,
+                level: **level,
+                xp: **xp,
+                stats: *stats,
+                blessing_secs: blessing.map(|blessing| blessing.remaining_secs()),
+                orbital_blades: orbital.count,
+                items: inventory.iter().collect(),
+            },
+        )
+        .collect::<Vec<_>>();
+    // the run is lost anyway
+    if players.is_empty() {
+        return;
+    }
+
+    let run = RunSave {
+        seed: **seed,
+        character: **character,
+        score: **score,
+        wave: wave.number,
+        wave_elapsed_secs: wave.timer.elapsed_secs(),
+        stats: stats.clone(),
+        players,
+        enemies: count_enemies(enemy_query.iter().map(|(kind, elite)| (*kind, elite))),
+    };
+    match run.save() {
+        Ok(()) => info!("Saved the run to {RUN_SAVE_PATH}"),
+        Err(e) => warn!("Couldn't save the run to {RUN_SAVE_PATH}: {e}"),
+    }
+    saved_run.0 = Some(run);
+}
+
+fn restore_run(
+    mut commands: Commands,
+    mut resume: ResMut<ResumeRun>,
+    mut score: ResMut<Score>,
+    mut milestones: ResMut<MilestonesReached>,
+    mut wave: ResMut<Wave>,
+    mut stats: ResMut<RunStats>,
+    mut game_rng: ResMut<GameRng>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Health,
+            &mut Level,
+            &mut Experience,
+            &mut PlayerStats,
+            &mut OrbitalWeapon,
+            &mut Inventory,
+        ),
+        With<Player>,
+    >,
+    table: Res<ScoreTable>,
+    text_atlases: Res<GlobTextAtlases>,
+    config: Res<WorldConfig>,
+) {
+    let Some(run) = resume.take() else {
+        return;
+    };
+
+    **score = run.score;
+    // don't announce the milestones the run already passed
+    **milestones = table
+        .milestones
+        .partition_point(|&milestone| milestone <= run.score);
+    wave.number = run.wave;
+    wave.timer
+        .set_elapsed(Duration::from_secs_f32(run.wave_elapsed_secs));
+    *stats = run.stats;
+
+    for (player, saved) in player_query.iter_mut().zip(&run.players) {
+        let (
+            ent,
+            mut transf,
+            mut hp,
+            mut level,
+            mut xp,
+            mut player_stats,
+            mut orbital,
+            mut inventory,
+        ) = player;
+        transf.translation = Vec2::from(saved.pos).extend(transf.translation.z);
+        *hp = Health {
+            current: saved.health,
+            max: saved.max_health,
+        };
+        **level = saved.level;
+        **xp = saved.xp;
+        *player_stats = saved.stats;
+        if let Some(secs) = saved.blessing_secs {
+            let mut timer = Timer::from_seconds(POI_BLESSING_SECS, TimerMode::Once);
+            timer.set_elapsed(Duration::from_secs_f32(POI_BLESSING_SECS - secs));
+            commands.entity(ent).insert(Blessing(timer));
+        }
+        orbital.count = saved.orbital_blades;
+        // the bonuses of the items are already in the stats
+        *inventory = Inventory::default();
+        for &(item, count) in saved.items.iter() {
+            for _ in 0..count {
+                inventory.add(item);
+            }
+        }
+    }
+
+    let rng = game_rng.stream(RngStream::Enemies);
+    let player_pos = run
+        .players
+        .first()
+        .map_or(Vec2::ZERO, |saved| saved.pos.into());
+    let mut enemy_entities = Vec::new();
+    for enemies in run.enemies.iter() {
+        for _ in 0..enemies.count {
+            let pos = random_spawn_pos(rng, player_pos, &config);
+            if enemies.elite {
+                commands.spawn((enemy_bundle(enemies.kind, pos, &text_atlases), Elite));
+            } else {
+                enemy_entities.push(enemy_bundle(enemies.kind, pos, &text_atlases));
+            }
+        }
+    }
+    commands.spawn_batch(enemy_entities);
+    info!("Continuing the run of seed {}", run.seed);
+}
+
+/// A finished run can't be continued.
+fn delete_saved_run(mut run_ended_events: EventReader<RunEnded>, mut saved_run: ResMut<SavedRun>) {
+    // a quit run is kept to be continued
+    if !run_ended_events
+        .read()
+        .any(|&run_ended| run_ended == RunEnded::GameOver)
+    {
+        return;
+    }
+    if saved_run.0.take().is_none() {
+        return;
+    }
+    if let Err(e) = RunSave::delete() {
+        warn!("Couldn't delete the saved run at {RUN_SAVE_PATH}: {e}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn run_save_json_roundtrip() {
+        let run = RunSave {
+            seed: 42,
+            character: Character::Ranger,
+            score: 1234,
+            wave: 3,
+            wave_elapsed_secs: 12.5,
+            stats: RunStats {
+                kills: 80,
+                time_survived_secs: 132.5,
+                ..default()
+            },
+            players: vec![PlayerSave {
+                pos: [10., -20.],
+                health: 30,
+                max_health: 60,
+                level: 4,
+                xp: 7,
+                stats: PlayerStats::default(),
+                blessing_secs: Some(4.5),
+                orbital_blades: 1,
+                items: vec![(PassiveItem::Boots, 2)],
+            }],
+            enemies: count_enemies([
+                (EnemyKind::Grunt, false),
+                (EnemyKind::Brute, false),
+                (EnemyKind::Grunt, true),
+                (EnemyKind::Grunt, false),
+            ]),
+        };
+        assert_eq!(
+            run.enemies,
+            [
+                EnemyCount {
+                    kind: EnemyKind::Grunt,
+                    elite: false,
+                    count: 2
+                },
+                EnemyCount {
+                    kind: EnemyKind::Brute,
+                    elite: false,
+                    count: 1
+                },
+                EnemyCount {
+                    kind: EnemyKind::Grunt,
+                    elite: true,
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(RunSave::from_json(&run.to_json()).unwrap(), run);
+        assert!(RunSave::from_json("{}").is_err());
+    }
+}
@@ -4,25 +4,591 @@
 //! [`Score`], while reseting `ScoreAccumulator`.
 //!
 //! Also contains a [`Worth`] component that is intended to be added to all the things that should
-//! be scored.
+//! be scored. The enemies get theirs from the [`ScoreTable`] loaded from [`SCORE_TABLE_PATH`].
+//!
+//! Kills in quick succession build up a [`Combo`] that multiplies the [`Worth`] of the next ones.
+//! Surviving adds to the [`Score`] too, at the rate of the [`SurvivalScore`]. Every kill sends a
+//! [`ScoreGained`] with what it was worth, for the popups. Once the [`Score`] passes one of the
+//! milestones of the [`ScoreTable`] a [`ScoreMilestone`] is sent, so the rest of the game can
+//! react to it without watching the [`Score`].
+//!
+//! The [`RunStats`] keep track of the rest of the current run's statistics.
+//!
+//! Once a run is over it's put together into a [`RunSummary`] and appended to the
+//! [`RUN_HISTORY_PATH`]. It's also recorded in the [`HighScores`], which are persisted to
+//! [`HIGH_SCORES_PATH`]. Both files are in the platform's data directory. The place of the run on
+//! the table is kept in the [`LastRunRank`] for the game over screen.
+//!
+//! The table is exported to the [`LEADERBOARD_PATH`] as JSON along with it. The runs in that file
+//! are merged into the table on startup, so it can be copied over from another machine. Every run
+//! keeps its seed and character, so it can be played again.
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
 
-use bevy::prelude::*;
+use crate::collision::DamageEvent;
+use crate::components::Invulnerable;
+use crate::enemy::{Boss, Elite, Enemy, EnemyKilled, EnemyKind};
+use crate::gun::{Bullet, BulletHit};
+use crate::player::character::SelectedCharacter;
+use crate::player::Player;
+use crate::prelude::*;
+use crate::settings::{cfg_entries, data_path};
+use crate::upgrades::{Upgrade, UpgradeChosen};
+use crate::world::WorldSeed;
 
 pub struct ScorePlugin;
 
 impl Plugin for ScorePlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.insert_resource(Score::default())
-            .add_systems(FixedUpdate, add_score_accum_to_score);
+        app.add_event::<ScoreGained>()
+            .add_event::<ScoreMilestone>()
+            .init_asset::<ScoreTable>()
+            .init_asset_loader::<ScoreTableLoader>()
+            .init_resource::<ScoreTable>()
+            .init_resource::<ScoreTableHandle>()
+            .insert_resource(Score::default())
+            .insert_resource(RunStats::default())
+            .insert_resource(Combo::default())
+            .insert_resource(SurvivalScore::default())
+            .insert_resource(HighScores::load())
+            .init_resource::<LastRunRank>()
+            .init_resource::<RunSummary>()
+            .init_resource::<MilestonesReached>()
+            .add_systems(
+                RunTeardown,
+                (
+                    reset_resource::<Score>,
+                    reset_resource::<RunStats>,
+                    reset_resource::<Combo>,
+                    reset_resource::<LastRunRank>,
+                    reset_resource::<MilestonesReached>,
+                    reset_survival_score,
+                ),
+            )
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                (summarize_run, (record_high_score, append_run_history)).chain(),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    add_score_accum_to_score,
+                    add_survival_score.run_if(in_state(GameState::GameRun)),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    track_time_survived,
+                    track_distance_traveled,
+                    count_kills,
+                    count_damage,
+                    count_shots,
+                    expire_combo,
+                    assign_enemy_worth,
+                    send_score_milestones,
+                )
+                    .run_if(in_state(GameState::GameRun)),
+            )
+            // the upgrades are picked in their own state
+            .add_systems(
+                Update,
+                track_upgrades_taken.run_if(in_state(GameState::LevelUp)),
+            )
+            .add_systems(Update, apply_score_table);
     }
 }
 
 #[derive(Resource, Deref, DerefMut, Default)]
 pub struct Score(pub u64);
 
-#[derive(Component, Deref, DerefMut)]
+/// Statistics of the current run, shown once it's over.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunStats {
+    pub time_survived_secs: f32,
+    pub kills: u32,
+    /// The kills of the regular enemies, indexed by their [`EnemyKind`].
+    pub kills_by_kind: [u32; EnemyKind::ALL.len()],
+    pub bosses_killed: u32,
+    /// Damage dealt to the enemies.
+    pub damage_dealt: u64,
+    /// Damage the players took, including what their shields absorbed.
+    pub damage_taken: u64,
+    /// Every bullet counts as a shot.
+    pub shots_fired: u32,
+    /// The enemies hit by the bullets, a piercing bullet can hit more than one.
+    pub shots_hit: u32,
+    /// Distance walked by all the players in pixels, without the teleports.
+    pub distance_traveled: f32,
+    /// The score of the kills, with the [`Combo`] multiplier.
+    pub kill_score: u64,
+    pub survival_score: u64,
+    /// Every upgrade picked during the run, in order.
+    pub upgrades_taken: Vec<Upgrade>,
+}
+
+impl RunStats {
+    /// The fraction of the shots that hit, from 0 to 1.
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            return 0.;
+        }
+        (self.shots_hit as f32 / self.shots_fired as f32).min(1.)
+    }
+}
+
+/// Sent when a kill scores, `amount` already includes the [`Combo`] multiplier.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ScoreGained {
+    pub amount: u64,
+    pub position: Vec2,
+}
+
+/// Sent once per run when the [`Score`] reaches one of the milestones of the [`ScoreTable`].
+#[derive(Event, Debug, Clone, Copy, Deref)]
+pub struct ScoreMilestone(pub u64);
+
+/// How many of the milestones of the [`ScoreTable`] the run has passed.
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct MilestonesReached(pub usize);
+
+/// The kills in a row with less than [`COMBO_WINDOW_SECS`] between them.
+#[derive(Resource, Debug, Default)]
+pub struct Combo {
+    pub kills: u32,
+    timer: Timer,
+}
+
+impl Combo {
+    /// Raised by one every [`COMBO_KILLS_PER_STEP`] kills, up to [`COMBO_MAX_MULTIPLIER`].
+    pub fn multiplier(&self) -> u64 {
+        (1 + (self.kills / COMBO_KILLS_PER_STEP) as u64).min(COMBO_MAX_MULTIPLIER)
+    }
+
+    /// Adds the kill to the combo and gives it another [`COMBO_WINDOW_SECS`].
+    pub fn add_kill(&mut self) {
+        self.kills += 1;
+        self.timer = Timer::from_seconds(COMBO_WINDOW_SECS, TimerMode::Once);
+    }
+}
+
+/// Adds `rate` points to the [`Score`] for every second survived.
+#[derive(Resource, Debug)]
+pub struct SurvivalScore {
+    pub rate: f32,
+    /// The fraction of a point that hasn't been added yet.
+    pending: f32,
+}
+
+impl Default for SurvivalScore {
+    fn default() -> Self {
+        SurvivalScore {
+            rate: SURVIVAL_SCORE_PER_SEC,
+            pending: 0.,
+        }
+    }
+}
+
+/// A finished run on the [`HighScores`] table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HighScore {
+    pub score: u64,
+    pub time_survived_secs: u32,
+    pub seed: u64,
+    pub character: String,
+}
+
+/// The [`HighScores`] as they are exported to the [`LEADERBOARD_PATH`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Leaderboard {
+    runs: Vec<HighScore>,
+}
+
+/// The best [`HIGH_SCORES_MAX`] local runs, the best one first.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Deref)]
+pub struct HighScores(Vec<HighScore>);
+
+impl HighScores {
+    /// Adds the run to the table if it's good enough, returns its rank starting from 0.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        // runs with the same score keep the older one first
+        let rank = self.0.partition_point(|other| other.score >= entry.score);
+        if rank >= HIGH_SCORES_MAX {
+            return None;
+        }
+        self.0.insert(rank, entry);
+        self.0.truncate(HIGH_SCORES_MAX);
+        Some(rank)
+    }
+
+    /// Adds the runs that aren't on the table yet, returns how many of them made it.
+    pub fn merge(&mut self, runs: impl IntoIterator<Item = HighScore>) -> usize {
+        let mut merged = 0;
+        for run in runs {
+            if !self.0.contains(&run) && self.insert(run).is_some() {
+                merged += 1;
+            }
+        }
+        merged
+    }
+
+    /// Loads the table from [`HIGH_SCORES_PATH`] and merges in the [`LEADERBOARD_PATH`], it's
+    /// empty if there are neither.
+    pub fn load() -> Self {
+        let mut high_scores = std::fs::read_to_string(data_path(HIGH_SCORES_PATH))
+            .map(|cfg| Self::from_cfg(&cfg))
+            .unwrap_or_default();
+
+        let Ok(json) = std::fs::read_to_string(data_path(LEADERBOARD_PATH)) else {
+            return high_scores;
+        };
+        match Self::from_json(&json) {
+            Ok(leaderboard) => {
+                let imported = high_scores.merge(leaderboard.0);
+                if imported > 0 {
+                    info!("Imported {imported} runs from {LEADERBOARD_PATH}");
+                }
+            }
+            Err(e) => warn!("Couldn't import the runs from {LEADERBOARD_PATH}: {e}"),
+        }
+        high_scores
+    }
+
+    /// Saves the table to [`HIGH_SCORES_PATH`] and exports it to [`LEADERBOARD_PATH`].
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = data_path(HIGH_SCORES_PATH);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_cfg())?;
+        std::fs::write(data_path(LEADERBOARD_PATH), self.to_json())
+    }
+
+    pub fn to_json(&self) -> String {
+        let leaderboard = Leaderboard {
+            runs: self.0.clone(),
+        };
+        // plain data, it can't fail
+        serde_json::to_string_pretty(&leaderboard).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let leaderboard = serde_json::from_str::<Leaderboard>(json)?;
+        let mut high_scores = HighScores::default();
+        high_scores.merge(leaderboard.runs);
+        Ok(high_scores)
+    }
+
+    /// One `run = score, time survived, seed, character` line per run.
+    pub fn to_cfg(&self) -> String {
+        self.0
+            .iter()
+            .map(|run| {
+                format!(
+                    "run = {}, {}, {}, {}\n",
+                    run.score, run.time_survived_secs, run.seed, run.character
+                )
+            })
+            .collect()
+    }
+
+    pub fn from_cfg(cfg: &str) -> Self {
+        let mut high_scores = HighScores::default();
+        for (key, val) in cfg_entries(cfg) {
+            if key != "run" {
+                warn!("Unknown high score entry: {key}");
+                continue;
+            }
+            let mut fields = val.splitn(4, ',').map(str::trim);
+            let (Some(Ok(score)), Some(Ok(time_survived_secs)), Some(Ok(seed)), Some(character)) = (
+                fields.next().map(str::parse),
+                fields.next().map(str::parse),
+                fields.next().map(str::parse),
+                fields.next(),
+            ) else {
+                warn!("Invalid high score entry: {val}");
+                continue;
+            };
+            high_scores.insert(HighScore {
+                score,
+                time_survived_secs,
+                seed,
+                character: character.to_string(),
+            });
+        }
+        high_scores
+    }
+}
+
+/// Where the [`Score`] of a run came from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ScoreBreakdown {
+    pub kills: u64,
+    pub survival: u64,
+    /// Everything else, like the pickups.
+    pub other: u64,
+}
+
+/// Everything about the last finished run, put together as it ends.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct RunSummary {
+    pub score: u64,
+    pub breakdown: ScoreBreakdown,
+    pub character: String,
+    pub seed: u64,
+    pub stats: RunStats,
+}
+
+impl RunSummary {
+    pub fn new(score: u64, character: &str, seed: u64, stats: RunStats) -> Self {
+        let breakdown = ScoreBreakdown {
+            kills: stats.kill_score,
+            survival: stats.survival_score,
+            other: score.saturating_sub(stats.kill_score + stats.survival_score),
+        };
+        RunSummary {
+            score,
+            breakdown,
+            character: character.to_string(),
+            seed,
+            stats,
+        }
+    }
+
+    /// The upgrades taken, in the order they were first picked, with how many times each.
+    pub fn upgrade_counts(&self) -> Vec<(Upgrade, u32)> {
+        let mut counts: Vec<(Upgrade, u32)> = Vec::new();
+        for &upgrade in self.stats.upgrades_taken.iter() {
+            match counts.iter_mut().find(|(counted, _)| *counted == upgrade) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((upgrade, 1)),
+            }
+        }
+        counts
+    }
+
+    /// A `[run]` header followed by one `key = value` line per field, ends with an empty line so
+    /// the runs in the history stay apart.
+    pub fn to_cfg(&self) -> String {
+        let stats = &self.stats;
+        let upgrades = stats
+            .upgrades_taken
+            .iter()
+            .map(|upgrade| upgrade.name())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let entries: [(&str, &dyn std::fmt::Display); 17] = [
+            ("score", &self.score),
+            ("kill_score", &self.breakdown.kills),
+            ("survival_score", &self.breakdown.survival),
+            ("other_score", &self.breakdown.other),
+            ("character", &self.character),
+            ("seed", &self.seed),
+            ("time_survived_secs", &(stats.time_survived_secs as u32)),
+            ("kills", &stats.kills),
+            (
+                "grunts_killed",
+                &stats.kills_by_kind[EnemyKind::Grunt as usize],
+            ),
+            (
+                "runners_killed",
+                &stats.kills_by_kind[EnemyKind::Runner as usize],
+            ),
+            (
+                "brutes_killed",
+                &stats.kills_by_kind[EnemyKind::Brute as usize],
+            ),
+            ("bosses_killed", &stats.bosses_killed),
+            ("damage_dealt", &stats.damage_dealt),
+            ("damage_taken", &stats.damage_taken),
+            ("shots_fired", &stats.shots_fired),
+            ("shots_hit", &stats.shots_hit),
+            ("upgrades", &upgrades),
+        ];
+        let mut cfg = "[run]\n".to_string();
+        for (key, val) in entries {
+            cfg += &format!("{key} = {val}\n");
+        }
+        cfg += &format!("distance_traveled = {:.0}\n\n", stats.distance_traveled);
+        cfg
+    }
+}
+
+/// The place of the last run on the [`HighScores`] table, starting from 0. `None` if it didn't
+/// make it.
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct LastRunRank(pub Option<usize>);
+
+#[derive(Component, Debug, Default, PartialEq, Deref, DerefMut)]
 pub struct Worth(pub u64);
 
+/// What the enemies and the survival are worth, for balancing. The [`Worth`] of an enemy is the
+/// one of its [`EnemyKind`], multiplied for the [`Elite`]s and the [`Boss`]es.
+///
+/// The tables are `key = value` files like the
+/// [`WorldConfig`](crate::world::config::WorldConfig), unknown keys and invalid values fall back
+/// to the defaults. Once the table is (re)loaded it applies to the live enemies too.
+#[derive(Asset, Resource, TypePath, Debug, Clone, PartialEq)]
+pub struct ScoreTable {
+    pub grunt: u64,
+    pub runner: u64,
+    pub brute: u64,
+    pub elite_mult: u64,
+    pub boss_mult: u64,
+    /// The rate of the [`SurvivalScore`].
+    pub survival_per_sec: f32,
+    /// The scores that send a [`ScoreMilestone`], from the lowest.
+    pub milestones: Vec<u64>,
+}
+
+impl Default for ScoreTable {
+    fn default() -> Self {
+        ScoreTable {
+            grunt: 1,
+            runner: 2,
+            brute: 4,
+            elite_mult: 10,
+            boss_mult: 100,
+            survival_per_sec: SURVIVAL_SCORE_PER_SEC,
+            milestones: SCORE_MILESTONES.to_vec(),
+        }
+    }
+}
+
+impl ScoreTable {
+    pub fn from_cfg(cfg: &str) -> Self {
+        let mut table = ScoreTable::default();
+        for (key, val) in cfg_entries(cfg) {
+            match key {
+                "grunt" => table.grunt = val.parse().unwrap_or(table.grunt),
+                "runner" => table.runner = val.parse().unwrap_or(table.runner),
+                "brute" => table.brute = val.parse().unwrap_or(table.brute),
+                "elite_mult" => table.elite_mult = val.parse().unwrap_or(table.elite_mult),
+                "boss_mult" => table.boss_mult = val.parse().unwrap_or(table.boss_mult),
+                "survival_per_sec" => {
+                    table.survival_per_sec = val.parse().unwrap_or(table.survival_per_sec)
+                }
+                // a comma separated list, in any order
+                "milestones" => {
+                    let milestones = val
+                        .split(',')
+                        .map(|milestone| milestone.trim().parse())
+                        .collect::<Result<Vec<u64>, _>>();
+                    match milestones {
+                        Ok(mut milestones) => {
+                            milestones.sort_unstable();
+                            milestones.dedup();
+                            table.milestones = milestones;
+                        }
+                        Err(_) => warn!("Invalid score milestones: {val}"),
+                    }
+                }
+                _ => warn!("Unknown score table key: {key}"),
+            }
+        }
+        table
+    }
+
+    pub fn worth(&self, kind: EnemyKind, elite: bool, boss: bool) -> u64 {
+        let base = match kind {
+            EnemyKind::Grunt => self.grunt,
+            EnemyKind::Runner => self.runner,
+            EnemyKind::Brute => self.brute,
+        };
+        let elite_mult = if elite { self.elite_mult } else { 1 };
+        let boss_mult = if boss { self.boss_mult } else { 1 };
+        base * elite_mult * boss_mult
+    }
+}
+
+#[derive(Resource, Debug, Deref)]
+pub struct ScoreTableHandle(pub Handle<ScoreTable>);
+
+impl FromWorld for ScoreTableHandle {
+    fn from_world(world: &mut World) -> Self {
+        ScoreTableHandle(world.resource::<AssetServer>().load(SCORE_TABLE_PATH))
+    }
+}
+
+#[derive(Default)]
+struct ScoreTableLoader;
+
+impl AssetLoader for ScoreTableLoader {
+    type Asset = ScoreTable;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let cfg = String::from_utf8(bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(ScoreTable::from_cfg(&cfg))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["score"]
+    }
+}
+
+/// Copies the table into the [`ScoreTable`] resource once it's loaded or modified.
+fn apply_score_table(
+    mut table: ResMut<ScoreTable>,
+    mut survival: ResMut<SurvivalScore>,
+    mut asset_events: EventReader<AssetEvent<ScoreTable>>,
+    handle: Res<ScoreTableHandle>,
+    tables: Res<Assets<ScoreTable>>,
+) {
+    let reloaded = asset_events.read().any(|event| {
+        event.is_loaded_with_dependencies(handle.id()) || event.is_modified(handle.id())
+    });
+    if !reloaded {
+        return;
+    }
+    if let Some(loaded) = tables.get(handle.id()) {
+        table.set_if_neq(loaded.clone());
+        survival.rate = loaded.survival_per_sec;
+    }
+}
+
+fn send_score_milestones(
+    mut milestone_events: EventWriter<ScoreMilestone>,
+    mut reached: ResMut<MilestonesReached>,
+    score: Res<Score>,
+    table: Res<ScoreTable>,
+) {
+    // a single big score can pass more of them at once
+    while let Some(&milestone) = table.milestones.get(**reached) {
+        if **score < milestone {
+            break;
+        }
+        **reached += 1;
+        milestone_events.send(ScoreMilestone(milestone));
+    }
+}
+
+/// Scores the new enemies, all of them when the table was reloaded.
+fn assign_enemy_worth(
+    mut enemy_query: Query<(Ref<Enemy>, &mut Worth, &EnemyKind, Has<Elite>, Has<Boss>)>,
+    table: Res<ScoreTable>,
+) {
+    let reloaded = table.is_changed();
+    for (enemy, mut worth, kind, elite, boss) in enemy_query.iter_mut() {
+        if reloaded || enemy.is_added() {
+            worth.set_if_neq(Worth(table.worth(*kind, elite, boss)));
+        }
+    }
+}
+
 #[derive(Component, Deref, DerefMut)]
 pub struct ScoreAccumulator(pub u64);
 
@@ -39,3 +605,234 @@ fn add_score_accum_to_score(
         **add_to_score = 0;
     }
 }
+
+fn add_survival_score(
+    mut survival: ResMut<SurvivalScore>,
+    mut score: ResMut<Score>,
+    mut stats: ResMut<RunStats>,
+    time: Res<Time>,
+) {
+    survival.pending += survival.rate * time.delta_secs();
+    let whole = survival.pending.floor();
+    if whole >= 1. {
+        survival.pending -= whole;
+        **score += whole as u64;
+        stats.survival_score += whole as u64;
+    }
+}
+
+/// Keeps the rate, only the pending fraction belongs to the run.
+fn reset_survival_score(mut survival: ResMut<SurvivalScore>) {
+    survival.pending = 0.;
+}
+
+fn track_time_survived(mut stats: ResMut<RunStats>, time: Res<Time>) {
+    stats.time_survived_secs += time.delta_secs();
+}
+
+/// Anything further than this in a single frame is a teleport.
+const MAX_STEP_DISTANCE: f32 = 50.;
+
+fn track_distance_traveled(
+    mut stats: ResMut<RunStats>,
+    mut last_positions: Local<Vec<(Entity, Vec2)>>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+) {
+    let positions = player_query
+        .iter()
+        .map(|(ent, transf)| (ent, transf.translation.truncate()))
+        .collect::<Vec<_>>();
+    for (ent, pos) in positions.iter() {
+        let Some((_, last_pos)) = last_positions.iter().find(|(last, _)| last == ent) else {
+            continue;
+        };
+        let step = pos.distance(*last_pos);
+        if step <= MAX_STEP_DISTANCE {
+            stats.distance_traveled += step;
+        }
+    }
+    *last_positions = positions;
+}
+
+fn count_kills(
+    mut stats: ResMut<RunStats>,
+    mut enemy_killed_events: EventReader<EnemyKilled>,
+    mut score_gained_events: EventReader<ScoreGained>,
+) {
+    stats.kill_score += score_gained_events
+        .read()
+        .map(|gained| gained.amount)
+        .sum::<u64>();
+    for killed in enemy_killed_events.read() {
+        stats.kills += 1;
+        if killed.boss {
+            stats.bosses_killed += 1;
+        } else {
+            stats.kills_by_kind[killed.kind as usize] += 1;
+        }
+    }
+}
+
+fn count_damage(
+    mut stats: ResMut<RunStats>,
+    mut damage_events: EventReader<DamageEvent>,
+    // the invulnerable don't take any damage
+    enemy_query: Query<(), (With<Enemy>, Without<Invulnerable>)>,
+    player_query: Query<(), (With<Player>, Without<Invulnerable>)>,
+) {
+    for event in damage_events.read() {
+        if enemy_query.contains(event.target) {
+            stats.damage_dealt += event.amount as u64;
+        } else if player_query.contains(event.target) {
+            stats.damage_taken += event.amount as u64;
+        }
+    }
+}
+
+fn count_shots(
+    mut stats: ResMut<RunStats>,
+    mut bullet_hit_events: EventReader<BulletHit>,
+    new_bullet_query: Query<(), Added<Bullet>>,
+) {
+    stats.shots_fired += new_bullet_query.iter().count() as u32;
+    stats.shots_hit += bullet_hit_events.read().count() as u32;
+}
+
+fn expire_combo(mut combo: ResMut<Combo>, time: Res<Time>) {
+    if combo.kills > 0 && combo.timer.tick(time.delta()).finished() {
+        *combo = Combo::default();
+    }
+}
+
+fn track_upgrades_taken(
+    mut stats: ResMut<RunStats>,
+    mut chosen_events: EventReader<UpgradeChosen>,
+) {
+    // only a single upgrade can be picked per level up
+    if let Some(chosen) = chosen_events.read().last() {
+        stats.upgrades_taken.push(**chosen);
+    }
+}
+
+fn summarize_run(
+    mut summary: ResMut<RunSummary>,
+    score: Res<Score>,
+    stats: Res<RunStats>,
+    character: Res<SelectedCharacter>,
+    seed: Res<WorldSeed>,
+) {
+    *summary = RunSummary::new(**score, character.name(), **seed, stats.clone());
+}
+
+fn append_run_history(summary: Res<RunSummary>) {
+    let path = data_path(RUN_HISTORY_PATH);
+    let append = || -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        std::io::Write::write_all(&mut file, summary.to_cfg().as_bytes())
+    };
+    if let Err(e) = append() {
+        warn!("Couldn't append the run to {RUN_HISTORY_PATH}: {e}");
+    }
+}
+
+pub fn record_high_score(
+    mut high_scores: ResMut<HighScores>,
+    mut last_run_rank: ResMut<LastRunRank>,
+    summary: Res<RunSummary>,
+) {
+    let run = HighScore {
+        score: summary.score,
+        time_survived_secs: summary.stats.time_survived_secs as u32,
+        seed: summary.seed,
+        character: summary.character.clone(),
+    };
+    **last_run_rank = high_scores.insert(run);
+    if last_run_rank.is_none() {
+        return;
+    }
+    if let Err(e) = high_scores.save() {
+        warn!("Couldn't save the high scores to {HIGH_SCORES_PATH}: {e}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(score: u64) -> HighScore {
+        HighScore {
+            score,
+            time_survived_secs: 60,
+            seed: score * 7,
+            character: "Knight".to_string(),
+        }
+    }
+
+    #[test]
+    fn high_scores_keep_the_best_runs() {
+        let mut high_scores = HighScores::default();
+        for score in 0..HIGH_SCORES_MAX as u64 {
+            high_scores.insert(run(score * 10));
+        }
+        assert_eq!(high_scores.insert(run(0)), None);
+        assert_eq!(high_scores.insert(run(15)), Some(HIGH_SCORES_MAX - 2));
+        assert_eq!(high_scores.len(), HIGH_SCORES_MAX);
+        assert_eq!(high_scores[0].score, (HIGH_SCORES_MAX as u64 - 1) * 10);
+
+        assert_eq!(HighScores::from_cfg(&high_scores.to_cfg()), high_scores);
+    }
+
+    #[test]
+    fn leaderboard_import_skips_known_runs() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(run(10));
+        high_scores.insert(run(20));
+
+        let mut other = HighScores::default();
+        other.insert(run(20));
+        other.insert(run(30));
+        let imported = HighScores::from_json(&other.to_json()).unwrap();
+        assert_eq!(imported, other);
+
+        assert_eq!(high_scores.merge(imported.0), 1);
+        assert_eq!(high_scores.len(), 3);
+        assert_eq!(high_scores[0], run(30));
+        assert!(HighScores::from_json("{ \"runs\": 3 }").is_err());
+    }
+
+    #[test]
+    fn score_table_multiplies_elites_and_bosses() {
+        let table = ScoreTable::from_cfg(
+            "runner = 3
+elite_mult = 4
+boss_mult = lots
+milestones = 50, 10, 20
+",
+        );
+        assert_eq!(table.milestones, [10, 20, 50]);
+        assert_eq!(table.worth(EnemyKind::Runner, false, false), 3);
+        assert_eq!(table.worth(EnemyKind::Runner, true, false), 12);
+        assert_eq!(
+            table.worth(EnemyKind::Grunt, false, true),
+            ScoreTable::default().boss_mult
+        );
+    }
+
+    #[test]
+    fn combo_multiplier_is_capped() {
+        let mut combo = Combo::default();
+        assert_eq!(combo.multiplier(), 1);
+        for _ in 0..COMBO_KILLS_PER_STEP {
+            combo.add_kill();
+        }
+        assert_eq!(combo.multiplier(), 2);
+        combo.kills = COMBO_KILLS_PER_STEP * 100;
+        assert_eq!(combo.multiplier(), COMBO_MAX_MULTIPLIER);
+    }
+}
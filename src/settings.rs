@@ -0,0 +1,872 @@
+//! Contains the [`SettingsPlugin`] that keeps the [`UserSettings`] and persists them to
+//! [`USER_SETTINGS_PATH`] in the platform's config directory whenever they change. The changes
+//! made on the settings screen, like dragging a volume slider, are only saved once it's left.
+//! The [`VideoSettings`] in them are applied to the primary [`Window`] here, the
+//! [`AudioSettings`] by the [`SoundPlugin`](crate::sound::SoundPlugin) and the
+//! [`InputMap`] is read through the [`ActionInput`](crate::input_map::ActionInput).
+//! The [`CameraConfig`] is persisted to [`CAMERA_SETTINGS_PATH`] and applied by the
+//! [`CamPlugin`].
+//!
+//! The settings are stored as simple `key = value` lines, unknown keys and invalid values are
+//! ignored and fall back to the defaults.
+//!
+//! The [`GameConfig`] at [`GAME_CONFIG_PATH`] is for the developers instead. It's loaded before
+//! the app is built and only read by the game. The [`LaunchArgs`] on the command line override the
+//! window setup of both.
+
+use std::path::PathBuf;
+
+use bevy::{
+    prelude::*,
+    window::{MonitorSelection, PresentMode, PrimaryWindow, WindowMode, WindowResolution},
+};
+
+use crate::input_map::InputMap;
+use crate::locale::Language;
+use crate::prelude::*;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        // might've already been loaded to set up the window
+        app.init_resource::<GameConfig>()
+            .init_resource::<UserSettings>()
+            .insert_resource(CameraConfig::load())
+            .add_systems(
+                Update,
+                (
+                    apply_video_settings,
+                    save_user_settings.run_if(
+                        not(resource_added::<UserSettings>).and(not(in_state(SettingsOpen))),
+                    ),
+                )
+                    .run_if(resource_changed::<UserSettings>),
+            )
+            .add_systems(OnExit(SettingsOpen), save_user_settings)
+            .add_systems(
+                Last,
+                save_user_settings.run_if(on_event::<AppExit>.and(in_state(SettingsOpen))),
+            )
+            .add_systems(
+                Update,
+                save_camera_config.run_if(
+                    resource_changed::<CameraConfig>.and(not(resource_added::<CameraConfig>)),
+                ),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowModeSetting {
+    Windowed,
+    #[default]
+    Borderless,
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    pub const ALL: [WindowModeSetting; 3] = [
+        WindowModeSetting::Windowed,
+        WindowModeSetting::Borderless,
+        WindowModeSetting::Fullscreen,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowModeSetting::Windowed => "Windowed",
+            WindowModeSetting::Borderless => "Borderless",
+            WindowModeSetting::Fullscreen => "Fullscreen",
+        }
+    }
+
+    /// Key of the localized name.
+    pub fn locale_key(&self) -> &'static str {
+        match self {
+            WindowModeSetting::Windowed => "settings.window_windowed",
+            WindowModeSetting::Borderless => "settings.window_borderless",
+            WindowModeSetting::Fullscreen => "settings.window_fullscreen",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let idx = Self::ALL.iter().position(|mode| mode == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+/// How the camera follows the players.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CameraFollowMode {
+    /// Always eases towards the players.
+    #[default]
+    Smooth,
+    /// Only moves once the players leave a rectangle in the middle of the screen.
+    DeadZone,
+}
+
+impl CameraFollowMode {
+    pub const ALL: [CameraFollowMode; 2] = [CameraFollowMode::Smooth, CameraFollowMode::DeadZone];
+
+    pub fn cfg_key(&self) -> &'static str {
+        match self {
+            CameraFollowMode::Smooth => "smooth",
+            CameraFollowMode::DeadZone => "dead_zone",
+        }
+    }
+
+    /// Key of the localized name.
+    pub fn locale_key(&self) -> &'static str {
+        match self {
+            CameraFollowMode::Smooth => "settings.camera_smooth",
+            CameraFollowMode::DeadZone => "settings.camera_dead_zone",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let idx = Self::ALL.iter().position(|mode| mode == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoSettings {
+    pub window_mode: WindowModeSetting,
+    /// Index of the monitor the game is displayed on.
+    pub monitor: usize,
+    pub vsync: bool,
+    pub ui_scale: f32,
+    /// Language of the UI text.
+    pub language: Language,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        VideoSettings {
+            window_mode: WindowModeSetting::default(),
+            monitor: 0,
+            vsync: false,
+            ui_scale: 1.,
+            language: Language::default(),
+        }
+    }
+}
+
+impl VideoSettings {
+    pub fn to_cfg(&self) -> String {
+        format!(
+            "window_mode = {}\nmonitor = {}\nvsync = {}\nui_scale = {}\nlanguage = {}\n",
+            self.window_mode.name(),
+            self.monitor,
+            self.vsync,
+            self.ui_scale,
+            self.language.code()
+        )
+    }
+
+    pub fn from_cfg(cfg: &str) -> Self {
+        VideoSettings::default().with_cfg(cfg)
+    }
+
+    /// Overrides the settings that are in the `cfg`.
+    pub fn with_cfg(self, cfg: &str) -> Self {
+        let mut settings = self;
+        for (key, val) in cfg_entries(cfg) {
+            match key {
+                "window_mode" => {
+                    if let Some(&mode) = WindowModeSetting::ALL.iter().find(|m| m.name() == val) {
+                        settings.window_mode = mode;
+                    }
+                }
+                "monitor" => settings.monitor = val.parse().unwrap_or(settings.monitor),
+                "vsync" => settings.vsync = val.parse().unwrap_or(settings.vsync),
+                "ui_scale" => settings.set_ui_scale(val.parse().unwrap_or(settings.ui_scale)),
+                "language" => {
+                    settings.language = Language::from_code(val).unwrap_or(settings.language)
+                }
+                _ => warn!("Unknown setting: {key}"),
+            }
+        }
+        settings
+    }
+
+    /// Sets the UI scale, rounded to a multiple of [`UI_SCALE_STEP`] between [`UI_SCALE_MIN`] and
+    /// [`UI_SCALE_MAX`].
+    pub fn set_ui_scale(&mut self, scale: f32) {
+        self.ui_scale = ui_scale_step(scale);
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        let monitor = MonitorSelection::Index(self.monitor);
+        match self.window_mode {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => WindowMode::BorderlessFullscreen(monitor),
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen(monitor),
+        }
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+
+    /// A window with the settings applied.
+    pub fn window(&self) -> Window {
+        Window {
+            mode: self.window_mode(),
+            present_mode: self.present_mode(),
+            position: WindowPosition::Centered(MonitorSelection::Index(self.monitor)),
+            ..default()
+        }
+    }
+}
+
+/// The configuration of the game itself, loaded from [`GAME_CONFIG_PATH`] at startup.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct GameConfig {
+    /// The window mode, monitor and vsync used until the player picks their own.
+    pub video: VideoSettings,
+    /// The [`WorldConfig`](crate::world::config::WorldConfig) asset, which sets the size of the
+    /// world among other things.
+    pub world_config: String,
+    /// Shows the FPS and the frame time graph during the run.
+    pub debug_overlay: bool,
+    /// Lets the [`DebugCam`](crate::camera::DebugCam) be toggled with [`DEBUG_CAM_KEY`].
+    pub debug_cam: bool,
+    /// Reloads the changed assets while the game runs, needs the `hot_reload` feature.
+    pub hot_reload: bool,
+    /// Seeds every run with the same [`WorldSeed`](crate::world::WorldSeed) to replay it,
+    /// otherwise every run gets a random one.
+    pub seed: Option<u64>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            video: VideoSettings::default(),
+            world_config: WORLD_CONFIG_PATH.to_string(),
+            debug_overlay: true,
+            debug_cam: true,
+            hot_reload: true,
+            seed: None,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Loads the config from [`GAME_CONFIG_PATH`], uses the defaults if there isn't one.
+    pub fn load() -> Self {
+        std::fs::read_to_string(GAME_CONFIG_PATH)
+            .map(|cfg| Self::from_cfg(&cfg))
+            .unwrap_or_default()
+    }
+
+    pub fn from_cfg(cfg: &str) -> Self {
+        let mut config = GameConfig::default();
+        let mut video_cfg = String::new();
+        for (key, val) in cfg_entries(cfg) {
+            match key {
+                "window_mode" | "monitor" | "vsync" => video_cfg += &format!("{key} = {val}\n"),
+                "world_config" => config.world_config = val.to_string(),
+                "debug_overlay" => {
+                    config.debug_overlay = val.parse().unwrap_or(config.debug_overlay)
+                }
+                "debug_cam" => config.debug_cam = val.parse().unwrap_or(config.debug_cam),
+                "hot_reload" => config.hot_reload = val.parse().unwrap_or(config.hot_reload),
+                "seed" => match val.parse() {
+                    Ok(seed) => config.seed = Some(seed),
+                    Err(_) => warn!("Invalid seed: {val}"),
+                },
+                _ => warn!("Unknown game config key: {key}"),
+            }
+        }
+        config.video = config.video.with_cfg(&video_cfg);
+        config
+    }
+}
+
+/// The window setup passed on the command line with `--windowed`, `--monitor N` and
+/// `--resolution WxH`. It overrides both the [`GameConfig`] and the saved [`VideoSettings`],
+/// so the game can be tried on another monitor without touching any files. `--seed N` replays
+/// the runs of the seed instead of the [`GameConfig::seed`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LaunchArgs {
+    pub windowed: bool,
+    pub monitor: Option<usize>,
+    /// Size of the window in logical pixels, only a window can have one, so it implies
+    /// `--windowed`.
+    pub resolution: Option<UVec2>,
+    pub seed: Option<u64>,
+}
+
+impl LaunchArgs {
+    /// The arguments the game was started with.
+    pub fn from_env() -> Self {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    /// Invalid arguments are reported and skipped, the logger isn't running yet.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut launch = LaunchArgs::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--windowed" => launch.windowed = true,
+                "--monitor" => match args.next().and_then(|val| val.parse().ok()) {
+                    Some(monitor) => launch.monitor = Some(monitor),
+                    None => eprintln!("--monitor expects the index of a monitor, e.g. --monitor 1"),
+                },
+                "--resolution" => match args.next().as_deref().and_then(parse_resolution) {
+                    Some(resolution) => {
+                        launch.windowed = true;
+                        launch.resolution = Some(resolution);
+                    }
+                    None => eprintln!("--resolution expects WxH, e.g. --resolution 1280x720"),
+                },
+                "--seed" => match args.next().and_then(|val| val.parse().ok()) {
+                    Some(seed) => launch.seed = Some(seed),
+                    None => eprintln!("--seed expects a number, e.g. --seed 42"),
+                },
+                _ => eprintln!("Unknown argument: {arg}"),
+            }
+        }
+        launch
+    }
+
+    pub fn apply(&self, video: VideoSettings) -> VideoSettings {
+        VideoSettings {
+            window_mode: if self.windowed {
+                WindowModeSetting::Windowed
+            } else {
+                video.window_mode
+            },
+            monitor: self.monitor.unwrap_or(video.monitor),
+            ..video
+        }
+    }
+
+    /// The `window` with the resolution, if there is one.
+    pub fn window(&self, window: Window) -> Window {
+        match self.resolution {
+            Some(resolution) => Window {
+                resolution: WindowResolution::new(resolution.x as f32, resolution.y as f32),
+                ..window
+            },
+            None => window,
+        }
+    }
+}
+
+fn parse_resolution(val: &str) -> Option<UVec2> {
+    let (width, height) = val.split_once('x')?;
+    let resolution = UVec2::new(width.parse().ok()?, height.parse().ok()?);
+    (resolution.min_element() > 0).then_some(resolution)
+}
+
+/// Volumes of the [`AudioChannel`]s, from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master: 0.8,
+            music: 0.6,
+            sfx: 0.8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioChannel {
+    Master,
+    Music,
+    Sfx,
+}
+
+impl AudioChannel {
+    pub const ALL: [AudioChannel; 3] =
+        [AudioChannel::Master, AudioChannel::Music, AudioChannel::Sfx];
+
+    /// Key of the localized name.
+    pub fn locale_key(&self) -> &'static str {
+        match self {
+            AudioChannel::Master => "settings.channel_master",
+            AudioChannel::Music => "settings.channel_music",
+            AudioChannel::Sfx => "settings.channel_sfx",
+        }
+    }
+
+    fn cfg_key(&self) -> &'static str {
+        match self {
+            AudioChannel::Master => "master",
+            AudioChannel::Music => "music",
+            AudioChannel::Sfx => "sfx",
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn volume(&self, channel: AudioChannel) -> f32 {
+        match channel {
+            AudioChannel::Master => self.master,
+            AudioChannel::Music => self.music,
+            AudioChannel::Sfx => self.sfx,
+        }
+    }
+
+    pub fn set_volume(&mut self, channel: AudioChannel, volume: f32) {
+        let volume = volume.clamp(0., 1.);
+        match channel {
+            AudioChannel::Master => self.master = volume,
+            AudioChannel::Music => self.music = volume,
+            AudioChannel::Sfx => self.sfx = volume,
+        }
+    }
+
+    pub fn to_cfg(&self) -> String {
+        AudioChannel::ALL
+            .iter()
+            .map(|channel| format!("{} = {}\n", channel.cfg_key(), self.volume(*channel)))
+            .collect()
+    }
+
+    pub fn from_cfg(cfg: &str) -> Self {
+        let mut settings = AudioSettings::default();
+        for (key, val) in cfg_entries(cfg) {
+            let Some(&channel) = AudioChannel::ALL.iter().find(|ch| ch.cfg_key() == key) else {
+                warn!("Unknown audio setting: {key}");
+                continue;
+            };
+            if let Ok(volume) = val.parse() {
+                settings.set_volume(channel, volume);
+            }
+        }
+        settings
+    }
+}
+
+/// Everything the player can change in the settings and controls menus, loaded at startup.
+///
+/// Each part is stored under its own prefix, e.g. `video.vsync = true` or `audio.music = 0.5`.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct UserSettings {
+    /// The window and the UI.
+    pub video: VideoSettings,
+    pub audio: AudioSettings,
+    /// The keybinds.
+    pub controls: InputMap,
+}
+
+impl FromWorld for UserSettings {
+    fn from_world(world: &mut World) -> Self {
+        let defaults = world
+            .get_resource::<GameConfig>()
+            .map_or_else(VideoSettings::default, |config| config.video);
+        UserSettings::load(defaults)
+    }
+}
+
+impl UserSettings {
+    const VIDEO_PREFIX: &str = "video.";
+    const AUDIO_PREFIX: &str = "audio.";
+    const CONTROLS_PREFIX: &str = "controls.";
+
+    /// Loads the settings from [`USER_SETTINGS_PATH`], the missing video settings are taken from
+    /// the `defaults`.
+    pub fn load(defaults: VideoSettings) -> Self {
+        std::fs::read_to_string(config_path(USER_SETTINGS_PATH))
+            .map(|cfg| Self::from_cfg(defaults, &cfg))
+            .unwrap_or_else(|_| UserSettings {
+                video: defaults,
+                audio: AudioSettings::default(),
+                controls: InputMap::default(),
+            })
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = config_path(USER_SETTINGS_PATH);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_cfg())
+    }
+
+    pub fn to_cfg(&self) -> String {
+        [
+            (Self::VIDEO_PREFIX, self.video.to_cfg()),
+            (Self::AUDIO_PREFIX, self.audio.to_cfg()),
+            (Self::CONTROLS_PREFIX, self.controls.to_cfg()),
+        ]
+        .iter()
+        .flat_map(|(prefix, cfg)| cfg.lines().map(move |line| format!("{prefix}{line}\n")))
+        .collect()
+    }
+
+    pub fn from_cfg(defaults: VideoSettings, cfg: &str) -> Self {
+        // hands every part only its own lines, without the prefix
+        let section = |prefix: &str| {
+            cfg.lines()
+                .filter_map(|line| line.trim().strip_prefix(prefix))
+                .map(|line| format!("{line}\n"))
+                .collect::<String>()
+        };
+        UserSettings {
+            video: defaults.with_cfg(&section(Self::VIDEO_PREFIX)),
+            audio: AudioSettings::from_cfg(&section(Self::AUDIO_PREFIX)),
+            controls: InputMap::from_cfg(&section(Self::CONTROLS_PREFIX)),
+        }
+    }
+}
+
+/// How the camera follows, zooms and shakes, applied by the [`CamPlugin`].
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct CameraConfig {
+    pub follow_mode: CameraFollowMode,
+    /// How quickly the camera eases towards the players, higher is faster.
+    pub follow_speed: f32,
+    /// The smallest scale of the camera, it can't zoom in any further.
+    pub zoom_min: f32,
+    /// The largest scale of the camera, it can't zoom out any further.
+    pub zoom_max: f32,
+    /// The camera doesn't move while the players are inside of this rectangle in the middle of
+    /// the screen, in the [`CameraFollowMode::DeadZone`].
+    pub dead_zone_size: Vec2,
+    /// How far towards the cursor the camera looks ahead, from 0 to [`CAM_LOOK_AHEAD_MAX_WEIGHT`].
+    pub look_ahead_weight: f32,
+    /// The furthest the camera looks ahead in pixels.
+    pub look_ahead_max: f32,
+    /// Multiplies the strength of the screen shakes, 0 turns them off.
+    pub shake_scale: f32,
+    /// Render the world at a fixed low resolution and scale it up by a whole factor, so the
+    /// sprites stay sharp. Fixes the zoom.
+    pub pixel_perfect: bool,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        CameraConfig {
+            follow_mode: CameraFollowMode::default(),
+            follow_speed: CAM_FOLLOW_SPEED,
+            zoom_min: CAM_ZOOM_MIN,
+            zoom_max: CAM_ZOOM_MAX,
+            dead_zone_size: CAM_DEAD_ZONE_SIZE,
+            look_ahead_weight: CAM_LOOK_AHEAD_WEIGHT,
+            look_ahead_max: CAM_LOOK_AHEAD_MAX,
+            shake_scale: 1.,
+            pixel_perfect: false,
+        }
+    }
+}
+
+impl CameraConfig {
+    /// Loads the config from [`CAMERA_SETTINGS_PATH`], uses the defaults if there isn't any.
+    pub fn load() -> Self {
+        std::fs::read_to_string(CAMERA_SETTINGS_PATH)
+            .map(|cfg| Self::from_cfg(&cfg))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::write(CAMERA_SETTINGS_PATH, self.to_cfg())
+    }
+
+    pub fn to_cfg(&self) -> String {
+        format!(
+            "follow_mode = {}\nfollow_speed = {}\nzoom_min = {}\nzoom_max = {}\n\
+             dead_zone_width = {}\ndead_zone_height = {}\nlook_ahead_weight = {}\n\
+             look_ahead_max = {}\nshake_scale = {}\npixel_perfect = {}\n",
+            self.follow_mode.cfg_key(),
+            self.follow_speed,
+            self.zoom_min,
+            self.zoom_max,
+            self.dead_zone_size.x,
+            self.dead_zone_size.y,
+            self.look_ahead_weight,
+            self.look_ahead_max,
+            self.shake_scale,
+            self.pixel_perfect
+        )
+    }
+
+    pub fn from_cfg(cfg: &str) -> Self {
+        let mut config = CameraConfig::default();
+        for (key, val) in cfg_entries(cfg) {
+            let Ok(num) = val.parse::<f32>() else {
+                match key {
+                    "follow_mode" => {
+                        if let Some(&mode) =
+                            CameraFollowMode::ALL.iter().find(|m| m.cfg_key() == val)
+                        {
+                            config.follow_mode = mode;
+                        }
+                    }
+                    "pixel_perfect" => {
+                        config.pixel_perfect = val.parse().unwrap_or(config.pixel_perfect)
+                    }
+                    _ => warn!("Unknown or invalid camera setting: {key}"),
+                }
+                continue;
+            };
+            // none of them make sense negative
+            let num = num.max(0.);
+            match key {
+                "follow_speed" => config.follow_speed = num,
+                "zoom_min" => config.zoom_min = num,
+                "zoom_max" => config.zoom_max = num,
+                "dead_zone_width" => config.dead_zone_size.x = num,
+                "dead_zone_height" => config.dead_zone_size.y = num,
+                "look_ahead_weight" => {
+                    config.look_ahead_weight = num.min(CAM_LOOK_AHEAD_MAX_WEIGHT)
+                }
+                "look_ahead_max" => config.look_ahead_max = num,
+                "shake_scale" => config.shake_scale = num,
+                _ => warn!("Unknown camera setting: {key}"),
+            }
+        }
+        // clamp panics unless 0 < min <= max
+        if !(config.zoom_min > 0. && config.zoom_min <= config.zoom_max) {
+            config.zoom_min = CAM_ZOOM_MIN;
+            config.zoom_max = CAM_ZOOM_MAX;
+        }
+        config
+    }
+}
+
+/// The step after the `current` one, wraps around to the first.
+pub fn next_step(steps: &[f32], current: f32) -> f32 {
+    steps
+        .iter()
+        .copied()
+        .find(|&step| step > current + f32::EPSILON)
+        .unwrap_or(steps[0])
+}
+
+/// Rounds the scale to the closest valid UI scale.
+pub fn ui_scale_step(scale: f32) -> f32 {
+    // divide by a whole number, so the common scales like 1.25 come out exact
+    let steps_per_unit = (1. / UI_SCALE_STEP).round();
+    (scale.clamp(UI_SCALE_MIN, UI_SCALE_MAX) * steps_per_unit).round() / steps_per_unit
+}
+
+/// Where the `file` is kept in the platform's data directory, it stays in the working directory
+/// when that's not known.
+pub fn data_path(file: &str) -> PathBuf {
+    let data_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+    };
+    data_dir.map_or_else(
+        || PathBuf::from(file),
+        |dir| dir.join(DATA_DIR_NAME).join(file),
+    )
+}
+
+/// Where the `file` is kept in the platform's config directory, it stays in the working
+/// directory when that's not known.
+pub fn config_path(file: &str) -> PathBuf {
+    let config_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Preferences"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    config_dir.map_or_else(
+        || PathBuf::from(file),
+        |dir| dir.join(DATA_DIR_NAME).join(file),
+    )
+}
+
+/// The trimmed `key = value` pairs of a settings file.
+pub(crate) fn cfg_entries(cfg: &str) -> impl Iterator<Item = (&str, &str)> {
+    cfg.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, val)| (key.trim(), val.trim()))
+}
+
+/// Only touches what differs, so changing the other settings leaves the window alone.
+fn apply_video_settings(
+    settings: Res<UserSettings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let video = &settings.video;
+    if ui_scale.0 != video.ui_scale {
+        ui_scale.0 = video.ui_scale;
+    }
+
+    let Ok(mut window) = window_query.get_single_mut() else {
+        return;
+    };
+    if window.mode != video.window_mode() {
+        window.mode = video.window_mode();
+    }
+    if window.present_mode != video.present_mode() {
+        window.present_mode = video.present_mode();
+    }
+    let position = WindowPosition::Centered(MonitorSelection::Index(video.monitor));
+    if video.window_mode == WindowModeSetting::Windowed && window.position != position {
+        window.position = position;
+    }
+}
+
+fn save_user_settings(settings: Res<UserSettings>) {
+    if let Err(e) = settings.save() {
+        warn!("Couldn't save the settings to {USER_SETTINGS_PATH}: {e}");
+    }
+}
+
+fn save_camera_config(config: Res<CameraConfig>) {
+    if let Err(e) = config.save() {
+        warn!("Couldn't save the camera settings to {CAMERA_SETTINGS_PATH}: {e}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::input_map::{Action, Binding};
+
+    #[test]
+    fn video_settings_cfg_roundtrip() {
+        let settings = VideoSettings {
+            window_mode: WindowModeSetting::Fullscreen,
+            monitor: 1,
+            vsync: true,
+            ui_scale: 1.25,
+            language: Language::German,
+        };
+        assert_eq!(VideoSettings::from_cfg(&settings.to_cfg()), settings);
+
+        // invalid values fall back to defaults
+        let settings = VideoSettings::from_cfg("monitor = two\nvsync = true\n");
+        assert_eq!(settings.monitor, 0);
+        assert!(settings.vsync);
+
+        // the UI scale is kept in range
+        let settings = VideoSettings::from_cfg("ui_scale = 10\n");
+        assert_eq!(settings.ui_scale, UI_SCALE_MAX);
+        assert_eq!(ui_scale_step(1.27), 1.25);
+    }
+
+    #[test]
+    fn game_config_sets_the_default_video_settings() {
+        let config = GameConfig::from_cfg(
+            "window_mode = Windowed\nmonitor = 1\ndebug_overlay = false\nui_scale = 2\nseed = 42\n",
+        );
+        assert!(!config.debug_overlay);
+        assert_eq!(config.seed, Some(42));
+        assert!(config.debug_cam);
+        // the UI scale is up to the player
+        assert_eq!(config.video.ui_scale, 1.);
+
+        let settings = config.video.with_cfg("monitor = 2\n");
+        assert_eq!(settings.window_mode, WindowModeSetting::Windowed);
+        assert_eq!(settings.monitor, 2);
+    }
+
+    #[test]
+    fn user_settings_cfg_roundtrip() {
+        let mut settings = UserSettings::from_cfg(VideoSettings::default(), "");
+        settings.video.vsync = true;
+        settings.audio.set_volume(AudioChannel::Music, 0.25);
+        settings
+            .controls
+            .rebind(Action::Reload, Binding::Key(KeyCode::KeyF));
+        assert_eq!(
+            UserSettings::from_cfg(VideoSettings::default(), &settings.to_cfg()),
+            settings
+        );
+
+        // each part only reads its own keys, the rest of the video settings are the defaults
+        let defaults = VideoSettings {
+            monitor: 1,
+            ..default()
+        };
+        let settings = UserSettings::from_cfg(defaults, "video.ui_scale = 2\nmaster = 0\n");
+        assert_eq!(settings.video.monitor, 1);
+        assert_eq!(settings.video.ui_scale, 2.);
+        assert_eq!(settings.audio, AudioSettings::default());
+    }
+
+    #[test]
+    fn launch_args_override_the_video_settings() {
+        let args = |args: &[&str]| LaunchArgs::parse(args.iter().map(|arg| arg.to_string()));
+
+        let launch = args(&[
+            "--monitor",
+            "2",
+            "--resolution",
+            "1280x720",
+            "--vsync",
+            "--seed",
+            "7",
+        ]);
+        assert_eq!(launch.monitor, Some(2));
+        assert_eq!(launch.seed, Some(7));
+        assert_eq!(launch.resolution, Some(UVec2::new(1280, 720)));
+        let video = launch.apply(VideoSettings::default());
+        assert_eq!(video.window_mode, WindowModeSetting::Windowed);
+        assert_eq!(video.monitor, 2);
+
+        // invalid values are skipped, the rest is kept
+        let launch = args(&["--resolution", "0x720", "--seed", "-1", "--monitor"]);
+        assert_eq!(launch, LaunchArgs::default());
+        let video = VideoSettings {
+            monitor: 1,
+            ..default()
+        };
+        assert_eq!(launch.apply(video), video);
+    }
+
+    #[test]
+    fn camera_config_cfg_roundtrip() {
+        let config = CameraConfig {
+            follow_mode: CameraFollowMode::DeadZone,
+            follow_speed: 3.,
+            zoom_min: 0.1,
+            zoom_max: 1.,
+            dead_zone_size: Vec2::new(100., 60.),
+            look_ahead_weight: 0.5,
+            look_ahead_max: 100.,
+            shake_scale: 0.5,
+            pixel_perfect: true,
+        };
+        assert_eq!(CameraConfig::from_cfg(&config.to_cfg()), config);
+
+        // swapped zoom limits fall back to defaults
+        let config = CameraConfig::from_cfg("zoom_min = 0.8\nzoom_max = 0.3\nshake_scale = -1\n");
+        assert_eq!(config.zoom_min, CAM_ZOOM_MIN);
+        assert_eq!(config.zoom_max, CAM_ZOOM_MAX);
+        assert_eq!(config.shake_scale, 0.);
+
+        assert_eq!(next_step(&[0., 0.5, 1.], 0.5), 1.);
+        assert_eq!(next_step(&[0., 0.5, 1.], 1.), 0.);
+    }
+
+    #[test]
+    fn audio_settings_clamp_volume() {
+        let settings = AudioSettings::from_cfg("master = 1.5\nmusic = -1\nsfx = 0.25\n");
+        assert_eq!(settings.master, 1.);
+        assert_eq!(settings.music, 0.);
+        assert_eq!(settings.sfx, 0.25);
+        assert_eq!(AudioSettings::from_cfg(&settings.to_cfg()), settings);
+    }
+}
@@ -0,0 +1,93 @@
+//! Contains the [`SoundPlugin`] that keeps the volume of everything that is playing in sync with
+//! the [`AudioSettings`] of the [`UserSettings`].
+//!
+//! Sounds should be spawned with either a [`Music`] or a [`Sfx`] marker, so the volume of their
+//! channel applies to them. Unmarked sounds only follow the master volume.
+
+use std::time::Duration;
+
+use bevy::{audio::Pitch, prelude::*};
+
+use crate::prelude::*;
+use crate::settings::{AudioSettings, UserSettings};
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                set_new_sink_volume,
+                (apply_volume, preview_volume).run_if(resource_changed::<UserSettings>),
+            ),
+        );
+    }
+}
+
+/// Marks the music, its volume is controlled by the music channel of the [`AudioSettings`].
+#[derive(Component, Debug, Default)]
+pub struct Music;
+
+/// Marks the sound effects, their volume is controlled by the SFX channel of the [`AudioSettings`].
+#[derive(Component, Debug, Default)]
+pub struct Sfx;
+
+fn sink_volume(settings: &AudioSettings, is_music: bool, is_sfx: bool) -> f32 {
+    let channel = if is_music {
+        settings.music
+    } else if is_sfx {
+        settings.sfx
+    } else {
+        1.
+    };
+    settings.master * channel
+}
+
+fn set_new_sink_volume(
+    sink_query: Query<(&AudioSink, Has<Music>, Has<Sfx>), Added<AudioSink>>,
+    settings: Res<UserSettings>,
+) {
+    for (sink, is_music, is_sfx) in sink_query.iter() {
+        sink.set_volume(sink_volume(&settings.audio, is_music, is_sfx));
+    }
+}
+
+/// Changes the volume of everything that's already playing.
+fn apply_volume(
+    sink_query: Query<(&AudioSink, Has<Music>, Has<Sfx>)>,
+    settings: Res<UserSettings>,
+) {
+    for (sink, is_music, is_sfx) in sink_query.iter() {
+        sink.set_volume(sink_volume(&settings.audio, is_music, is_sfx));
+    }
+}
+
+/// Plays a short beep so the new volume can be heard while it's being changed.
+fn preview_volume(
+    mut commands: Commands,
+    mut pitches: ResMut<Assets<Pitch>>,
+    mut last_preview: Local<Option<Duration>>,
+    mut last_volumes: Local<Option<AudioSettings>>,
+    settings: Res<UserSettings>,
+    time: Res<Time<Real>>,
+) {
+    // not for the loaded volumes or the other settings
+    let previous = last_volumes.replace(settings.audio);
+    if previous.is_none() || previous == Some(settings.audio) {
+        return;
+    }
+
+    let now = time.elapsed();
+    // don't beep every frame while dragging a slider
+    if last_preview.is_some_and(|last| (now - last).as_secs_f32() < VOLUME_PREVIEW_INTERVAL_SECS) {
+        return;
+    }
+    *last_preview = Some(now);
+
+    commands.spawn((
+        AudioPlayer(pitches.add(Pitch::new(440., Duration::from_millis(80)))),
+        PlaybackSettings::DESPAWN,
+        Sfx,
+    ));
+}
@@ -1,12 +1,357 @@
-use bevy::prelude::States;
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*, state::state::StateTransitionSteps};
+
+pub struct StatePlugin;
+
+impl Plugin for StatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<GameState>()
+            .add_computed_state::<InRun>()
+            .add_sub_state::<Encounter>()
+            .add_computed_state::<RunPhase>()
+            .add_sub_state::<PauseScreen>()
+            .add_computed_state::<SettingsOpen>()
+            .add_event::<RunStarted>()
+            .add_event::<RunEnded>()
+            .add_event::<MenuOpened>()
+            .add_systems(
+                StateTransition,
+                (
+                    (
+                        despawn_on_exit::<GameState>,
+                        despawn_on_exit::<PauseScreen>,
+                        despawn_on_exit::<SettingsOpen>,
+                    )
+                        .in_set(StateTransitionSteps::ExitSchedules),
+                    send_transition_events.in_set(StateTransitionSteps::EnterSchedules),
+                ),
+            )
+            .init_schedule(RunTeardown)
+            .add_systems(RunTeardown, despawn_run_entities)
+            // a finished run is left either for a retry or the menu
+            .add_systems(OnExit(GameState::GameOver), teardown_run)
+            // the run is saved as it's paused, it can be continued later
+            .add_systems(
+                OnTransition {
+                    exited: GameState::Paused,
+                    entered: GameState::MainMenu,
+                },
+                teardown_run,
+            );
+    }
+}
 
 /// Represents the current state of the game.
-/// `AssetLoad` —> `Init` —> `Running`
+/// `AssetLoad` —> `MainMenu` —> `CharacterSelect` —> `Init` —> `Running` —> `GameOver`
+/// The `Settings` and `Controls` are reachable from the `MainMenu`, a saved run is continued from
+/// it straight through `Init`. The settings are reachable from `Paused` as well, as its
+/// [`PauseScreen`], so the run isn't left for them.
+/// From `GameOver` the game can go back to the `MainMenu` or retry straight from `Init`.
+/// While running the game can switch to `LevelUp`, which pauses the gameplay until an upgrade is
+/// picked. `Paused` is toggled with [`PAUSE_KEY`](crate::prelude::PAUSE_KEY) and stops the
+/// virtual time as well, from it the run can be left for the `MainMenu`.
+/// The finer phases of a run are told apart by the [`RunPhase`]. The [`RunStarted`], [`RunEnded`]
+/// and [`MenuOpened`] events are sent around the transitions that matter to most modules.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, States)]
 pub enum GameState {
     #[default]
     AssetLoad,
     MainMenu,
+    Settings,
+    Controls,
+    CharacterSelect,
     GameInit,
     GameRun,
+    LevelUp,
+    Paused,
+    GameOver,
+}
+
+impl GameState {
+    /// The states that show a menu over or instead of the world.
+    pub fn is_menu(self) -> bool {
+        matches!(
+            self,
+            GameState::MainMenu
+                | GameState::Settings
+                | GameState::Controls
+                | GameState::CharacterSelect
+                | GameState::LevelUp
+                | GameState::Paused
+                | GameState::GameOver
+        )
+    }
+}
+
+/// Sent as a new or a continued run starts playing.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RunStarted;
+
+/// Sent as the run ends. A quit run has already gone through the [`RunTeardown`] by then, a lost
+/// one only goes through it once the `GameOver` is left.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunEnded {
+    /// All the players died.
+    GameOver,
+    /// The run was left from the pause menu, it's saved and can be continued.
+    Quit,
+}
+
+/// Sent every time the game enters a state with a menu, see [`GameState::is_menu`].
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Deref)]
+pub struct MenuOpened(pub GameState);
+
+/// Turns the state transitions into the events above, so the modules that only care about the
+/// run starting or ending don't have to know every transition that leads there.
+fn send_transition_events(
+    mut transitions: EventReader<StateTransitionEvent<GameState>>,
+    mut run_started_events: EventWriter<RunStarted>,
+    mut run_ended_events: EventWriter<RunEnded>,
+    mut menu_opened_events: EventWriter<MenuOpened>,
+) {
+    for transition in transitions.read() {
+        let (exited, Some(entered)) = (transition.exited, transition.entered) else {
+            continue;
+        };
+        if exited == Some(entered) {
+            continue;
+        }
+        match (exited, entered) {
+            (Some(GameState::GameInit), GameState::GameRun) => {
+                run_started_events.send(RunStarted);
+            }
+            (_, GameState::GameOver) => {
+                run_ended_events.send(RunEnded::GameOver);
+            }
+            (Some(GameState::Paused), GameState::MainMenu) => {
+                run_ended_events.send(RunEnded::Quit);
+            }
+            _ => {}
+        }
+        if entered.is_menu() {
+            menu_opened_events.send(MenuOpened(entered));
+        }
+    }
+}
+
+/// Exists for as long as a run does, the `LevelUp` and `Paused` included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InRun;
+
+impl ComputedStates for InRun {
+    type SourceStates = GameState;
+
+    fn compute(game_state: GameState) -> Option<Self> {
+        matches!(
+            game_state,
+            GameState::GameRun | GameState::LevelUp | GameState::Paused
+        )
+        .then_some(InRun)
+    }
+}
+
+/// What the run is currently facing. It's kept through the `LevelUp` and `Paused`, so an
+/// encounter carries on where it was left off, and starts out as `Regular` in every new run.
+#[derive(SubStates, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[source(InRun = InRun)]
+pub enum Encounter {
+    #[default]
+    Regular,
+    /// The camera shows the newly spawned boss, the players, the enemies and the spawning wait
+    /// for it.
+    BossIntro,
+}
+
+/// The phase of the run, computed from the [`GameState`] and the [`Encounter`].
+/// It doesn't exist outside of a run, so `in_state(RunPhase::Playing)` gates the systems that
+/// should only run while the players are in control.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RunPhase {
+    Playing,
+    LevelUpSelection,
+    Paused,
+    BossIntro,
+}
+
+impl ComputedStates for RunPhase {
+    type SourceStates = (GameState, Option<Encounter>);
+
+    fn compute((game_state, encounter): (GameState, Option<Encounter>)) -> Option<Self> {
+        match (game_state, encounter) {
+            (GameState::GameRun, Some(Encounter::BossIntro)) => Some(RunPhase::BossIntro),
+            (GameState::GameRun, _) => Some(RunPhase::Playing),
+            (GameState::LevelUp, _) => Some(RunPhase::LevelUpSelection),
+            (GameState::Paused, _) => Some(RunPhase::Paused),
+            _ => None,
+        }
+    }
+}
+
+/// The screen shown over the [`GameState::Paused`] run.
+#[derive(SubStates, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[source(GameState = GameState::Paused)]
+pub enum PauseScreen {
+    #[default]
+    Menu,
+    Settings,
+}
+
+/// Exists while the settings screen is open, either as the [`GameState::Settings`] from the main
+/// menu or as the [`PauseScreen::Settings`] in the middle of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SettingsOpen;
+
+impl ComputedStates for SettingsOpen {
+    type SourceStates = (GameState, Option<PauseScreen>);
+
+    fn compute((game_state, pause_screen): (GameState, Option<PauseScreen>)) -> Option<Self> {
+        (game_state == GameState::Settings || pause_screen == Some(PauseScreen::Settings))
+            .then_some(SettingsOpen)
+    }
+}
+
+/// Runs once the run is left for good, which isn't the same as leaving [`GameState::GameRun`]
+/// since the `LevelUp` and `Paused` are left back into the same run.
+///
+/// The modules put the systems that reset their resources here, so the next run starts from the
+/// same state as the first one after the launch.
+#[derive(ScheduleLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RunTeardown;
+
+fn teardown_run(world: &mut World) {
+    world.run_schedule(RunTeardown);
+}
+
+/// Despawns the entity, together with its children, once the game leaves the state.
+/// The screens and menus that only belong to one state are spawned with it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DespawnOnExit<S: States = GameState>(pub S);
+
+fn despawn_on_exit<S: States>(
+    mut commands: Commands,
+    mut transitions: EventReader<StateTransitionEvent<S>>,
+    scoped_query: Query<(Entity, &DespawnOnExit<S>)>,
+) {
+    let Some(transition) = transitions.read().last() else {
+        return;
+    };
+    let Some(exited) = transition
+        .exited
+        .as_ref()
+        .filter(|&exited| Some(exited) != transition.entered.as_ref())
+    else {
+        return;
+    };
+    for (ent, scope) in scoped_query.iter() {
+        if scope.0 == *exited {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
+
+/// Marks entities that only live for the duration of a single run.
+/// They get despawned with [`despawn_run_entities`] in the [`RunTeardown`].
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct RunScoped;
+
+/// Despawns all the entities with a [`RunScoped`] component.
+pub fn despawn_run_entities(mut commands: Commands, run_query: Query<Entity, With<RunScoped>>) {
+    for ent in run_query.iter() {
+        // children might've already been despawned together with their parent
+        if let Some(ent_cmds) = commands.get_entity(ent) {
+            ent_cmds.despawn_recursive();
+        }
+    }
+}
+
+/// Resets the resource to its default value, so a new run doesn't carry over the previous one's state.
+pub fn reset_resource<R: Resource + Default>(mut res: ResMut<R>) {
+    *res = R::default();
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::state::app::StatesPlugin;
+
+    use super::*;
+
+    fn enter(app: &mut App, state: GameState) {
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(state);
+        app.update();
+    }
+
+    fn sent<E: Event + Clone>(app: &App) -> Vec<E> {
+        app.world()
+            .resource::<Events<E>>()
+            .iter_current_update_events()
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn transitions_send_run_and_menu_events() {
+        let mut app = App::new();
+        app.add_plugins((StatesPlugin, StatePlugin));
+        app.update();
+
+        enter(&mut app, GameState::MainMenu);
+        assert_eq!(sent::<MenuOpened>(&app), [MenuOpened(GameState::MainMenu)]);
+
+        enter(&mut app, GameState::GameInit);
+        assert!(sent::<RunStarted>(&app).is_empty());
+        enter(&mut app, GameState::GameRun);
+        assert_eq!(sent::<RunStarted>(&app).len(), 1);
+        assert!(sent::<MenuOpened>(&app).is_empty());
+
+        enter(&mut app, GameState::Paused);
+        assert_eq!(sent::<MenuOpened>(&app), [MenuOpened(GameState::Paused)]);
+        assert!(sent::<RunEnded>(&app).is_empty());
+        // resuming doesn't start the run again
+        enter(&mut app, GameState::GameRun);
+        assert!(sent::<RunStarted>(&app).is_empty());
+        enter(&mut app, GameState::Paused);
+
+        enter(&mut app, GameState::MainMenu);
+        assert_eq!(sent::<RunEnded>(&app), [RunEnded::Quit]);
+        assert_eq!(sent::<MenuOpened>(&app), [MenuOpened(GameState::MainMenu)]);
+
+        enter(&mut app, GameState::GameInit);
+        enter(&mut app, GameState::GameRun);
+        enter(&mut app, GameState::GameOver);
+        assert_eq!(sent::<RunEnded>(&app), [RunEnded::GameOver]);
+        assert_eq!(sent::<MenuOpened>(&app), [MenuOpened(GameState::GameOver)]);
+    }
+
+    #[test]
+    fn settings_from_the_pause_menu_keep_the_run() {
+        let mut app = App::new();
+        app.add_plugins((StatesPlugin, StatePlugin));
+        app.update();
+        let settings_open = |app: &App| app.world().contains_resource::<State<SettingsOpen>>();
+
+        enter(&mut app, GameState::Settings);
+        assert!(settings_open(&app));
+        enter(&mut app, GameState::GameInit);
+        enter(&mut app, GameState::GameRun);
+        enter(&mut app, GameState::Paused);
+        assert!(!settings_open(&app));
+
+        let menu = app.world_mut().spawn(DespawnOnExit(PauseScreen::Menu)).id();
+        app.world_mut()
+            .resource_mut::<NextState<PauseScreen>>()
+            .set(PauseScreen::Settings);
+        app.update();
+        assert!(settings_open(&app));
+        assert!(app.world().get_entity(menu).is_err());
+        assert!(app.world().contains_resource::<State<InRun>>());
+        assert_eq!(
+            **app.world().resource::<State<GameState>>(),
+            GameState::Paused
+        );
+        assert!(sent::<RunEnded>(&app).is_empty());
+
+        enter(&mut app, GameState::GameRun);
+        assert!(!settings_open(&app));
+    }
 }
@@ -0,0 +1,160 @@
+//! Contains the [`TimeScalePlugin`] that speeds up and slows down the gameplay.
+//!
+//! A [`HitStop`] briefly freezes the game on a big hit, a [`SlowMotion`] slows it down for a
+//! while. Both scale the virtual [`Time`], so every system that reads `Res<Time>` during the run
+//! slows down together: the players, the enemies, the guns and the animations. The UI and the
+//! sounds run on `Time<Real>` and keep their pace. The effects themselves last for real time.
+//!
+//! The scale only applies while the run is in [`GameState::GameRun`], the effects are held while
+//! a menu is open and play out once the game resumes. The [`GameState::Paused`] stops the virtual
+//! time altogether, so no timer of the run advances until it's resumed, and so do the assets of
+//! the state while they are still loading.
+
+use bevy::prelude::*;
+
+use crate::prelude::*;
+use crate::resources::AssetLoadProgress;
+
+pub struct TimeScalePlugin;
+
+impl Plugin for TimeScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeScale>()
+            .add_event::<HitStop>()
+            .add_event::<SlowMotion>()
+            .add_systems(RunTeardown, reset_resource::<TimeScale>)
+            .add_systems(OnExit(GameState::GameRun), restore_time_speed)
+            .add_systems(Update, update_time_scale)
+            .add_systems(PostUpdate, hold_virtual_time);
+    }
+}
+
+/// Freezes the gameplay for `secs`, a longer one extends the current hit-stop.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct HitStop {
+    pub secs: f32,
+}
+
+/// Slows the gameplay down to `scale` for `secs`, a new one replaces the current one.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SlowMotion {
+    pub scale: f32,
+    pub secs: f32,
+}
+
+/// The effects that currently scale the gameplay.
+#[derive(Resource, Debug, Default)]
+pub struct TimeScale {
+    hit_stop: Timer,
+    slow_motion: Option<(f32, Timer)>,
+}
+
+impl TimeScale {
+    /// How fast the gameplay runs, a hit-stop wins over a slow motion.
+    pub fn scale(&self) -> f32 {
+        if self.hit_stop.remaining_secs() > 0. {
+            return 0.;
+        }
+        match &self.slow_motion {
+            Some((scale, timer)) if timer.remaining_secs() > 0. => *scale,
+            _ => 1.,
+        }
+    }
+
+    fn hit_stop(&mut self, secs: f32) {
+        if self.hit_stop.remaining_secs() < secs {
+            self.hit_stop = Timer::from_seconds(secs, TimerMode::Once);
+        }
+    }
+
+    fn slow_motion(&mut self, scale: f32, secs: f32) {
+        self.slow_motion = Some((scale.max(0.), Timer::from_seconds(secs, TimerMode::Once)));
+    }
+
+    fn tick(&mut self, delta: std::time::Duration) {
+        self.hit_stop.tick(delta);
+        if let Some((_, timer)) = &mut self.slow_motion {
+            timer.tick(delta);
+        }
+    }
+}
+
+fn update_time_scale(
+    mut hit_stop_events: EventReader<HitStop>,
+    mut slow_motion_events: EventReader<SlowMotion>,
+    mut time_scale: ResMut<TimeScale>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    real_time: Res<Time<Real>>,
+    state: Res<State<GameState>>,
+) {
+    // the events are read in the menus too, a level up sends its slow motion as the menu opens
+    for hit_stop in hit_stop_events.read() {
+        time_scale.hit_stop(hit_stop.secs);
+    }
+    if let Some(slow_motion) = slow_motion_events.read().last() {
+        time_scale.slow_motion(slow_motion.scale, slow_motion.secs);
+    }
+    if *state.get() != GameState::GameRun {
+        return;
+    }
+
+    time_scale.tick(real_time.delta());
+    let scale = time_scale.scale();
+    if virtual_time.relative_speed() != scale {
+        virtual_time.set_relative_speed(scale);
+    }
+}
+
+/// The menus run at the normal speed.
+fn restore_time_speed(mut virtual_time: ResMut<Time<Virtual>>) {
+    virtual_time.set_relative_speed(1.);
+}
+
+/// The only system that pauses the virtual time, the run is held while it's paused or while the
+/// assets of the state load.
+fn hold_virtual_time(
+    mut virtual_time: ResMut<Time<Virtual>>,
+    state: Res<State<GameState>>,
+    progress: Res<AssetLoadProgress>,
+) {
+    let hold = match **state {
+        GameState::Paused => true,
+        // the progress is of the textures until the startup is done
+        GameState::AssetLoad => false,
+        _ => progress.fraction() < 1.,
+    };
+    if hold == virtual_time.is_paused() {
+        return;
+    }
+    if hold {
+        virtual_time.pause();
+    } else {
+        virtual_time.unpause();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn hit_stop_wins_over_slow_motion() {
+        let mut time_scale = TimeScale::default();
+        assert_eq!(time_scale.scale(), 1.);
+
+        time_scale.slow_motion(0.25, 1.);
+        time_scale.hit_stop(0.1);
+        // a shorter hit-stop doesn't cut the current one short
+        time_scale.hit_stop(0.05);
+        assert_eq!(time_scale.scale(), 0.);
+
+        time_scale.tick(Duration::from_secs_f32(0.08));
+        assert_eq!(time_scale.scale(), 0.);
+        time_scale.tick(Duration::from_secs_f32(0.04));
+        assert_eq!(time_scale.scale(), 0.25);
+        time_scale.tick(Duration::from_secs(1));
+        assert_eq!(time_scale.scale(), 1.);
+    }
+}
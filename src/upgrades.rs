@@ -0,0 +1,232 @@
+//! Contains [`UpgradePlugin`] that lets the player pick an upgrade every time they level up.
+//!
+//! On every [`LevelUp`] or [`FreeUpgrade`] the game switches to [`GameState::LevelUp`], which
+//! pauses all the gameplay systems, and rolls a few random [`UpgradeChoices`]. The GUI presents
+//! them and sends an [`UpgradeChosen`] event, which gets applied to the player's [`PlayerStats`].
+
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::ZoomPulse;
+use crate::components::Health;
+use crate::experience::LevelUp;
+use crate::inventory::{ItemAcquired, PassiveItem};
+use crate::locale::Localizer;
+use crate::orbital::OrbitalWeapon;
+use crate::player::{Player, PlayerStats};
+use crate::prelude::*;
+use crate::rng::{GameRng, RngStream};
+use crate::time_scale::SlowMotion;
+
+pub struct UpgradePlugin;
+
+impl Plugin for UpgradePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(UpgradeChoices::default())
+            .insert_resource(PendingLevelUps::default())
+            .add_event::<UpgradeChosen>()
+            .add_event::<FreeUpgrade>()
+            .add_systems(
+                RunTeardown,
+                (
+                    reset_resource::<UpgradeChoices>,
+                    reset_resource::<PendingLevelUps>,
+                ),
+            )
+            .add_systems(Update, queue_level_ups.run_if(in_state(GameState::GameRun)))
+            .add_systems(
+                Update,
+                apply_chosen_upgrade.run_if(in_state(GameState::LevelUp)),
+            );
+    }
+}
+
+/// Everything the player can pick when leveling up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Upgrade {
+    Damage,
+    FireRate,
+    MoveSpeed,
+    MaxHealth,
+    OrbitingBlade,
+    Item(PassiveItem),
+}
+
+impl Upgrade {
+    pub const ALL: [Upgrade; 7] = [
+        Upgrade::Damage,
+        Upgrade::FireRate,
+        Upgrade::MoveSpeed,
+        Upgrade::MaxHealth,
+        Upgrade::OrbitingBlade,
+        Upgrade::Item(PassiveItem::Boots),
+        Upgrade::Item(PassiveItem::ArmorPlate),
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Upgrade::Damage => "Damage",
+            Upgrade::FireRate => "Fire Rate",
+            Upgrade::MoveSpeed => "Move Speed",
+            Upgrade::MaxHealth => "Max Health",
+            Upgrade::OrbitingBlade => "Orbiting Blade",
+            Upgrade::Item(item) => item.name(),
+        }
+    }
+
+    /// Key of the localized name.
+    pub fn locale_key(self) -> &'static str {
+        match self {
+            Upgrade::Damage => "upgrade.damage",
+            Upgrade::FireRate => "upgrade.fire_rate",
+            Upgrade::MoveSpeed => "upgrade.move_speed",
+            Upgrade::MaxHealth => "upgrade.max_health",
+            Upgrade::OrbitingBlade => "upgrade.orbiting_blade",
+            Upgrade::Item(item) => item.locale_key(),
+        }
+    }
+
+    pub fn description(self, localizer: &Localizer) -> String {
+        match self {
+            Upgrade::Damage => localizer.trf(
+                "upgrade.damage_description",
+                &[&(UPGRADE_DAMAGE_BONUS * 100.).round()],
+            ),
+            Upgrade::FireRate => localizer.trf(
+                "upgrade.fire_rate_description",
+                &[&(UPGRADE_FIRE_RATE_BONUS * 100.).round()],
+            ),
+            Upgrade::MoveSpeed => localizer.trf(
+                "upgrade.move_speed_description",
+                &[&(UPGRADE_MOVE_SPEED_BONUS * 100.).round()],
+            ),
+            Upgrade::MaxHealth => localizer.trf(
+                "upgrade.max_health_description",
+                &[&UPGRADE_MAX_HEALTH_BONUS],
+            ),
+            Upgrade::OrbitingBlade => localizer
+                .tr("upgrade.orbiting_blade_description")
+                .to_string(),
+            Upgrade::Item(item) => item.description(localizer),
+        }
+    }
+
+    /// The sprite index in the common spritesheet and its tint.
+    pub fn icon(self) -> (usize, Color) {
+        match self {
+            Upgrade::Damage => (11, Color::srgb(1.0, 0.3, 0.3)),
+            Upgrade::FireRate => (10, Color::srgb(1.0, 0.9, 0.3)),
+            Upgrade::MoveSpeed => (10, Color::srgb(0.3, 1.0, 0.5)),
+            Upgrade::MaxHealth => (11, Color::srgb(1.0, 0.5, 0.7)),
+            Upgrade::OrbitingBlade => (11, Color::srgb(0.6, 0.8, 1.0)),
+            Upgrade::Item(_) => (10, Color::srgb(0.8, 0.6, 0.3)),
+        }
+    }
+}
+
+/// The upgrades currently offered to the player.
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct UpgradeChoices(pub Vec<Upgrade>);
+
+/// Players that leveled up and haven't picked an upgrade yet, once per level up.
+/// The choices on offer are always for the first one.
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct PendingLevelUps(pub Vec<Entity>);
+
+/// Lets the player pick an upgrade without leveling up.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FreeUpgrade {
+    pub entity: Entity,
+}
+
+/// Sent by the GUI once the player picks one of the [`UpgradeChoices`].
+#[derive(Event, Debug, Clone, Copy, Deref)]
+pub struct UpgradeChosen(pub Upgrade);
+
+fn roll_upgrade_choices(game_rng: &mut GameRng) -> Vec<Upgrade> {
+    Upgrade::ALL
+        .choose_multiple(game_rng.stream(RngStream::Upgrades), UPGRADE_CHOICES_NUM)
+        .copied()
+        .collect()
+}
+
+fn queue_level_ups(
+    mut level_up_events: EventReader<LevelUp>,
+    mut free_upgrade_events: EventReader<FreeUpgrade>,
+    mut pending: ResMut<PendingLevelUps>,
+    mut choices: ResMut<UpgradeChoices>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut zoom_events: EventWriter<ZoomPulse>,
+    mut slow_motion_events: EventWriter<SlowMotion>,
+    mut game_rng: ResMut<GameRng>,
+    player_query: Query<(), With<Player>>,
+) {
+    pending.extend(
+        level_up_events
+            .read()
+            .map(|level_up| level_up.entity)
+            .chain(free_upgrade_events.read().map(|free| free.entity))
+            .filter(|&entity| player_query.contains(entity)),
+    );
+
+    if !pending.is_empty() {
+        **choices = roll_upgrade_choices(&mut game_rng);
+        next_state.set(GameState::LevelUp);
+        zoom_events.send(ZoomPulse {
+            scale: LEVEL_UP_ZOOM_SCALE,
+            secs: LEVEL_UP_ZOOM_SECS,
+        });
+        slow_motion_events.send(SlowMotion {
+            scale: LEVEL_UP_SLOW_MO_SCALE,
+            secs: LEVEL_UP_SLOW_MO_SECS,
+        });
+    }
+}
+
+fn apply_chosen_upgrade(
+    mut chosen_events: EventReader<UpgradeChosen>,
+    mut pending: ResMut<PendingLevelUps>,
+    mut choices: ResMut<UpgradeChoices>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut item_events: EventWriter<ItemAcquired>,
+    mut game_rng: ResMut<GameRng>,
+    mut player_query: Query<(&mut PlayerStats, &mut Health, &mut OrbitalWeapon), With<Player>>,
+) {
+    // only a single upgrade can be picked per level up
+    let Some(chosen) = chosen_events.read().last() else {
+        return;
+    };
+
+    let Some(&player_ent) = pending.first() else {
+        return;
+    };
+    pending.remove(0);
+
+    // the player might have died in the meantime
+    if let Ok((mut stats, mut hp, mut orbital)) = player_query.get_mut(player_ent) {
+        match **chosen {
+            Upgrade::Damage => stats.damage_mult += UPGRADE_DAMAGE_BONUS,
+            Upgrade::FireRate => stats.fire_rate_mult += UPGRADE_FIRE_RATE_BONUS,
+            Upgrade::MoveSpeed => stats.move_speed += PLAYER_SPEED * UPGRADE_MOVE_SPEED_BONUS,
+            Upgrade::MaxHealth => {
+                hp.max += UPGRADE_MAX_HEALTH_BONUS;
+                hp.heal(UPGRADE_MAX_HEALTH_BONUS as u16);
+            }
+            Upgrade::OrbitingBlade => orbital.count += 1,
+            Upgrade::Item(item) => {
+                item_events.send(ItemAcquired {
+                    entity: player_ent,
+                    item,
+                });
+            }
+        }
+    }
+
+    if !pending.is_empty() {
+        // stay in the selection and offer new upgrades for the next level
+        **choices = roll_upgrade_choices(&mut game_rng);
+    } else {
+        next_state.set(GameState::GameRun);
+    }
+}
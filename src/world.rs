@@ -1,50 +1,506 @@
 //! Generic world entities.
 //! Handles the initialization of the camera, the map, the decorations, etc.
-use bevy::prelude::*;
-use rand::Rng;
+//!
+//! Every run generates a new world from the [`WorldSeed`], everything random in the run is rolled
+//! from the [`GameRng`] seeded by it, so a run can be reproduced. The [`WorldNoise`] splits it into
+//! [`Biome`]s and clumps the decorations and obstacles inside them, the biomes also decide which
+//! enemies spawn there.
+//!
+//! The decorations and the border walls are [`Cullable`], they're hidden while they're far
+//! outside of the camera view. With [`WorldConfig::batch_decor`] the decorations are instead baked
+//! into an image per chunk, like the ground.
+//!
+//! The size of the world and the amount of decorations are set by the [`WorldConfig`].
+//!
+//! The ground is a tilemap generated for the whole world every run. Every chunk of
+//! [`WORLD_CHUNK_TILES`] x [`WORLD_CHUNK_TILES`] tiles is baked into a single image once the floor
+//! atlas is loaded, so the whole ground is only a handful of sprites.
+use std::time::Duration;
 
+use bevy::utils::HashMap;
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension},
+    },
+    time::common_conditions::on_timer,
+};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::camera::{camera_view_rect, PlayerCam};
+use crate::collision::{ColliderShape, Obstacle};
+use crate::gui::toast::Notify;
+use crate::locale::Localizer;
 use crate::prelude::*;
+use crate::quadtree::quad_collider::Shape;
 use crate::resources::GlobTextAtlases;
+use crate::rng::{GameRng, RngStream};
+use crate::save::ResumeRun;
+use crate::settings::GameConfig;
+
+pub mod biome;
+pub mod config;
+mod decor;
+pub mod noise;
+pub mod terrain;
+pub mod weather;
+
+use biome::Biome;
+use config::{apply_world_config, WorldConfig, WorldConfigHandle, WorldConfigLoader};
+use decor::{bake_decor_chunks, DecorChunk};
+use noise::ValueNoise;
+
+const GROUND_DETAIL_CHANCE: f64 = 0.1;
+/// Size of the clumps of decorations in pixels.
+const DETAIL_SIZE: f32 = 80.;
+/// The decorations are placed by rejection sampling, gives up after this many tries per decoration.
+const DECOR_ATTEMPTS: u32 = 10;
+const OBSTACLE_SCALE: std::ops::Range<f32> = 1.5..2.5;
+const OBSTACLE_CLUSTER_WALLS: std::ops::Range<u32> = 3..8;
+const OBSTACLE_CLUSTER_SPREAD: f32 = 60.;
+/// Walls atlas indices of the plain wall tiles.
+const WALL_TILES: [usize; 3] = [2, 4, 7];
 
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::GameInit), spawn_world_decor);
+        app.init_asset::<WorldConfig>()
+            .init_asset_loader::<WorldConfigLoader>()
+            .init_resource::<WorldConfig>()
+            .init_resource::<WorldConfigHandle>()
+            .init_resource::<WorldSeed>()
+            .init_resource::<GameRng>()
+            .add_systems(
+                OnEnter(GameState::GameInit),
+                (
+                    pick_world_seed,
+                    (
+                        announce_world_seed,
+                        spawn_world_decor,
+                        spawn_ground,
+                        spawn_obstacles,
+                        spawn_border_walls,
+                    ),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    apply_world_config,
+                    bake_ground_chunks.run_if(any_with_component::<GroundChunk>),
+                    bake_decor_chunks.run_if(any_with_component::<DecorChunk>),
+                    cull_far_sprites
+                        .run_if(on_timer(Duration::from_secs_f32(CULL_REFRESH_RATE_SECS))),
+                ),
+            );
+    }
+}
+
+/// Seed of the current world, a new one is picked every run.
+#[derive(Resource, Debug, Default, Clone, Copy, Deref)]
+pub struct WorldSeed(pub u64);
+
+/// The noise fields the world is generated from.
+pub struct WorldNoise {
+    biome: ValueNoise,
+    detail: ValueNoise,
+}
+
+impl WorldNoise {
+    pub fn new(seed: WorldSeed) -> Self {
+        WorldNoise {
+            biome: ValueNoise::new(*seed),
+            detail: ValueNoise::new(seed.rotate_left(32) ^ 0xD1B5_4A32_D192_ED03),
+        }
+    }
+
+    pub fn biome(&self, pos: Vec2) -> Biome {
+        match self.biome.fractal(pos / WORLD_BIOME_SIZE, 3) {
+            n if n < 0.42 => Biome::Swamp,
+            n if n < 0.58 => Biome::Meadow,
+            _ => Biome::Ruins,
+        }
+    }
+
+    /// Small clumps inside the biomes, from 0 to 1.
+    pub fn detail(&self, pos: Vec2) -> f32 {
+        self.detail.fractal(pos / DETAIL_SIZE, 2)
     }
 }
 
+/// Hidden while it's further than [`CULL_MARGIN`] outside of the camera view.
+#[derive(Component, Debug, Default)]
+#[require(Visibility)]
+pub struct Cullable;
+
 #[derive(Component)]
-#[require(Transform, Sprite)]
+#[require(Transform, Sprite, RunScoped, Cullable)]
 struct Decor;
 
-fn spawn_world_decor(mut commands: Commands, text_atlases: Res<GlobTextAtlases>) {
-    let mut rng = rand::thread_rng();
+/// A tile of the walls around the world, the collision is handled by the [`Obstacle`]s of the
+/// sides.
+#[derive(Component)]
+#[require(Transform, Sprite, RunScoped, Cullable)]
+struct BorderWall;
 
-    let decor = (0..WORLD_DECOR_NUM)
-        .map(|_| {
-            let layout = text_atlases.foliage.clone().unwrap().layout;
-            let image = text_atlases.foliage.clone().unwrap().image;
-            let index = rng.gen_range(4..6);
-            let random_flip = rng.gen_bool(0.5);
+/// A chunk of the ground that hasn't been baked into an image yet.
+#[derive(Component)]
+#[require(Transform, Visibility, RunScoped)]
+struct GroundChunk {
+    /// Floor atlas index of every tile, row by row from the top.
+    tiles: Vec<usize>,
+}
 
-            let whalf = WORLD_SIZE * 0.5;
-            let x = rng.gen_range(-whalf..whalf);
-            let y = rng.gen_range(-whalf..whalf);
-            let scale = rng.gen_range(0.75..1.5);
-            // lower entities get rendered in front of the entities above to give perception of depth
-            // returns 1..=2, entities lower on the map get a number closer to 2.
-            let z_offset = -(-WORLD_SIZE + y - whalf) / 1000.0;
+/// Reseeds the [`GameRng`], everything generated from it at [`GameState::GameInit`] has to run
+/// after this. The seed is random unless the [`GameConfig::seed`] replays a run, a continued
+/// [`ResumeRun`] keeps its own.
+pub fn pick_world_seed(
+    mut seed: ResMut<WorldSeed>,
+    mut game_rng: ResMut<GameRng>,
+    config: Res<GameConfig>,
+    resume: Res<ResumeRun>,
+) {
+    let resumed_seed = resume.0.as_ref().map(|run| run.seed);
+    *seed = WorldSeed(resumed_seed.or(config.seed).unwrap_or_else(rand::random));
+    *game_rng = GameRng::new(**seed);
+    info!("World seed: {}", **seed);
+}
+
+fn announce_world_seed(
+    mut notify_events: EventWriter<Notify>,
+    seed: Res<WorldSeed>,
+    localizer: Localizer,
+) {
+    notify_events.send(Notify::new(localizer.trf("toast.world_seed", &[&**seed])));
+}
+
+fn spawn_world_decor(
+    mut commands: Commands,
+    mut game_rng: ResMut<GameRng>,
+    config: Res<WorldConfig>,
+    text_atlases: Res<GlobTextAtlases>,
+    seed: Res<WorldSeed>,
+) {
+    let rng = game_rng.stream(RngStream::Decor);
+    let noise = WorldNoise::new(*seed);
+
+    let whalf = config.half_size();
+    let decor = (0..config.decor_num * DECOR_ATTEMPTS)
+        .filter_map(|_| {
+            let pos = Vec2::new(rng.gen_range(-whalf..whalf), rng.gen_range(-whalf..whalf));
+            let biome = noise.biome(pos);
+            if rng.gen::<f32>() >= biome.decor_density() * noise.detail(pos) {
+                return None;
+            }
+
+            let atlas = biome.decor_atlas(&text_atlases);
+            let layout = atlas.layout.clone();
+            let image = atlas.image.clone();
+            let index = *biome.decor_tiles().choose(rng).unwrap();
+            let random_flip = rng.gen_bool(0.5);
+            let scale = rng.gen_range(config.decor_scale.clone());
 
             let mut sprite = Sprite::from_atlas_image(image, TextureAtlas { layout, index });
             sprite.flip_x = random_flip;
-            (
+            sprite.color = biome.decor_tint();
+            Some((
                 sprite,
-                Transform::from_xyz(x, y, 10. + z_offset).with_scale(Vec3::splat(scale)),
+                Transform::from_translation(pos.extend(config.depth_z(pos.y)))
+                    .with_scale(Vec3::splat(scale)),
                 Decor,
+            ))
+        })
+        .take(config.decor_num as usize)
+        .collect::<Vec<_>>();
+
+    if !config.batch_decor {
+        commands.spawn_batch(decor);
+        return;
+    }
+
+    let chunk_size = (text_atlases.floor().tile_size.x as usize * WORLD_CHUNK_TILES) as f32;
+    let mut chunks = HashMap::<IVec2, Vec<(Sprite, Transform)>>::new();
+    for (sprite, transf, _) in decor {
+        let chunk = (transf.translation.truncate() / chunk_size)
+            .floor()
+            .as_ivec2();
+        chunks.entry(chunk).or_default().push((sprite, transf));
+    }
+    for (_, decor) in chunks {
+        commands.spawn((
+            DecorChunk { decor },
+            // the whole chunk is drawn at the depth of its top edge
+            Transform::from_xyz(0., 0., config.decor_z),
+        ));
+    }
+}
+
+/// Spawns clusters of crumbling walls in the biomes that have them, outside of the safe zone
+/// around the player spawn.
+fn spawn_obstacles(
+    mut commands: Commands,
+    mut game_rng: ResMut<GameRng>,
+    config: Res<WorldConfig>,
+    text_atlases: Res<GlobTextAtlases>,
+    seed: Res<WorldSeed>,
+) {
+    let rng = game_rng.stream(RngStream::Obstacles);
+    let noise = WorldNoise::new(*seed);
+    let whalf = config.half_size() - OBSTACLE_CLUSTER_SPREAD;
+
+    let cluster_centers = (0..WORLD_OBSTACLE_CLUSTERS * DECOR_ATTEMPTS)
+        .map(|_| Vec2::new(rng.gen_range(-whalf..whalf), rng.gen_range(-whalf..whalf)))
+        .filter(|&pos| {
+            noise.biome(pos).has_obstacles()
+                && pos.length() > config.safe_zone_radius + OBSTACLE_CLUSTER_SPREAD
+        })
+        .take(WORLD_OBSTACLE_CLUSTERS as usize)
+        .collect::<Vec<_>>();
+
+    let walls = text_atlases.walls();
+    let tile_size = walls.tile_size.as_vec2();
+    for center in cluster_centers {
+        for _ in 0..rng.gen_range(OBSTACLE_CLUSTER_WALLS) {
+            let offset = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU))
+                * rng.gen_range(0.0..OBSTACLE_CLUSTER_SPREAD);
+            let pos = center + offset;
+            let scale = rng.gen_range(OBSTACLE_SCALE);
+
+            let mut sprite = Sprite::from_atlas_image(
+                walls.image.clone(),
+                TextureAtlas {
+                    layout: walls.layout.clone(),
+                    index: *WALL_TILES.choose(rng).unwrap(),
+                },
+            );
+            sprite.color = Biome::Ruins.decor_tint();
+            commands.spawn((
+                sprite,
+                Transform::from_translation(pos.extend(config.depth_z(pos.y)))
+                    .with_scale(Vec3::splat(scale)),
+                Obstacle,
+                ColliderShape(Shape::Quad(Rectangle::from_size(tile_size * scale))),
+            ));
+        }
+    }
+}
+
+/// Walls the world in, so nothing can leave it.
+fn spawn_border_walls(
+    mut commands: Commands,
+    mut game_rng: ResMut<GameRng>,
+    config: Res<WorldConfig>,
+    text_atlases: Res<GlobTextAtlases>,
+) {
+    let rng = game_rng.stream(RngStream::BorderWalls);
+    let walls = text_atlases.walls();
+
+    let tile_size = walls.tile_size.x as f32;
+    let wall_tiles = (WORLD_WALL_THICKNESS / tile_size).ceil() as i32;
+    let side_tiles = (config.size / tile_size).ceil() as i32;
+    let whalf = side_tiles as f32 * tile_size * 0.5;
+
+    let tiles = (-wall_tiles..side_tiles + wall_tiles)
+        .flat_map(|x| (-wall_tiles..side_tiles + wall_tiles).map(move |y| (x, y)))
+        // only the ring around the world
+        .filter(|&(x, y)| !(0..side_tiles).contains(&x) || !(0..side_tiles).contains(&y))
+        .map(|(x, y)| {
+            let pos = (Vec2::new(x as f32, y as f32) + 0.5) * tile_size - whalf;
+            let index = *WALL_TILES.choose(rng).unwrap();
+            (
+                Sprite::from_atlas_image(
+                    walls.image.clone(),
+                    TextureAtlas {
+                        layout: walls.layout.clone(),
+                        index,
+                    },
+                ),
+                Transform::from_translation(pos.extend(config.depth_z(pos.y))),
+                BorderWall,
             )
         })
         .collect::<Vec<_>>();
+    commands.spawn_batch(tiles);
+
+    let thickness = wall_tiles as f32 * tile_size;
+    let length = (whalf + thickness) * 2.;
+    let offset = whalf + thickness * 0.5;
+    for (pos, size) in [
+        (Vec2::new(0., offset), Vec2::new(length, thickness)),
+        (Vec2::new(0., -offset), Vec2::new(length, thickness)),
+        (Vec2::new(offset, 0.), Vec2::new(thickness, length)),
+        (Vec2::new(-offset, 0.), Vec2::new(thickness, length)),
+    ] {
+        commands.spawn((
+            Transform::from_translation(pos.extend(0.)),
+            Obstacle,
+            ColliderShape(Shape::Quad(Rectangle::from_size(size))),
+        ));
+    }
+}
+
+/// Hides the [`Cullable`] sprites far away from the camera and shows them again once it gets
+/// close. The margin keeps them from popping in at the edges between the refreshes.
+fn cull_far_sprites(
+    mut cullable_query: Query<(&mut Visibility, &GlobalTransform), With<Cullable>>,
+    cam_query: Query<(&OrthographicProjection, &GlobalTransform), With<PlayerCam>>,
+) {
+    let views = cam_query
+        .iter()
+        .map(|(projection, cam_transf)| {
+            camera_view_rect(projection, cam_transf).inflate(CULL_MARGIN)
+        })
+        .collect::<Vec<_>>();
+
+    for (mut visibility, transf) in cullable_query.iter_mut() {
+        let pos = transf.translation().truncate();
+        let new_visibility = if views.iter().any(|view| view.contains(pos)) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        // don't trigger change detection for all of them every time
+        visibility.set_if_neq(new_visibility);
+    }
+}
+
+/// Generates the ground tiles for the whole world from the tiles of the biomes.
+fn spawn_ground(
+    mut commands: Commands,
+    mut game_rng: ResMut<GameRng>,
+    config: Res<WorldConfig>,
+    seed: Res<WorldSeed>,
+    text_atlases: Res<GlobTextAtlases>,
+) {
+    let rng = game_rng.stream(RngStream::Ground);
+    let noise = WorldNoise::new(*seed);
 
-    commands.spawn_batch(decor);
+    let tile_size = text_atlases.floor().tile_size.x as f32;
+    let chunk_size = tile_size * WORLD_CHUNK_TILES as f32;
+    let chunks = (config.size / chunk_size).ceil() as usize;
+    let whalf = chunks as f32 * chunk_size * 0.5;
+    let biome_at = |x: usize, y: usize| {
+        let pos = (Vec2::new(x as f32, y as f32) + 0.5) * tile_size - whalf;
+        noise.biome(pos)
+    };
+
+    for chunk_y in 0..chunks {
+        for chunk_x in 0..chunks {
+            let mut tiles = Vec::with_capacity(WORLD_CHUNK_TILES * WORLD_CHUNK_TILES);
+            // the images are stored from the top, the world y goes up
+            for row in (0..WORLD_CHUNK_TILES).rev() {
+                for col in 0..WORLD_CHUNK_TILES {
+                    let x = chunk_x * WORLD_CHUNK_TILES + col;
+                    let y = chunk_y * WORLD_CHUNK_TILES + row;
+                    let biome = biome_at(x, y);
+                    let choices = if rng.gen_bool(GROUND_DETAIL_CHANCE) {
+                        biome.ground_detail_tiles()
+                    } else {
+                        biome.ground_tiles()
+                    };
+                    tiles.push(*choices.choose(rng).unwrap());
+                }
+            }
+
+            let center = Vec2::new(chunk_x as f32 + 0.5, chunk_y as f32 + 0.5) * chunk_size;
+            commands.spawn((
+                GroundChunk { tiles },
+                // below everything else
+                Transform::from_translation((center - whalf).extend(0.)),
+            ));
+        }
+    }
+}
+
+/// Bakes the tiles of every [`GroundChunk`] into a single image once the floor atlas is loaded.
+fn bake_ground_chunks(
+    mut commands: Commands,
+    chunk_query: Query<(Entity, &GroundChunk)>,
+    text_atlases: Res<GlobTextAtlases>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let floor = text_atlases.floor();
+    let Some(atlas) = images.get(&floor.image) else {
+        return;
+    };
+
+    let tile_size = floor.tile_size.x as usize;
+    let chunk_px = (WORLD_CHUNK_TILES * tile_size) as u32;
+    // the fallback texture doesn't have any tiles, it's stretched over the chunk instead
+    if atlas.width() < tile_size as u32 {
+        for (ent, _) in chunk_query.iter() {
+            commands
+                .entity(ent)
+                .insert(Sprite {
+                    image: floor.image.clone(),
+                    custom_size: Some(Vec2::splat(chunk_px as f32)),
+                    ..default()
+                })
+                .remove::<GroundChunk>();
+        }
+        return;
+    }
+    let baked = chunk_query
+        .iter()
+        .map(|(ent, chunk)| {
+            let data = blit_tiles(&atlas.data, atlas.width() as usize, tile_size, &chunk.tiles);
+            let image = Image::new(
+                Extent3d {
+                    width: chunk_px,
+                    height: chunk_px,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                data,
+                atlas.texture_descriptor.format,
+                RenderAssetUsages::RENDER_WORLD,
+            );
+            (ent, image)
+        })
+        .collect::<Vec<_>>();
+
+    for (ent, image) in baked {
+        commands
+            .entity(ent)
+            .insert(Sprite::from_image(images.add(image)))
+            .remove::<GroundChunk>();
+    }
+}
+
+/// Copies the square tiles out of the RGBA atlas into a square image of the tiles, the tiles are
+/// laid out row by row.
+fn blit_tiles(atlas: &[u8], atlas_width: usize, tile_size: usize, tiles: &[usize]) -> Vec<u8> {
+    const BYTES_PER_PX: usize = 4;
+    let atlas_cols = atlas_width / tile_size;
+    let side_tiles = (tiles.len() as f32).sqrt() as usize;
+    let side_px = side_tiles * tile_size;
+    let row_bytes = tile_size * BYTES_PER_PX;
+
+    let mut data = vec![0; side_px * side_px * BYTES_PER_PX];
+    for (idx, &tile) in tiles.iter().enumerate() {
+        let (dst_x, dst_y) = (idx % side_tiles * tile_size, idx / side_tiles * tile_size);
+        let (src_x, src_y) = (tile % atlas_cols * tile_size, tile / atlas_cols * tile_size);
+        for row in 0..tile_size {
+            let src = ((src_y + row) * atlas_width + src_x) * BYTES_PER_PX;
+            let dst = ((dst_y + row) * side_px + dst_x) * BYTES_PER_PX;
+            data[dst..dst + row_bytes].copy_from_slice(&atlas[src..src + row_bytes]);
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn blit_tiles_copies_atlas_tiles() {
+        // 2x1 atlas of 1px tiles
+        let atlas = [1, 1, 1, 1, 2, 2, 2, 2];
+        let data = blit_tiles(&atlas, 2, 1, &[1, 0, 0, 1]);
+        assert_eq!(data, [2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2]);
+    }
 }
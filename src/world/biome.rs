@@ -0,0 +1,89 @@
+//! The [`Biome`]s the world is split into, each with its own tiles, decorations and enemies.
+
+use bevy::prelude::*;
+
+use crate::enemy::EnemyKind;
+use crate::resources::{GlobTextAtlases, TextureAtlasHandle};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Biome {
+    /// Open grassland with some plants and flowers.
+    Meadow,
+    /// Dark ground overgrown with dense foliage.
+    Swamp,
+    /// Dirt with rubble and crumbling walls that block the way.
+    Ruins,
+}
+
+impl Biome {
+    /// Floor atlas indices of the ground tiles.
+    pub fn ground_tiles(self) -> &'static [usize] {
+        match self {
+            Biome::Meadow => &[0, 1, 2, 8],
+            Biome::Swamp => &[35],
+            Biome::Ruins => &[16, 23, 24],
+        }
+    }
+
+    /// Floor atlas indices of the ground tiles with some detail, sprinkled between the plain ones.
+    pub fn ground_detail_tiles(self) -> &'static [usize] {
+        match self {
+            Biome::Meadow => &[7, 9, 10, 11, 12, 13, 14, 15, 17, 20, 21, 22],
+            Biome::Swamp => &[21, 22],
+            Biome::Ruins => &[24],
+        }
+    }
+
+    /// Chance of a decoration being placed, before the detail noise.
+    pub fn decor_density(self) -> f32 {
+        match self {
+            Biome::Meadow => 0.3,
+            Biome::Swamp => 1.,
+            Biome::Ruins => 0.4,
+        }
+    }
+
+    /// The atlas the decorations are picked from.
+    pub fn decor_atlas(self, text_atlases: &GlobTextAtlases) -> TextureAtlasHandle {
+        match self {
+            Biome::Meadow | Biome::Swamp => text_atlases.foliage(),
+            Biome::Ruins => text_atlases.walls(),
+        }
+    }
+
+    /// Indices of the decorations in the [`Biome::decor_atlas`].
+    pub fn decor_tiles(self) -> &'static [usize] {
+        match self {
+            Biome::Meadow => &[0, 1, 4, 5],
+            Biome::Swamp => &[0, 1],
+            // the broken off wall pieces
+            Biome::Ruins => &[1, 3, 8, 11],
+        }
+    }
+
+    pub fn decor_tint(self) -> Color {
+        match self {
+            Biome::Meadow => Color::WHITE,
+            Biome::Swamp => Color::srgb(0.55, 0.75, 0.55),
+            Biome::Ruins => Color::srgb(0.8, 0.8, 0.8),
+        }
+    }
+
+    /// Whether clusters of walls are spawned in the biome.
+    pub fn has_obstacles(self) -> bool {
+        self == Biome::Ruins
+    }
+
+    /// Weights of the enemies that spawn in the biome.
+    pub fn enemy_table(self) -> &'static [(EnemyKind, u32)] {
+        match self {
+            Biome::Meadow => &[(EnemyKind::Grunt, 8), (EnemyKind::Runner, 2)],
+            Biome::Swamp => &[
+                (EnemyKind::Grunt, 3),
+                (EnemyKind::Runner, 5),
+                (EnemyKind::Brute, 2),
+            ],
+            Biome::Ruins => &[(EnemyKind::Grunt, 5), (EnemyKind::Brute, 5)],
+        }
+    }
+}
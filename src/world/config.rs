@@ -0,0 +1,190 @@
+//! The [`WorldConfig`] holds the parameters the world is generated with.
+//!
+//! The configs are `key = value` files in `assets/world/`, the one the next run is generated
+//! with is picked by the [`WorldConfigHandle`]. Unknown keys and invalid values are ignored and
+//! fall back to the defaults. Changes only apply to the runs started after the config is
+//! (re)loaded.
+
+use std::ops::Range;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, LoadContext},
+    prelude::*,
+};
+
+use crate::prelude::*;
+use crate::settings::{cfg_entries, GameConfig};
+
+#[derive(Asset, Resource, TypePath, Debug, Clone, PartialEq)]
+pub struct WorldConfig {
+    /// Width and height of the world, centered on the origin.
+    pub size: f32,
+    pub decor_num: u32,
+    pub decor_scale: Range<f32>,
+    /// Z of the decorations and walls at the top edge of the world.
+    pub decor_z: f32,
+    /// How much closer the ones at the bottom edge are drawn, so they overlap the ones above.
+    pub depth_z_range: f32,
+    /// Neither obstacles nor enemies spawn this close to the player spawn at the origin.
+    pub safe_zone_radius: f32,
+    /// Bake the decorations of every chunk into a single image instead of separate sprites.
+    pub batch_decor: bool,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        WorldConfig {
+            size: 2000.,
+            decor_num: 1000,
+            decor_scale: 0.75..1.5,
+            decor_z: 12.,
+            depth_z_range: 2.,
+            safe_zone_radius: 200.,
+            batch_decor: true,
+        }
+    }
+}
+
+impl WorldConfig {
+    pub fn from_cfg(cfg: &str) -> Self {
+        let mut config = WorldConfig::default();
+        for (key, val) in cfg_entries(cfg) {
+            match key {
+                "size" => config.size = val.parse().unwrap_or(config.size),
+                "decor_num" => config.decor_num = val.parse().unwrap_or(config.decor_num),
+                "decor_scale_min" => {
+                    config.decor_scale.start = val.parse().unwrap_or(config.decor_scale.start)
+                }
+                "decor_scale_max" => {
+                    config.decor_scale.end = val.parse().unwrap_or(config.decor_scale.end)
+                }
+                "decor_z" => config.decor_z = val.parse().unwrap_or(config.decor_z),
+                "depth_z_range" => {
+                    config.depth_z_range = val.parse().unwrap_or(config.depth_z_range)
+                }
+                "safe_zone_radius" => {
+                    config.safe_zone_radius = val.parse().unwrap_or(config.safe_zone_radius)
+                }
+                "batch_decor" => config.batch_decor = val.parse().unwrap_or(config.batch_decor),
+                _ => warn!("Unknown world config key: {key}"),
+            }
+        }
+        // an empty range would panic when the decorations are generated
+        if config.decor_scale.is_empty() {
+            config.decor_scale = WorldConfig::default().decor_scale;
+        }
+        // the spawns keep up to a terrain patch away from the border, their ranges would be empty
+        if !config.size.is_finite() || config.size <= TERRAIN_PATCH_MAX_SIZE * 2. {
+            config.size = WorldConfig::default().size;
+        }
+        config
+    }
+
+    /// Half of the [`WorldConfig::size`], the world spans from `-half_size` to `half_size`.
+    pub fn half_size(&self) -> f32 {
+        self.size * 0.5
+    }
+
+    /// The area of the world.
+    pub fn rect(&self) -> Rect {
+        Rect::from_center_size(Vec2::ZERO, Vec2::splat(self.size))
+    }
+
+    pub fn in_safe_zone(&self, pos: Vec2) -> bool {
+        pos.length_squared() < self.safe_zone_radius * self.safe_zone_radius
+    }
+
+    /// Z of the decorations and walls, lower entities get rendered in front of the entities above
+    /// to give perception of depth.
+    pub fn depth_z(&self, y: f32) -> f32 {
+        let from_top = (self.half_size() - y) / self.size;
+        self.decor_z + from_top.clamp(0., 1.) * self.depth_z_range
+    }
+}
+
+/// The [`WorldConfig`] the next run is generated with.
+#[derive(Resource, Debug, Deref)]
+pub struct WorldConfigHandle(pub Handle<WorldConfig>);
+
+impl FromWorld for WorldConfigHandle {
+    fn from_world(world: &mut World) -> Self {
+        let path = world.get_resource::<GameConfig>().map_or_else(
+            || WORLD_CONFIG_PATH.to_string(),
+            |config| config.world_config.clone(),
+        );
+        WorldConfigHandle(world.resource::<AssetServer>().load(path))
+    }
+}
+
+#[derive(Default)]
+pub(super) struct WorldConfigLoader;
+
+impl AssetLoader for WorldConfigLoader {
+    type Asset = WorldConfig;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let cfg = String::from_utf8(bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(WorldConfig::from_cfg(&cfg))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["world"]
+    }
+}
+
+/// Copies the selected config into the [`WorldConfig`] resource once it's loaded or whenever
+/// another one is selected.
+pub(super) fn apply_world_config(
+    mut config: ResMut<WorldConfig>,
+    mut asset_events: EventReader<AssetEvent<WorldConfig>>,
+    handle: Res<WorldConfigHandle>,
+    configs: Res<Assets<WorldConfig>>,
+) {
+    let reloaded = asset_events.read().any(|event| {
+        event.is_loaded_with_dependencies(handle.id()) || event.is_modified(handle.id())
+    });
+    if !reloaded && !handle.is_changed() {
+        return;
+    }
+    if let Some(loaded) = configs.get(handle.id()) {
+        config.set_if_neq(loaded.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn world_config_from_cfg() {
+        let config = WorldConfig::from_cfg("size = 4000\ndecor_scale_min = 2\ndecor_num = lots\n");
+        assert_eq!(config.size, 4000.);
+        assert_eq!(config.decor_num, WorldConfig::default().decor_num);
+        // 2..1.5 is empty, keep the default range
+        assert_eq!(config.decor_scale, WorldConfig::default().decor_scale);
+
+        assert!(config.in_safe_zone(Vec2::splat(10.)));
+        assert!(!config.in_safe_zone(Vec2::X * config.safe_zone_radius));
+
+        assert_eq!(config.depth_z(config.half_size()), config.decor_z);
+        assert_eq!(
+            config.depth_z(-config.half_size()),
+            config.decor_z + config.depth_z_range
+        );
+
+        for size in ["0", "-500", "NaN", "inf", "300"] {
+            let config = WorldConfig::from_cfg(&format!("size = {size}\n"));
+            assert_eq!(config.size, WorldConfig::default().size);
+        }
+    }
+}
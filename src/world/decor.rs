@@ -0,0 +1,193 @@
+//! Bakes the static decorations of a chunk into a single image, so thousands of them are only a
+//! handful of sprites. Enabled with [`WorldConfig::batch_decor`](super::config::WorldConfig).
+//!
+//! The decorations of a chunk are drawn from the top down, so the lower ones still cover the
+//! ones above them. They can stick out of their chunk, the baked image covers all of them.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension},
+    },
+};
+
+use crate::prelude::RunScoped;
+
+const BYTES_PER_PX: usize = 4;
+
+/// The decorations of a chunk that haven't been baked into an image yet.
+#[derive(Component)]
+#[require(Transform, Visibility, RunScoped)]
+pub(super) struct DecorChunk {
+    pub decor: Vec<(Sprite, Transform)>,
+}
+
+/// Bakes every [`DecorChunk`] once the atlases of all its decorations are loaded.
+pub(super) fn bake_decor_chunks(
+    mut commands: Commands,
+    mut chunk_query: Query<(Entity, &mut Transform, &DecorChunk)>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let mut baked = Vec::new();
+    for (ent, mut chunk_transf, chunk) in chunk_query.iter_mut() {
+        // the source rect in the atlas and the world rect of every decoration
+        let Some(mut parts) = chunk
+            .decor
+            .iter()
+            .map(|(sprite, transf)| {
+                let atlas = sprite.texture_atlas.as_ref()?;
+                let tile = *layouts.get(&atlas.layout)?.textures.get(atlas.index)?;
+                let image = images.get(&sprite.image)?;
+                // the fallback texture is smaller than the tile, it's stretched over it
+                let src = if tile.max.cmple(image.size()).all() {
+                    tile
+                } else {
+                    URect::from_corners(UVec2::ZERO, image.size())
+                };
+                let size = tile.size().as_vec2() * transf.scale.truncate();
+                let dst = Rect::from_center_size(transf.translation.truncate(), size);
+                Some((sprite, src, dst))
+            })
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+
+        let bounds = parts
+            .iter()
+            .fold(Rect::EMPTY, |bounds, (_, _, dst)| bounds.union(*dst));
+        let width = bounds.width().ceil() as usize;
+        let height = bounds.height().ceil() as usize;
+        let mut data = vec![0; width * height * BYTES_PER_PX];
+        let mut format = None;
+
+        // top down, like the depth of the separate sprites
+        parts.sort_by(|(_, _, a), (_, _, b)| b.center().y.total_cmp(&a.center().y));
+        for (sprite, src, dst) in parts {
+            let atlas = images.get(&sprite.image).unwrap();
+            format = Some(atlas.texture_descriptor.format);
+            // the image rows go from the top, the world y goes up
+            let dst_min = Vec2::new(dst.min.x - bounds.min.x, bounds.max.y - dst.max.y);
+            blit_sprite(
+                &mut data,
+                width,
+                &atlas.data,
+                atlas.width() as usize,
+                src,
+                Rect::from_corners(dst_min, dst_min + dst.size()),
+                sprite.flip_x,
+                sprite.color.to_srgba().to_f32_array(),
+            );
+        }
+
+        let Some(format) = format else {
+            commands.entity(ent).despawn();
+            continue;
+        };
+        let image = Image::new(
+            Extent3d {
+                width: width as u32,
+                height: height as u32,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            format,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        chunk_transf.translation.x = bounds.center().x;
+        chunk_transf.translation.y = bounds.center().y;
+        baked.push((ent, image));
+    }
+
+    for (ent, image) in baked {
+        commands
+            .entity(ent)
+            .insert(Sprite::from_image(images.add(image)))
+            .remove::<DecorChunk>();
+    }
+}
+
+/// Draws the `src` rect of the RGBA atlas scaled to the `dst` rect of the image over what's
+/// already there, tinted by the `tint`. Uses the nearest pixel like the sprites.
+fn blit_sprite(
+    data: &mut [u8],
+    width: usize,
+    atlas: &[u8],
+    atlas_width: usize,
+    src: URect,
+    dst: Rect,
+    flip_x: bool,
+    tint: [f32; 4],
+) {
+    let height = data.len() / BYTES_PER_PX / width;
+    let scale = dst.size() / src.size().as_vec2();
+    let (min_x, min_y) = (dst.min.x.max(0.) as usize, dst.min.y.max(0.) as usize);
+    let max_x = (dst.max.x.ceil() as usize).min(width);
+    let max_y = (dst.max.y.ceil() as usize).min(height);
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let local = (Vec2::new(x as f32, y as f32) + 0.5 - dst.min) / scale;
+            let (u, v) = (local.x as u32, local.y as u32);
+            if u >= src.width() || v >= src.height() {
+                continue;
+            }
+            let u = if flip_x { src.width() - 1 - u } else { u };
+            let src_idx =
+                ((src.min.y + v) as usize * atlas_width + (src.min.x + u) as usize) * BYTES_PER_PX;
+            let dst_idx = (y * width + x) * BYTES_PER_PX;
+
+            let src_px: [f32; 4] =
+                std::array::from_fn(|c| atlas[src_idx + c] as f32 / 255. * tint[c]);
+            let dst_px: [f32; 4] = std::array::from_fn(|c| data[dst_idx + c] as f32 / 255.);
+            let alpha = src_px[3] + dst_px[3] * (1. - src_px[3]);
+            if alpha <= 0. {
+                continue;
+            }
+            for c in 0..3 {
+                let color =
+                    (src_px[c] * src_px[3] + dst_px[c] * dst_px[3] * (1. - src_px[3])) / alpha;
+                data[dst_idx + c] = (color * 255.).round() as u8;
+            }
+            data[dst_idx + 3] = (alpha * 255.).round() as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn blit_sprite_scales_flips_and_blends() {
+        // 2x1 atlas: an opaque red and a transparent pixel
+        let atlas = [255, 0, 0, 255, 0, 0, 0, 0];
+        let src = URect::new(0, 0, 2, 1);
+
+        let mut data = vec![0; 4 * 2 * BYTES_PER_PX];
+        let dst = Rect::new(0., 0., 4., 2.);
+        blit_sprite(&mut data, 4, &atlas, 2, src, dst, false, [1.; 4]);
+        // every pixel is doubled, the transparent half stays empty
+        assert_eq!(
+            &data[..16],
+            &[255, 0, 0, 255, 255, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        // flipped and half transparent over the existing red
+        blit_sprite(
+            &mut data,
+            4,
+            &[0, 0, 255, 255, 0, 0, 0, 0],
+            2,
+            src,
+            dst,
+            true,
+            [1., 1., 1., 0.5],
+        );
+        assert_eq!(&data[..8], &[255, 0, 0, 255, 255, 0, 0, 255]);
+        assert_eq!(&data[8..12], &[0, 0, 255, 128]);
+    }
+}
@@ -0,0 +1,88 @@
+//! A seeded 2D value noise, gives the generated world its structure.
+
+use bevy::math::Vec2;
+
+/// Smoothly interpolated random values on a grid, the same seed always gives the same noise.
+#[derive(Debug, Clone, Copy)]
+pub struct ValueNoise {
+    seed: u64,
+}
+
+impl ValueNoise {
+    pub fn new(seed: u64) -> Self {
+        ValueNoise { seed }
+    }
+
+    /// The noise at the position, from 0 to 1. The features are about 1 unit apart.
+    pub fn get(&self, pos: Vec2) -> f32 {
+        let cell = pos.floor();
+        let (x, y) = (cell.x as i64, cell.y as i64);
+        // smoothstep, so the grid lines don't show
+        let t = pos - cell;
+        let t = t * t * (3. - 2. * t);
+
+        let bottom = lerp(self.lattice(x, y), self.lattice(x + 1, y), t.x);
+        let top = lerp(self.lattice(x, y + 1), self.lattice(x + 1, y + 1), t.x);
+        lerp(bottom, top, t.y)
+    }
+
+    /// Sums the octaves of the noise, each one with double the frequency and half the amplitude
+    /// of the previous one. Still from 0 to 1.
+    pub fn fractal(&self, pos: Vec2, octaves: u32) -> f32 {
+        let (mut sum, mut total, mut amplitude, mut frequency) = (0., 0., 1., 1.);
+        for octave in 0..octaves {
+            // offset the octaves, so they don't all line up at the origin
+            let offset = Vec2::splat(octave as f32 * 17.31);
+            sum += self.get(pos * frequency + offset) * amplitude;
+            total += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.;
+        }
+        sum / total
+    }
+
+    /// A random value from 0 to 1 for the grid point.
+    fn lattice(&self, x: i64, y: i64) -> f32 {
+        // splitmix64 finalizer
+        let mut hash = self.seed
+            ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+        hash ^= hash >> 30;
+        hash = hash.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        hash ^= hash >> 27;
+        hash = hash.wrapping_mul(0x94D0_49BB_1331_11EB);
+        hash ^= hash >> 31;
+        // the top 24 bits fit into the f32 mantissa
+        (hash >> 40) as f32 / (1 << 24) as f32
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn value_noise_is_seeded_and_in_range() {
+        let noise = ValueNoise::new(42);
+        let positions = (0..200)
+            .map(|i| Vec2::new(i as f32 * 0.37 - 30., i as f32 * -0.53 + 12.))
+            .collect::<Vec<_>>();
+
+        for &pos in &positions {
+            let val = noise.fractal(pos, 4);
+            assert!((0. ..1.).contains(&val));
+            assert_eq!(val, ValueNoise::new(42).fractal(pos, 4));
+        }
+        assert!(positions
+            .iter()
+            .any(|&pos| noise.get(pos) != ValueNoise::new(7).get(pos)));
+
+        // continuous between the grid points
+        let pos = Vec2::new(3.5, -2.25);
+        assert!((noise.get(pos) - noise.get(pos + Vec2::splat(0.001))).abs() < 0.01);
+    }
+}
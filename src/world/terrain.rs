@@ -0,0 +1,122 @@
+//! Contains the [`TerrainPlugin`] that places patches of mud and shallow water around the world.
+//!
+//! A [`TerrainRegion`] uses its [`ColliderShape`] as a sensor like the pickups, it never blocks
+//! anything. The players and the enemies inside of it get its speed multiplier as their
+//! [`SpeedModifier`], the slowest one when they stand in more of them at once.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::collision::ColliderShape;
+use crate::components::SpeedModifier;
+use crate::prelude::*;
+use crate::quadtree::quad_collider::{QuadCollider, Shape};
+use crate::rng::{GameRng, RngStream};
+use crate::world::{biome::Biome, config::WorldConfig, pick_world_seed, WorldNoise, WorldSeed};
+
+/// Gives up on the patches that can't find a spot after this many tries.
+const TERRAIN_ATTEMPTS: u32 = 20;
+
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::GameInit),
+            spawn_terrain.after(pick_world_seed),
+        )
+        .add_systems(
+            Update,
+            apply_terrain_modifiers.run_if(in_state(GameState::GameRun)),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerrainKind {
+    Mud,
+    Shallows,
+}
+
+impl TerrainKind {
+    /// Multiplies the movement speed of everything inside.
+    pub fn speed_mult(self) -> f32 {
+        match self {
+            TerrainKind::Mud => MUD_SPEED_MULT,
+            TerrainKind::Shallows => SHALLOWS_SPEED_MULT,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            TerrainKind::Mud => Color::srgba(0.35, 0.25, 0.12, 0.7),
+            TerrainKind::Shallows => Color::srgba(0.2, 0.45, 0.7, 0.55),
+        }
+    }
+}
+
+impl Biome {
+    /// The terrain that the patches in the biome are made of.
+    pub fn terrain(self) -> Option<TerrainKind> {
+        match self {
+            Biome::Meadow => Some(TerrainKind::Shallows),
+            Biome::Swamp => Some(TerrainKind::Mud),
+            Biome::Ruins => None,
+        }
+    }
+}
+
+#[derive(Component, Debug, Deref)]
+#[require(Transform, Sprite, RunScoped)]
+pub struct TerrainRegion(pub TerrainKind);
+
+/// Places the patches outside of the safe zone, the [`Biome`] decides what they are made of.
+fn spawn_terrain(
+    mut commands: Commands,
+    mut game_rng: ResMut<GameRng>,
+    config: Res<WorldConfig>,
+    seed: Res<WorldSeed>,
+) {
+    let rng = game_rng.stream(RngStream::Terrain);
+    let noise = WorldNoise::new(*seed);
+    let whalf = config.half_size() - TERRAIN_PATCH_MAX_SIZE;
+
+    for _ in 0..TERRAIN_PATCHES {
+        let Some((pos, kind)) = (0..TERRAIN_ATTEMPTS)
+            .map(|_| Vec2::new(rng.gen_range(-whalf..whalf), rng.gen_range(-whalf..whalf)))
+            .filter(|&pos| !config.in_safe_zone(pos))
+            .find_map(|pos| Some((pos, noise.biome(pos).terrain()?)))
+        else {
+            continue;
+        };
+        let size = Vec2::new(
+            rng.gen_range(TERRAIN_PATCH_MIN_SIZE..TERRAIN_PATCH_MAX_SIZE),
+            rng.gen_range(TERRAIN_PATCH_MIN_SIZE..TERRAIN_PATCH_MAX_SIZE),
+        );
+        commands.spawn((
+            Sprite::from_color(kind.color(), size),
+            // on the ground, below the decor
+            Transform::from_translation(pos.extend(1.)),
+            ColliderShape(Shape::Quad(Rectangle::from_size(size))),
+            TerrainRegion(kind),
+        ));
+    }
+}
+
+fn apply_terrain_modifiers(
+    mut mover_query: Query<(&Transform, &ColliderShape, &mut SpeedModifier)>,
+    terrain_query: Query<(&Transform, &ColliderShape, &TerrainRegion)>,
+) {
+    for (transf, shape, mut modifier) in mover_query.iter_mut() {
+        let coll = QuadCollider::new(transf.translation.truncate(), **shape);
+        let mult = terrain_query
+            .iter()
+            .filter(|(terrain_transf, terrain_shape, _)| {
+                QuadCollider::new(terrain_transf.translation.truncate(), ***terrain_shape)
+                    .intersects(coll)
+            })
+            .map(|(_, _, terrain)| terrain.speed_mult())
+            .fold(1., f32::min);
+        modifier.set_if_neq(SpeedModifier(mult));
+    }
+}
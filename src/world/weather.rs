@@ -0,0 +1,241 @@
+//! Contains the [`WeatherPlugin`] that changes the [`Weather`] every [`WEATHER_CHANGE_SECS`].
+//!
+//! The next weather is picked from the [`Biome`] the players are in. Rain slows everyone down a
+//! little and fog shrinks the [`ENEMY_AGGRO_RADIUS`], the rest is only visual: the rain drops
+//! are sprites spawned around the camera view and both fog and rain tint the screen with an
+//! overlay.
+
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::camera::{camera_view_rect, PlayerCam};
+use crate::gui::toast::Notify;
+use crate::locale::Localizer;
+use crate::player::Player;
+use crate::prelude::*;
+use crate::rng::{GameRng, RngStream};
+use crate::world::{biome::Biome, WorldNoise, WorldSeed};
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Weather>()
+            .add_systems(RunTeardown, reset_resource::<Weather>)
+            .add_systems(OnEnter(GameState::GameInit), spawn_weather_overlay)
+            .add_systems(
+                Update,
+                (
+                    change_weather,
+                    fade_weather_overlay,
+                    (spawn_rain_drops, update_rain_drops).chain(),
+                )
+                    .run_if(in_state(GameState::GameRun)),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WeatherKind {
+    #[default]
+    Clear,
+    Rain,
+    Fog,
+}
+
+impl WeatherKind {
+    /// Multiplies the movement speed of the players and the enemies.
+    pub fn move_speed_mult(self) -> f32 {
+        match self {
+            WeatherKind::Rain => RAIN_MOVE_SPEED_MULT,
+            WeatherKind::Clear | WeatherKind::Fog => 1.,
+        }
+    }
+
+    /// Multiplies the [`ENEMY_AGGRO_RADIUS`].
+    pub fn aggro_radius_mult(self) -> f32 {
+        match self {
+            WeatherKind::Fog => FOG_AGGRO_RADIUS_MULT,
+            WeatherKind::Clear | WeatherKind::Rain => 1.,
+        }
+    }
+
+    fn overlay_color(self) -> Color {
+        match self {
+            WeatherKind::Clear => Color::NONE,
+            WeatherKind::Rain => Color::srgba(0.1, 0.15, 0.3, 0.25),
+            WeatherKind::Fog => Color::srgba(0.7, 0.72, 0.75, 0.5),
+        }
+    }
+
+    fn toast_key(self) -> &'static str {
+        match self {
+            WeatherKind::Clear => "toast.weather_clear",
+            WeatherKind::Rain => "toast.weather_rain",
+            WeatherKind::Fog => "toast.weather_fog",
+        }
+    }
+}
+
+impl Biome {
+    /// Weights of the weather in the biome.
+    pub fn weather_table(self) -> &'static [(WeatherKind, u32)] {
+        match self {
+            Biome::Meadow => &[
+                (WeatherKind::Clear, 6),
+                (WeatherKind::Rain, 3),
+                (WeatherKind::Fog, 1),
+            ],
+            Biome::Swamp => &[
+                (WeatherKind::Clear, 2),
+                (WeatherKind::Rain, 3),
+                (WeatherKind::Fog, 5),
+            ],
+            Biome::Ruins => &[
+                (WeatherKind::Clear, 5),
+                (WeatherKind::Rain, 2),
+                (WeatherKind::Fog, 3),
+            ],
+        }
+    }
+}
+
+/// The current weather, every run starts with a clear sky.
+#[derive(Resource, Debug)]
+pub struct Weather {
+    pub kind: WeatherKind,
+    change_timer: Timer,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Weather {
+            kind: WeatherKind::default(),
+            change_timer: Timer::from_seconds(WEATHER_CHANGE_SECS, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Tints the screen, fades to the color of the current weather.
+#[derive(Component)]
+#[require(Node, BackgroundColor, RunScoped)]
+struct WeatherOverlay;
+
+#[derive(Component, Deref, DerefMut)]
+#[require(Transform, Sprite, RunScoped)]
+struct RainDrop(Timer);
+
+fn spawn_weather_overlay(mut commands: Commands) {
+    commands.spawn((
+        WeatherOverlay,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            ..default()
+        },
+        // below the rest of the UI
+        GlobalZIndex(-1),
+        PickingBehavior::IGNORE,
+    ));
+}
+
+fn change_weather(
+    mut weather: ResMut<Weather>,
+    mut notify_events: EventWriter<Notify>,
+    player_query: Query<&Transform, With<Player>>,
+    mut game_rng: ResMut<GameRng>,
+    seed: Res<WorldSeed>,
+    localizer: Localizer,
+    time: Res<Time>,
+) {
+    if !weather.change_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let Some(player_transf) = player_query.iter().next() else {
+        return;
+    };
+
+    let biome = WorldNoise::new(*seed).biome(player_transf.translation.truncate());
+    let next = biome
+        .weather_table()
+        .choose_weighted(game_rng.stream(RngStream::Weather), |(_, weight)| *weight)
+        .map_or(WeatherKind::Clear, |(kind, _)| *kind);
+    if next != weather.kind {
+        weather.kind = next;
+        notify_events.send(Notify::new(localizer.tr(next.toast_key())));
+    }
+}
+
+fn fade_weather_overlay(
+    mut overlay_query: Query<&mut BackgroundColor, With<WeatherOverlay>>,
+    weather: Res<Weather>,
+    time: Res<Time>,
+) {
+    let target = weather.kind.overlay_color().to_srgba();
+    let t = (time.delta_secs() / WEATHER_FADE_SECS).min(1.);
+    for mut bg in overlay_query.iter_mut() {
+        bg.0 = bg.0.to_srgba().mix(&target, t).into();
+    }
+}
+
+/// Spawns the rain drops all over the camera view, a bit past its top and right edge so the
+/// screen doesn't clear up at the edges while they fall.
+fn spawn_rain_drops(
+    mut commands: Commands,
+    mut pending_drops: Local<f32>,
+    cam_query: Query<(&OrthographicProjection, &GlobalTransform), With<PlayerCam>>,
+    mut game_rng: ResMut<GameRng>,
+    weather: Res<Weather>,
+    time: Res<Time>,
+) {
+    if weather.kind != WeatherKind::Rain {
+        *pending_drops = 0.;
+        return;
+    }
+
+    *pending_drops += RAIN_DROPS_PER_SEC * time.delta_secs();
+    let rng = game_rng.stream(RngStream::Effects);
+    let count = *pending_drops as u32;
+    // every view of a split screen gets its own
+    let drops = cam_query
+        .iter()
+        .map(|(projection, cam_transf)| camera_view_rect(projection, cam_transf))
+        .flat_map(|view| (0..count).map(move |_| view))
+        .map(|view| {
+            // the wind carries them to the left, start a bit further right
+            let x = rng.gen_range(view.min.x..view.max.x + view.width() * 0.2);
+            let y = rng.gen_range(view.min.y..view.max.y + 20.);
+            (
+                Sprite {
+                    color: Color::srgba(0.6, 0.7, 0.9, 0.6),
+                    custom_size: Some(Vec2::new(1., 6.)),
+                    ..default()
+                },
+                Transform::from_xyz(x, y, 500.).with_rotation(Quat::from_rotation_z(
+                    RAIN_DROP_VELOCITY.to_angle() - FRAC_PI_2,
+                )),
+                RainDrop(Timer::from_seconds(RAIN_DROP_LIFE_SECS, TimerMode::Once)),
+            )
+        })
+        .collect::<Vec<_>>();
+    *pending_drops = pending_drops.fract();
+
+    commands.spawn_batch(drops);
+}
+
+fn update_rain_drops(
+    mut commands: Commands,
+    mut drop_query: Query<(Entity, &mut Transform, &mut RainDrop)>,
+    time: Res<Time>,
+) {
+    for (ent, mut transf, mut drop) in drop_query.iter_mut() {
+        if drop.tick(time.delta()).finished() {
+            commands.entity(ent).despawn();
+            continue;
+        }
+        transf.translation += (RAIN_DROP_VELOCITY * time.delta_secs()).extend(0.);
+    }
+}